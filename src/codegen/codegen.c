@@ -8,6 +8,8 @@
 #include "sub_compiler.h"
 #include "windows_compat.h"
 #include <stdarg.h>
+#include <ctype.h>
+#include <limits.h>
 
 /* Optimization Context */
 typedef struct {
@@ -79,12 +81,253 @@ static char* sb_to_string(StringBuilder *sb) {
     return result;
 }
 
+/* Append text as a quoted C string literal. Control bytes go out as
+   three-digit octal escapes so a following digit can't extend them. */
+static void sb_append_c_string(StringBuilder *sb, const char *text) {
+    sb_append(sb, "\"");
+    for (const unsigned char *p = (const unsigned char *)text; *p; p++) {
+        switch (*p) {
+            case '\n': sb_append(sb, "\\n"); break;
+            case '\t': sb_append(sb, "\\t"); break;
+            case '\r': sb_append(sb, "\\r"); break;
+            case '\\': sb_append(sb, "\\\\"); break;
+            case '"':  sb_append(sb, "\\\""); break;
+            default:
+                if (*p < 0x20 || *p == 0x7f) sb_append(sb, "\\%03o", *p);
+                else sb_append(sb, "%c", *p);
+                break;
+        }
+    }
+    sb_append(sb, "\"");
+}
+
+/* Name Mangling */
+static const char *const C_RESERVED[] = {
+    "auto", "break", "case", "char", "const", "continue", "default", "do",
+    "double", "else", "enum", "extern", "float", "for", "goto", "if",
+    "inline", "int", "long", "register", "restrict", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union",
+    "unsigned", "void", "volatile", "while", "bool", "true", "false", "NULL",
+    /* names the generated program itself defines or includes */
+    "main", "printf", "fprintf", "snprintf", "malloc", "free", "exit",
+    "strlen", "strcmp", "strstr", "memcpy", "atol", "atof", "toupper",
+    "tolower", "stdin", "stdout", "stderr", NULL
+};
+
+static const char *const JS_RESERVED[] = {
+    "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "export", "extends", "false",
+    "finally", "for", "function", "if", "import", "in", "instanceof", "new",
+    "null", "return", "super", "switch", "this", "throw", "true", "try",
+    "typeof", "var", "void", "while", "with", "yield", "let", "static",
+    "implements", "interface", "package", "private", "protected", "public",
+    "await", "arguments", "eval", "undefined", "NaN", "Infinity", "console",
+    "_subIndex", NULL
+};
+
+static const char *const ASM_RESERVED[] = {
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "eax", "ebx",
+    "ecx", "edx", "esi", "edi", "ebp", "esp", "section", "global", "extern",
+    "db", "dw", "dd", "dq", "resb", "resw", "resd", "resq", "equ", "times",
+    "_start", NULL
+};
+
+typedef struct MangledName {
+    MangleTarget target;
+    char *name;
+    char *mangled;
+    struct MangledName *next;
+} MangledName;
+
+static MangledName *g_mangled = NULL;
+
+static bool mangle_needed(const char *name, MangleTarget target) {
+    if (strncmp(name, "sub_", 4) == 0) return true;
+    if (target == MANGLE_C) {
+        /* C reserves __x and _X for the implementation */
+        if (name[0] == '_' && (name[1] == '_' || (name[1] >= 'A' && name[1] <= 'Z'))) return true;
+        if (strncmp(name, "SUB_", 4) == 0) return true;
+    }
+    for (const char *p = name; *p; p++) {
+        if ((unsigned char)*p >= 0x80) return true;
+    }
+    const char *const *reserved = target == MANGLE_C ? C_RESERVED :
+                                  target == MANGLE_JS ? JS_RESERVED : ASM_RESERVED;
+    for (int i = 0; reserved[i]; i++) {
+        if (strcmp(reserved[i], name) == 0) return true;
+    }
+    return false;
+}
+
+const char* mangle(const char *name, MangleTarget target) {
+    if (!name || !mangle_needed(name, target)) return name;
+
+    for (MangledName *m = g_mangled; m; m = m->next) {
+        if (m->target == target && strcmp(m->name, name) == 0) return m->mangled;
+    }
+
+    MangledName *m = calloc(1, sizeof(MangledName));
+    char *out = malloc(strlen("sub_") + strlen(name) * 4 + 1);
+    if (!m || !out) {
+        free(m);
+        free(out);
+        return name;
+    }
+    char *w = out + sprintf(out, "sub_");
+    for (const unsigned char *p = (const unsigned char *)name; *p; p++) {
+        if (*p == '_') w += sprintf(w, "__");
+        else if (*p < 0x80 && (isalnum(*p))) *w++ = (char)*p;
+        else w += sprintf(w, "_x%02x", *p);
+    }
+    *w = '\0';
+    m->target = target;
+    m->name = strdup(name);
+    m->mangled = out;
+    m->next = g_mangled;
+    g_mangled = m;
+    return out;
+}
+
+/* Backend Capabilities */
+#define CAP(type) (1ULL << (type))
+#define CAP_LOOP_JUMPS (CAP(AST_BREAK_STMT) | CAP(AST_CONTINUE_STMT))
+#define CAP_TUPLES     (CAP(AST_TUPLE_EXPR) | CAP(AST_DESTRUCTURE_DECL))
+#define CAP_AGGREGATES (CAP(AST_ARRAY_LITERAL) | CAP(AST_ARRAY_ACCESS) | \
+                        CAP(AST_OBJECT_LITERAL) | CAP(AST_MEMBER_ACCESS))
+/* Every backend lowers a range at the head of a for loop; this is a..b
+   used as a value */
+#define CAP_RANGES     CAP(AST_RANGE_EXPR)
+
+static const char *const SELECT_CALL[] = {"select", NULL};
+
+/* The constructs and builtins each backend has no lowering for; generating
+   them would drop the code or emit something the target compiler rejects */
+typedef struct {
+    const char *backend;
+    unsigned long long unsupported;
+    const char *const *calls;
+    bool no_for_each;   // no lowering for a for loop over an array or string
+} BackendGaps;
+
+static const BackendGaps BACKEND_GAPS[] = {
+    {"C",          CAP_LOOP_JUMPS | CAP_AGGREGATES | CAP(AST_TERNARY_EXPR) |
+                   CAP_RANGES, NULL, true},
+    {"web",        CAP_LOOP_JUMPS | CAP_AGGREGATES | CAP(AST_TERNARY_EXPR) |
+                   CAP(AST_UNARY_EXPR) | CAP_RANGES, NULL, false},
+    {"C++",        CAP(AST_ARRAY_LITERAL) | CAP(AST_OBJECT_LITERAL) | CAP_RANGES, NULL, true},
+    {"Python",     CAP_LOOP_JUMPS, NULL, false},
+    {"JavaScript", CAP_LOOP_JUMPS, NULL, false},
+    {"Java",       CAP_LOOP_JUMPS | CAP_TUPLES, SELECT_CALL, false},
+    {"Swift",      CAP_LOOP_JUMPS | CAP_AGGREGATES | CAP(AST_TERNARY_EXPR) |
+                   CAP(AST_UNARY_EXPR) | CAP(AST_CONST_DECL) | CAP_RANGES, SELECT_CALL, true},
+    {"Kotlin",     CAP_LOOP_JUMPS | CAP_AGGREGATES | CAP_TUPLES | CAP(AST_TERNARY_EXPR) |
+                   CAP(AST_UNARY_EXPR) | CAP(AST_CONST_DECL) | CAP_RANGES, NULL, true},
+    {"Ruby",       CAP_LOOP_JUMPS | CAP(AST_TERNARY_EXPR) | CAP(AST_UNARY_EXPR), NULL, false},
+    {"Go",         CAP_TUPLES, NULL, false},
+    {"Rust",       CAP_LOOP_JUMPS | CAP(AST_CONST_DECL), NULL, false},
+};
+
+static const char* construct_name(ASTNodeType type) {
+    switch (type) {
+        case AST_BREAK_STMT:       return "'break'";
+        case AST_CONTINUE_STMT:    return "'continue'";
+        case AST_CONST_DECL:       return "'const' declarations";
+        case AST_TERNARY_EXPR:     return "the ternary operator";
+        case AST_UNARY_EXPR:       return "unary operators";
+        case AST_ARRAY_LITERAL:    return "array literals";
+        case AST_ARRAY_ACCESS:     return "array indexing";
+        case AST_OBJECT_LITERAL:   return "object literals";
+        case AST_MEMBER_ACCESS:    return "member access";
+        case AST_TUPLE_EXPR:       return "tuples";
+        case AST_DESTRUCTURE_DECL: return "tuple destructuring";
+        case AST_RANGE_EXPR:       return "ranges outside a 'for' loop";
+        default:                   return "this construct";
+    }
+}
+
+static bool unsupported_call(const ASTNode *node, const char *const *calls) {
+    if (node->type != AST_CALL_EXPR || !node->value) return false;
+    for (int i = 0; calls && calls[i]; i++) {
+        if (strcmp(calls[i], node->value) == 0) return true;
+    }
+    return false;
+}
+
+static int report_unsupported(const ASTNode *node, const BackendGaps *gaps) {
+    if (!node) return 0;
+    const char *backend = gaps->backend;
+    unsigned long long unsupported = gaps->unsupported;
+    bool for_each = node->type == AST_FOR_STMT && node->condition;
+    if ((unsupported & CAP(node->type)) || unsupported_call(node, gaps->calls) ||
+        (for_each && gaps->no_for_each)) {
+        SourceSpan span = ast_span(node);
+        char msg[160];
+        if (for_each && !(unsupported & CAP(node->type))) {
+            snprintf(msg, sizeof(msg), "The %s backend does not support 'for' over an array or string", backend);
+        } else if (node->type == AST_CALL_EXPR) {
+            snprintf(msg, sizeof(msg), "The %s backend does not support %s()", backend, node->value);
+        } else {
+            snprintf(msg, sizeof(msg), "The %s backend does not support %s",
+                     backend, construct_name(node->type));
+        }
+        fprintf(stderr, "[line %d, col %d] Codegen error: %s\n", span.line, span.column, msg);
+        diagnostic_record(false, NULL, span.line, span.column, "%s", msg);
+        diagnostic_print_span(span);
+        return 1;
+    }
+
+    /* The C backend folds len() of an array literal to a constant */
+    if (node->type == AST_CALL_EXPR && strcmp(backend, "C") == 0 && node->value &&
+        strcmp(node->value, "len") == 0 && node->child_count == 1 &&
+        node->children[0]->type == AST_ARRAY_LITERAL) {
+        return 0;
+    }
+
+    int count = 0;
+    if (node->type != AST_PROGRAM && node->type != AST_BLOCK) {
+        count += report_unsupported(node->left, gaps);
+        count += report_unsupported(node->right, gaps);
+        count += report_unsupported(node->condition, gaps);
+        count += report_unsupported(node->body, gaps);
+    }
+    for (int i = 0; i < node->child_count; i++) {
+        ASTNode *child = node->children[i];
+        if (node->type == AST_FOR_STMT && child && child->type == AST_RANGE_EXPR) {
+            /* The loop's own range is always lowered; only check its bounds */
+            count += report_unsupported(child->left, gaps);
+            count += report_unsupported(child->right, gaps);
+            continue;
+        }
+        count += report_unsupported(child, gaps);
+    }
+    return count;
+}
+
+bool codegen_check_support(const ASTNode *ast, const char *backend) {
+    for (size_t i = 0; i < sizeof(BACKEND_GAPS) / sizeof(BACKEND_GAPS[0]); i++) {
+        if (strcmp(BACKEND_GAPS[i].backend, backend) == 0) {
+            return report_unsupported(ast, &BACKEND_GAPS[i]) == 0;
+        }
+    }
+    return true;
+}
+
 /* Forward declarations */
 static void generate_node(StringBuilder *sb, ASTNode *node, int indent);
 static void generate_expression(StringBuilder *sb, ASTNode *node);
+static ASTNode* block_first(ASTNode *node);
 static void optimize_remove_dead_code(ASTNode *node);
 static bool is_node_pure(ASTNode *node);
 static void optimize_constant_folding(ASTNode *node);
+static void optimize_algebraic(ASTNode *node);
+
+/* Off by default: float rewrites must preserve IEEE results unless the
+   user opts in with --fast-math. */
+static bool g_fast_math = false;
+
+void optimizer_set_fast_math(bool enabled) {
+    g_fast_math = enabled;
+}
 
 /* Dead Code Elimination */
 static bool is_node_pure(ASTNode *node) {
@@ -103,122 +346,1245 @@ static bool is_node_pure(ASTNode *node) {
     }
 }
 
-static void optimize_remove_dead_code(ASTNode *node) {
-    if (!node) return;
-    
-    switch (node->type) {
-        case AST_PROGRAM:
-        case AST_BLOCK: {
-            ASTNode **new_children = malloc(sizeof(ASTNode*) * node->child_count);
-            int new_count = 0;
-            
-            for (int i = 0; i < node->child_count; i++) {
-                ASTNode *child = node->children[i];
-                
-                if (!child) continue;
-                
-                optimize_remove_dead_code(child);
-                
-                bool keep = true;
-                if (child->type == AST_LITERAL && !is_node_pure(child)) {
-                    keep = false;
-                }
-                
-                if (keep && (child->type == AST_VAR_DECL || 
-                    child->type == AST_CONST_DECL ||
-                    child->type == AST_FUNCTION_DECL ||
-                    child->type == AST_ASSIGN_STMT ||
-                    child->type == AST_CALL_EXPR ||
-                    child->type == AST_RETURN_STMT ||
-                    child->type == AST_IF_STMT ||
-                    child->type == AST_FOR_STMT ||
-                    child->type == AST_WHILE_STMT ||
-                    child->type == AST_BLOCK ||
-                    child->type == AST_BINARY_EXPR)) {
-                    new_children[new_count++] = child;
-                }
-            }
-            
-            free(node->children);
-            node->children = new_children;
-            node->child_count = new_count;
-            break;
+/* As a leave callback a block's statements have already been cleaned up
+   when it filters them */
+static void remove_dead_statements(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    switch (node->type) {
+        case AST_PROGRAM:
+        case AST_BLOCK: {
+            ASTNode **new_children = malloc(sizeof(ASTNode*) * node->child_count);
+            int new_count = 0;
+            
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *child = node->children[i];
+                
+                if (!child) continue;
+                
+                /* A bare name or literal does nothing; every other statement
+                   stays, so the children and the `next` chain still agree */
+                if ((child->type == AST_LITERAL || child->type == AST_IDENTIFIER) && is_node_pure(child)) {
+                    child->next = NULL;
+                    parser_free_ast(child);
+                    continue;
+                }
+                new_children[new_count++] = child;
+            }
+            
+            free(node->children);
+            node->children = new_children;
+            node->child_count = new_count;
+            for (int i = 0; i < new_count; i++) new_children[i]->next = i + 1 < new_count ? new_children[i + 1] : NULL;
+            node->body = new_count > 0 ? new_children[0] : NULL;
+            break;
+        }
+        default:
+            break;
+    }
+}
+
+static void optimize_remove_dead_code(ASTNode *node) {
+    ASTVisitor cleaner = { .leave = remove_dead_statements };
+    ast_walk(node, &cleaner);
+}
+
+/* Turn an expression into the literal `text`, which stands for the whole
+   original expression and so takes its span */
+static void become_literal(ASTNode *node, const char *text, DataType type) {
+    SourceSpan span = ast_span(node);
+    node->line = span.line;
+    node->column = span.column;
+    node->end_line = span.end_line;
+    node->end_column = span.end_column;
+    node->type = AST_LITERAL;
+    free(node->value);
+    node->value = strdup(text);
+    node->data_type = type;
+
+    parser_free_ast(node->left);
+    parser_free_ast(node->right);
+    node->left = NULL;
+    node->right = NULL;
+    for (int i = 0; i < node->child_count; i++) {
+        parser_free_ast(node->children[i]);
+    }
+    free(node->children);
+    node->children = NULL;
+    node->child_count = 0;
+}
+
+static bool is_string_literal(const ASTNode *node) {
+    return node && node->type == AST_LITERAL && node->data_type == TYPE_STRING && node->value;
+}
+
+/* Backends disagree on whether strings count bytes or characters, so only
+   ASCII strings have a length and indices every target agrees on */
+static bool is_ascii(const char *s) {
+    for (; *s; s++) {
+        if ((unsigned char)*s >= 0x80) return false;
+    }
+    return true;
+}
+
+/* "ab" + "cd", len("abc") and "abc"[i] on literals */
+static void fold_string_literals(ASTNode *node) {
+    if (node->type == AST_BINARY_EXPR && node->value && strcmp(node->value, "+") == 0 &&
+        is_string_literal(node->left) && is_string_literal(node->right)) {
+        size_t left_len = strlen(node->left->value);
+        size_t right_len = strlen(node->right->value);
+        char *joined = malloc(left_len + right_len + 1);
+        if (!joined) return;
+        memcpy(joined, node->left->value, left_len);
+        memcpy(joined + left_len, node->right->value, right_len + 1);
+        become_literal(node, joined, TYPE_STRING);
+        free(joined);
+    } else if (node->type == AST_CALL_EXPR && node->value && strcmp(node->value, "len") == 0 &&
+               node->child_count == 1 && is_string_literal(node->children[0]) &&
+               is_ascii(node->children[0]->value) && ast_literal_is_verbatim(node->children[0])) {
+        char length[32];
+        snprintf(length, sizeof(length), "%zu", strlen(node->children[0]->value));
+        become_literal(node, length, TYPE_INT);
+    } else if (node->type == AST_ARRAY_ACCESS && is_string_literal(node->left) &&
+               is_ascii(node->left->value) && ast_literal_is_verbatim(node->left) && node->right && node->right->type == AST_LITERAL &&
+               node->right->data_type == TYPE_INT && node->right->value) {
+        char *end;
+        long index = strtol(node->right->value, &end, 10);
+        if (*end != '\0' || index < 0 || (size_t)index >= strlen(node->left->value)) return;
+        char ch[2] = { node->left->value[index], '\0' };
+        become_literal(node, ch, TYPE_STRING);
+    }
+}
+
+/* Fold a binary expression whose operands are int literals; as a leave
+   callback it runs after the operands, so nested constants fold first */
+static void fold_binary_literals(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    fold_string_literals(node);
+    if (node->type != AST_BINARY_EXPR) return;
+
+    if (node->left && node->right && 
+        node->left->type == AST_LITERAL && 
+        node->right->type == AST_LITERAL) {
+        
+        if (node->value) {
+            char *left_end, *right_end;
+            long left_val = strtol(node->left->value, &left_end, 10);
+            long right_val = strtol(node->right->value, &right_end, 10);
+            
+            if (*left_end == '\0' && *right_end == '\0') {
+                long result = 0;
+                
+                /* An overflowing constant is left for the runtime --overflow mode */
+                if (strcmp(node->value, "+") == 0) {
+                    if (__builtin_add_overflow(left_val, right_val, &result)) return;
+                } else if (strcmp(node->value, "-") == 0) {
+                    if (__builtin_sub_overflow(left_val, right_val, &result)) return;
+                } else if (strcmp(node->value, "*") == 0) {
+                    if (__builtin_mul_overflow(left_val, right_val, &result)) return;
+                } else if (strcmp(node->value, "/") == 0 && left_val == LONG_MIN && right_val == -1) {
+                    return;
+                } else if (strcmp(node->value, "/") == 0 && right_val != 0) {
+                    result = left_val / right_val;
+                } else {
+                    return;
+                }
+                if (!int_fits(result)) return;
+                
+                char folded_val[32];
+                snprintf(folded_val, sizeof(folded_val), "%ld", result);
+                become_literal(node, folded_val, TYPE_INT);
+            }
+        }
+    }
+}
+
+static void optimize_constant_folding(ASTNode *node) {
+    ASTVisitor folder = { .leave = fold_binary_literals };
+    ast_walk(node, &folder);
+}
+
+static bool is_numeric_literal(ASTNode *node, double value) {
+    if (!node || node->type != AST_LITERAL || !node->value) return false;
+    if (node->data_type != TYPE_INT && node->data_type != TYPE_FLOAT) return false;
+    char *end;
+    double v = strtod(node->value, &end);
+    return *end == '\0' && v == value;
+}
+
+/* Replace a binary node by one of its operands, discarding the other. The
+   result keeps the source range of the whole expression it stands for. */
+static void replace_with_operand(ASTNode *node, ASTNode *keep) {
+    ASTNode *drop = keep == node->left ? node->right : node->left;
+    ASTNode *next = node->next;
+    SourceSpan span = ast_span(node);
+    parser_free_ast(drop);
+    free(node->value);
+    *node = *keep;
+    node->next = next;
+    node->line = span.line;
+    node->column = span.column;
+    node->end_line = span.end_line;
+    node->end_column = span.end_column;
+    free(keep);
+}
+
+/* Algebraic identities (x + 0, x * 1, x * 0, ...). Integer identities are
+   always exact. x - 0.0, x * 1.0 and x / 1.0 are exact in IEEE arithmetic
+   too, but x + 0.0 turns -0.0 into 0.0 and x * 0.0 loses NaN, infinity and
+   the sign of zero, so those float rewrites only run under --fast-math. */
+static void simplify_identity(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    if (node->type != AST_BINARY_EXPR || !node->value || !node->left || !node->right) return;
+    ASTNode *l = node->left, *r = node->right;
+    if ((l->data_type != TYPE_INT && l->data_type != TYPE_FLOAT) ||
+        (r->data_type != TYPE_INT && r->data_type != TYPE_FLOAT)) return;
+
+    bool is_float = node->data_type == TYPE_FLOAT;
+    bool unsafe_ok = !is_float || g_fast_math;
+    const char *op = node->value;
+
+    if (strcmp(op, "+") == 0) {
+        if (!unsafe_ok) return;
+        if (is_numeric_literal(r, 0) && l->data_type == node->data_type) replace_with_operand(node, l);
+        else if (is_numeric_literal(l, 0) && r->data_type == node->data_type) replace_with_operand(node, r);
+    } else if (strcmp(op, "-") == 0) {
+        if (is_numeric_literal(r, 0) && l->data_type == node->data_type) replace_with_operand(node, l);
+    } else if (strcmp(op, "*") == 0) {
+        if (is_numeric_literal(r, 1) && l->data_type == node->data_type) replace_with_operand(node, l);
+        else if (is_numeric_literal(l, 1) && r->data_type == node->data_type) replace_with_operand(node, r);
+        else if (!unsafe_ok) return;
+        else if (is_numeric_literal(r, 0) && r->data_type == node->data_type && is_node_pure(l)) replace_with_operand(node, r);
+        else if (is_numeric_literal(l, 0) && l->data_type == node->data_type && is_node_pure(r)) replace_with_operand(node, l);
+    } else if (strcmp(op, "/") == 0) {
+        if (is_numeric_literal(r, 1) && l->data_type == node->data_type) replace_with_operand(node, l);
+    }
+}
+
+static void optimize_algebraic(ASTNode *node) {
+    ASTVisitor simplifier = { .leave = simplify_identity };
+    ast_walk(node, &simplifier);
+}
+
+/* Loop-invariant code motion (subc -O3). An expression inside a while or
+   for loop that only reads names the loop never assigns is computed once,
+   into a temporary declared just before the loop. The loop may run zero
+   times, so only arithmetic, comparisons and logic that cannot fail are
+   moved: no division, and nothing under --overflow check. A loop that calls
+   anything but a builtin is left alone, since the call could change the
+   globals the expression reads. */
+typedef struct {
+    const char **names;     /* assigned or declared somewhere in the loop */
+    int count;
+    int capacity;
+    bool unknown_calls;
+} LoopWrites;
+
+typedef struct {
+    ASTNode *expr;          /* the expression moved out of the loop */
+    char *temp;
+} HoistedExpr;
+
+typedef struct {
+    HoistedExpr *items;
+    int count;
+    int capacity;
+} HoistList;
+
+static bool g_licm = false;
+static int g_licm_temp_count = 0;
+
+void optimizer_set_licm(bool enabled) {
+    g_licm = enabled;
+}
+
+static void loop_writes_add(LoopWrites *w, const char *name) {
+    if (!name) return;
+    if (w->count == w->capacity) {
+        int capacity = w->capacity ? w->capacity * 2 : 8;
+        const char **names = realloc(w->names, capacity * sizeof(char*));
+        if (!names) {
+            w->unknown_calls = true;    /* cannot track: move nothing */
+            return;
+        }
+        w->names = names;
+        w->capacity = capacity;
+    }
+    w->names[w->count++] = name;
+}
+
+static bool loop_writes_has(const LoopWrites *w, const char *name) {
+    for (int i = 0; i < w->count; i++) {
+        if (strcmp(w->names[i], name) == 0) return true;
+    }
+    return false;
+}
+
+static bool is_side_effect_free_builtin(const char *name) {
+    return name && (strcmp(name, "print") == 0 || strcmp(name, "len") == 0 ||
+                    strcmp(name, "str") == 0 || strcmp(name, "select") == 0 ||
+                    string_builtin_lookup(name) >= 0);
+}
+
+static bool licm_note_write(ASTVisitor *visitor, ASTNode *node) {
+    LoopWrites *w = visitor->data;
+    switch (node->type) {
+        case AST_ASSIGN_STMT: {
+            ASTNode *target = node->left;
+            while (target && target->type != AST_IDENTIFIER) target = target->left;
+            if (target) loop_writes_add(w, target->value);
+            else w->unknown_calls = true;
+            break;
+        }
+        case AST_VAR_DECL:
+        case AST_CONST_DECL:
+        case AST_FOR_STMT:
+            loop_writes_add(w, node->value);
+            break;
+        case AST_DESTRUCTURE_DECL:
+            for (int i = 0; i < node->child_count; i++) loop_writes_add(w, node->children[i]->value);
+            break;
+        case AST_CALL_EXPR:
+            if (!is_side_effect_free_builtin(node->value)) w->unknown_calls = true;
+            break;
+        default:
+            break;
+    }
+    return true;
+}
+
+static void licm_collect_writes(ASTNode *node, LoopWrites *w) {
+    ASTVisitor collector = { .enter = licm_note_write, .data = w };
+    ast_walk(node, &collector);
+}
+
+static bool licm_scalar(const ASTNode *node) {
+    return !node->optional &&
+           (node->data_type == TYPE_INT || node->data_type == TYPE_FLOAT || node->data_type == TYPE_BOOL);
+}
+
+static bool licm_safe_operator(const ASTNode *node) {
+    static const char *const arithmetic[] = {"+", "-", "*", NULL};
+    static const char *const other[] = {"<", "<=", ">", ">=", "==", "!=", "&&", "||", "!", NULL};
+    if (!node->value) return false;
+    for (int i = 0; other[i]; i++) {
+        if (strcmp(node->value, other[i]) == 0) return true;
+    }
+    for (int i = 0; arithmetic[i]; i++) {
+        if (strcmp(node->value, arithmetic[i]) == 0) return overflow_mode() != OVERFLOW_CHECK;
+    }
+    return false;
+}
+
+/* Reads no name that the loop writes, and cannot fail; sets *reads_name if
+   it reads any name at all (all-literal expressions are folding's job) */
+static bool licm_invariant(const ASTNode *node, const LoopWrites *w, bool *reads_name) {
+    if (!node || !licm_scalar(node)) return false;
+    switch (node->type) {
+        case AST_LITERAL:
+            return true;
+        case AST_IDENTIFIER:
+            *reads_name = true;
+            return node->value && !loop_writes_has(w, node->value);
+        case AST_UNARY_EXPR:
+            return licm_safe_operator(node) && !node->right && licm_invariant(node->left, w, reads_name);
+        case AST_BINARY_EXPR:
+            return licm_safe_operator(node) && licm_invariant(node->left, w, reads_name) &&
+                   licm_invariant(node->right, w, reads_name);
+        default:
+            return false;
+    }
+}
+
+static bool licm_same_expr(const ASTNode *a, const ASTNode *b) {
+    if (!a || !b) return a == b;
+    if (a->type != b->type || a->data_type != b->data_type) return false;
+    if ((a->value == NULL) != (b->value == NULL)) return false;
+    if (a->value && strcmp(a->value, b->value) != 0) return false;
+    return licm_same_expr(a->left, b->left) && licm_same_expr(a->right, b->right);
+}
+
+/* Turn `node` into a read of a temporary holding its value; an equal
+   expression already moved out of this loop shares its temporary */
+static void licm_hoist_expr(ASTNode *node, HoistList *list) {
+    SourceSpan span = ast_span(node);
+    const char *temp = NULL;
+    for (int i = 0; i < list->count && !temp; i++) {
+        if (licm_same_expr(list->items[i].expr, node)) temp = list->items[i].temp;
+    }
+    if (temp) {
+        parser_free_ast(node->left);
+        parser_free_ast(node->right);
+        free(node->value);
+    } else {
+        if (list->count == list->capacity) {
+            int capacity = list->capacity ? list->capacity * 2 : 4;
+            HoistedExpr *items = realloc(list->items, capacity * sizeof(HoistedExpr));
+            if (!items) return;
+            list->items = items;
+            list->capacity = capacity;
+        }
+        ASTNode *expr = malloc(sizeof(ASTNode));
+        char name[32];
+        /* '$' cannot appear in a SUB identifier, so no user name mangles to this */
+        snprintf(name, sizeof(name), "sub_licm$%d", g_licm_temp_count);
+        char *copy = strdup(name);
+        if (!expr || !copy) {
+            free(expr);
+            free(copy);
+            return;
+        }
+        g_licm_temp_count++;
+        *expr = *node;
+        expr->next = NULL;
+        list->items[list->count].expr = expr;
+        list->items[list->count].temp = copy;
+        list->count++;
+        temp = copy;
+    }
+    /* The read stands for the whole original expression */
+    node->type = AST_IDENTIFIER;
+    node->value = strdup(temp);
+    node->left = NULL;
+    node->right = NULL;
+    node->line = span.line;
+    node->column = span.column;
+    node->end_line = span.end_line;
+    node->end_column = span.end_column;
+}
+
+static void licm_hoist_from(ASTNode *node, const LoopWrites *w, HoistList *list) {
+    if (!node || node->type == AST_FUNCTION_DECL || node->type == AST_ARROW_FUNCTION) return;
+    bool reads_name = false;
+    if ((node->type == AST_BINARY_EXPR || node->type == AST_UNARY_EXPR) &&
+        licm_invariant(node, w, &reads_name) && reads_name) {
+        licm_hoist_expr(node, list);
+        return;
+    }
+    for (int i = 0; i < node->child_count; i++) {
+        /* A bare expression statement is dead code, not something to move */
+        ASTNode *child = node->children[i];
+        if ((node->type == AST_BLOCK || node->type == AST_PROGRAM) &&
+            (child->type == AST_BINARY_EXPR || child->type == AST_UNARY_EXPR)) continue;
+        licm_hoist_from(child, w, list);
+    }
+    licm_hoist_from(node->left, w, list);
+    licm_hoist_from(node->right, w, list);
+    licm_hoist_from(node->condition, w, list);
+    if (node->type != AST_BLOCK && node->type != AST_PROGRAM) licm_hoist_from(node->body, w, list);
+}
+
+/* Declare the temporaries of `list` in `block` just before its statement at
+   `index`, keeping the children array and the statement chain in step */
+static int licm_insert_decls(ASTNode *block, int index, HoistList *list) {
+    if (list->count == 0) return 0;
+    ASTNode **children = realloc(block->children, (block->child_count + list->count) * sizeof(ASTNode*));
+    if (!children) return 0;
+    block->children = children;
+    memmove(&children[index + list->count], &children[index],
+            (block->child_count - index) * sizeof(ASTNode*));
+    ASTNode *loop = children[index + list->count];
+    for (int i = 0; i < list->count; i++) {
+        ASTNode *expr = list->items[i].expr;
+        ASTNode *decl = calloc(1, sizeof(ASTNode));
+        decl->type = AST_VAR_DECL;
+        decl->value = list->items[i].temp;
+        decl->right = expr;
+        decl->data_type = expr->data_type;
+        SourceSpan span = ast_span(expr);
+        decl->line = span.line;
+        decl->column = span.column;
+        list->items[i].temp = NULL;
+        children[index + i] = decl;
+    }
+    for (int i = index > 0 ? index - 1 : 0; i < index + list->count; i++) {
+        children[i]->next = children[i + 1];
+    }
+    if (block->body == loop) block->body = children[index];
+    block->child_count += list->count;
+    return list->count;
+}
+
+/* #[cold] code is not worth the extra temporaries or copies */
+static bool skip_cold_functions(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    return node->type != AST_FUNCTION_DECL || !ast_has_attribute(node, "cold");
+}
+
+/* As a leave callback, inner loops are done first, so their temporaries
+   can move further out */
+static void hoist_block_invariants(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    if (node->type != AST_BLOCK && node->type != AST_PROGRAM) return;
+
+    for (int i = 0; i < node->child_count; i++) {
+        ASTNode *loop = node->children[i];
+        if (loop->type != AST_WHILE_STMT && loop->type != AST_FOR_STMT) continue;
+
+        LoopWrites writes = {NULL, 0, 0, false};
+        licm_collect_writes(loop, &writes);
+        if (!writes.unknown_calls) {
+            HoistList list = {NULL, 0, 0};
+            /* A for loop evaluates its range once already */
+            if (loop->type == AST_WHILE_STMT) licm_hoist_from(loop->condition, &writes, &list);
+            licm_hoist_from(loop->body, &writes, &list);
+            i += licm_insert_decls(node, i, &list);
+            for (int j = 0; j < list.count; j++) free(list.items[j].temp);
+            free(list.items);
+        }
+        free(writes.names);
+    }
+}
+
+static void optimize_loop_invariants(ASTNode *node) {
+    ASTVisitor hoister = { .enter = skip_cold_functions, .leave = hoist_block_invariants };
+    ast_walk(node, &hoister);
+}
+
+/* Loop unrolling (-O3): `for i in a..b` whose bounds are integer literals
+   and that runs at most UNROLL_MAX_TRIPS times becomes that many copies of
+   its body, with i replaced by its value in each one. The body may not
+   break, continue, assign or redeclare i, or declare anything at its top
+   level (the copies share one scope), and all the copies together stay
+   within UNROLL_MAX_STATEMENTS statements. */
+#define UNROLL_MAX_TRIPS 8
+#define UNROLL_MAX_STATEMENTS 32
+
+static bool g_unroll = false;
+
+void optimizer_set_unrolling(bool enabled) {
+    g_unroll = enabled;
+}
+
+static bool is_unroll_blocker(const ASTNode *node, const void *data) {
+    (void)data;
+    return node->type == AST_BREAK_STMT || node->type == AST_CONTINUE_STMT ||
+           node->type == AST_FUNCTION_DECL || node->type == AST_ARROW_FUNCTION;
+}
+
+static bool unroll_blocked(ASTNode *node) {
+    return ast_find(node, is_unroll_blocker, NULL) != NULL;
+}
+
+static bool int_literal_value(const ASTNode *node, long long *value) {
+    if (!node || node->type != AST_LITERAL || node->data_type != TYPE_INT || !node->value) return false;
+    char *end = NULL;
+    *value = strtoll(node->value, &end, 10);
+    return end != node->value && *end == '\0';
+}
+
+/* The trip range of a loop that may be unrolled, else false */
+static bool unroll_bounds(const ASTNode *loop, long long *start, long long *end) {
+    if (loop->type != AST_FOR_STMT || !loop->value || loop->child_count != 1 || !loop->body) return false;
+    const ASTNode *range = loop->children[0];
+    if (range->type != AST_RANGE_EXPR) return false;
+    if (range->right) {
+        if (!int_literal_value(range->left, start) || !int_literal_value(range->right, end)) return false;
+    } else {
+        *start = 0;
+        if (!int_literal_value(range->left, end)) return false;
+    }
+    long long trips = *end > *start ? *end - *start : 0;
+    int statements = loop->body->child_count;
+    if (trips > UNROLL_MAX_TRIPS || trips * statements > UNROLL_MAX_STATEMENTS) return false;
+    for (int i = 0; i < statements; i++) {
+        ASTNodeType type = loop->body->children[i]->type;
+        if (type == AST_VAR_DECL || type == AST_CONST_DECL || type == AST_DESTRUCTURE_DECL) return false;
+    }
+    if (unroll_blocked(loop->body)) return false;
+    LoopWrites writes = {NULL, 0, 0, false};
+    licm_collect_writes(loop->body, &writes);
+    bool writes_counter = loop_writes_has(&writes, loop->value);
+    free(writes.names);
+    return !writes_counter;
+}
+
+/* parser_clone_ast() copies a block's statement chain and its children
+   separately; point the chain back at the children so every pass sees
+   the same nodes */
+static bool relink_block(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    if (node->child_count > 0 && node->body != node->children[0]) {
+        parser_free_ast(node->body);
+        node->body = node->children[0];
+        for (int i = 0; i < node->child_count; i++) {
+            node->children[i]->next = i + 1 < node->child_count ? node->children[i + 1] : NULL;
+        }
+    }
+    return true;
+}
+
+static void unroll_relink(ASTNode *node) {
+    ASTVisitor relinker = { .visit[AST_BLOCK] = relink_block };
+    ast_walk(node, &relinker);
+}
+
+typedef struct {
+    const char *name;
+    const char *value;
+} Substitution;
+
+static bool substitute_identifier(ASTVisitor *visitor, ASTNode *node) {
+    const Substitution *sub = visitor->data;
+    if (node->value && strcmp(node->value, sub->name) == 0) become_literal(node, sub->value, TYPE_INT);
+    return false;
+}
+
+static void unroll_substitute(ASTNode *node, const char *name, const char *value) {
+    Substitution sub = { name, value };
+    ASTVisitor substituter = { .visit[AST_IDENTIFIER] = substitute_identifier, .data = &sub };
+    ast_walk(node, &substituter);
+}
+
+/* Replace block->children[index] with the n statements in `stmts` */
+static void splice_statements(ASTNode *block, int index, ASTNode **stmts, int n) {
+    int count = block->child_count - 1 + n;
+    ASTNode **children = malloc((count > 0 ? count : 1) * sizeof(ASTNode*));
+    if (!children) return;
+    memcpy(children, block->children, index * sizeof(ASTNode*));
+    memcpy(children + index, stmts, n * sizeof(ASTNode*));
+    memcpy(children + index + n, block->children + index + 1,
+           (block->child_count - index - 1) * sizeof(ASTNode*));
+    ASTNode *old = block->children[index];
+    free(block->children);
+    block->children = children;
+    block->child_count = count;
+    for (int i = 0; i < count; i++) children[i]->next = i + 1 < count ? children[i + 1] : NULL;
+    block->body = count > 0 ? children[0] : NULL;
+    old->next = NULL;
+    parser_free_ast(old);
+}
+
+/* As a leave callback, inner loops are done first, so an outer loop sees
+   their unrolled form */
+static void unroll_block_loops(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    if (node->type != AST_BLOCK && node->type != AST_PROGRAM) return;
+
+    for (int i = 0; i < node->child_count; i++) {
+        ASTNode *loop = node->children[i];
+        long long start, end;
+        if (!unroll_bounds(loop, &start, &end)) continue;
+        int statements = loop->body->child_count;
+        int n = end > start ? (int)(end - start) * statements : 0;
+        ASTNode **copies = malloc((n > 0 ? n : 1) * sizeof(ASTNode*));
+        if (!copies) continue;
+        int made = 0;
+        for (long long v = start; v < end; v++) {
+            char text[32];
+            snprintf(text, sizeof(text), "%lld", v);
+            for (int s = 0; s < statements; s++) {
+                ASTNode *stmt = loop->body->children[s];
+                ASTNode *saved = stmt->next;
+                stmt->next = NULL;
+                ASTNode *copy = parser_clone_ast(stmt);
+                stmt->next = saved;
+                if (!copy) continue;
+                unroll_relink(copy);
+                unroll_substitute(copy, loop->value, text);
+                copies[made++] = copy;
+            }
+        }
+        splice_statements(node, i, copies, made);
+        free(copies);
+        i += made - 1;
+    }
+}
+
+static void optimize_unroll_loops(ASTNode *node) {
+    ASTVisitor unroller = { .enter = skip_cold_functions, .leave = unroll_block_loops };
+    ast_walk(node, &unroller);
+}
+
+/* Inlining (-O2 and above): a call to a function whose whole body is
+   `return expr`, where expr only combines literals and the function's own
+   parameters, becomes expr with the arguments put in for the parameters.
+   The arguments must be pure, since a parameter may be used more than once
+   or not at all, and must have the parameter's type. #[inline] lifts the
+   size limit, #[no_inline] and #[cold] on the callee rule it out, and no
+   call inside a #[cold] function is inlined. */
+static bool g_inline = false;
+
+void optimizer_set_inlining(bool enabled) {
+    g_inline = enabled;
+}
+
+/* Largest expression (in nodes) inlined without #[inline]; twice that when
+   the caller is #[hot] */
+#define INLINE_MAX_NODES 8
+
+typedef struct {
+    ASTNode **funcs;         /* top-level functions, by declaration order */
+    int count;
+    const char **nested;     /* names declared below the top level */
+    int nested_count;
+    const ASTNode *caller;   /* function being rewritten, or NULL at top level */
+} InlineContext;
+
+static int inline_param_index(const ASTNode *func, const char *name) {
+    for (int i = 0; name && i < func->child_count; i++) {
+        if (func->children[i]->value && strcmp(func->children[i]->value, name) == 0) return i;
+    }
+    return -1;
+}
+
+/* Node count of an expression built from literals, the function's own
+   parameters and operators, or -1 if it uses anything else */
+static int inline_expr_size(const ASTNode *expr, const ASTNode *func) {
+    if (!expr) return 0;
+    switch (expr->type) {
+        case AST_LITERAL:
+            return 1;
+        case AST_IDENTIFIER:
+            return inline_param_index(func, expr->value) >= 0 ? 1 : -1;
+        case AST_BINARY_EXPR:
+        case AST_UNARY_EXPR: {
+            int l = inline_expr_size(expr->left, func);
+            int r = inline_expr_size(expr->right, func);
+            return l < 0 || r < 0 ? -1 : 1 + l + r;
+        }
+        default:
+            return -1;
+    }
+}
+
+/* The expression `func` returns, if its body is nothing but that return */
+static ASTNode* inline_body(const ASTNode *func) {
+    ASTNode *stmt = block_first(func->body);
+    if (!stmt || stmt->next || func->body->child_count > 1 || stmt->type != AST_RETURN_STMT ||
+        stmt->optional || func->optional || func->tuple) return NULL;
+    return stmt->right;
+}
+
+/* Calls are matched by name, so a name that a nested function, variable or
+   parameter also declares may not mean the top-level function at the call;
+   such calls are left alone */
+static ASTNode* inline_callee(const InlineContext *ctx, const ASTNode *call) {
+    for (int i = 0; i < ctx->nested_count; i++) {
+        if (strcmp(ctx->nested[i], call->value) == 0) return NULL;
+    }
+    ASTNode *found = NULL;
+    for (int i = 0; i < ctx->count; i++) {
+        if (strcmp(ctx->funcs[i]->value, call->value) != 0) continue;
+        if (found) return NULL;   /* declared twice; leave it to the backend */
+        found = ctx->funcs[i];
+    }
+    return found;
+}
+
+/* Put clones of the arguments in place of the parameters in expr. What is
+   left of the callee's body takes the call's source span. */
+static void inline_substitute(ASTNode *expr, const ASTNode *func, const ASTNode *call) {
+    if (!expr) return;
+    if (expr->type == AST_IDENTIFIER) {
+        ASTNode *const *args = call->children;
+        ASTNode *arg = parser_clone_ast(args[inline_param_index(func, expr->value)]);
+        if (!arg) return;
+        free(expr->value);
+        *expr = *arg;
+        free(arg);
+        return;
+    }
+    expr->line = call->line;
+    expr->column = call->column;
+    expr->end_line = call->end_line;
+    expr->end_column = call->end_column;
+    inline_substitute(expr->left, func, call);
+    inline_substitute(expr->right, func, call);
+}
+
+static void inline_call(InlineContext *ctx, ASTNode *call) {
+    if (!call->value || (ctx->caller && ast_has_attribute(ctx->caller, "cold"))) return;
+    ASTNode *func = inline_callee(ctx, call);
+    if (!func || ast_has_attribute(func, "no_inline") || ast_has_attribute(func, "cold")) return;
+    ASTNode *expr = inline_body(func);
+    if (!expr || call->child_count != func->child_count || expr->data_type != call->data_type) return;
+
+    int limit = INLINE_MAX_NODES * (ctx->caller && ast_has_attribute(ctx->caller, "hot") ? 2 : 1);
+    int size = inline_expr_size(expr, func);
+    if (size < 0 || (size > limit && !ast_has_attribute(func, "inline"))) return;
+    for (int i = 0; i < call->child_count; i++) {
+        ASTNode *arg = call->children[i], *param = func->children[i];
+        if (!is_node_pure(arg) || param->optional || arg->data_type != param->data_type ||
+            (param->data_type != TYPE_INT && param->data_type != TYPE_FLOAT &&
+             param->data_type != TYPE_BOOL)) return;
+    }
+
+    ASTNode *inlined = parser_clone_ast(expr);
+    if (!inlined) return;
+    inline_substitute(inlined, func, call);
+    for (int i = 0; i < call->child_count; i++) parser_free_ast(call->children[i]);
+    free(call->children);
+    free(call->value);
+    free(call->metadata);
+    ASTNode *next = call->next;   /* the call may be a statement */
+    *call = *inlined;
+    call->next = next;
+    free(inlined);
+}
+
+static bool inline_visit_function(ASTVisitor *visitor, ASTNode *node) {
+    InlineContext *ctx = visitor->data;
+    const ASTNode *outer = ctx->caller;
+    ctx->caller = node;
+    ast_walk_operands(node, visitor);
+    ctx->caller = outer;
+    return false;
+}
+
+static void inline_leave(ASTVisitor *visitor, ASTNode *node) {
+    if (node->type == AST_CALL_EXPR) inline_call(visitor->data, node);
+}
+
+static bool inline_collect_nested(ASTVisitor *visitor, ASTNode *node) {
+    InlineContext *ctx = visitor->data;
+    bool declares = node->type == AST_FUNCTION_DECL || node->type == AST_VAR_DECL ||
+                    node->type == AST_CONST_DECL || node->type == AST_PARAM_DECL;
+    if (declares && node->value) {
+        const char **grown = realloc(ctx->nested, sizeof(char*) * (ctx->nested_count + 1));
+        if (!grown) return false;
+        ctx->nested = grown;
+        ctx->nested[ctx->nested_count++] = node->value;
+    }
+    return true;
+}
+
+static void optimize_inline_calls(ASTNode *program) {
+    InlineContext ctx = {NULL, 0, NULL, 0, NULL};
+    ASTVisitor collector = { .enter = inline_collect_nested, .data = &ctx };
+    for (int i = 0; i < program->child_count; i++) {
+        ASTNode *stmt = program->children[i];
+        ast_walk_operands(stmt, &collector);
+        if (stmt->type != AST_FUNCTION_DECL || !stmt->value) continue;
+        ASTNode **grown = realloc(ctx.funcs, sizeof(ASTNode*) * (ctx.count + 1));
+        if (!grown) break;
+        ctx.funcs = grown;
+        ctx.funcs[ctx.count++] = stmt;
+    }
+    if (ctx.count > 0) {
+        ASTVisitor visitor = {0};
+        visitor.visit[AST_FUNCTION_DECL] = inline_visit_function;
+        visitor.leave = inline_leave;
+        visitor.data = &ctx;
+        ast_walk(program, &visitor);
+    }
+    free(ctx.funcs);
+    free(ctx.nested);
+}
+
+/* Passes run in order: inlining (-O2 and up), integer constant folding,
+   algebraic identities (float-unsafe ones gated on --fast-math),
+   loop-invariant code motion (-O3 only, or -O2 inside #[hot] functions;
+   never inside #[cold] ones), then dead code removal. */
+void optimize_c_output(ASTNode *node) {
+    if (!node) return;
+    
+    if (g_inline && node->type == AST_PROGRAM) optimize_inline_calls(node);
+    optimize_constant_folding(node);
+    optimize_algebraic(node);
+    if (g_unroll) {
+        optimize_unroll_loops(node);
+        /* The copies put literals where the counter was */
+        optimize_constant_folding(node);
+        optimize_algebraic(node);
+    }
+    if (g_licm) {
+        optimize_loop_invariants(node);
+    } else if (g_inline) {
+        for (int i = 0; i < node->child_count; i++) {
+            ASTNode *stmt = node->children[i];
+            if (stmt->type == AST_FUNCTION_DECL && ast_has_attribute(stmt, "hot")) {
+                optimize_loop_invariants(stmt);
+            }
+        }
+    }
+    optimize_remove_dead_code(node);
+}
+
+/* Integers are stored as long; with --int-width 32 every + - * goes
+   through a helper that overflows at 32 bits, even under wrap */
+static bool uses_overflow_helpers(void) {
+    return overflow_mode() != OVERFLOW_WRAP || int_width() == 32;
+}
+
+static const char* overflow_helper_suffix(void) {
+    switch (overflow_mode()) {
+        case OVERFLOW_CHECK:    return "checked";
+        case OVERFLOW_SATURATE: return "saturating";
+        default:                return "wrapping";
+    }
+}
+
+/* Overflow-aware helper for an integer + - * / %, or NULL for anything else */
+static const char* overflow_helper(ASTNode *node) {
+    static char name[32];
+    if (!node->value || node->data_type != TYPE_INT ||
+        node->left->data_type != TYPE_INT || !node->right || node->right->data_type != TYPE_INT) {
+        return NULL;
+    }
+    const char *op = strcmp(node->value, "+") == 0 ? "add" :
+                     strcmp(node->value, "-") == 0 ? "sub" :
+                     strcmp(node->value, "*") == 0 ? "mul" :
+                     strcmp(node->value, "/") == 0 ? "div" :
+                     strcmp(node->value, "%") == 0 ? "mod" : NULL;
+    if (!op) return NULL;
+    snprintf(name, sizeof(name), "sub_%s_%s", op, overflow_helper_suffix());
+    return name;
+}
+
+/* Prelude helpers for --overflow check|saturate, and for wrap at a 32-bit
+   --int-width; 64-bit wrap uses plain operators */
+static void generate_overflow_helpers(StringBuilder *sb) {
+    static const struct { const char *name, *op, *saturate_if; } ops[] = {
+        {"add", "+", "b > 0"},
+        {"sub", "-", "b < 0"},
+        {"mul", "*", "(a < 0) == (b < 0)"},
+    };
+    if (!uses_overflow_helpers()) return;
+    const char *result_type = int_width() == 32 ? "int" : "long";
+    const char *max = int_width() == 32 ? "INT_MAX" : "LONG_MAX";
+    const char *min = int_width() == 32 ? "INT_MIN" : "LONG_MIN";
+    sb_append(sb, "/* Integer Overflow Helpers */\n");
+    sb_append(sb, "#include <limits.h>\n");
+    for (size_t i = 0; i < sizeof(ops) / sizeof(ops[0]); i++) {
+        if (overflow_mode() == OVERFLOW_WRAP) {
+            sb_append(sb, "static inline long sub_%s_wrapping(long a, long b) {\n", ops[i].name);
+            sb_append(sb, "    %s r;\n", result_type);
+            sb_append(sb, "    __builtin_%s_overflow(a, b, &r);\n", ops[i].name);
+            sb_append(sb, "    return r;\n");
+            sb_append(sb, "}\n");
+        } else if (overflow_mode() == OVERFLOW_CHECK) {
+            sb_append(sb, "static inline long sub_%s_checked(long a, long b, int line) {\n", ops[i].name);
+            sb_append(sb, "    %s r;\n", result_type);
+            sb_append(sb, "    if (__builtin_%s_overflow(a, b, &r)) {\n", ops[i].name);
+            sb_append(sb, "        fprintf(stderr, \"[line %%d] Runtime error: Integer overflow in %%ld %s %%ld\\n\", line, a, b);\n", ops[i].op);
+            sb_append(sb, "        exit(EXIT_FAILURE);\n");
+            sb_append(sb, "    }\n");
+            sb_append(sb, "    return r;\n");
+            sb_append(sb, "}\n");
+        } else {
+            sb_append(sb, "static inline long sub_%s_saturating(long a, long b) {\n", ops[i].name);
+            sb_append(sb, "    %s r;\n", result_type);
+            sb_append(sb, "    if (__builtin_%s_overflow(a, b, &r)) return %s ? %s : %s;\n",
+                      ops[i].name, ops[i].saturate_if, max, min);
+            sb_append(sb, "    return r;\n");
+            sb_append(sb, "}\n");
+        }
+    }
+    /* MIN / -1 is the one quotient that does not fit; MIN % -1 is 0, but
+       traps in C like the division does */
+    const char *line_param = overflow_mode() == OVERFLOW_CHECK ? ", int line" : "";
+    const char *suffix = overflow_helper_suffix();
+    sb_append(sb, "static inline long sub_div_%s(long a, long b%s) {\n", suffix, line_param);
+    sb_append(sb, "    if (a == %s && b == -1) {\n", min);
+    if (overflow_mode() == OVERFLOW_CHECK) {
+        sb_append(sb, "        fprintf(stderr, \"[line %%d] Runtime error: Integer overflow in %%ld / %%ld\\n\", line, a, b);\n");
+        sb_append(sb, "        exit(EXIT_FAILURE);\n");
+    } else {
+        sb_append(sb, "        return %s;\n", overflow_mode() == OVERFLOW_SATURATE ? max : min);
+    }
+    sb_append(sb, "    }\n");
+    sb_append(sb, "    return a / b;\n");
+    sb_append(sb, "}\n");
+    sb_append(sb, "static inline long sub_mod_%s(long a, long b%s) {\n", suffix, line_param);
+    if (overflow_mode() == OVERFLOW_CHECK) sb_append(sb, "    (void)line;\n");
+    sb_append(sb, "    return b == -1 ? 0 : a %% b;\n");
+    sb_append(sb, "}\n");
+    /* int(x) results narrow the same way at a 32-bit --int-width */
+    if (int_width() == 32) {
+        if (overflow_mode() == OVERFLOW_CHECK) {
+            sb_append(sb, "static inline long sub_int_narrow_checked(long v, int line) {\n");
+            sb_append(sb, "    if (v < INT_MIN || v > INT_MAX) {\n");
+            sb_append(sb, "        fprintf(stderr, \"[line %%d] Runtime error: Integer overflow converting %%ld to a 32-bit int\\n\", line, v);\n");
+            sb_append(sb, "        exit(EXIT_FAILURE);\n");
+            sb_append(sb, "    }\n");
+            sb_append(sb, "    return v;\n");
+            sb_append(sb, "}\n");
+        } else if (overflow_mode() == OVERFLOW_SATURATE) {
+            sb_append(sb, "static inline long sub_int_narrow_saturating(long v) { return v > INT_MAX ? INT_MAX : v < INT_MIN ? INT_MIN : v; }\n");
+        } else {
+            sb_append(sb, "static inline long sub_int_narrow_wrapping(long v) { return (int)(unsigned int)v; }\n");
+        }
+    }
+    sb_append(sb, "\n");
+}
+
+/* Optional values (T?). Strings use a NULL pointer; scalars are a
+   {has, value} pair named after their C type, e.g. sub_opt_long. */
+static ASTNode *g_c_program = NULL;    /* for the parameters of a callee */
+static ASTNode *g_c_function = NULL;   /* function whose body is being generated */
+
+static bool is_optional(const ASTNode *node, const void *data) {
+    (void)data;
+    return node->optional || node->type == AST_UNWRAP_EXPR;
+}
+
+static bool ast_uses_optionals(ASTNode *node) {
+    return ast_find(node, is_optional, NULL) != NULL;
+}
+
+static void generate_optional_helpers(StringBuilder *sb, ASTNode *ast) {
+    if (!ast_uses_optionals(ast)) return;
+    sb_append(sb, "/* Optional Values */\n");
+    sb_append(sb, "#define SUB_OPTIONAL(T) \\\n");
+    sb_append(sb, "    typedef struct { bool has; T value; } sub_opt_##T; \\\n");
+    sb_append(sb, "    static inline sub_opt_##T sub_some_##T(T v) { sub_opt_##T o = {true, v}; return o; } \\\n");
+    sb_append(sb, "    static inline T sub_unwrap_##T(sub_opt_##T o, int line) { \\\n");
+    sb_append(sb, "        if (!o.has) { fprintf(stderr, \"[line %%d] Runtime error: Unwrapped a null optional value\\n\", line); exit(EXIT_FAILURE); } \\\n");
+    sb_append(sb, "        return o.value; \\\n");
+    sb_append(sb, "    }\n");
+    sb_append(sb, "SUB_OPTIONAL(long)\n");
+    sb_append(sb, "SUB_OPTIONAL(double)\n");
+    sb_append(sb, "SUB_OPTIONAL(bool)\n");
+    sb_append(sb, "static inline char* sub_unwrap_str(char *s, int line) {\n");
+    sb_append(sb, "    if (!s) { fprintf(stderr, \"[line %%d] Runtime error: Unwrapped a null optional value\\n\", line); exit(EXIT_FAILURE); }\n");
+    sb_append(sb, "    return s;\n");
+    sb_append(sb, "}\n\n");
+}
+
+/* select(c, a, b) is a call, so C evaluates every argument before picking
+   one; gcc lowers the pick to a conditional move */
+static void generate_select_helpers(StringBuilder *sb, ASTNode *ast) {
+    static const char *const types[][2] = {
+        {"long", "long"}, {"double", "double"}, {"bool", "bool"}, {"str", "char*"},
+    };
+    if (!ast_calls_function(ast, "select")) return;
+    sb_append(sb, "/* select() */\n");
+    for (size_t i = 0; i < sizeof(types) / sizeof(types[0]); i++) {
+        sb_append(sb, "static inline %s sub_select_%s(bool c, %s a, %s b) { return c ? a : b; }\n",
+                  types[i][1], types[i][0], types[i][1], types[i][1]);
+    }
+    sb_append(sb, "\n");
+}
+
+/* C type behind an optional of `type`; NULL for strings, which are pointers */
+static const char* optional_c_type(DataType type) {
+    switch (type) {
+        case TYPE_STRING: return NULL;
+        case TYPE_FLOAT:  return "double";
+        case TYPE_BOOL:   return "bool";
+        default:          return "long";
+    }
+}
+
+static bool is_null_literal(ASTNode *node) {
+    return node && node->type == AST_LITERAL && node->data_type == TYPE_NULL;
+}
+
+/* `value` stored into an optional of `type`: null, a plain value to wrap,
+   or another optional */
+static void generate_optional_value(StringBuilder *sb, ASTNode *value, DataType type) {
+    const char *ctype = optional_c_type(type);
+    if (is_null_literal(value)) {
+        if (ctype) sb_append(sb, "(sub_opt_%s){false, 0}", ctype);
+        else sb_append(sb, "NULL");
+    } else if (value->optional || !ctype) {
+        generate_expression(sb, value);
+    } else {
+        sb_append(sb, "sub_some_%s(", ctype);
+        generate_expression(sb, value);
+        sb_append(sb, ")");
+    }
+}
+
+static ASTNode* find_function_decl(const char *name) {
+    for (ASTNode *stmt = g_c_program ? block_first(g_c_program) : NULL; stmt; stmt = stmt->next) {
+        if (stmt->type == AST_FUNCTION_DECL && stmt->value && strcmp(stmt->value, name) == 0) {
+            return stmt;
         }
-        default:
-            for (int i = 0; i < node->child_count; i++) {
-                optimize_remove_dead_code(node->children[i]);
-            }
-            if (node->left) optimize_remove_dead_code(node->left);
-            if (node->right) optimize_remove_dead_code(node->right);
-            if (node->condition) optimize_remove_dead_code(node->condition);
-            if (node->body) optimize_remove_dead_code(node->body);
-            break;
     }
+    return NULL;
 }
 
-static void optimize_constant_folding(ASTNode *node) {
-    if (!node) return;
-    
-    if (node->type == AST_BINARY_EXPR) {
-        optimize_constant_folding(node->left);
-        optimize_constant_folding(node->right);
-        
-        if (node->left && node->right && 
-            node->left->type == AST_LITERAL && 
-            node->right->type == AST_LITERAL) {
-            
-            if (node->value) {
-                char *left_end, *right_end;
-                long left_val = strtol(node->left->value, &left_end, 10);
-                long right_val = strtol(node->right->value, &right_end, 10);
-                
-                if (*left_end == '\0' && *right_end == '\0') {
-                    long result = 0;
-                    
-                    if (strcmp(node->value, "+") == 0) {
-                        result = left_val + right_val;
-                    } else if (strcmp(node->value, "-") == 0) {
-                        result = left_val - right_val;
-                    } else if (strcmp(node->value, "*") == 0) {
-                        result = left_val * right_val;
-                    } else if (strcmp(node->value, "/") == 0 && right_val != 0) {
-                        result = left_val / right_val;
-                    } else {
-                        return;
-                    }
-                    
-                    char folded_val[32];
-                    snprintf(folded_val, sizeof(folded_val), "%ld", result);
-                    
-                    node->type = AST_LITERAL;
-                    free(node->value);
-                    node->value = strdup(folded_val);
-                    node->data_type = TYPE_INT;
-
-                    parser_free_ast(node->left);
-                    parser_free_ast(node->right);
-                    node->left = NULL;
-                    node->right = NULL;
-                }
-            }
+/* String pool: a literal used more than once is emitted once, as
+   `static char sub_str_N[]` (the same type as the literal), and referenced
+   by name. N counts pooled strings in order of first use. */
+typedef struct PooledString {
+    const char *text;
+    bool repeated;    /* used by more than one literal */
+    int index;        /* -1 unless repeated */
+    struct PooledString *next;
+} PooledString;
+
+static PooledString *g_string_pool = NULL;
+static int g_match_count = 0;    /* numbers the sub_match_N temporaries */
+static int g_unpack_count = 0;   /* numbers the sub_unpack_N temporaries */
+
+static void string_pool_free(void) {
+    while (g_string_pool) {
+        PooledString *next = g_string_pool->next;
+        free(g_string_pool);
+        g_string_pool = next;
+    }
+}
+
+static bool string_pool_collect(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    if (node->data_type != TYPE_STRING || !node->value) return true;
+    PooledString **slot = &g_string_pool;
+    while (*slot && strcmp((*slot)->text, node->value) != 0) slot = &(*slot)->next;
+    if (*slot) {
+        (*slot)->repeated = true;
+    } else if ((*slot = calloc(1, sizeof(PooledString)))) {
+        (*slot)->text = node->value;
+        (*slot)->index = -1;
+    }
+    return true;
+}
+
+static void generate_string_pool(StringBuilder *sb, ASTNode *ast) {
+    string_pool_free();
+    ASTVisitor collector = { .visit[AST_LITERAL] = string_pool_collect };
+    ast_walk(ast, &collector);
+    int count = 0;
+    for (PooledString *p = g_string_pool; p; p = p->next) {
+        if (!p->repeated) continue;
+        if (count == 0) sb_append(sb, "/* String Pool */\n");
+        p->index = count++;
+        sb_append(sb, "static char sub_str_%d[] = ", p->index);
+        sb_append_c_string(sb, p->text);
+        sb_append(sb, ";\n");
+    }
+    if (count > 0) sb_append(sb, "\n");
+}
+
+static int string_pool_index(const char *text) {
+    for (PooledString *p = g_string_pool; p; p = p->next) {
+        if (strcmp(p->text, text) == 0) return p->index;
+    }
+    return -1;
+}
+
+/* Tuples are structs named after their element types, e.g. sub_tuple_ls
+   for (int, string), with fields _0, _1, ... A nested tuple is spelled
+   t<elements>_ inside the name of the outer one. */
+typedef struct TupleType {
+    char *name;
+    struct TupleType *next;
+} TupleType;
+
+static TupleType *g_tuple_types = NULL;
+
+static void tuple_types_free(void) {
+    while (g_tuple_types) {
+        TupleType *next = g_tuple_types->next;
+        free(g_tuple_types->name);
+        free(g_tuple_types);
+        g_tuple_types = next;
+    }
+}
+
+static void tuple_codes(const ASTNode *tuple, char *buf, size_t size) {
+    size_t len = strlen(buf);
+    for (int i = 0; i < tuple->child_count && len + 1 < size; i++) {
+        const ASTNode *elem = tuple->children[i];
+        switch (elem->data_type) {
+            case TYPE_TUPLE:
+                if (!elem->tuple) break;
+                buf[len++] = 't';
+                buf[len] = '\0';
+                tuple_codes(elem->tuple, buf, size);
+                len = strlen(buf);
+                if (len + 1 < size) buf[len++] = '_';
+                break;
+            case TYPE_STRING: buf[len++] = 's'; break;
+            case TYPE_FLOAT:  buf[len++] = 'd'; break;
+            case TYPE_BOOL:   buf[len++] = 'b'; break;
+            default:          buf[len++] = 'l'; break;
         }
-    } else {
-        for (int i = 0; i < node->child_count; i++) {
-            optimize_constant_folding(node->children[i]);
+        buf[len] = '\0';
+    }
+}
+
+static void c_tuple_name(const ASTNode *tuple, char *buf, size_t size) {
+    snprintf(buf, size, "sub_tuple_");
+    tuple_codes(tuple, buf, size);
+}
+
+/* C type of a value of `type`; `tuple` describes tuple types */
+static void c_value_type(DataType type, const ASTNode *tuple, char *buf, size_t size) {
+    if (type == TYPE_TUPLE && tuple) c_tuple_name(tuple, buf, size);
+    else if (type == TYPE_STRING) snprintf(buf, size, "char*");
+    else if (type == TYPE_FLOAT) snprintf(buf, size, "double");
+    else if (type == TYPE_BOOL) snprintf(buf, size, "bool");
+    else snprintf(buf, size, "long");
+}
+
+/* The struct for `tuple` and, with `helpers`, its sub_str_from_ helper,
+   after those of any nested tuples; each distinct tuple type is emitted once */
+static void generate_tuple_type(StringBuilder *sb, const ASTNode *tuple, bool helpers) {
+    char name[128];
+    c_tuple_name(tuple, name, sizeof(name));
+    for (TupleType *t = g_tuple_types; t; t = t->next) {
+        if (strcmp(t->name, name) == 0) return;
+    }
+    for (int i = 0; i < tuple->child_count; i++) {
+        const ASTNode *elem = tuple->children[i];
+        if (elem->data_type == TYPE_TUPLE && elem->tuple) generate_tuple_type(sb, elem->tuple, helpers);
+    }
+    TupleType *entry = calloc(1, sizeof(TupleType));
+    if (!entry || !(entry->name = strdup(name))) {
+        free(entry);
+        return;
+    }
+    entry->next = g_tuple_types;
+    g_tuple_types = entry;
+
+    char fmt[512] = "(", args[1024] = "";
+    sb_append(sb, "typedef struct {");
+    for (int i = 0; i < tuple->child_count; i++) {
+        const ASTNode *elem = tuple->children[i];
+        char type[128], arg[192];
+        c_value_type(elem->data_type, elem->tuple, type, sizeof(type));
+        sb_append(sb, " %s _%d;", type, i);
+        const char *spec = "%s";
+        if (elem->data_type == TYPE_TUPLE && elem->tuple) {
+            snprintf(arg, sizeof(arg), "sub_str_from_%s(t._%d)", type + strlen("sub_"), i);
+        } else if (elem->data_type == TYPE_BOOL) {
+            snprintf(arg, sizeof(arg), "t._%d ? \"true\" : \"false\"", i);
+        } else {
+            spec = elem->data_type == TYPE_STRING ? "%s" : elem->data_type == TYPE_FLOAT ? "%g" : "%ld";
+            snprintf(arg, sizeof(arg), "t._%d", i);
         }
-        if (node->left) optimize_constant_folding(node->left);
-        if (node->right) optimize_constant_folding(node->right);
-        if (node->condition) optimize_constant_folding(node->condition);
-        if (node->body) optimize_constant_folding(node->body);
+        size_t len = strlen(fmt);
+        snprintf(fmt + len, sizeof(fmt) - len, "%s%s", i > 0 ? ", " : "", spec);
+        len = strlen(args);
+        snprintf(args + len, sizeof(args) - len, ", %s", arg);
     }
+    strncat(fmt, ")", sizeof(fmt) - strlen(fmt) - 1);
+    sb_append(sb, " } %s;\n", name);
+    if (!helpers) return;
+    sb_append(sb, "static inline char* sub_str_from_%s(%s t) {\n", name + strlen("sub_"), name);
+    sb_append(sb, "    int n = snprintf(NULL, 0, \"%s\"%s);\n", fmt, args);
+    sb_append(sb, "    char *out = malloc((size_t)n + 1);\n");
+    sb_append(sb, "    SUB_CHECK_NULL(out, \"tuple to string\");\n");
+    sb_append(sb, "    snprintf(out, (size_t)n + 1, \"%s\"%s);\n", fmt, args);
+    sb_append(sb, "    return out;\n");
+    sb_append(sb, "}\n");
 }
 
-/* Optimization stub - can be expanded */
-void optimize_c_output(ASTNode *node) {
-    if (!node) return;
-    
-    optimize_constant_folding(node);
-    optimize_remove_dead_code(node);
+typedef struct {
+    StringBuilder *sb;
+    bool helpers;
+} TupleCollector;
+
+static bool tuple_type_visit(ASTVisitor *visitor, ASTNode *node) {
+    TupleCollector *collector = visitor->data;
+    if (!g_tuple_types) sb_append(collector->sb, "/* Tuples */\n");
+    generate_tuple_type(collector->sb, node, collector->helpers);
+    return true;
+}
+
+/* Define every tuple type the program builds, in order of first use */
+static void tuple_types_collect(StringBuilder *sb, ASTNode *ast, bool helpers) {
+    TupleCollector collector = { sb, helpers };
+    ASTVisitor visitor = { .visit[AST_TUPLE_EXPR] = tuple_type_visit, .data = &collector };
+    ast_walk(ast, &visitor);
+}
+
+static void generate_tuple_types(StringBuilder *sb, ASTNode *ast) {
+    tuple_types_free();
+    tuple_types_collect(sb, ast, true);
+    if (g_tuple_types) sb_append(sb, "\n");
 }
 
 /* Helper to generate indentation */
@@ -236,6 +1602,78 @@ static ASTNode* block_first(ASTNode *node) {
     return NULL;
 }
 
+/* `ret name(params)` for a function declaration, shared by its definition
+   and the --crate-type lib header */
+static void generate_function_signature(StringBuilder *sb, ASTNode *node) {
+    /* Determine return type from AST */
+    const char *ret_type = "void";
+    char tuple_type[128];
+    if (node->tuple) {
+        c_tuple_name(node->tuple, tuple_type, sizeof(tuple_type));
+        ret_type = tuple_type;
+    }
+    else if (node->data_type == TYPE_INT) ret_type = "long";
+    else if (node->data_type == TYPE_FLOAT) ret_type = "double";
+    else if (node->data_type == TYPE_STRING) ret_type = "char*";
+    else if (node->data_type == TYPE_BOOL) ret_type = "bool";
+    else {
+        /* If data_type is unknown, scan body for return statements */
+        ASTNode *body_stmt = node->body ? (node->body->body ? node->body->body : 
+            (node->body->children ? node->body->children[0] : node->body)) : NULL;
+        while (body_stmt) {
+            if (body_stmt->type == AST_RETURN_STMT) {
+                if (body_stmt->right) {
+                    if (body_stmt->right->data_type == TYPE_STRING) { ret_type = "char*"; break; }
+                    else if (body_stmt->right->data_type == TYPE_FLOAT) { ret_type = "double"; break; }
+                    else if (body_stmt->right->data_type == TYPE_BOOL) { ret_type = "bool"; break; }
+                    else { ret_type = "long"; break; } /* default to long for numeric returns */
+                }
+                break;
+            }
+            body_stmt = body_stmt->next;
+        }
+    }
+    if (node->optional && strcmp(ret_type, "char*") != 0) {
+        sb_append(sb, "sub_opt_%s %s(", optional_c_type(node->data_type),
+                  mangle(node->value ? node->value : "func", MANGLE_C));
+    } else {
+        sb_append(sb, "%s %s(", ret_type, mangle(node->value ? node->value : "func", MANGLE_C));
+    }
+    for (int i = 0; i < node->child_count; i++) {
+        ASTNode *param = node->children[i];
+        if (i > 0) sb_append(sb, ", ");
+        if (param->optional) {
+            const char *ctype = optional_c_type(param->data_type);
+            if (ctype) sb_append(sb, "sub_opt_%s ", ctype);
+            else sb_append(sb, "char *");
+        } else {
+            sb_append(sb, "long ");
+        }
+        sb_append(sb, "%s", mangle(param->value ? param->value : "arg", MANGLE_C));
+    }
+    /* `f()` would declare a function with unspecified parameters */
+    if (node->child_count == 0) sb_append(sb, "void");
+    sb_append(sb, ")");
+}
+
+/* `const T name` for a constant declaration */
+static void generate_const_declarator(StringBuilder *sb, ASTNode *node) {
+    const char *name = mangle(node->value ? node->value : "const", MANGLE_C);
+    if (node->data_type == TYPE_TUPLE && node->tuple) {
+        char type[128];
+        c_tuple_name(node->tuple, type, sizeof(type));
+        sb_append(sb, "const %s %s", type, name);
+    } else if (node->data_type == TYPE_STRING) {
+        sb_append(sb, "const char *%s", name);
+    } else if (node->data_type == TYPE_BOOL) {
+        sb_append(sb, "const bool %s", name);
+    } else if (node->data_type == TYPE_FLOAT) {
+        sb_append(sb, "const double %s", name);
+    } else {
+        sb_append(sb, "const long %s", name);
+    }
+}
+
 /* Generate expression code */
 static void generate_expression(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
@@ -244,7 +1682,9 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
         case AST_LITERAL:
             if (node->value) {
                 if (node->data_type == TYPE_STRING) {
-                    sb_append(sb, "\"%s\"", node->value);
+                    int pooled = string_pool_index(node->value);
+                    if (pooled >= 0) sb_append(sb, "sub_str_%d", pooled);
+                    else sb_append_c_string(sb, node->value);
                 } else if (node->data_type == TYPE_BOOL) {
                     if (strcmp(node->value, "true") == 0) {
                         sb_append(sb, "true");
@@ -259,11 +1699,29 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
             
         case AST_IDENTIFIER:
             if (node->value) {
-                sb_append(sb, "%s", node->value);
+                sb_append(sb, "%s", mangle(node->value, MANGLE_C));
             }
             break;
             
         case AST_BINARY_EXPR:
+            if (node->left && node->value && (strcmp(node->value, "==") == 0 || strcmp(node->value, "!=") == 0) &&
+                (is_null_literal(node->left) || is_null_literal(node->right))) {
+                /* x == null / x != null on an optional */
+                ASTNode *other = is_null_literal(node->left) ? node->right : node->left;
+                bool eq = strcmp(node->value, "==") == 0;
+                if (!other->optional) {
+                    sb_append(sb, eq ? "(0)" : "(1)");
+                } else if (other->data_type == TYPE_STRING) {
+                    sb_append(sb, "(");
+                    generate_expression(sb, other);
+                    sb_append(sb, eq ? " == NULL)" : " != NULL)");
+                } else {
+                    sb_append(sb, eq ? "(!(" : "((");
+                    generate_expression(sb, other);
+                    sb_append(sb, ").has)");
+                }
+                break;
+            }
             if (node->left) {
                 /* String concatenation: "str" + expr or expr + "str" */
                 if (node->value && strcmp(node->value, "+") == 0 &&
@@ -303,6 +1761,14 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
                     sb_append(sb, ", ");
                     generate_expression(sb, node->right);
                     sb_append(sb, ") %s)", cop);
+                } else if (uses_overflow_helpers() && overflow_helper(node)) {
+                    /* --overflow check|saturate or --int-width 32: route through the prelude helpers */
+                    sb_append(sb, "%s(", overflow_helper(node));
+                    generate_expression(sb, node->left);
+                    sb_append(sb, ", ");
+                    generate_expression(sb, node->right);
+                    if (overflow_mode() == OVERFLOW_CHECK) sb_append(sb, ", %d", node->line);
+                    sb_append(sb, ")");
                 } else {
                     sb_append(sb, "(");
                     generate_expression(sb, node->left);
@@ -314,6 +1780,15 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
             break;
             
         case AST_UNARY_EXPR:
+            if (uses_overflow_helpers() && node->value && strcmp(node->value, "-") == 0 &&
+                node->right && node->right->data_type == TYPE_INT) {
+                bool check = overflow_mode() == OVERFLOW_CHECK;
+                sb_append(sb, "sub_sub_%s(0, ", overflow_helper_suffix());
+                generate_expression(sb, node->right);
+                if (check) sb_append(sb, ", %d", node->line);
+                sb_append(sb, ")");
+                break;
+            }
             sb_append(sb, "(%s", node->value ? node->value : "-");
             if (node->right) {
                 generate_expression(sb, node->right);
@@ -330,10 +1805,12 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
                         for (int i = 0; i < node->child_count; i++) {
                             ASTNode *arg = node->children[i];
                             const char *fmt = "%ld";
-                            if (arg->data_type == TYPE_INT) fmt = "%ld";
+                            if (arg->optional || arg->data_type == TYPE_NULL) fmt = "%s";
+                            else if (arg->data_type == TYPE_INT) fmt = "%ld";
                             else if (arg->data_type == TYPE_FLOAT) fmt = "%g";
                             else if (arg->data_type == TYPE_BOOL) fmt = "%d";
                             else if (arg->data_type == TYPE_STRING) fmt = "%s";
+                            else if (arg->data_type == TYPE_TUPLE) fmt = "%s";
                             else if (arg->type == AST_LITERAL && arg->value) {
                                 char *end;
                                 (void)strtol(arg->value, &end, 10);
@@ -351,7 +1828,32 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
                         }
                         sb_append(sb, "\", ");
                         for (int i = 0; i < node->child_count; i++) {
-                            generate_expression(sb, node->children[i]);
+                            ASTNode *arg = node->children[i];
+                            if (arg->optional) {
+                                /* Optionals print as their value or "null" */
+                                const char *ctype = optional_c_type(arg->data_type);
+                                sb_append(sb, "({");
+                                if (ctype) {
+                                    sb_append(sb, "sub_opt_%s _o = ", ctype);
+                                    generate_expression(sb, arg);
+                                    sb_append(sb, "; _o.has ? %s(_o.value) : \"null\";})",
+                                              arg->data_type == TYPE_FLOAT ? "sub_str_from_double" : "sub_str_from_long");
+                                } else {
+                                    sb_append(sb, "char *_o = ");
+                                    generate_expression(sb, arg);
+                                    sb_append(sb, "; _o ? _o : \"null\";})");
+                                }
+                            } else if (arg->data_type == TYPE_NULL) {
+                                sb_append(sb, "\"null\"");
+                            } else if (arg->data_type == TYPE_TUPLE && arg->tuple) {
+                                char name[128];
+                                c_tuple_name(arg->tuple, name, sizeof(name));
+                                sb_append(sb, "sub_str_from_%s(", name + strlen("sub_"));
+                                generate_expression(sb, arg);
+                                sb_append(sb, ")");
+                            } else {
+                                generate_expression(sb, arg);
+                            }
                             if (i + 1 < node->child_count) {
                                 sb_append(sb, ", ");
                             }
@@ -362,29 +1864,48 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
                     sb_append(sb, ")");
                 } else {
                     const char *fn = node->value;
-                    if (strcmp(fn, "float") == 0) {
-                        if (node->child_count > 0 && node->children[0]->data_type == TYPE_STRING) sb_append(sb, "sub_float_from_str(");
-                        else sb_append(sb, "(double)(");
-                    }
-                    else if (strcmp(fn, "int") == 0) {
-                        if (node->child_count > 0 && node->children[0]->data_type == TYPE_STRING) sb_append(sb, "sub_int_from_str(");
-                        else sb_append(sb, "(long)(");
-                    }
-                    else if (strcmp(fn, "str") == 0) {
+                    if (strcmp(fn, "str") == 0) {
                         if (node->child_count > 0) {
                             if (node->children[0]->data_type == TYPE_FLOAT) sb_append(sb, "sub_str_from_double(");
                             else if (node->children[0]->data_type == TYPE_STRING) sb_append(sb, "sub_strdup(");
+                            else if (node->children[0]->data_type == TYPE_TUPLE && node->children[0]->tuple) {
+                                char name[128];
+                                c_tuple_name(node->children[0]->tuple, name, sizeof(name));
+                                sb_append(sb, "sub_str_from_%s(", name + strlen("sub_"));
+                            }
                             else sb_append(sb, "sub_str_from_long(");
                         } else {
                             sb_append(sb, "sub_strdup(\"\")");
                         }
                     }
                     else if (strcmp(fn, "input") == 0) sb_append(sb, "sub_input(");
-                    else sb_append(sb, "%s(", fn);
+                    else if (string_builtin_lookup(fn) >= 0) sb_append(sb, "sub_%s(", fn);
+                    else if (strcmp(fn, "select") == 0 && node->child_count == 3) {
+                        sb_append(sb, "sub_select_%s(", node->data_type == TYPE_FLOAT ? "double" :
+                                                        node->data_type == TYPE_BOOL ? "bool" :
+                                                        node->data_type == TYPE_STRING ? "str" : "long");
+                    }
+                    else if (strcmp(fn, "len") == 0 && node->child_count == 1) {
+                        /* Array literals have a compile-time length; strings use strlen() */
+                        if (node->children[0]->type == AST_ARRAY_LITERAL) {
+                            sb_append(sb, "%dL", ast_array_length(node->children[0]));
+                            break;
+                        }
+                        sb_append(sb, "(long)strlen(");
+                    }
+                    else sb_append(sb, "%s(", mangle(fn, MANGLE_C));
                     
                     if (strcmp(fn, "str") != 0 || node->child_count > 0) {
+                        ASTNode *callee = find_function_decl(fn);
                         for (int i = 0; i < node->child_count; i++) {
-                            generate_expression(sb, node->children[i]);
+                            ASTNode *param = callee && i < callee->child_count ? callee->children[i] : NULL;
+                            if (param && param->optional) {
+                                generate_optional_value(sb, node->children[i],
+                                                        param->data_type != TYPE_UNKNOWN ? param->data_type
+                                                                                         : node->children[i]->data_type);
+                            } else {
+                                generate_expression(sb, node->children[i]);
+                            }
                             if (i + 1 < node->child_count) {
                                 sb_append(sb, ", ");
                             }
@@ -395,6 +1916,111 @@ static void generate_expression(StringBuilder *sb, ASTNode *node) {
             }
             break;
             
+        case AST_UNWRAP_EXPR: {
+            /* Implicit unwraps follow a null check; x? checks at runtime */
+            const char *ctype = optional_c_type(node->data_type);
+            if (!node->value) {
+                if (ctype) sb_append(sb, "(");
+                generate_expression(sb, node->left);
+                if (ctype) sb_append(sb, ").value");
+            } else {
+                if (ctype) sb_append(sb, "sub_unwrap_%s(", ctype);
+                else sb_append(sb, "sub_unwrap_str(");
+                generate_expression(sb, node->left);
+                sb_append(sb, ", %d)", node->line);
+            }
+            break;
+        }
+
+        case AST_TUPLE_EXPR: {
+            char name[128];
+            c_tuple_name(node, name, sizeof(name));
+            sb_append(sb, "((%s){", name);
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expression(sb, node->children[i]);
+            }
+            sb_append(sb, "})");
+            break;
+        }
+
+        case AST_MATCH_EXPR: {
+            /* The value is evaluated once into sub_match_N, then the arms
+               become a ?: chain; semantic analysis guarantees the last arm
+               catches whatever the others do not */
+            int id = g_match_count++;
+            DataType subject = node->left ? node->left->data_type : TYPE_INT;
+            bool numeric = node->data_type == TYPE_INT || node->data_type == TYPE_FLOAT;
+            sb_append(sb, "({ %s sub_match_%d = ",
+                      subject == TYPE_STRING ? "const char *" : optional_c_type(subject), id);
+            generate_expression(sb, node->left);
+            sb_append(sb, "; ");
+            if (numeric) sb_append(sb, "(%s)(", optional_c_type(node->data_type));
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    if (subject == TYPE_STRING) {
+                        sb_append(sb, "strcmp(sub_match_%d, ", id);
+                        generate_expression(sb, arm->left);
+                        sb_append(sb, ") == 0 ? ");
+                    } else {
+                        sb_append(sb, "sub_match_%d == ", id);
+                        generate_expression(sb, arm->left);
+                        sb_append(sb, " ? ");
+                    }
+                }
+                sb_append(sb, "(");
+                generate_expression(sb, arm->right);
+                sb_append(sb, ")");
+                if (i < node->child_count - 1) sb_append(sb, " : ");
+            }
+            sb_append(sb, numeric ? "); })" : "; })");
+            break;
+        }
+
+        case AST_CAST_EXPR: {
+            DataType from = node->right ? node->right->data_type : TYPE_AUTO;
+            switch (node->data_type) {
+                case TYPE_INT:
+                    if (int_width() == 32) {
+                        sb_append(sb, "sub_int_narrow_%s(", overflow_helper_suffix());
+                        sb_append(sb, from == TYPE_STRING ? "sub_int_from_str(" : "(long)(");
+                        generate_expression(sb, node->right);
+                        sb_append(sb, ")");
+                        if (overflow_mode() == OVERFLOW_CHECK) sb_append(sb, ", %d", node->line);
+                        sb_append(sb, ")");
+                        return;
+                    }
+                    sb_append(sb, from == TYPE_STRING ? "sub_int_from_str(" : "(long)(");
+                    break;
+                case TYPE_FLOAT:
+                    sb_append(sb, from == TYPE_STRING ? "sub_float_from_str(" : "(double)(");
+                    break;
+                case TYPE_BOOL:
+                    sb_append(sb, "((");
+                    generate_expression(sb, node->right);
+                    sb_append(sb, ") != 0)");
+                    return;
+                case TYPE_STRING:
+                    if (from == TYPE_BOOL) {
+                        sb_append(sb, "sub_strdup((");
+                        generate_expression(sb, node->right);
+                        sb_append(sb, ") ? \"true\" : \"false\")");
+                        return;
+                    }
+                    if (from == TYPE_FLOAT) sb_append(sb, "sub_str_from_double(");
+                    else if (from == TYPE_STRING) sb_append(sb, "sub_strdup(");
+                    else sb_append(sb, "sub_str_from_long(");
+                    break;
+                default:
+                    sb_append(sb, "(");
+                    break;
+            }
+            generate_expression(sb, node->right);
+            sb_append(sb, ")");
+            break;
+        }
+            
         default:
             break;
     }
@@ -414,27 +2040,41 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
             
         case AST_VAR_DECL:
             indent_code(sb, indent);
-            if (node->data_type == TYPE_STRING) {
-                sb_append(sb, "char *%s", node->value ? node->value : "var");
+            if (node->optional) {
+                const char *ctype = optional_c_type(node->data_type);
+                if (ctype) sb_append(sb, "sub_opt_%s %s = ", ctype, mangle(node->value ? node->value : "var", MANGLE_C));
+                else sb_append(sb, "char *%s = ", mangle(node->value ? node->value : "var", MANGLE_C));
+                if (node->right) generate_optional_value(sb, node->right, node->data_type);
+                else sb_append(sb, ctype ? "(sub_opt_%s){false, 0}" : "NULL", ctype);
+            } else if (node->data_type == TYPE_TUPLE && node->tuple) {
+                char name[128];
+                c_tuple_name(node->tuple, name, sizeof(name));
+                sb_append(sb, "%s %s", name, mangle(node->value ? node->value : "var", MANGLE_C));
+                if (node->right) {
+                    sb_append(sb, " = ");
+                    generate_expression(sb, node->right);
+                }
+            } else if (node->data_type == TYPE_STRING) {
+                sb_append(sb, "char *%s", mangle(node->value ? node->value : "var", MANGLE_C));
                 if (node->right) {
                     sb_append(sb, " = sub_strdup(");
                     generate_expression(sb, node->right);
                     sb_append(sb, ")");
                 }
             } else if (node->data_type == TYPE_BOOL) {
-                sb_append(sb, "bool %s", node->value ? node->value : "var");
+                sb_append(sb, "bool %s", mangle(node->value ? node->value : "var", MANGLE_C));
                 if (node->right) {
                     sb_append(sb, " = ");
                     generate_expression(sb, node->right);
                 }
             } else if (node->data_type == TYPE_FLOAT) {
-                sb_append(sb, "double %s", node->value ? node->value : "var");
+                sb_append(sb, "double %s", mangle(node->value ? node->value : "var", MANGLE_C));
                 if (node->right) {
                     sb_append(sb, " = ");
                     generate_expression(sb, node->right);
                 }
             } else {
-                sb_append(sb, "long %s", node->value ? node->value : "var");
+                sb_append(sb, "long %s", mangle(node->value ? node->value : "var", MANGLE_C));
                 if (node->right) {
                     sb_append(sb, " = ");
                     generate_expression(sb, node->right);
@@ -445,53 +2085,24 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
             
         case AST_CONST_DECL:
             indent_code(sb, indent);
-            if (node->data_type == TYPE_STRING) {
-                sb_append(sb, "const char *%s", node->value ? node->value : "const");
-            } else if (node->data_type == TYPE_BOOL) {
-                sb_append(sb, "const bool %s", node->value ? node->value : "const");
-            } else if (node->data_type == TYPE_FLOAT) {
-                sb_append(sb, "const double %s", node->value ? node->value : "const");
-            } else {
-                sb_append(sb, "const long %s", node->value ? node->value : "const");
-            }
+            generate_const_declarator(sb, node);
             sb_append(sb, " = ");
             generate_expression(sb, node->right);
             sb_append(sb, ";\n");
             break;
             
         case AST_FUNCTION_DECL: {
-            /* Determine return type from AST */
-            const char *ret_type = "void";
-            if (node->data_type == TYPE_INT) ret_type = "long";
-            else if (node->data_type == TYPE_FLOAT) ret_type = "double";
-            else if (node->data_type == TYPE_STRING) ret_type = "char*";
-            else if (node->data_type == TYPE_BOOL) ret_type = "bool";
-            else {
-                /* If data_type is unknown, scan body for return statements */
-                ASTNode *body_stmt = node->body ? (node->body->body ? node->body->body : 
-                    (node->body->children ? node->body->children[0] : node->body)) : NULL;
-                while (body_stmt) {
-                    if (body_stmt->type == AST_RETURN_STMT) {
-                        if (body_stmt->right) {
-                            if (body_stmt->right->data_type == TYPE_STRING) { ret_type = "char*"; break; }
-                            else if (body_stmt->right->data_type == TYPE_FLOAT) { ret_type = "double"; break; }
-                            else if (body_stmt->right->data_type == TYPE_BOOL) { ret_type = "bool"; break; }
-                            else { ret_type = "long"; break; } /* default to long for numeric returns */
-                        }
-                        break;
-                    }
-                    body_stmt = body_stmt->next;
-                }
-            }
-            sb_append(sb, "\n%s %s(", ret_type, node->value ? node->value : "func");
-            for (int i = 0; i < node->child_count; i++) {
-                if (i > 0) sb_append(sb, ", ");
-                sb_append(sb, "long %s", node->children[i]->value ? node->children[i]->value : "arg");
-            }
-            sb_append(sb, ") {\n");
+            sb_append(sb, "\n");
+            if (ast_has_attribute(node, "no_inline")) sb_append(sb, "__attribute__((noinline)) ");
+            if (ast_has_attribute(node, "hot")) sb_append(sb, "__attribute__((hot)) ");
+            if (ast_has_attribute(node, "cold")) sb_append(sb, "__attribute__((cold)) ");
+            generate_function_signature(sb, node);
+            sb_append(sb, " {\n");
+            g_c_function = node;
             if (node->body) {
                 generate_node(sb, node->body, indent + 1);
             }
+            g_c_function = NULL;
             sb_append(sb, "}\n\n");
             break;
         }
@@ -522,7 +2133,7 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
         case AST_FOR_STMT:
             indent_code(sb, indent);
             {
-                const char *var = node->value ? node->value : "i";
+                const char *var = mangle(node->value ? node->value : "i", MANGLE_C);
                 if (node->children && node->child_count > 0 &&
                     node->children[0]->type == AST_RANGE_EXPR) {
                     ASTNode *range = node->children[0];
@@ -568,7 +2179,12 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
         case AST_RETURN_STMT:
             indent_code(sb, indent);
             sb_append(sb, "return");
-            if (node->right) {
+            if (node->right && node->optional) {
+                DataType type = g_c_function && g_c_function->data_type != TYPE_UNKNOWN
+                                ? g_c_function->data_type : node->right->data_type;
+                sb_append(sb, " ");
+                generate_optional_value(sb, node->right, type);
+            } else if (node->right) {
                 sb_append(sb, " ");
                 generate_expression(sb, node->right);
             }
@@ -585,7 +2201,8 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
             indent_code(sb, indent);
             generate_expression(sb, node->left);
             sb_append(sb, " = ");
-            generate_expression(sb, node->right);
+            if (node->optional) generate_optional_value(sb, node->right, node->data_type);
+            else generate_expression(sb, node->right);
             sb_append(sb, ";\n");
             break;
             
@@ -606,6 +2223,34 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
             }
             break;
             
+        case AST_DESTRUCTURE_DECL: {
+            /* Unpack through a temporary so the tuple is evaluated once */
+            const ASTNode *tuple = node->right ? node->right->tuple : NULL;
+            if (!tuple) break;
+            char name[128];
+            int id = ++g_unpack_count;
+            c_tuple_name(tuple, name, sizeof(name));
+            indent_code(sb, indent);
+            sb_append(sb, "%s sub_unpack_%d = ", name, id);
+            generate_expression(sb, node->right);
+            sb_append(sb, ";\n");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *target = node->children[i];
+                char type[128];
+                c_value_type(target->data_type, target->tuple, type, sizeof(type));
+                indent_code(sb, indent);
+                sb_append(sb, "%s%s %s = sub_unpack_%d._%d;\n", target->type == AST_CONST_DECL ? "const " : "",
+                          type, mangle(target->value, MANGLE_C), id, i);
+            }
+            break;
+        }
+
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "exit((int)(");
+            generate_expression(sb, node->right);
+            sb_append(sb, "));\n");
+            break;
         default:
             for (int i = 0; i < node->child_count; i++) {
                 generate_node(sb, node->children[i], indent);
@@ -616,24 +2261,30 @@ static void generate_node(StringBuilder *sb, ASTNode *node, int indent) {
 
 /* Generate C code from AST */
 static char* generate_c_code(ASTNode *ast) {
+    // Optimize first: folding can remove a construct C lacks, like "abc"[0]
+    optimize_c_output(ast);
+    if (!codegen_check_support(ast, "C")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
-    // Apply optimizations before code generation
-    optimize_c_output(ast);
-    
     // Generate standard headers (C99 compliant)
-    sb_append(sb, "/*\n");
-    sb_append(sb, " * Generated by SUB Language Compiler\n");
-    sb_append(sb, " * C99 Compliant Output\n");
-    sb_append(sb, " */\n\n");
+    if (codegen_banner()) {
+        sb_append(sb, "/*\n");
+        sb_append(sb, " * %s\n", codegen_banner());
+        sb_append(sb, " * C99 Compliant Output\n");
+        /* No timestamps or absolute paths beyond what the driver was given, so
+           the same input always produces the same file */
+        if (source_path()) sb_append(sb, " * Source: %s\n", source_path());
+        sb_append(sb, " */\n\n");
+    }
     
     sb_append(sb, "/* Standard Library Headers */\n");
     sb_append(sb, "#include <stdio.h>\n");
     sb_append(sb, "#include <stdlib.h>\n");
     sb_append(sb, "#include <string.h>\n");
     sb_append(sb, "#include <stdbool.h>\n");
-    sb_append(sb, "#include <stddef.h>\n\n");
+    sb_append(sb, "#include <stddef.h>\n");
+    sb_append(sb, "#include <ctype.h>\n\n");
     
     sb_append(sb, "/* Memory Management Helpers */\n");
     sb_append(sb, "#ifndef SUB_STRSAFE\n");
@@ -674,8 +2325,45 @@ static char* generate_c_code(ASTNode *ast) {
     sb_append(sb, "static inline double sub_float_from_str(const char* s) { return s ? atof(s) : 0.0; }\n");
     sb_append(sb, "static inline long sub_int_from_str(const char* s) { return s ? atol(s) : 0; }\n");
     sb_append(sb, "static inline char* sub_str_from_long(long v) { char buf[64]; snprintf(buf, sizeof(buf), \"%%ld\", v); return sub_strdup(buf); }\n");
-    sb_append(sb, "static inline char* sub_str_from_double(double v) { char buf[64]; snprintf(buf, sizeof(buf), \"%%g\", v); return sub_strdup(buf); }\n\n");
+    sb_append(sb, "static inline char* sub_str_from_double(double v) { char buf[64]; snprintf(buf, sizeof(buf), \"%%g\", v); return sub_strdup(buf); }\n");
+    sb_append(sb, "static inline char* sub_substr(const char* s, long start, long count) {\n");
+    sb_append(sb, "    long n = (long)strlen(s);\n");
+    sb_append(sb, "    if (start < 0 || count < 0 || start + count > n) {\n");
+    sb_append(sb, "        fprintf(stderr, \"substr(): start %%ld, length %%ld is out of range for a string of length %%ld\\n\", start, count, n);\n");
+    sb_append(sb, "        exit(EXIT_FAILURE);\n");
+    sb_append(sb, "    }\n");
+    sb_append(sb, "    char *out = malloc((size_t)count + 1);\n");
+    sb_append(sb, "    SUB_CHECK_NULL(out, \"substr()\");\n");
+    sb_append(sb, "    memcpy(out, s + start, (size_t)count);\n");
+    sb_append(sb, "    out[count] = '\\0';\n");
+    sb_append(sb, "    return out;\n");
+    sb_append(sb, "}\n");
+    sb_append(sb, "static inline long sub_index_of(const char* s, const char* t) { const char *p = strstr(s, t); return p ? (long)(p - s) : -1; }\n");
+    sb_append(sb, "static inline char* sub_to_upper(const char* s) { char *out = sub_strdup(s); for (char *p = out; p && *p; p++) *p = (char)toupper((unsigned char)*p); return out; }\n");
+    sb_append(sb, "static inline char* sub_to_lower(const char* s) { char *out = sub_strdup(s); for (char *p = out; p && *p; p++) *p = (char)tolower((unsigned char)*p); return out; }\n\n");
     
+    generate_overflow_helpers(sb);
+    generate_optional_helpers(sb, ast);
+    generate_select_helpers(sb, ast);
+    generate_string_pool(sb, ast);
+    generate_tuple_types(sb, ast);
+    g_match_count = 0;
+    g_unpack_count = 0;
+    g_c_program = ast;
+
+    /* A library has no main(); its constants live at file scope */
+    bool lib = crate_type() == CRATE_TYPE_LIB;
+    if (lib && ast && ast->type == AST_PROGRAM) {
+        bool first = true;
+        for (ASTNode *stmt = block_first(ast); stmt != NULL; stmt = stmt->next) {
+            if (stmt->type == AST_CONST_DECL) {
+                if (first) sb_append(sb, "/* Constants */\n");
+                first = false;
+                generate_node(sb, stmt, 0);
+            }
+        }
+    }
+
     /* Pass 1: Generate function declarations at file scope */
     if (ast && (ast->type == AST_PROGRAM || ast->type == AST_BLOCK)) {
         for (ASTNode *stmt = block_first(ast); stmt != NULL; stmt = stmt->next) {
@@ -685,6 +2373,12 @@ static char* generate_c_code(ASTNode *ast) {
         }
     }
     
+    if (lib) {
+        string_pool_free();
+        tuple_types_free();
+        return sb_to_string(sb);
+    }
+
     /* Pass 2: Wrap non-function top-level statements in main() */
     sb_append(sb, "int main(int argc, char *argv[]) {\n");
     sb_append(sb, "    (void)argc;\n");
@@ -700,6 +2394,8 @@ static char* generate_c_code(ASTNode *ast) {
     
     sb_append(sb, "    return EXIT_SUCCESS;\n");
     sb_append(sb, "}\n");
+    string_pool_free();
+    tuple_types_free();
     
     return sb_to_string(sb);
 }
@@ -710,7 +2406,7 @@ static char* generate_android(ASTNode *ast UNUSED) {
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
-    sb_append(sb, "// Android Java Code Generated from SUB Language\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n", codegen_banner());
     sb_append(sb, "package com.sublang.app;\n\n");
     sb_append(sb, "import android.app.Activity;\n");
     sb_append(sb, "import android.os.Bundle;\n");
@@ -736,7 +2432,7 @@ static char* generate_ios(ASTNode *ast UNUSED) {
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
-    sb_append(sb, "// iOS Swift Code Generated from SUB Language\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n", codegen_banner());
     sb_append(sb, "import UIKit\n\n");
     
     sb_append(sb, "class ViewController: UIViewController {\n");
@@ -766,7 +2462,7 @@ static void generate_js_expression(StringBuilder *sb, ASTNode *node) {
             }
             break;
         case AST_IDENTIFIER:
-            sb_append(sb, "%s", node->value ? node->value : "x");
+            sb_append(sb, "%s", mangle(node->value ? node->value : "x", MANGLE_JS));
             break;
         case AST_BINARY_EXPR:
             sb_append(sb, "(");
@@ -780,8 +2476,19 @@ static void generate_js_expression(StringBuilder *sb, ASTNode *node) {
                 sb_append(sb, "console.log(");
                 if (node->child_count > 0) generate_js_expression(sb, node->children[0]);
                 sb_append(sb, ")");
+            } else if (node->value && strcmp(node->value, "select") == 0 && node->child_count == 3) {
+                sb_append(sb, "((c, a, b) => c ? a : b)(");
+                for (int i = 0; i < 3; i++) {
+                    if (i > 0) sb_append(sb, ", ");
+                    generate_js_expression(sb, node->children[i]);
+                }
+                sb_append(sb, ")");
+            } else if (node->value && strcmp(node->value, "len") == 0 && node->child_count == 1) {
+                sb_append(sb, "(");
+                generate_js_expression(sb, node->children[0]);
+                sb_append(sb, ").length");
             } else {
-                sb_append(sb, "%s(", node->value ? node->value : "func");
+                sb_append(sb, "%s(", mangle(node->value ? node->value : "func", MANGLE_JS));
                 for (int i = 0; i < node->child_count; i++) {
                     if (i > 0) sb_append(sb, ", ");
                     generate_js_expression(sb, node->children[i]);
@@ -789,6 +2496,51 @@ static void generate_js_expression(StringBuilder *sb, ASTNode *node) {
                 sb_append(sb, ")");
             }
             break;
+        case AST_UNWRAP_EXPR:
+            generate_js_expression(sb, node->left);
+            break;
+        case AST_TUPLE_EXPR:
+            /* Tuples are arrays */
+            sb_append(sb, "[");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_js_expression(sb, node->children[i]);
+            }
+            sb_append(sb, "]");
+            break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "((sub_match) => ");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, "sub_match === ");
+                    generate_js_expression(sb, arm->left);
+                    sb_append(sb, " ? ");
+                }
+                sb_append(sb, "(");
+                generate_js_expression(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? ") : " : ")");
+            }
+            sb_append(sb, ")(");
+            generate_js_expression(sb, node->left);
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR:
+            if (node->data_type == TYPE_INT) {
+                bool from_str = node->right && node->right->data_type == TYPE_STRING;
+                sb_append(sb, from_str ? "parseInt(" : "Math.trunc(");
+                generate_js_expression(sb, node->right);
+                sb_append(sb, from_str ? ", 10)" : ")");
+            } else if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "((");
+                generate_js_expression(sb, node->right);
+                sb_append(sb, ") !== 0)");
+            } else {
+                sb_append(sb, node->data_type == TYPE_STRING ? "String(" : "Number(");
+                generate_js_expression(sb, node->right);
+                sb_append(sb, ")");
+            }
+            break;
         default:
             break;
     }
@@ -806,7 +2558,7 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_VAR_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "let %s", node->value ? node->value : "x");
+            sb_append(sb, "let %s", mangle(node->value ? node->value : "x", MANGLE_JS));
             if (node->right) {
                 sb_append(sb, " = ");
                 generate_js_expression(sb, node->right);
@@ -815,7 +2567,17 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_CONST_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "const %s = ", node->value ? node->value : "x");
+            sb_append(sb, "const %s = ", mangle(node->value ? node->value : "x", MANGLE_JS));
+            generate_js_expression(sb, node->right);
+            sb_append(sb, ";\n");
+            break;
+        case AST_DESTRUCTURE_DECL:
+            indent_code(sb, indent);
+            sb_append(sb, "%s [", node->children[0]->type == AST_CONST_DECL ? "const" : "let");
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", mangle(node->children[i]->value, MANGLE_JS));
+            }
+            sb_append(sb, "] = ");
             generate_js_expression(sb, node->right);
             sb_append(sb, ";\n");
             break;
@@ -833,10 +2595,10 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_FUNCTION_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "function %s(", node->value ? node->value : "func");
+            sb_append(sb, "function %s(", mangle(node->value ? node->value : "func", MANGLE_JS));
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
-                sb_append(sb, "%s", node->children[i]->value ? node->children[i]->value : "arg");
+                sb_append(sb, "%s", mangle(node->children[i]->value ? node->children[i]->value : "arg", MANGLE_JS));
             }
             sb_append(sb, ") {\n");
             if (node->body) generate_js_node(sb, node->body, indent + 1);
@@ -866,7 +2628,7 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_FOR_STMT: {
             indent_code(sb, indent);
-            const char *var = node->value ? node->value : "i";
+            const char *var = mangle(node->value ? node->value : "i", MANGLE_JS);
             if (node->children && node->child_count > 0 && node->children[0]->type == AST_RANGE_EXPR) {
                 ASTNode *range = node->children[0];
                 sb_append(sb, "for (let %s = ", var);
@@ -882,6 +2644,10 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
                     sb_append(sb, "0; %s < 10", var);
                 }
                 sb_append(sb, "; %s++) {\n", var);
+            } else if (node->condition) {
+                sb_append(sb, "for (const %s of ", var);
+                generate_js_expression(sb, node->condition);
+                sb_append(sb, ") {\n");
             } else {
                 sb_append(sb, "for (let %s = 0; %s < 10; %s++) {\n", var, var, var);
             }
@@ -917,6 +2683,7 @@ static void generate_js_node(StringBuilder *sb, ASTNode *node, int indent) {
 }
 
 static char* generate_web(ASTNode *ast) {
+    if (!codegen_check_support(ast, "web")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
@@ -931,7 +2698,7 @@ static char* generate_web(ASTNode *ast) {
     sb_append(sb, "    <h1>SUB Language Application</h1>\n");
     sb_append(sb, "    <div id='output'></div>\n");
     sb_append(sb, "    <script>\n");
-    sb_append(sb, "    // Generated from SUB Language Compiler\n");
+    if (codegen_banner()) sb_append(sb, "    // %s\n", codegen_banner());
     sb_append(sb, "    const _out = document.getElementById('output');\n");
     sb_append(sb, "    const _origLog = console.log;\n");
     sb_append(sb, "    console.log = function(...args) { _out.textContent += args.join(' ') + '\\n'; _origLog.apply(console, args); };\n\n");
@@ -990,3 +2757,44 @@ char* codegen_generate(ASTNode *ast, Platform platform) {
 char* codegen_generate_c(ASTNode *ast, Platform platform) {
     return generate_c_code(ast);
 }
+
+/* The header that goes with C generated under --crate-type lib: the types
+   its signatures use, and a declaration of every function and constant */
+char* codegen_generate_c_header(ASTNode *ast, const char *guard) {
+    if (!ast) return NULL;
+    StringBuilder *sb = sb_create();
+    if (!sb) return NULL;
+
+    if (codegen_banner()) {
+        sb_append(sb, "/* %s */\n", codegen_banner());
+        if (source_path()) sb_append(sb, "/* Source: %s */\n", source_path());
+    }
+    sb_append(sb, "#ifndef %s\n", guard);
+    sb_append(sb, "#define %s\n\n", guard);
+    sb_append(sb, "#include <stdbool.h>\n\n");
+    if (ast_uses_optionals(ast)) {
+        sb_append(sb, "/* Optional Values */\n");
+        sb_append(sb, "typedef struct { bool has; long value; } sub_opt_long;\n");
+        sb_append(sb, "typedef struct { bool has; double value; } sub_opt_double;\n");
+        sb_append(sb, "typedef struct { bool has; bool value; } sub_opt_bool;\n\n");
+    }
+    tuple_types_free();
+    tuple_types_collect(sb, ast, false);
+    if (g_tuple_types) sb_append(sb, "\n");
+    tuple_types_free();
+
+    /* Names that start with `_` are internal, as in the --emit-manifest exports */
+    for (ASTNode *stmt = block_first(ast); stmt != NULL; stmt = stmt->next) {
+        if (!stmt->value || stmt->value[0] == '_') continue;
+        if (stmt->type == AST_CONST_DECL) {
+            sb_append(sb, "extern ");
+            generate_const_declarator(sb, stmt);
+            sb_append(sb, ";\n");
+        } else if (stmt->type == AST_FUNCTION_DECL) {
+            generate_function_signature(sb, stmt);
+            sb_append(sb, ";\n");
+        }
+    }
+    sb_append(sb, "\n#endif /* %s */\n", guard);
+    return sb_to_string(sb);
+}
@@ -117,52 +117,13 @@ static void generate_expr_cpp(StringBuilder *sb, ASTNode *node);
 static void generate_node_cpp(StringBuilder *sb, ASTNode *node, int indent);
 
 /* Check if AST uses string types (to decide on #include <string>) */
-static bool ast_needs_string(ASTNode *node) {
-    if (!node) return false;
-    if (node->type == AST_LITERAL && node->data_type == TYPE_STRING) return true;
-    if (ast_needs_string(node->left)) return true;
-    if (ast_needs_string(node->right)) return true;
-    if (ast_needs_string(node->condition)) return true;
-    if (ast_needs_string(node->body)) return true;
-    if (ast_needs_string(node->next)) return true;
-    if (node->children) {
-        for (int i = 0; i < node->child_count; i++) {
-            if (ast_needs_string(node->children[i])) return true;
-        }
-    }
-    return false;
-}
-
-static bool ast_needs_vector(ASTNode *node) {
-    if (!node) return false;
-    if (node->type == AST_ARRAY_LITERAL) return true;
-    if (ast_needs_vector(node->left)) return true;
-    if (ast_needs_vector(node->right)) return true;
-    if (ast_needs_vector(node->condition)) return true;
-    if (ast_needs_vector(node->body)) return true;
-    if (ast_needs_vector(node->next)) return true;
-    if (node->children) {
-        for (int i = 0; i < node->child_count; i++) {
-            if (ast_needs_vector(node->children[i])) return true;
-        }
-    }
-    return false;
+static bool is_string_literal(const ASTNode *node, const void *data) {
+    (void)data;
+    return node->type == AST_LITERAL && node->data_type == TYPE_STRING;
 }
 
-static bool ast_needs_map(ASTNode *node) {
-    if (!node) return false;
-    if (node->type == AST_OBJECT_LITERAL) return true;
-    if (ast_needs_map(node->left)) return true;
-    if (ast_needs_map(node->right)) return true;
-    if (ast_needs_map(node->condition)) return true;
-    if (ast_needs_map(node->body)) return true;
-    if (ast_needs_map(node->next)) return true;
-    if (node->children) {
-        for (int i = 0; i < node->child_count; i++) {
-            if (ast_needs_map(node->children[i])) return true;
-        }
-    }
-    return false;
+static bool ast_needs_string(ASTNode *node) {
+    return ast_find(node, is_string_literal, NULL) != NULL;
 }
 
 
@@ -224,6 +185,46 @@ static void generate_expr_cpp(StringBuilder *sb, ASTNode *node) {
                     sb_append(sb, "\"\"");
                 }
                 sb_append(sb, " << std::endl");
+            } else if (strcmp(fn, "select") == 0 && node->child_count == 3) {
+                /* A call evaluates every argument before one is picked */
+                sb_append(sb, "[](bool c, auto a, auto b) { return c ? a : b; }(");
+                for (int i = 0; i < 3; i++) {
+                    if (i > 0) sb_append(sb, ", ");
+                    generate_expr_cpp(sb, node->children[i]);
+                }
+                sb_append(sb, ")");
+            } else if (strcmp(fn, "len") == 0 && node->child_count == 1) {
+                /* std::string and std::vector both expose size() */
+                sb_append(sb, "static_cast<long>(");
+                generate_expr_cpp(sb, node->children[0]);
+                sb_append(sb, ".size())");
+            } else if (string_builtin_lookup(fn) >= 0 &&
+                       node->child_count == string_builtin_arity(string_builtin_lookup(fn))) {
+                switch (string_builtin_lookup(fn)) {
+                    case STR_BUILTIN_SUBSTR:
+                        sb_append(sb, "sub_substr(");
+                        generate_expr_cpp(sb, node->children[0]);
+                        sb_append(sb, ", ");
+                        generate_expr_cpp(sb, node->children[1]);
+                        sb_append(sb, ", ");
+                        generate_expr_cpp(sb, node->children[2]);
+                        sb_append(sb, ")");
+                        break;
+                    case STR_BUILTIN_INDEX_OF:
+                        sb_append(sb, "[](const std::string &s, const std::string &t) { "
+                                      "auto i = s.find(t); return i == std::string::npos ? -1L : static_cast<long>(i); }(");
+                        generate_expr_cpp(sb, node->children[0]);
+                        sb_append(sb, ", ");
+                        generate_expr_cpp(sb, node->children[1]);
+                        sb_append(sb, ")");
+                        break;
+                    default:
+                        sb_append(sb, "[](std::string s) { for (auto &c : s) c = static_cast<char>(std::%s(static_cast<unsigned char>(c))); return s; }(",
+                                  string_builtin_lookup(fn) == STR_BUILTIN_TO_UPPER ? "toupper" : "tolower");
+                        generate_expr_cpp(sb, node->children[0]);
+                        sb_append(sb, ")");
+                        break;
+                }
             } else {
                 if (node->value) {
                     sb_append(sb, "%s(", fn);
@@ -268,12 +269,63 @@ static void generate_expr_cpp(StringBuilder *sb, ASTNode *node) {
             break;
 
         case AST_ARRAY_ACCESS:
+            /* at() throws std::out_of_range (negative indices wrap to huge
+               size_t values) where operator[] would be undefined behaviour */
             generate_expr_cpp(sb, node->left);
-            sb_append(sb, "[");
+            sb_append(sb, ".at(");
             generate_expr_cpp(sb, node->right);
-            sb_append(sb, "]");
+            sb_append(sb, ")");
             break;
 
+        case AST_UNWRAP_EXPR:
+            generate_expr_cpp(sb, node->left);
+            break;
+        case AST_TUPLE_EXPR:
+            sb_append(sb, "std::make_tuple(");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_cpp(sb, node->children[i]);
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "[&](const auto &sub_match) { return ");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, "sub_match == ");
+                    generate_expr_cpp(sb, arm->left);
+                    sb_append(sb, " ? ");
+                }
+                sb_append(sb, "(");
+                generate_expr_cpp(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? ") : " : ")");
+            }
+            sb_append(sb, "; }(");
+            generate_expr_cpp(sb, node->left);
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR: {
+            bool from_str = node->right && node->right->data_type == TYPE_STRING;
+            if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "((");
+                generate_expr_cpp(sb, node->right);
+                sb_append(sb, ") != 0)");
+                break;
+            }
+            if (node->data_type == TYPE_STRING && node->right && node->right->data_type == TYPE_BOOL) {
+                sb_append(sb, "std::string((");
+                generate_expr_cpp(sb, node->right);
+                sb_append(sb, ") ? \"true\" : \"false\")");
+                break;
+            }
+            if (node->data_type == TYPE_INT) sb_append(sb, from_str ? "std::stol(" : "static_cast<long>(");
+            else if (node->data_type == TYPE_FLOAT) sb_append(sb, from_str ? "std::stod(" : "static_cast<double>(");
+            else sb_append(sb, from_str ? "std::string(" : "std::to_string(");
+            generate_expr_cpp(sb, node->right);
+            sb_append(sb, ")");
+            break;
+        }
         default:
             break;
     }
@@ -315,6 +367,18 @@ static void generate_node_cpp(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, ";\n");
             break;
 
+        case AST_DESTRUCTURE_DECL:
+            /* Structured bindings (C++17) */
+            indent_code(sb, indent);
+            sb_append(sb, "%sauto [", node->children[0]->type == AST_CONST_DECL ? "const " : "");
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", node->children[i]->value);
+            }
+            sb_append(sb, "] = ");
+            generate_expr_cpp(sb, node->right);
+            sb_append(sb, ";\n");
+            break;
+
         case AST_FUNCTION_DECL:
             /* `auto` parameter types require C++20 abbreviated function
                templates, which callers don't reliably compile with
@@ -539,6 +603,12 @@ static void generate_node_cpp(StringBuilder *sb, ASTNode *node, int indent) {
             }
             break;
 
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "std::exit(static_cast<int>(");
+            generate_expr_cpp(sb, node->right);
+            sb_append(sb, "));\n");
+            break;
         default:
             break;
     }
@@ -584,23 +654,62 @@ char* codegen_cpp(ASTNode *ast, const char *source, CPPCodegenOptions *options)
     (void)source; /* Reserved for embedded code extraction */
     (void)options; /* Options reserved for future fine-tuning */
 
+    if (!codegen_check_support(ast, "C++")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
 
-    sb_append(sb, "// Generated by SUB Language Compiler (C++17 Target)\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s (C++17 Target)\n\n", codegen_banner());
 
     /* Emit includes based on AST analysis */
     sb_append(sb, "#include <iostream>\n");
     if (ast_needs_string(ast)) {
         sb_append(sb, "#include <string>\n");
+        sb_append(sb, "#include <cctype>\n");
     }
-    if (ast_needs_vector(ast)) {
+    if (ast_contains_type(ast, AST_ARRAY_LITERAL)) {
         sb_append(sb, "#include <vector>\n");
     }
-    if (ast_needs_map(ast)) {
+    if (ast_contains_type(ast, AST_OBJECT_LITERAL)) {
         sb_append(sb, "#include <map>\n");
     }
+    if (ast_contains_type(ast, AST_TUPLE_EXPR)) {
+        sb_append(sb, "#include <tuple>\n");
+    }
+    if (ast_contains_type(ast, AST_EXIT_STMT)) {
+        sb_append(sb, "#include <cstdlib>\n");
+    }
+    bool substr = ast_calls_function(ast, "substr");
+    if (substr) {
+        if (!ast_needs_string(ast)) sb_append(sb, "#include <string>\n");
+        sb_append(sb, "#include <stdexcept>\n");
+    }
     sb_append(sb, "\n");
+    if (ast_contains_type(ast, AST_TUPLE_EXPR)) {
+        /* std::tuple has no operator<<; print it as subi does, (1, two, true) */
+        sb_append(sb, "template<typename... T>\n");
+        sb_append(sb, "std::ostream &operator<<(std::ostream &os, const std::tuple<T...> &t);\n");
+        sb_append(sb, "template<typename T>\n");
+        sb_append(sb, "static void sub_print_element(std::ostream &os, const T &e) { os << e; }\n");
+        sb_append(sb, "static void sub_print_element(std::ostream &os, bool e) { os << (e ? \"true\" : \"false\"); }\n");
+        sb_append(sb, "template<typename... T>\n");
+        sb_append(sb, "std::ostream &operator<<(std::ostream &os, const std::tuple<T...> &t) {\n");
+        sb_append(sb, "    os << \"(\";\n");
+        sb_append(sb, "    std::size_t i = 0;\n");
+        sb_append(sb, "    std::apply([&](const auto &...e) { ((os << (i++ ? \", \" : \"\"), sub_print_element(os, e)), ...); }, t);\n");
+        sb_append(sb, "    return os << \")\";\n");
+        sb_append(sb, "}\n\n");
+    }
+    if (substr) {
+        /* std::string::substr would cut a length that runs past the end short */
+        sb_append(sb, "static std::string sub_substr(const std::string &s, long start, long n) {\n");
+        sb_append(sb, "    long len = static_cast<long>(s.size());\n");
+        sb_append(sb, "    if (start < 0 || n < 0 || start > len || n > len - start) {\n");
+        sb_append(sb, "        throw std::out_of_range(\"substr(): start \" + std::to_string(start) + \", length \" +\n");
+        sb_append(sb, "                                std::to_string(n) + \" is out of range for a string of length \" + std::to_string(len));\n");
+        sb_append(sb, "    }\n");
+        sb_append(sb, "    return s.substr(start, n);\n");
+        sb_append(sb, "}\n\n");
+    }
 
     /* Two-pass approach: functions first, then main() with top-level statements */
     StringBuilder *main_sb = sb_create();
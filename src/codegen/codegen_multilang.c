@@ -7,6 +7,7 @@
 #define _GNU_SOURCE
 #include "sub_compiler.h"
 #include "type_system.h"
+#include "codegen_cpp.h"
 #include "windows_compat.h"
 #include <stdarg.h>
 #include <ctype.h>
@@ -115,6 +116,86 @@ static char* extract_embedded_code(const char *source, const char *lang) {
     return sb_to_string(sb);
 }
 
+/* String builtins expand from per-target templates in which @0..@2 stand
+   for the (parenthesized) call arguments, indexed by StringBuiltin. */
+static void emit_template(StringBuilder *sb, ASTNode *call, const char *template,
+                          void (*gen)(StringBuilder *, ASTNode *)) {
+    for (const char *p = template; *p; p++) {
+        if (p[0] == '@' && p[1] >= '0' && p[1] <= '2') {
+            sb_append(sb, "(");
+            gen(sb, call->children[p[1] - '0']);
+            sb_append(sb, ")");
+            p++;
+        } else {
+            sb_append(sb, "%c", *p);
+        }
+    }
+}
+
+static bool emit_string_builtin(StringBuilder *sb, ASTNode *call, const char *const templates[],
+                                void (*gen)(StringBuilder *, ASTNode *)) {
+    int builtin = string_builtin_lookup(call->value);
+    if (builtin < 0 || call->child_count != string_builtin_arity(builtin)) return false;
+    emit_template(sb, call, templates[builtin], gen);
+    return true;
+}
+
+/* select(c, a, b) must evaluate all three arguments, so each template
+   passes them to an inline function instead of using the target's ?: */
+static bool emit_select(StringBuilder *sb, ASTNode *call, const char *template,
+                        void (*gen)(StringBuilder *, ASTNode *)) {
+    if (!call->value || strcmp(call->value, "select") != 0 || call->child_count != 3) return false;
+    emit_template(sb, call, template, gen);
+    return true;
+}
+
+/* Python, JavaScript and Ruby quietly accept negative indices (wrapping or
+   yielding undefined), so computed indices go through a guard that raises
+   instead. A literal index has already been checked by semantic analysis. */
+static bool index_needs_guard(ASTNode *index) {
+    return index && index->type != AST_LITERAL;
+}
+
+static bool is_guarded_access(const ASTNode *node, const void *data) {
+    (void)data;
+    return node->type == AST_ARRAY_ACCESS && index_needs_guard(node->right);
+}
+
+static bool ast_needs_index_guard(ASTNode *node) {
+    return ast_find(node, is_guarded_access, NULL) != NULL;
+}
+
+// A repeat [value; count] evaluates its value once. A value of one of these
+// types is immutable, so every slot shares it; any other value is copied
+static bool repeat_shares_value(const ASTNode *node) {
+    DataType type = node->children[0]->data_type;
+    return type == TYPE_INT || type == TYPE_FLOAT || type == TYPE_BOOL ||
+           type == TYPE_STRING || type == TYPE_NULL;
+}
+
+typedef struct {
+    bool copied_only;
+    bool found;
+} RepeatSearch;
+
+static bool find_array_repeat(ASTVisitor *visitor, ASTNode *node) {
+    RepeatSearch *search = visitor->data;
+    if (ast_is_array_repeat(node) && (!search->copied_only || !repeat_shares_value(node)))
+        search->found = true;
+    return !search->found;
+}
+
+// True if a repeat literal in the tree needs the fill helper: any repeat at
+// all, or with `copied_only` only one whose value is copied into each slot
+static bool ast_needs_fill(ASTNode *ast, bool copied_only) {
+    RepeatSearch search = {copied_only, false};
+    ASTVisitor visitor = {0};
+    visitor.visit[AST_ARRAY_LITERAL] = find_array_repeat;
+    visitor.data = &search;
+    ast_walk(ast, &visitor);
+    return search.found;
+}
+
 /* Forward declarations */
 static void generate_node_python(StringBuilder *sb, ASTNode *node, int indent);
 
@@ -178,6 +259,13 @@ static char* escape_string_for_codegen(const char *raw) {
    PYTHON CODE GENERATOR - REAL
    ======================================== */
 
+static const char *const PY_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_sub_substr(@0, @1, @2)",
+    "@0.find(@1)",
+    "@0.upper()",
+    "@0.lower()",
+};
+
 static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
     
@@ -187,7 +275,9 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
                 char *escaped = escape_string_for_codegen(node->value ? node->value : "");
                 sb_append(sb, "\"%s\"", escaped ? escaped : "");
                 free(escaped);
-            } else if (node->value) {
+            } else if (node->data_type == TYPE_BOOL && node->value) {
+                sb_append(sb, strcmp(node->value, "true") == 0 ? "True" : "False");
+            } else if (node->value && node->data_type != TYPE_NULL) {
                 sb_append(sb, "%s", node->value);
             } else {
                 sb_append(sb, "None");
@@ -196,15 +286,20 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
         case AST_IDENTIFIER:
             sb_append(sb, "%s", node->value ? node->value : "var");
             break;
-        case AST_BINARY_EXPR:
+        case AST_BINARY_EXPR: {
+            /* Python spells the short-circuit operators as words */
+            const char *op = node->value ? node->value : "+";
+            if (strcmp(op, "&&") == 0) op = "and";
+            else if (strcmp(op, "||") == 0) op = "or";
             sb_append(sb, "(");
             generate_expr_python(sb, node->left);
-            sb_append(sb, " %s ", node->value ? node->value : "+");
+            sb_append(sb, " %s ", op);
             generate_expr_python(sb, node->right);
             sb_append(sb, ")");
             break;
+        }
         case AST_UNARY_EXPR:
-            sb_append(sb, "%s", node->value ? node->value : "");
+            sb_append(sb, "%s", node->value && strcmp(node->value, "!") == 0 ? "not " : node->value ? node->value : "");
             generate_expr_python(sb, node->right);
             break;
         case AST_TERNARY_EXPR:
@@ -217,6 +312,9 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, ")");
             break;
         case AST_CALL_EXPR:
+            if (emit_string_builtin(sb, node, PY_STRING_BUILTINS, generate_expr_python)) break;
+            if (emit_select(sb, node, "(lambda c, a, b: a if c else b)(@0, @1, @2)", generate_expr_python)) break;
+            bool prints = node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0);
             if (node->value && strcmp(node->value, "show") == 0) {
                 sb_append(sb, "print(");
             } else if (node->value) {
@@ -226,12 +324,24 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
                 sb_append(sb, "(");
             }
             for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arg = node->children[i];
                 if (i > 0) sb_append(sb, ", ");
-                generate_expr_python(sb, node->children[i]);
+                /* SUB prints a missing value as null, where Python says None */
+                bool maybe_null = prints && (arg->optional || arg->data_type == TYPE_NULL);
+                if (maybe_null) sb_append(sb, "(lambda v: \"null\" if v is None else v)(");
+                generate_expr_python(sb, arg);
+                if (maybe_null) sb_append(sb, ")");
             }
             sb_append(sb, ")");
             break;
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                bool shared = repeat_shares_value(node);
+                sb_append(sb, shared ? "[" : "_sub_fill(");
+                generate_expr_python(sb, node->children[0]);
+                sb_append(sb, shared ? "] * %d" : ", %d)", ast_array_length(node));
+                break;
+            }
             sb_append(sb, "[");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -239,6 +349,14 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
             }
             sb_append(sb, "]");
             break;
+        case AST_RANGE_EXPR:
+            /* a..b outside a for loop; a..=b was rewritten to a..(b + 1) */
+            sb_append(sb, "list(range(");
+            generate_expr_python(sb, node->left);
+            sb_append(sb, ", ");
+            generate_expr_python(sb, node->right);
+            sb_append(sb, "))");
+            break;
         case AST_OBJECT_LITERAL:
             sb_append(sb, "{");
             for (int i = 0; i < node->child_count; i++) {
@@ -256,9 +374,53 @@ static void generate_expr_python(StringBuilder *sb, ASTNode *node) {
             break;
         case AST_ARRAY_ACCESS:
             generate_expr_python(sb, node->left);
-            sb_append(sb, "[");
+            sb_append(sb, index_needs_guard(node->right) ? "[_sub_index(" : "[");
             generate_expr_python(sb, node->right);
-            sb_append(sb, "]");
+            sb_append(sb, index_needs_guard(node->right) ? ")]" : "]");
+            break;
+        case AST_UNWRAP_EXPR:
+            /* An implicit unwrap follows a null check; x? is checked here */
+            if (node->value) sb_append(sb, "_sub_unwrap(");
+            generate_expr_python(sb, node->left);
+            if (node->value) sb_append(sb, ", %d)", node->line);
+            break;
+        case AST_TUPLE_EXPR:
+            sb_append(sb, "(");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_python(sb, node->children[i]);
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_MATCH_EXPR:
+            /* A lambda evaluates the matched value once */
+            sb_append(sb, "(lambda _sub_match: ");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                sb_append(sb, "(");
+                generate_expr_python(sb, arm->right);
+                sb_append(sb, ")");
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, " if _sub_match == ");
+                    generate_expr_python(sb, arm->left);
+                    sb_append(sb, " else ");
+                }
+            }
+            sb_append(sb, ")(");
+            generate_expr_python(sb, node->left);
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR:
+            if (node->data_type == TYPE_STRING && node->right && node->right->data_type == TYPE_BOOL) {
+                /* Python's str(True) is "True"; SUB prints booleans lowercase */
+                sb_append(sb, "(\"true\" if ");
+                generate_expr_python(sb, node->right);
+                sb_append(sb, " else \"false\")");
+                break;
+            }
+            sb_append(sb, "%s(", node->data_type == TYPE_STRING ? "str" : node->value);
+            generate_expr_python(sb, node->right);
+            sb_append(sb, ")");
             break;
         default:
             break;
@@ -297,6 +459,16 @@ static void generate_node_python(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "\n");
             break;
             
+        case AST_DESTRUCTURE_DECL:
+            indent_code(sb, indent);
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", node->children[i]->value);
+            }
+            sb_append(sb, " = ");
+            generate_expr_python(sb, node->right);
+            sb_append(sb, "\n");
+            break;
+            
         case AST_FUNCTION_DECL:
             sb_append(sb, "\ndef %s(", node->value ? node->value : "func");
             for (int i = 0; i < node->child_count; i++) {
@@ -437,17 +609,49 @@ static void generate_node_python(StringBuilder *sb, ASTNode *node, int indent) {
             }
             break;
             
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "raise SystemExit(");
+            generate_expr_python(sb, node->right);
+            sb_append(sb, ")\n");
+            break;
         default:
             break;
     }
 }
 
 char* codegen_python(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Python")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
     sb_append(sb, "#!/usr/bin/env python3\n");
-    sb_append(sb, "# Generated by SUB Language Compiler\n\n");
+    if (codegen_banner()) sb_append(sb, "# %s\n\n", codegen_banner());
+    if (ast_needs_index_guard(ast)) {
+        sb_append(sb, "def _sub_index(i):\n");
+        sb_append(sb, "    if i < 0:\n");
+        sb_append(sb, "        raise IndexError(\"negative array index %%d\" %% i)\n");
+        sb_append(sb, "    return i\n\n");
+    }
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "def _sub_substr(s, start, n):\n");
+        sb_append(sb, "    if start < 0 or n < 0 or start > len(s) or n > len(s) - start:\n");
+        sb_append(sb, "        raise IndexError(\"substr(): start %%d, length %%d is out of range for a string of length %%d\" %% (start, n, len(s)))\n");
+        sb_append(sb, "    return s[start:start + n]\n\n");
+    }
+    if (ast_contains_type(ast, AST_UNWRAP_EXPR)) {
+        sb_append(sb, "def _sub_unwrap(v, line):\n");
+        sb_append(sb, "    if v is None:\n");
+        sb_append(sb, "        raise ValueError(\"line %%d: unwrapped a null optional value\" %% line)\n");
+        sb_append(sb, "    return v\n\n");
+    }
+    if (ast_needs_fill(ast, true)) {
+        sb_append(sb, "def _sub_fill(v, n):\n");
+        sb_append(sb, "    import copy\n");
+        sb_append(sb, "    if isinstance(v, list) and not any(isinstance(x, (list, dict)) for x in v):\n");
+        sb_append(sb, "        return [v[:] for _ in range(n)]\n");
+        sb_append(sb, "    return [copy.deepcopy(v) for _ in range(n)]\n\n");
+    }
     
     // Check for embedded Python code first
     char *embedded = extract_embedded_code(source, "python");
@@ -461,8 +665,8 @@ char* codegen_python(ASTNode *ast, const char *source) {
     // Generate from AST
     generate_node_python(sb, ast, 0);
     
-    // Add main guard if no embedded code
-    if (!has_embedded) {
+    // Add main guard if no embedded code; a library module has no entry point
+    if (!has_embedded && crate_type() == CRATE_TYPE_BIN) {
         sb_append(sb, "\nif __name__ == '__main__':\n");
         sb_append(sb, "    pass\n");
     }
@@ -474,6 +678,61 @@ char* codegen_python(ASTNode *ast, const char *source) {
    JAVASCRIPT CODE GENERATOR - REAL
    ======================================== */
 
+static const char *const JS_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_subSubstr(@0, @1, @2)",
+    "@0.indexOf(@1)",
+    "@0.toUpperCase()",
+    "@0.toLowerCase()",
+};
+
+/* The TypeScript target is the JavaScript generator with type annotations
+   on declarations and function signatures */
+static bool g_js_typescript = false;
+
+static const char* ts_scalar_type(DataType type) {
+    switch (type) {
+        case TYPE_INT:
+        case TYPE_FLOAT:  return "number";
+        case TYPE_STRING: return "string";
+        case TYPE_BOOL:   return "boolean";
+        default:          return NULL;
+    }
+}
+
+/* The TypeScript type for an element type written in a [T] annotation */
+static const char* ts_named_type(const char *name) {
+    if (!name) return NULL;
+    if (strcmp(name, "int") == 0 || strcmp(name, "float") == 0) return "number";
+    if (strcmp(name, "string") == 0) return "string";
+    if (strcmp(name, "bool") == 0) return "boolean";
+    return NULL;
+}
+
+/* ": T" for a declaration, parameter or return type, or nothing when the
+   type is not known (TypeScript then infers it). decl's [T] annotation, or
+   else the initializer `value`, gives an array's element type. */
+static void ts_annotate(StringBuilder *sb, const ASTNode *decl, bool optional, const ASTNode *value,
+                        const char *fallback) {
+    if (!g_js_typescript) return;
+    DataType type = decl->data_type;
+    const char *name = ts_scalar_type(type);
+    char array[32];
+    if (type == TYPE_ARRAY) {
+        const char *elem = NULL;
+        for (int i = 0; value && value->type == AST_ARRAY_LITERAL && i < value->child_count; i++) {
+            const char *t = ts_scalar_type(value->children[i]->data_type);
+            if (!t || (elem && strcmp(elem, t) != 0)) { elem = NULL; break; }
+            elem = t;
+        }
+        if (ts_named_type(decl->metadata)) elem = ts_named_type(decl->metadata);
+        snprintf(array, sizeof(array), "%s[]", elem ? elem : "any");
+        name = array;
+    }
+    if (!name) name = fallback;
+    if (!name) return;
+    sb_append(sb, ": %s%s", name, optional ? " | null" : "");
+}
+
 static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
     
@@ -490,7 +749,7 @@ static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
             }
             break;
         case AST_IDENTIFIER:
-            sb_append(sb, "%s", node->value ? node->value : "var");
+            sb_append(sb, "%s", mangle(node->value ? node->value : "var", MANGLE_JS));
             break;
         case AST_BINARY_EXPR:
             sb_append(sb, "(");
@@ -513,10 +772,18 @@ static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, ")");
             break;
         case AST_CALL_EXPR:
+            if (emit_string_builtin(sb, node, JS_STRING_BUILTINS, generate_expr_js)) break;
+            if (emit_select(sb, node, "((c, a, b) => c ? a : b)(@0, @1, @2)", generate_expr_js)) break;
             if (node->value && strcmp(node->value, "show") == 0) {
                 sb_append(sb, "console.log(");
+            } else if (node->value && strcmp(node->value, "len") == 0 && node->child_count == 1) {
+                /* len(x) -> x.length (strings and arrays alike) */
+                sb_append(sb, "(");
+                generate_expr_js(sb, node->children[0]);
+                sb_append(sb, ").length");
+                break;
             } else if (node->value) {
-                sb_append(sb, "%s(", node->value);
+                sb_append(sb, "%s(", mangle(node->value, MANGLE_JS));
             } else {
                 generate_expr_js(sb, node->left);
                 sb_append(sb, "(");
@@ -528,6 +795,18 @@ static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, ")");
             break;
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                if (repeat_shares_value(node)) {
+                    sb_append(sb, "new Array(%d).fill(", ast_array_length(node));
+                    generate_expr_js(sb, node->children[0]);
+                    sb_append(sb, ")");
+                } else {
+                    sb_append(sb, "_subFill(");
+                    generate_expr_js(sb, node->children[0]);
+                    sb_append(sb, ", %d)", ast_array_length(node));
+                }
+                break;
+            }
             sb_append(sb, "[");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -535,6 +814,13 @@ static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
             }
             sb_append(sb, "]");
             break;
+        case AST_RANGE_EXPR:
+            sb_append(sb, "((lo, hi) => Array.from({length: Math.max(0, hi - lo)}, (_, i) => lo + i))(");
+            generate_expr_js(sb, node->left);
+            sb_append(sb, ", ");
+            generate_expr_js(sb, node->right);
+            sb_append(sb, ")");
+            break;
         case AST_OBJECT_LITERAL:
             sb_append(sb, "{");
             for (int i = 0; i < node->child_count; i++) {
@@ -552,17 +838,139 @@ static void generate_expr_js(StringBuilder *sb, ASTNode *node) {
             break;
         case AST_ARRAY_ACCESS:
             generate_expr_js(sb, node->left);
-            sb_append(sb, "[");
+            sb_append(sb, index_needs_guard(node->right) ? "[_subIndex(" : "[");
             generate_expr_js(sb, node->right);
+            sb_append(sb, index_needs_guard(node->right) ? ")]" : "]");
+            break;
+        case AST_UNWRAP_EXPR:
+            if (node->value) sb_append(sb, "_subUnwrap(");
+            generate_expr_js(sb, node->left);
+            if (node->value) sb_append(sb, ", %d)", node->line);
+            break;
+        case AST_TUPLE_EXPR:
+            /* Tuples are arrays */
+            sb_append(sb, "[");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_js(sb, node->children[i]);
+            }
             sb_append(sb, "]");
             break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "((sub_match) => ");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, "sub_match === ");
+                    generate_expr_js(sb, arm->left);
+                    sb_append(sb, " ? ");
+                }
+                sb_append(sb, "(");
+                generate_expr_js(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? ") : " : ")");
+            }
+            sb_append(sb, ")(");
+            generate_expr_js(sb, node->left);
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR:
+            if (node->data_type == TYPE_INT) {
+                bool from_str = node->right && node->right->data_type == TYPE_STRING;
+                sb_append(sb, from_str ? "parseInt(" : "Math.trunc(");
+                generate_expr_js(sb, node->right);
+                sb_append(sb, from_str ? ", 10)" : ")");
+            } else if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "((");
+                generate_expr_js(sb, node->right);
+                sb_append(sb, ") !== 0)");
+            } else {
+                sb_append(sb, node->data_type == TYPE_STRING ? "String(" : "Number(");
+                generate_expr_js(sb, node->right);
+                sb_append(sb, ")");
+            }
+            break;
         default:
             break;
     }
 }
 
+/* Source maps for JavaScript and TypeScript: for each line of the output,
+   the source line of the first statement generated on it (0 for none) */
+static bool g_source_map_wanted = false;
+static StringBuilder *g_map_sb = NULL;
+static size_t g_map_scanned = 0;
+static int g_map_line = 0;
+static int *g_map_lines = NULL;
+static int g_map_capacity = 0;
+static char *g_source_map = NULL;
+
+static void map_statement(StringBuilder *sb, const ASTNode *node) {
+    if (sb != g_map_sb || node->line < 1 || node->type == AST_PROGRAM || node->type == AST_BLOCK) return;
+    for (; g_map_scanned < sb->size; g_map_scanned++) {
+        if (sb->buffer[g_map_scanned] == '\n') g_map_line++;
+    }
+    if (g_map_line >= g_map_capacity) {
+        int capacity = g_map_capacity ? g_map_capacity * 2 : 64;
+        while (capacity <= g_map_line) capacity *= 2;
+        int *grown = realloc(g_map_lines, sizeof(int) * capacity);
+        if (!grown) return;
+        memset(grown + g_map_capacity, 0, sizeof(int) * (capacity - g_map_capacity));
+        g_map_lines = grown;
+        g_map_capacity = capacity;
+    }
+    if (!g_map_lines[g_map_line]) g_map_lines[g_map_line] = node->line;
+}
+
+// One Base64 VLQ field of a source map's mappings
+static void append_vlq(StringBuilder *sb, int value) {
+    static const char digits[] =
+        "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    unsigned int v = value < 0 ? ((unsigned int)-value << 1) | 1 : (unsigned int)value << 1;
+    do {
+        unsigned int digit = v & 31;
+        v >>= 5;
+        sb_append(sb, "%c", digits[digit | (v ? 32 : 0)]);
+    } while (v);
+}
+
+// A version 3 source map from the lines map_statement() recorded; every
+// segment starts its line and points at column 0 of the source line
+static char* build_source_map(void) {
+    StringBuilder *sb = sb_create();
+    if (!sb) return NULL;
+    const char *name = source_path() ? source_path() : "input.sb";
+    sb_append(sb, "{\"version\":3,\"sources\":[\"");
+    for (const char *p = name; *p; p++) {
+        if (*p == '"' || *p == '\\') sb_append(sb, "\\%c", *p);
+        else if ((unsigned char)*p >= 0x20) sb_append(sb, "%c", *p);
+    }
+    sb_append(sb, "\"],\"names\":[],\"mappings\":\"");
+    int previous = 0;
+    for (int line = 0; line < g_map_capacity && line <= g_map_line; line++) {
+        if (line > 0) sb_append(sb, ";");
+        if (!g_map_lines[line]) continue;
+        sb_append(sb, "AA");
+        append_vlq(sb, g_map_lines[line] - 1 - previous);
+        sb_append(sb, "A");
+        previous = g_map_lines[line] - 1;
+    }
+    sb_append(sb, "\"}\n");
+    return sb_to_string(sb);
+}
+
+void codegen_set_source_map(bool enabled) {
+    g_source_map_wanted = enabled;
+}
+
+char* codegen_take_source_map(void) {
+    char *map = g_source_map;
+    g_source_map = NULL;
+    return map;
+}
+
 static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
     if (!node) return;
+    map_statement(sb, node);
     
     switch (node->type) {
         case AST_PROGRAM:
@@ -573,7 +981,10 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
             
         case AST_VAR_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "let %s = ", node->value ? node->value : "var");
+            sb_append(sb, "let %s", mangle(node->value ? node->value : "var", MANGLE_JS));
+            // Without an initializer it holds null until assigned
+            ts_annotate(sb, node, node->optional || !node->right, node->right, NULL);
+            sb_append(sb, " = ");
             if (node->right) {
                 generate_expr_js(sb, node->right);
             } else {
@@ -584,7 +995,10 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
             
         case AST_CONST_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "const %s = ", node->value ? node->value : "CONST");
+            if (indent == 0 && crate_type() == CRATE_TYPE_LIB) sb_append(sb, "export ");
+            sb_append(sb, "const %s", mangle(node->value ? node->value : "CONST", MANGLE_JS));
+            ts_annotate(sb, node, node->optional, node->right, NULL);
+            sb_append(sb, " = ");
             if (node->right) {
                 generate_expr_js(sb, node->right);
             } else {
@@ -593,16 +1007,33 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, ";\n");
             break;
             
+        case AST_DESTRUCTURE_DECL:
+            indent_code(sb, indent);
+            sb_append(sb, "%s [", node->children[0]->type == AST_CONST_DECL ? "const" : "let");
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", mangle(node->children[i]->value, MANGLE_JS));
+            }
+            sb_append(sb, "] = ");
+            generate_expr_js(sb, node->right);
+            sb_append(sb, ";\n");
+            break;
+            
         case AST_FUNCTION_DECL:
             indent_code(sb, indent);
-            sb_append(sb, "function %s(", node->value ? node->value : "func");
+            /* A library's top-level functions are its ES module exports */
+            if (indent == 0 && crate_type() == CRATE_TYPE_LIB) sb_append(sb, "export ");
+            sb_append(sb, "function %s(", mangle(node->value ? node->value : "func", MANGLE_JS));
             // Parameters
             if (node->children && node->child_count > 0) {
                 for (int i = 0; i < node->child_count; i++) {
-                    sb_append(sb, "%s%s", i > 0 ? ", " : "", node->children[i]->value);
+                    ASTNode *param = node->children[i];
+                    sb_append(sb, "%s%s", i > 0 ? ", " : "", mangle(param->value, MANGLE_JS));
+                    ts_annotate(sb, param, param->optional, NULL, "any");
                 }
             }
-            sb_append(sb, ") {\n");
+            sb_append(sb, ")");
+            ts_annotate(sb, node, node->optional, NULL, NULL);
+            sb_append(sb, " {\n");
             
             if (node->body) generate_node_js(sb, node->body, indent + 1);
             indent_code(sb, indent);
@@ -641,18 +1072,19 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "\n");
             break;
             
-        case AST_FOR_STMT:
+        case AST_FOR_STMT: {
+            const char *var = mangle(node->value ? node->value : "i", MANGLE_JS);
             indent_code(sb, indent);
             // Check for range expression
             if (node->children && node->child_count > 0 && node->children[0]->type == AST_RANGE_EXPR) {
                 ASTNode *range = node->children[0];
-                sb_append(sb, "for (let %s = ", node->value ? node->value : "i");
+                sb_append(sb, "for (let %s = ", var);
                 if (range->right) {
                     if (range->left) generate_expr_js(sb, range->left); else sb_append(sb, "0");
                 } else {
                     sb_append(sb, "0");
                 }
-                sb_append(sb, "; %s < ", node->value ? node->value : "i");
+                sb_append(sb, "; %s < ", var);
                 if (range->right) {
                     generate_expr_js(sb, range->right);
                 } else if (range->left) {
@@ -660,26 +1092,24 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
                 } else {
                     sb_append(sb, "10");
                 }
-                sb_append(sb, "; %s++) {\n", node->value ? node->value : "i");
+                sb_append(sb, "; %s++) {\n", var);
             } 
             // Check for collection iteration
             else if (node->condition) {
-                sb_append(sb, "for (let %s of ", node->value ? node->value : "item");
+                sb_append(sb, "for (let %s of ", var);
                 generate_expr_js(sb, node->condition);
                 sb_append(sb, ") {\n");
             }
             // Fallback
             else {
-                sb_append(sb, "for (let %s = 0; %s < 10; %s++) {\n", 
-                         node->value ? node->value : "i",
-                         node->value ? node->value : "i",
-                         node->value ? node->value : "i");
+                sb_append(sb, "for (let %s = 0; %s < 10; %s++) {\n", var, var, var);
             }
             
             generate_node_js(sb, node->body, indent + 1);
             indent_code(sb, indent);
             sb_append(sb, "}\n");
             break;
+        }
             
         case AST_WHILE_STMT:
             indent_code(sb, indent);
@@ -735,16 +1165,53 @@ static void generate_node_js(StringBuilder *sb, ASTNode *node, int indent) {
             }
             break;
             
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "process.exit(");
+            generate_expr_js(sb, node->right);
+            sb_append(sb, ");\n");
+            break;
         default:
             break;
     }
 }
 
 char* codegen_javascript(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "JavaScript")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
-    sb_append(sb, "// Generated by SUB Language Compiler\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n\n", codegen_banner());
+    if (ast_needs_index_guard(ast)) {
+        sb_append(sb, "function _subIndex(i%s) {\n", g_js_typescript ? ": number" : "");
+        sb_append(sb, "    if (i < 0) throw new RangeError(\"negative array index \" + i);\n");
+        sb_append(sb, "    return i;\n");
+        sb_append(sb, "}\n\n");
+    }
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, g_js_typescript ? "function _subSubstr(s: string, start: number, n: number): string {\n"
+                                      : "function _subSubstr(s, start, n) {\n");
+        sb_append(sb, "    if (start < 0 || n < 0 || start > s.length || n > s.length - start) {\n");
+        sb_append(sb, "        throw new RangeError(\"substr(): start \" + start + \", length \" + n + \" is out of range for a string of length \" + s.length);\n");
+        sb_append(sb, "    }\n");
+        sb_append(sb, "    return s.slice(start, start + n);\n");
+        sb_append(sb, "}\n\n");
+    }
+    if (ast_contains_type(ast, AST_UNWRAP_EXPR)) {
+        sb_append(sb, g_js_typescript ? "function _subUnwrap<T>(v: T | null, line: number): T {\n"
+                                      : "function _subUnwrap(v, line) {\n");
+        sb_append(sb, "    if (v === null || v === undefined) throw new TypeError(\"line \" + line + \": unwrapped a null optional value\");\n");
+        sb_append(sb, "    return v;\n");
+        sb_append(sb, "}\n\n");
+    }
+    if (ast_needs_fill(ast, true)) {
+        sb_append(sb, g_js_typescript ? "function _subFill<T>(v: T, n: number): T[] {\n"
+                                      : "function _subFill(v, n) {\n");
+        sb_append(sb, "    const flat = Array.isArray(v) && v.every((x) => x === null || typeof x !== \"object\");\n");
+        sb_append(sb, g_js_typescript ? "    return Array.from({length: n}, () => flat ? (v as unknown[]).slice() as T : structuredClone(v));\n"
+                                      : "    return Array.from({length: n}, () => flat ? v.slice() : structuredClone(v));\n");
+        sb_append(sb, "}\n\n");
+    }
     
     // Check for embedded JavaScript
     char *embedded = extract_embedded_code(source, "javascript");
@@ -752,15 +1219,41 @@ char* codegen_javascript(ASTNode *ast, const char *source) {
         sb_append(sb, "%s\n", embedded);
         free(embedded);
     }
+    free(g_source_map);
+    g_source_map = NULL;
+    if (g_source_map_wanted) {
+        g_map_sb = sb;
+        g_map_scanned = 0;
+        g_map_line = 0;
+        if (g_map_lines) memset(g_map_lines, 0, sizeof(int) * g_map_capacity);
+    }
     generate_node_js(sb, ast, 0);
+    if (g_map_sb) {
+        g_source_map = build_source_map();
+        g_map_sb = NULL;
+    }
     
     return sb_to_string(sb);
 }
 
+char* codegen_typescript(ASTNode *ast, const char *source) {
+    g_js_typescript = true;
+    char *code = codegen_javascript(ast, source);
+    g_js_typescript = false;
+    return code;
+}
+
 /* ========================================
    JAVA CODE GENERATOR - FULL AST
    ======================================== */
 
+static const char *const JAVA_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_subSubstr(@0, @1, @2)",
+    "@0.indexOf(@1)",
+    "@0.toUpperCase()",
+    "@0.toLowerCase()",
+};
+
 static void generate_expr_java(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
     
@@ -800,11 +1293,16 @@ static void generate_expr_java(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, ")");
             break;
         case AST_CALL_EXPR: {
+            if (emit_string_builtin(sb, node, JAVA_STRING_BUILTINS, generate_expr_java)) break;
             const char *fn = node->value ? node->value : "func";
             if (strcmp(fn, "print") == 0 || strcmp(fn, "show") == 0) {
                 sb_append(sb, "System.out.println(");
                 if (node->child_count > 0) generate_expr_java(sb, node->children[0]);
                 sb_append(sb, ")");
+            } else if (strcmp(fn, "len") == 0 && node->child_count == 1) {
+                /* Strings use length(), the List.of() arrays use size() */
+                generate_expr_java(sb, node->children[0]);
+                sb_append(sb, node->children[0]->data_type == TYPE_STRING ? ".length()" : ".size()");
             } else {
                 if (node->value) {
                     sb_append(sb, "%s(", fn);
@@ -821,6 +1319,13 @@ static void generate_expr_java(StringBuilder *sb, ASTNode *node) {
             break;
         }
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                /* the lists are immutable, so the slots can share one value */
+                sb_append(sb, "java.util.Collections.nCopies(%d, ", ast_array_length(node));
+                generate_expr_java(sb, node->children[0]);
+                sb_append(sb, ")");
+                break;
+            }
             sb_append(sb, "java.util.List.of(");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -828,6 +1333,13 @@ static void generate_expr_java(StringBuilder *sb, ASTNode *node) {
             }
             sb_append(sb, ")");
             break;
+        case AST_RANGE_EXPR:
+            sb_append(sb, "java.util.stream.LongStream.range(");
+            generate_expr_java(sb, node->left);
+            sb_append(sb, ", ");
+            generate_expr_java(sb, node->right);
+            sb_append(sb, ").boxed().toList()");
+            break;
         case AST_OBJECT_LITERAL:
             sb_append(sb, "java.util.Map.of(");
             for (int i = 0; i < node->child_count; i++) {
@@ -849,6 +1361,48 @@ static void generate_expr_java(StringBuilder *sb, ASTNode *node) {
             generate_expr_java(sb, node->right);
             sb_append(sb, ")");
             break;
+        case AST_UNWRAP_EXPR:
+            generate_expr_java(sb, node->left);
+            break;
+        case AST_MATCH_EXPR:
+            /* Java cannot switch on long, so this is a ?: chain */
+            sb_append(sb, "(");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, "(");
+                    generate_expr_java(sb, node->left);
+                    if (arm->left->data_type == TYPE_STRING) {
+                        sb_append(sb, ").equals(");
+                        generate_expr_java(sb, arm->left);
+                        sb_append(sb, ") ? ");
+                    } else {
+                        sb_append(sb, ") == ");
+                        generate_expr_java(sb, arm->left);
+                        sb_append(sb, " ? ");
+                    }
+                }
+                sb_append(sb, "(");
+                generate_expr_java(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? ") : " : ")");
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR: {
+            bool from_str = node->right && node->right->data_type == TYPE_STRING;
+            if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "((");
+                generate_expr_java(sb, node->right);
+                sb_append(sb, ") != 0)");
+                break;
+            }
+            if (node->data_type == TYPE_INT) sb_append(sb, from_str ? "Long.parseLong(" : "(long)(");
+            else if (node->data_type == TYPE_FLOAT) sb_append(sb, from_str ? "Double.parseDouble(" : "(double)(");
+            else sb_append(sb, "String.valueOf(");
+            generate_expr_java(sb, node->right);
+            sb_append(sb, ")");
+            break;
+        }
         default:
             fprintf(stderr, "Warning: Unsupported expression node %d in Java generator\n", node->type);
             break;
@@ -949,6 +1503,15 @@ static void generate_node_java(StringBuilder *sb, ASTNode *node, int indent) {
                         sb_append(sb, "0; %s < 10", var);
                     }
                     sb_append(sb, "; %s++) {\n", var);
+                } else if (node->condition && node->condition->data_type == TYPE_STRING) {
+                    /* One-character strings, like subi */
+                    sb_append(sb, "for (var %s : (", var);
+                    generate_expr_java(sb, node->condition);
+                    sb_append(sb, ").chars().mapToObj(Character::toString).toArray(String[]::new)) {\n");
+                } else if (node->condition) {
+                    sb_append(sb, "for (var %s : ", var);
+                    generate_expr_java(sb, node->condition);
+                    sb_append(sb, ") {\n");
                 } else {
                     sb_append(sb, "for (int %s = 0; %s < 10; %s++) {\n", var, var, var);
                 }
@@ -998,6 +1561,15 @@ static void generate_node_java(StringBuilder *sb, ASTNode *node, int indent) {
             }
             break;
             
+        case AST_STATIC_ASSERT:
+            /* Checked at compile time; emits nothing */
+            break;
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "System.exit((int) (");
+            generate_expr_java(sb, node->right);
+            sb_append(sb, "));\n");
+            break;
         default:
             fprintf(stderr, "Warning: Unsupported AST node %d in Java generator\n", node->type);
             break;
@@ -1005,10 +1577,11 @@ static void generate_node_java(StringBuilder *sb, ASTNode *node, int indent) {
 }
 
 char* codegen_java(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Java")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
     
-    sb_append(sb, "// Generated by SUB Language Compiler\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n\n", codegen_banner());
 
     char *embedded = extract_embedded_code(source, "java");
     if (embedded) {
@@ -1017,6 +1590,14 @@ char* codegen_java(ASTNode *ast, const char *source) {
     }
 
     sb_append(sb, "public class SubProgram {\n");
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "    static String _subSubstr(String s, long start, long n) {\n");
+        sb_append(sb, "        if (start < 0 || n < 0 || start > s.length() || n > s.length() - start) {\n");
+        sb_append(sb, "            throw new StringIndexOutOfBoundsException(\"substr(): start \" + start + \", length \" + n + \" is out of range for a string of length \" + s.length());\n");
+        sb_append(sb, "        }\n");
+        sb_append(sb, "        return s.substring((int) start, (int) (start + n));\n");
+        sb_append(sb, "    }\n\n");
+    }
 
     /* Two-pass approach: functions and non-function statements separated */
     StringBuilder *main_sb = sb_create();
@@ -1053,6 +1634,13 @@ char* codegen_java(ASTNode *ast, const char *source) {
    SWIFT CODE GENERATOR - FULL AST
    ======================================== */
 
+static const char *const SWIFT_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_subSubstr(@0, @1, @2)",
+    "(@0.firstRange(of: @1).map { @0.distance(from: @0.startIndex, to: $0.lowerBound) } ?? -1)",
+    "@0.uppercased()",
+    "@0.lowercased()",
+};
+
 static void generate_expr_swift(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
     switch (node->type) {
@@ -1071,7 +1659,12 @@ static void generate_expr_swift(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, " %s ", node->value ? node->value : "+");
             generate_expr_swift(sb, node->right); sb_append(sb, ")"); break;
         case AST_CALL_EXPR:
+            if (emit_string_builtin(sb, node, SWIFT_STRING_BUILTINS, generate_expr_swift)) break;
             if (node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0)) sb_append(sb, "print(");
+            else if (node->value && strcmp(node->value, "len") == 0 && node->child_count == 1) {
+                sb_append(sb, "("); generate_expr_swift(sb, node->children[0]);
+                sb_append(sb, ").count"); break;
+            }
             else if (node->value) sb_append(sb, "%s(", node->value);
             else { generate_expr_swift(sb, node->left); sb_append(sb, "("); }
             for (int i = 0; i < node->child_count; i++) {
@@ -1079,6 +1672,47 @@ static void generate_expr_swift(StringBuilder *sb, ASTNode *node) {
                 generate_expr_swift(sb, node->children[i]);
             }
             sb_append(sb, ")"); break;
+        case AST_UNWRAP_EXPR:
+            generate_expr_swift(sb, node->left);
+            break;
+        case AST_TUPLE_EXPR:
+            sb_append(sb, "(");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_swift(sb, node->children[i]);
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "(");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, "(");
+                    generate_expr_swift(sb, node->left);
+                    sb_append(sb, ") == ");
+                    generate_expr_swift(sb, arm->left);
+                    sb_append(sb, " ? ");
+                }
+                sb_append(sb, "(");
+                generate_expr_swift(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? ") : " : ")");
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_CAST_EXPR:
+            if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "(("); generate_expr_swift(sb, node->right);
+                sb_append(sb, ") != 0)"); break;
+            }
+            sb_append(sb, "%s(", node->data_type == TYPE_INT ? "Int" :
+                                 node->data_type == TYPE_FLOAT ? "Double" : "String");
+            generate_expr_swift(sb, node->right);
+            /* Int("x") and Double("x") are optionals in Swift */
+            if (node->data_type != TYPE_STRING && node->right && node->right->data_type == TYPE_STRING)
+                sb_append(sb, ") ?? 0");
+            else sb_append(sb, ")");
+            break;
         default: break;
     }
 }
@@ -1096,6 +1730,15 @@ static void generate_node_swift(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "var %s = ", node->value ? node->value : "var");
             if (node->right) generate_expr_swift(sb, node->right); else sb_append(sb, "nil");
             sb_append(sb, "\n"); break;
+        case AST_DESTRUCTURE_DECL:
+            indent_code(sb, indent);
+            sb_append(sb, "%s (", node->children[0]->type == AST_CONST_DECL ? "let" : "var");
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", node->children[i]->value);
+            }
+            sb_append(sb, ") = ");
+            generate_expr_swift(sb, node->right);
+            sb_append(sb, "\n"); break;
         case AST_FUNCTION_DECL:
             sb_append(sb, "\nfunc %s(", node->value ? node->value : "func");
             if (node->children && node->child_count > 0) {
@@ -1206,14 +1849,30 @@ static void generate_node_swift(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_CALL_EXPR:
             indent_code(sb, indent); generate_expr_swift(sb, node); sb_append(sb, "\n"); break;
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "exit(Int32(");
+            generate_expr_swift(sb, node->right);
+            sb_append(sb, "))\n");
+            break;
         default: break;
     }
 }
 
 char* codegen_swift(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Swift")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
-    sb_append(sb, "// Generated by SUB\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n\n", codegen_banner());
+    if (ast_contains_type(ast, AST_EXIT_STMT)) sb_append(sb, "import Foundation\n\n");
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "func _subSubstr(_ s: String, _ start: Int, _ n: Int) -> String {\n");
+        sb_append(sb, "    if start < 0 || n < 0 || start > s.count || n > s.count - start {\n");
+        sb_append(sb, "        fatalError(\"substr(): start \\(start), length \\(n) is out of range for a string of length \\(s.count)\")\n");
+        sb_append(sb, "    }\n");
+        sb_append(sb, "    return String(s.dropFirst(start).prefix(n))\n");
+        sb_append(sb, "}\n\n");
+    }
     char *e = extract_embedded_code(source, "swift");
     if (e) {
         sb_append(sb, "%s\n", e);
@@ -1227,6 +1886,13 @@ char* codegen_swift(ASTNode *ast, const char *source) {
    KOTLIN CODE GENERATOR - FULL AST
    ======================================== */
 
+static const char *const KOTLIN_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_subSubstr(@0, @1, @2)",
+    "@0.indexOf(@1)",
+    "@0.uppercase()",
+    "@0.lowercase()",
+};
+
 static void generate_expr_kotlin(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
     switch (node->type) {
@@ -1245,7 +1911,14 @@ static void generate_expr_kotlin(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, " %s ", node->value ? node->value : "+");
             generate_expr_kotlin(sb, node->right); sb_append(sb, ")"); break;
         case AST_CALL_EXPR:
+            if (emit_string_builtin(sb, node, KOTLIN_STRING_BUILTINS, generate_expr_kotlin)) break;
+            if (emit_select(sb, node, "run { val c = @0; val a = @1; val b = @2; if (c) a else b }",
+                            generate_expr_kotlin)) break;
             if (node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0)) sb_append(sb, "println(");
+            else if (node->value && strcmp(node->value, "len") == 0 && node->child_count == 1) {
+                sb_append(sb, "("); generate_expr_kotlin(sb, node->children[0]);
+                sb_append(sb, node->children[0]->data_type == TYPE_STRING ? ").length" : ").size"); break;
+            }
             else if (node->value) sb_append(sb, "%s(", node->value);
             else { generate_expr_kotlin(sb, node->left); sb_append(sb, "("); }
             for (int i = 0; i < node->child_count; i++) {
@@ -1253,6 +1926,32 @@ static void generate_expr_kotlin(StringBuilder *sb, ASTNode *node) {
                 generate_expr_kotlin(sb, node->children[i]);
             }
             sb_append(sb, ")"); break;
+        case AST_UNWRAP_EXPR:
+            generate_expr_kotlin(sb, node->left);
+            break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "when ("); generate_expr_kotlin(sb, node->left); sb_append(sb, ") { ");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    generate_expr_kotlin(sb, arm->left);
+                    /* SUB ints are Long in Kotlin */
+                    if (arm->left->data_type == TYPE_INT) sb_append(sb, "L");
+                    sb_append(sb, " -> ");
+                } else {
+                    sb_append(sb, "else -> ");
+                }
+                generate_expr_kotlin(sb, arm->right);
+                sb_append(sb, i < node->child_count - 1 ? "; " : " }");
+            }
+            break;
+        case AST_CAST_EXPR:
+            sb_append(sb, "("); generate_expr_kotlin(sb, node->right);
+            if (node->data_type == TYPE_INT) sb_append(sb, ").toLong()");
+            else if (node->data_type == TYPE_FLOAT) sb_append(sb, ").toDouble()");
+            else if (node->data_type == TYPE_BOOL) sb_append(sb, ") != 0L");
+            else sb_append(sb, ").toString()");
+            break;
         default: break;
     }
 }
@@ -1380,14 +2079,29 @@ static void generate_node_kotlin(StringBuilder *sb, ASTNode *node, int indent) {
             break;
         case AST_CALL_EXPR:
             indent_code(sb, indent); generate_expr_kotlin(sb, node); sb_append(sb, "\n"); break;
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "kotlin.system.exitProcess((");
+            generate_expr_kotlin(sb, node->right);
+            sb_append(sb, ").toInt())\n");
+            break;
         default: break;
     }
 }
 
 char* codegen_kotlin(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Kotlin")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
-    sb_append(sb, "// Generated by SUB\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n\n", codegen_banner());
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "fun _subSubstr(s: String, start: Long, n: Long): String {\n");
+        sb_append(sb, "    if (start < 0 || n < 0 || start > s.length || n > s.length - start) {\n");
+        sb_append(sb, "        throw IndexOutOfBoundsException(\"substr(): start $start, length $n is out of range for a string of length ${s.length}\")\n");
+        sb_append(sb, "    }\n");
+        sb_append(sb, "    return s.substring(start.toInt(), (start + n).toInt())\n");
+        sb_append(sb, "}\n\n");
+    }
     char *e = extract_embedded_code(source, "kotlin");
     if (e) {
         sb_append(sb, "%s\n", e);
@@ -1430,6 +2144,13 @@ static void indent_ruby(StringBuilder *sb, int level) {
 
 static void generate_node_ruby(StringBuilder *sb, ASTNode *node, int indent);
 
+static const char *const RUBY_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "_sub_substr(@0, @1, @2)",
+    "(@0.index(@1) || -1)",
+    "@0.upcase",
+    "@0.downcase",
+};
+
 static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
 
@@ -1439,7 +2160,7 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
                 char *escaped = escape_string_for_codegen(node->value ? node->value : "");
                 sb_append(sb, "\"%s\"", escaped ? escaped : "");
                 free(escaped);
-            } else if (node->value) {
+            } else if (node->value && node->data_type != TYPE_NULL) {
                 sb_append(sb, "%s", node->value);
             } else {
                 sb_append(sb, "nil");
@@ -1459,6 +2180,8 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
             break;
 
         case AST_CALL_EXPR: {
+            if (emit_string_builtin(sb, node, RUBY_STRING_BUILTINS, generate_expr_ruby)) break;
+            if (emit_select(sb, node, "->(c, a, b) { c ? a : b }.(@0, @1, @2)", generate_expr_ruby)) break;
             const char *func_name = node->value ? node->value : "func";
             if (strcmp(func_name, "print") == 0 || strcmp(func_name, "show") == 0) {
                 sb_append(sb, "puts");
@@ -1466,6 +2189,10 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
                     sb_append(sb, " ");
                     generate_expr_ruby(sb, node->children[0]);
                 }
+            } else if (strcmp(func_name, "len") == 0 && node->child_count == 1) {
+                sb_append(sb, "(");
+                generate_expr_ruby(sb, node->children[0]);
+                sb_append(sb, ").length");
             } else {
                 if (node->value) {
                     sb_append(sb, "%s(", func_name);
@@ -1482,6 +2209,14 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
             break;
         }
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                bool shared = repeat_shares_value(node);
+                sb_append(sb, shared ? "Array.new(%d, " : "_sub_fill(", ast_array_length(node));
+                generate_expr_ruby(sb, node->children[0]);
+                if (shared) sb_append(sb, ")");
+                else sb_append(sb, ", %d)", ast_array_length(node));
+                break;
+            }
             sb_append(sb, "[");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -1489,6 +2224,13 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
             }
             sb_append(sb, "]");
             break;
+        case AST_RANGE_EXPR:
+            sb_append(sb, "(");
+            generate_expr_ruby(sb, node->left);
+            sb_append(sb, "...");
+            generate_expr_ruby(sb, node->right);
+            sb_append(sb, ").to_a");
+            break;
         case AST_OBJECT_LITERAL:
             sb_append(sb, "{");
             for (int i = 0; i < node->child_count; i++) {
@@ -1506,11 +2248,47 @@ static void generate_expr_ruby(StringBuilder *sb, ASTNode *node) {
             break;
         case AST_ARRAY_ACCESS:
             generate_expr_ruby(sb, node->left);
-            sb_append(sb, "[");
+            sb_append(sb, index_needs_guard(node->right) ? "[_sub_index(" : "[");
             generate_expr_ruby(sb, node->right);
-            sb_append(sb, "]");
+            sb_append(sb, index_needs_guard(node->right) ? ")]" : "]");
             break;
 
+        case AST_UNWRAP_EXPR:
+            if (node->value) sb_append(sb, "_sub_unwrap(");
+            generate_expr_ruby(sb, node->left);
+            if (node->value) sb_append(sb, ", %d)", node->line);
+            break;
+        case AST_TUPLE_EXPR:
+            /* Tuples are arrays */
+            sb_append(sb, "[");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_ruby(sb, node->children[i]);
+            }
+            sb_append(sb, "]");
+            break;
+        case AST_MATCH_EXPR:
+            sb_append(sb, "(case "); generate_expr_ruby(sb, node->left);
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                if (arm->left && i < node->child_count - 1) {
+                    sb_append(sb, " when "); generate_expr_ruby(sb, arm->left);
+                    sb_append(sb, " then ");
+                } else {
+                    sb_append(sb, " else ");
+                }
+                generate_expr_ruby(sb, arm->right);
+            }
+            sb_append(sb, " end)");
+            break;
+        case AST_CAST_EXPR:
+            sb_append(sb, "(");
+            generate_expr_ruby(sb, node->right);
+            if (node->data_type == TYPE_INT) sb_append(sb, ").to_i");
+            else if (node->data_type == TYPE_FLOAT) sb_append(sb, ").to_f");
+            else if (node->data_type == TYPE_BOOL) sb_append(sb, " != 0)");
+            else sb_append(sb, ").to_s");
+            break;
         default:
             break;
     }
@@ -1548,6 +2326,16 @@ static void generate_node_ruby(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "\n");
             break;
 
+        case AST_DESTRUCTURE_DECL:
+            indent_ruby(sb, indent);
+            for (int i = 0; i < node->child_count; i++) {
+                sb_append(sb, "%s%s", i > 0 ? ", " : "", node->children[i]->value);
+            }
+            sb_append(sb, " = ");
+            generate_expr_ruby(sb, node->right);
+            sb_append(sb, "\n");
+            break;
+
         case AST_FUNCTION_DECL:
             sb_append(sb, "\n");
             indent_ruby(sb, indent);
@@ -1638,11 +2426,16 @@ static void generate_node_ruby(StringBuilder *sb, ASTNode *node, int indent) {
                     generate_expr_ruby(sb, range);
                 }
             } else if (node->condition) {
+                sb_append(sb, "(");
                 generate_expr_ruby(sb, node->condition);
+                sb_append(sb, ")");
             } else {
                 sb_append(sb, "(0...10)");  // Legacy fallback
             }
-            sb_append(sb, ".each do |%s|\n", node->value ? node->value : "i");
+            // Strings have no .each; .each_char yields one-character strings
+            sb_append(sb, node->condition && node->condition->data_type == TYPE_STRING
+                              ? ".each_char do |%s|\n" : ".each do |%s|\n",
+                      node->value ? node->value : "i");
             generate_node_ruby(sb, node->body, indent + 1);
             if (!node->body) {
                 indent_ruby(sb, indent + 1);
@@ -1707,17 +2500,49 @@ static void generate_node_ruby(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "# UI: %s\n", node->value ? node->value : "component");
             break;
 
+        case AST_EXIT_STMT:
+            indent_ruby(sb, indent);
+            sb_append(sb, "exit(");
+            generate_expr_ruby(sb, node->right);
+            sb_append(sb, ")\n");
+            break;
         default:
             break;
     }
 }
 
 char* codegen_ruby(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Ruby")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
 
     sb_append(sb, "#!/usr/bin/env ruby\n");
-    sb_append(sb, "# Generated by SUB Language Compiler\n\n");
+    if (codegen_banner()) sb_append(sb, "# %s\n\n", codegen_banner());
+    if (ast_needs_index_guard(ast)) {
+        sb_append(sb, "def _sub_index(i)\n");
+        sb_append(sb, "  raise IndexError, \"negative array index #{i}\" if i < 0\n");
+        sb_append(sb, "  i\n");
+        sb_append(sb, "end\n\n");
+    }
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "def _sub_substr(s, start, n)\n");
+        sb_append(sb, "  if start < 0 || n < 0 || start > s.length || n > s.length - start\n");
+        sb_append(sb, "    raise IndexError, \"substr(): start #{start}, length #{n} is out of range for a string of length #{s.length}\"\n");
+        sb_append(sb, "  end\n");
+        sb_append(sb, "  s[start, n]\n");
+        sb_append(sb, "end\n\n");
+    }
+    if (ast_contains_type(ast, AST_UNWRAP_EXPR)) {
+        sb_append(sb, "def _sub_unwrap(v, line)\n");
+        sb_append(sb, "  raise TypeError, \"line #{line}: unwrapped a null optional value\" if v.nil?\n");
+        sb_append(sb, "  v\n");
+        sb_append(sb, "end\n\n");
+    }
+    if (ast_needs_fill(ast, true)) {
+        sb_append(sb, "def _sub_fill(v, n)\n");
+        sb_append(sb, "  Array.new(n) { Marshal.load(Marshal.dump(v)) }\n");
+        sb_append(sb, "end\n\n");
+    }
 
     char *embedded = extract_embedded_code(source, "ruby");
     if (embedded) {
@@ -1740,21 +2565,32 @@ static void indent_go(StringBuilder *sb, int level) {
     }
 }
 
-static bool ast_needs_fmt(ASTNode *node) {
-    if (!node) return false;
+static bool uses_fmt(const ASTNode *node, const void *data) {
+    (void)data;
     if (node->type == AST_CALL_EXPR && node->value &&
         (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0))
         return true;
-    if (ast_needs_fmt(node->left)) return true;
-    if (ast_needs_fmt(node->right)) return true;
-    if (ast_needs_fmt(node->condition)) return true;
-    if (ast_needs_fmt(node->body)) return true;
-    if (ast_needs_fmt(node->next)) return true;
-    for (int i = 0; i < node->child_count; i++) {
-        if (node->children && ast_needs_fmt(node->children[i]))
-            return true;
+    /* string(x) and parsing casts go through fmt.Sprint / fmt.Sscan */
+    return node->type == AST_CAST_EXPR &&
+           (node->data_type == TYPE_STRING || (node->right && node->right->data_type == TYPE_STRING));
+}
+
+static bool ast_needs_fmt(ASTNode *node) {
+    return ast_find(node, uses_fmt, NULL) != NULL;
+}
+
+/* index_of / to_upper / to_lower map onto the strings package */
+static bool uses_strings(const ASTNode *node, const void *data) {
+    (void)data;
+    if (node->type == AST_CALL_EXPR) {
+        int builtin = string_builtin_lookup(node->value);
+        if (builtin >= 0 && builtin != STR_BUILTIN_SUBSTR) return true;
     }
-    return false;
+    return node->type == AST_FOR_STMT && node->condition && node->condition->data_type == TYPE_STRING;
+}
+
+static bool ast_needs_strings(ASTNode *node) {
+    return ast_find(node, uses_strings, NULL) != NULL;
 }
 
 static bool is_go_package_level_node(ASTNode *node) {
@@ -1777,6 +2613,13 @@ static bool is_go_package_level_node(ASTNode *node) {
 
 static void generate_node_go(StringBuilder *sb, ASTNode *node, int indent);
 
+static const char *const GO_STRING_BUILTINS[STR_BUILTIN_COUNT] = {
+    "subSubstr(@0, @1, @2)",
+    "int64(strings.Index(@0, @1))",
+    "strings.ToUpper(@0)",
+    "strings.ToLower(@0)",
+};
+
 static void generate_expr_go(StringBuilder *sb, ASTNode *node) {
     if (!node) return;
 
@@ -1821,6 +2664,23 @@ static void generate_expr_go(StringBuilder *sb, ASTNode *node) {
             break;
 
         case AST_CALL_EXPR: {
+            if (emit_string_builtin(sb, node, GO_STRING_BUILTINS, generate_expr_go)) break;
+            /* A concrete result type, so select(c, 1, 2) + 1 type-checks;
+               both arms are converted, since Go does not mix int and float64 */
+            const char *select_type = node->data_type == TYPE_INT ? "int" :
+                                      node->data_type == TYPE_FLOAT ? "float64" :
+                                      node->data_type == TYPE_BOOL ? "bool" :
+                                      node->data_type == TYPE_STRING ? "string" : NULL;
+            char select_template[160];
+            if (select_type) {
+                snprintf(select_template, sizeof(select_template),
+                         "func(c bool, a, b %s) %s { if c { return a }; return b }(@0, %s(@1), %s(@2))",
+                         select_type, select_type, select_type, select_type);
+            } else {
+                snprintf(select_template, sizeof(select_template), "%s",
+                         "func(c bool, a, b interface{}) interface{} { if c { return a }; return b }(@0, @1, @2)");
+            }
+            if (emit_select(sb, node, select_template, generate_expr_go)) break;
             const char *func_name = node->value ? node->value : "fn";
             if (strcmp(func_name, "print") == 0 || strcmp(func_name, "show") == 0) {
                 sb_append(sb, "fmt.Println(");
@@ -1848,6 +2708,12 @@ static void generate_expr_go(StringBuilder *sb, ASTNode *node) {
         }
 
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                sb_append(sb, "subFill(");
+                generate_expr_go(sb, node->children[0]);
+                sb_append(sb, ", %d)", ast_array_length(node));
+                break;
+            }
             sb_append(sb, "[]interface{}{");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -1857,6 +2723,20 @@ static void generate_expr_go(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, "}");
             break;
 
+        case AST_RANGE_EXPR:
+            sb_append(sb, "func(lo, hi int) []interface{} {\n"
+                          "\t\tr := []interface{}{}\n"
+                          "\t\tfor i := lo; i < hi; i++ {\n"
+                          "\t\t\tr = append(r, i)\n"
+                          "\t\t}\n"
+                          "\t\treturn r\n"
+                          "\t}(");
+            generate_expr_go(sb, node->left);
+            sb_append(sb, ", ");
+            generate_expr_go(sb, node->right);
+            sb_append(sb, ")");
+            break;
+
         case AST_OBJECT_LITERAL:
             sb_append(sb, "map[string]interface{}{");
             for (int i = 0; i < node->child_count; i++) {
@@ -1881,6 +2761,51 @@ static void generate_expr_go(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, "]");
             break;
 
+        case AST_UNWRAP_EXPR:
+            generate_expr_go(sb, node->left);
+            break;
+
+        case AST_MATCH_EXPR:
+            sb_append(sb, "func() interface{} { switch ");
+            generate_expr_go(sb, node->left);
+            sb_append(sb, " {");
+            for (int i = 0; i < node->child_count - 1; i++) {
+                ASTNode *arm = node->children[i];
+                if (!arm->left) continue;
+                sb_append(sb, " case ");
+                generate_expr_go(sb, arm->left);
+                sb_append(sb, ": return ");
+                generate_expr_go(sb, arm->right);
+                sb_append(sb, ";");
+            }
+            sb_append(sb, " }; return ");
+            if (node->child_count > 0) generate_expr_go(sb, node->children[node->child_count - 1]->right);
+            sb_append(sb, " }()");
+            break;
+        case AST_CAST_EXPR:
+            if (node->data_type == TYPE_INT && node->right && node->right->data_type == TYPE_STRING) {
+                sb_append(sb, "func() int64 { var n int64; fmt.Sscan(");
+                generate_expr_go(sb, node->right);
+                sb_append(sb, ", &n); return n }()");
+                break;
+            }
+            if (node->data_type == TYPE_FLOAT && node->right && node->right->data_type == TYPE_STRING) {
+                sb_append(sb, "func() float64 { var f float64; fmt.Sscan(");
+                generate_expr_go(sb, node->right);
+                sb_append(sb, ", &f); return f }()");
+                break;
+            }
+            if (node->data_type == TYPE_BOOL) {
+                sb_append(sb, "((");
+                generate_expr_go(sb, node->right);
+                sb_append(sb, ") != 0)");
+                break;
+            }
+            sb_append(sb, "%s(", node->data_type == TYPE_INT ? "int64" :
+                                 node->data_type == TYPE_FLOAT ? "float64" : "fmt.Sprint");
+            generate_expr_go(sb, node->right);
+            sb_append(sb, ")");
+            break;
         default:
             break;
     }
@@ -2011,6 +2936,12 @@ static void generate_node_go(StringBuilder *sb, ASTNode *node, int indent) {
                     generate_expr_go(sb, range);
                     sb_append(sb, " {\n");
                 }
+            } else if (node->condition && node->condition->data_type == TYPE_STRING) {
+                /* Ranging over a string gives runes; split it into strings */
+                sb_append(sb, "for _, %s := range strings.Split(",
+                          node->value ? node->value : "item");
+                generate_expr_go(sb, node->condition);
+                sb_append(sb, ", \"\") {\n");
             } else if (node->condition) {
                 sb_append(sb, "for _, %s := range ",
                           node->value ? node->value : "item");
@@ -2144,22 +3075,38 @@ static void generate_node_go(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, "// UI: %s\n", node->value ? node->value : "component");
             break;
 
+        case AST_EXIT_STMT:
+            indent_go(sb, indent);
+            sb_append(sb, "os.Exit(int(");
+            generate_expr_go(sb, node->right);
+            sb_append(sb, "))\n");
+            break;
         default:
             break;
     }
 }
 
 char* codegen_go(ASTNode *ast, const char *source) {
+    if (!codegen_check_support(ast, "Go")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
 
-    sb_append(sb, "// Generated by SUB Language Compiler\n\n");
+    if (codegen_banner()) sb_append(sb, "// %s\n\n", codegen_banner());
 
     sb_append(sb, "package main\n\n");
 
-    bool needs_fmt = ast_needs_fmt(ast);
-    if (needs_fmt) {
-        sb_append(sb, "import \"fmt\"\n\n");
+    const char *imports[3];
+    int import_count = 0;
+    bool substr = ast_calls_function(ast, "substr");
+    if (ast_needs_fmt(ast) || substr) imports[import_count++] = "fmt";
+    if (ast_contains_type(ast, AST_EXIT_STMT)) imports[import_count++] = "os";
+    if (ast_needs_strings(ast)) imports[import_count++] = "strings";
+    if (import_count == 1) {
+        sb_append(sb, "import \"%s\"\n\n", imports[0]);
+    } else if (import_count > 1) {
+        sb_append(sb, "import (\n");
+        for (int i = 0; i < import_count; i++) sb_append(sb, "\t\"%s\"\n", imports[i]);
+        sb_append(sb, ")\n\n");
     }
 
     char *embedded = extract_embedded_code(source, "go");
@@ -2167,6 +3114,35 @@ char* codegen_go(ASTNode *ast, const char *source) {
         sb_append(sb, "%s\n", embedded);
         free(embedded);
     }
+    if (substr) {
+        sb_append(sb, "func subSubstr(s string, start, n int64) string {\n"
+                      "\tif start < 0 || n < 0 || start > int64(len(s)) || n > int64(len(s))-start {\n"
+                      "\t\tpanic(fmt.Sprintf(\"substr(): start %%d, length %%d is out of range for a string of length %%d\", start, n, len(s)))\n"
+                      "\t}\n"
+                      "\treturn s[start : start+n]\n"
+                      "}\n\n");
+    }
+    if (ast_needs_fill(ast, false)) {
+        /* slices share their backing array, so each slot gets its own copy */
+        sb_append(sb, "func subCopy(v interface{}) interface{} {\n"
+                      "\ta, ok := v.([]interface{})\n"
+                      "\tif !ok {\n"
+                      "\t\treturn v\n"
+                      "\t}\n"
+                      "\tc := make([]interface{}, len(a))\n"
+                      "\tfor i := range a {\n"
+                      "\t\tc[i] = subCopy(a[i])\n"
+                      "\t}\n"
+                      "\treturn c\n"
+                      "}\n\n"
+                      "func subFill(v interface{}, n int) []interface{} {\n"
+                      "\tr := make([]interface{}, n)\n"
+                      "\tfor i := range r {\n"
+                      "\t\tr[i] = subCopy(v)\n"
+                      "\t}\n"
+                      "\treturn r\n"
+                      "}\n\n");
+    }
 
     /* Pass 1: emit package-level declarations, track main and executable stmts */
     bool has_user_main = false;
@@ -2193,6 +3169,10 @@ char* codegen_go(ASTNode *ast, const char *source) {
         }
         sb_append(sb, has_user_main ? "func init() {\n" : "func main() {\n");
         for (ASTNode *stmt = block_first(ast); stmt; stmt = stmt->next) {
+            /* Go runs main() itself once init() returns, so a top-level
+               main() call (or --entry main) must not run it a second time */
+            if (has_user_main && stmt->type == AST_CALL_EXPR && stmt->child_count == 0 &&
+                stmt->value && strcmp(stmt->value, "main") == 0) continue;
             if (!is_go_package_level_node(stmt)) {
                 generate_node_go(sb, stmt, 1);
             }
@@ -2202,3 +3182,22 @@ char* codegen_go(ASTNode *ast, const char *source) {
 
     return sb_to_string(sb);
 }
+
+char* codegen_generate_language(const char *name, ASTNode *ast, const char *source) {
+    /* C target → use the existing platform codegen (produces C code) */
+    if (strcasecmp(name, "c") == 0) return codegen_generate(ast, PLATFORM_LINUX);
+    if (strcasecmp(name, "cpp") == 0 || strcasecmp(name, "c++") == 0) return codegen_cpp_generate(ast, source);
+    /* TypeScript → the JavaScript codegen with type annotations */
+    if (strcasecmp(name, "typescript") == 0 || strcasecmp(name, "ts") == 0) return codegen_typescript(ast, source);
+    if (strcasecmp(name, "python") == 0     || strcasecmp(name, "py") == 0)     return codegen_python(ast, source);
+    if (strcasecmp(name, "javascript") == 0 || strcasecmp(name, "js") == 0)     return codegen_javascript(ast, source);
+    if (strcasecmp(name, "java") == 0)                                          return codegen_java(ast, source);
+    if (strcasecmp(name, "swift") == 0)                                         return codegen_swift(ast, source);
+    if (strcasecmp(name, "kotlin") == 0     || strcasecmp(name, "kt") == 0)     return codegen_kotlin(ast, source);
+    if (strcasecmp(name, "rust") == 0       || strcasecmp(name, "rs") == 0)     return codegen_rust(ast, source);
+    if (strcasecmp(name, "go") == 0         || strcasecmp(name, "golang") == 0) return codegen_go(ast, source);
+    if (strcasecmp(name, "ruby") == 0       || strcasecmp(name, "rb") == 0)     return codegen_ruby(ast, source);
+    if (strcasecmp(name, "assembly") == 0   || strcasecmp(name, "asm") == 0)    return codegen_assembly(ast, source);
+    if (strcasecmp(name, "css") == 0)                                           return codegen_css(ast, source);
+    return NULL;
+}
@@ -31,6 +31,11 @@ static StringBuilder* sb_create(void) {
     return sb;
 }
 
+/* The Rust type of a SUB int */
+static const char* rust_int(void) {
+    return int_width() == 32 ? "i32" : "i64";
+}
+
 static void sb_free(StringBuilder *sb) {
     if (sb) {
         free(sb->buffer);
@@ -81,6 +86,48 @@ static void indent_code(StringBuilder *sb, int level) {
 
 static void generate_expr_rust(StringBuilder *sb, ASTNode *node);
 
+/* The Rust type of a value of `type`, or NULL when it is not known;
+   `tuple` describes tuple types */
+static bool rust_value_type(StringBuilder *sb, DataType type, const ASTNode *tuple) {
+    switch (type) {
+        case TYPE_INT: sb_append(sb, "%s", rust_int()); return true;
+        case TYPE_FLOAT: sb_append(sb, "f64"); return true;
+        case TYPE_STRING: sb_append(sb, "String"); return true;
+        case TYPE_BOOL: sb_append(sb, "bool"); return true;
+        case TYPE_TUPLE:
+            if (!tuple) return false;
+            sb_append(sb, "(");
+            for (int i = 0; i < tuple->child_count; i++) {
+                const ASTNode *elem = tuple->children[i];
+                if (i > 0) sb_append(sb, ", ");
+                if (!rust_value_type(sb, elem->data_type, elem->tuple)) sb_append(sb, "_");
+            }
+            sb_append(sb, ")");
+            return true;
+        default:
+            return false;
+    }
+}
+
+/* Tuples have no Display, so print one element at a time: the format
+   string goes to fmt and the field paths (t.0, t.1.0, ...) to args */
+static void rust_tuple_format(StringBuilder *fmt, StringBuilder *args, const ASTNode *tuple, const char *path) {
+    sb_append(fmt, "(");
+    for (int i = 0; i < tuple->child_count; i++) {
+        const ASTNode *elem = tuple->children[i];
+        char field[128];
+        snprintf(field, sizeof(field), "%s.%d", path, i);
+        if (i > 0) sb_append(fmt, ", ");
+        if (elem->data_type == TYPE_TUPLE && elem->tuple) {
+            rust_tuple_format(fmt, args, elem->tuple, field);
+        } else {
+            sb_append(fmt, "{}");
+            sb_append(args, ", %s", field);
+        }
+    }
+    sb_append(fmt, ")");
+}
+
 static ASTNode* block_first(ASTNode *node) {
     if (!node) return NULL;
     if (node->body) return node->body;
@@ -89,21 +136,6 @@ static ASTNode* block_first(ASTNode *node) {
     return NULL;
 }
 
-static bool ast_contains_object(ASTNode *node) {
-    if (!node) return false;
-    if (node->type == AST_OBJECT_LITERAL) return true;
-    if (node->left && ast_contains_object(node->left)) return true;
-    if (node->right && ast_contains_object(node->right)) return true;
-    if (node->condition && ast_contains_object(node->condition)) return true;
-    if (node->body && ast_contains_object(node->body)) return true;
-    if (node->children) {
-        for (int i = 0; i < node->child_count; i++) {
-            if (ast_contains_object(node->children[i])) return true;
-        }
-    }
-    return false;
-}
-
 static void generate_node_rust(StringBuilder *sb, ASTNode *node, int indent) {
     if (!node) return;
     
@@ -125,13 +157,31 @@ static void generate_node_rust(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, ";\n");
             break;
             
+        case AST_DESTRUCTURE_DECL:
+            indent_code(sb, indent);
+            sb_append(sb, "let (");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *target = node->children[i];
+                sb_append(sb, "%s%s%s", i > 0 ? ", " : "", target->type == AST_CONST_DECL ? "" : "mut ",
+                          target->value);
+            }
+            sb_append(sb, ") = ");
+            generate_expr_rust(sb, node->right);
+            sb_append(sb, ";\n");
+            break;
+
         case AST_FUNCTION_DECL:
             sb_append(sb, "\nfn %s(", node->value ? node->value : "func");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
-                sb_append(sb, "%s: i64", node->children[i]->value ? node->children[i]->value : "arg");
+                sb_append(sb, "%s: %s", node->children[i]->value ? node->children[i]->value : "arg", rust_int());
+            }
+            sb_append(sb, ")");
+            if (node->tuple) {
+                sb_append(sb, " -> ");
+                rust_value_type(sb, TYPE_TUPLE, node->tuple);
             }
-            sb_append(sb, ") {\n");
+            sb_append(sb, " {\n");
             if (node->body) {
                 generate_node_rust(sb, node->body, indent + 1);
             }
@@ -179,10 +229,15 @@ static void generate_node_rust(StringBuilder *sb, ASTNode *node, int indent) {
                     generate_expr_rust(sb, range->left);
                 }
                 sb_append(sb, " {\n");
+            } else if (node->condition && node->condition->data_type == TYPE_STRING) {
+                sb_append(sb, "for %s in (", node->value ? node->value : "item");
+                generate_expr_rust(sb, node->condition);
+                sb_append(sb, ").chars().map(|c| c.to_string()) {\n");
             } else if (node->condition) {
+                /* Iterate a copy so the vector is still usable afterwards */
                 sb_append(sb, "for %s in ", node->value ? node->value : "item");
                 generate_expr_rust(sb, node->condition);
-                sb_append(sb, " {\n");
+                sb_append(sb, node->condition->type == AST_IDENTIFIER ? ".clone() {\n" : " {\n");
             } else {
                 sb_append(sb, "for %s in 0..10 {\n", node->value ? node->value : "i");
             }
@@ -221,6 +276,12 @@ static void generate_node_rust(StringBuilder *sb, ASTNode *node, int indent) {
             sb_append(sb, ";\n");
             break;
             
+        case AST_EXIT_STMT:
+            indent_code(sb, indent);
+            sb_append(sb, "std::process::exit((");
+            generate_expr_rust(sb, node->right);
+            sb_append(sb, ") as i32);\n");
+            break;
         default:
             break;
     }
@@ -261,10 +322,62 @@ static void generate_expr_rust(StringBuilder *sb, ASTNode *node) {
             sb_append(sb, " }");
             break;
         case AST_CALL_EXPR:
-            if (node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0)) {
+            if (node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0) &&
+                node->child_count > 0 && node->children[0]->data_type == TYPE_TUPLE && node->children[0]->tuple) {
+                StringBuilder *fmt = sb_create(), *args = sb_create();
+                if (fmt && args) {
+                    rust_tuple_format(fmt, args, node->children[0]->tuple, "t");
+                    sb_append(sb, "{ let t = &");
+                    generate_expr_rust(sb, node->children[0]);
+                    sb_append(sb, "; println!(\"%s\"%s) }", fmt->buffer, args->buffer);
+                }
+                sb_free(fmt);
+                sb_free(args);
+            } else if (node->value && (strcmp(node->value, "print") == 0 || strcmp(node->value, "show") == 0)) {
                 sb_append(sb, "println!(\"{}\", ");
                 if (node->child_count > 0) generate_expr_rust(sb, node->children[0]);
                 sb_append(sb, ")");
+            } else if (node->value && strcmp(node->value, "select") == 0 && node->child_count == 3) {
+                /* The tuple evaluates every argument before one is picked */
+                sb_append(sb, "{ let (c, a, b) = (");
+                for (int i = 0; i < 3; i++) {
+                    if (i > 0) sb_append(sb, ", ");
+                    generate_expr_rust(sb, node->children[i]);
+                }
+                sb_append(sb, "); if c { a } else { b } }");
+            } else if (node->value && strcmp(node->value, "len") == 0 && node->child_count == 1) {
+                /* .len() is usize; SUB integers are i64 (i32 at --int-width 32) */
+                sb_append(sb, "(");
+                generate_expr_rust(sb, node->children[0]);
+                sb_append(sb, ".len() as %s)", rust_int());
+            } else if (node->value && string_builtin_lookup(node->value) >= 0 &&
+                       node->child_count == string_builtin_arity(string_builtin_lookup(node->value))) {
+                /* String builtins index by usize and report positions as i64 */
+                switch (string_builtin_lookup(node->value)) {
+                    case STR_BUILTIN_SUBSTR:
+                        sb_append(sb, "sub_substr(&");
+                        generate_expr_rust(sb, node->children[0]);
+                        sb_append(sb, ", ");
+                        generate_expr_rust(sb, node->children[1]);
+                        sb_append(sb, ", ");
+                        generate_expr_rust(sb, node->children[2]);
+                        sb_append(sb, ")");
+                        break;
+                    case STR_BUILTIN_INDEX_OF:
+                        generate_expr_rust(sb, node->children[0]);
+                        sb_append(sb, ".find(&*");
+                        generate_expr_rust(sb, node->children[1]);
+                        sb_append(sb, ").map(|i| i as %s).unwrap_or(-1)", rust_int());
+                        break;
+                    case STR_BUILTIN_TO_UPPER:
+                        generate_expr_rust(sb, node->children[0]);
+                        sb_append(sb, ".to_uppercase()");
+                        break;
+                    default:
+                        generate_expr_rust(sb, node->children[0]);
+                        sb_append(sb, ".to_lowercase()");
+                        break;
+                }
             } else {
                 if (node->value) {
                     sb_append(sb, "%s(", node->value);
@@ -280,6 +393,12 @@ static void generate_expr_rust(StringBuilder *sb, ASTNode *node) {
             }
             break;
         case AST_ARRAY_LITERAL:
+            if (ast_is_array_repeat(node)) {
+                sb_append(sb, "vec![");
+                generate_expr_rust(sb, node->children[0]);
+                sb_append(sb, "; %d]", ast_array_length(node));
+                break;
+            }
             sb_append(sb, "vec![");
             for (int i = 0; i < node->child_count; i++) {
                 if (i > 0) sb_append(sb, ", ");
@@ -287,6 +406,13 @@ static void generate_expr_rust(StringBuilder *sb, ASTNode *node) {
             }
             sb_append(sb, "]");
             break;
+        case AST_RANGE_EXPR:
+            sb_append(sb, "(");
+            generate_expr_rust(sb, node->left);
+            sb_append(sb, "..");
+            generate_expr_rust(sb, node->right);
+            sb_append(sb, ").collect::<Vec<_>>()");
+            break;
         case AST_OBJECT_LITERAL:
             sb_append(sb, "HashMap::from([");
             for (int i = 0; i < node->child_count; i++) {
@@ -309,6 +435,51 @@ static void generate_expr_rust(StringBuilder *sb, ASTNode *node) {
             generate_expr_rust(sb, node->right);
             sb_append(sb, "]");
             break;
+        case AST_UNWRAP_EXPR:
+            generate_expr_rust(sb, node->left);
+            break;
+        case AST_TUPLE_EXPR:
+            sb_append(sb, "(");
+            for (int i = 0; i < node->child_count; i++) {
+                if (i > 0) sb_append(sb, ", ");
+                generate_expr_rust(sb, node->children[i]);
+            }
+            sb_append(sb, ")");
+            break;
+        case AST_MATCH_EXPR: {
+            /* &* turns both String and &str into a &str the literals match */
+            bool string_subject = node->left && node->left->data_type == TYPE_STRING;
+            sb_append(sb, string_subject ? "match &*(" : "match ");
+            generate_expr_rust(sb, node->left);
+            sb_append(sb, string_subject ? ") {" : " {");
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *arm = node->children[i];
+                sb_append(sb, " ");
+                if (!arm->left || i == node->child_count - 1) sb_append(sb, "_");
+                else if (arm->left->data_type == TYPE_STRING) sb_append(sb, "\"%s\"", arm->left->value);
+                else generate_expr_rust(sb, arm->left);
+                sb_append(sb, " => ");
+                generate_expr_rust(sb, arm->right);
+                sb_append(sb, ",");
+            }
+            sb_append(sb, " }");
+            break;
+        }
+        case AST_CAST_EXPR: {
+            bool from_str = node->right && node->right->data_type == TYPE_STRING;
+            sb_append(sb, "(");
+            generate_expr_rust(sb, node->right);
+            if (node->data_type == TYPE_INT)
+                /* parse at 64 bits so a 32-bit --int-width wraps like the other targets */
+                sb_append(sb, from_str ? ".trim().parse::<i64>().unwrap_or(0) as %s)" : " as i64 as %s)", rust_int());
+            else if (node->data_type == TYPE_FLOAT)
+                sb_append(sb, from_str ? ".trim().parse::<f64>().unwrap_or(0.0))" : " as f64)");
+            else if (node->data_type == TYPE_BOOL)
+                sb_append(sb, " != 0)");
+            else
+                sb_append(sb, ").to_string()");
+            break;
+        }
         default:
             break;
     }
@@ -316,12 +487,22 @@ static void generate_expr_rust(StringBuilder *sb, ASTNode *node) {
 
 char* codegen_rust(ASTNode *ast, const char *source) {
     (void)source; // Source is not used yet in Rust codegen
+    if (!codegen_check_support(ast, "Rust")) return NULL;
     StringBuilder *sb = sb_create();
     if (!sb) return NULL;
-    sb_append(sb, "// Generated by SUB Language Compiler (Rust Target)\n\n");
-    if (ast_contains_object(ast)) {
+    if (codegen_banner()) sb_append(sb, "// %s (Rust Target)\n\n", codegen_banner());
+    if (ast_contains_type(ast, AST_OBJECT_LITERAL)) {
         sb_append(sb, "use std::collections::HashMap;\n\n");
     }
+    if (ast_calls_function(ast, "substr")) {
+        sb_append(sb, "fn sub_substr(s: &str, start: %s, n: %s) -> String {\n", rust_int(), rust_int());
+        sb_append(sb, "    let len = s.len() as %s;\n", rust_int());
+        sb_append(sb, "    if start < 0 || n < 0 || start > len || n > len - start {\n");
+        sb_append(sb, "        panic!(\"substr(): start {}, length {} is out of range for a string of length {}\", start, n, len);\n");
+        sb_append(sb, "    }\n");
+        sb_append(sb, "    s[start as usize..(start + n) as usize].to_string()\n");
+        sb_append(sb, "}\n\n");
+    }
 
     StringBuilder *main_sb = sb_create();
     if (!main_sb) {
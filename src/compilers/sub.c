@@ -14,17 +14,6 @@
 #include <strings.h>
 #endif
 
-/* External codegen functions (from codegen_multilang.c) */
-extern char* codegen_python(ASTNode *ast, const char *source);
-extern char* codegen_javascript(ASTNode *ast, const char *source);
-extern char* codegen_java(ASTNode *ast, const char *source);
-extern char* codegen_swift(ASTNode *ast, const char *source);
-extern char* codegen_kotlin(ASTNode *ast, const char *source);
-extern char* codegen_rust(ASTNode *ast, const char *source);
-extern char* codegen_go(ASTNode *ast, const char *source);
-extern char* codegen_ruby(ASTNode *ast, const char *source);
-extern char* codegen_assembly(ASTNode *ast, const char *source);
-extern char* codegen_css(ASTNode *ast, const char *source);
 
 /* Helper: strip directory and .sb/.sub extension from input filename */
 static void get_output_basename(const char *input_file, char *out, size_t n) {
@@ -40,6 +29,10 @@ static void get_output_basename(const char *input_file, char *out, size_t n) {
 
 // Utility: Read file contents
 char* read_file(const char *filename) {
+    if (path_is_directory(filename)) {
+        fprintf(stderr, "Error: %s is a directory, not a source file\n", filename);
+        return NULL;
+    }
     FILE *file = fopen(filename, "rb");
     if (!file) {
         fprintf(stderr, "Error: Cannot open file %s\n", filename);
@@ -79,18 +72,21 @@ char* read_file(const char *filename) {
     content[size] = '\0';
     
     fclose(file);
-    return content;
+    return decode_source(filename, content, (size_t)size);
 }
 
 // Utility: Write file contents
 void write_file(const char *filename, const char *content) {
-    FILE *file = fopen(filename, "w");
+    char *formatted = apply_output_newlines(content);
+    FILE *file = formatted ? fopen(filename, "wb") : NULL;
     if (!file) {
         fprintf(stderr, "Error: Cannot write to file %s\n", filename);
+        free(formatted);
         return;
     }
-    fprintf(file, "%s", content);
+    fputs(formatted, file);
     fclose(file);
+    free(formatted);
 }
 
 // Target type classification
@@ -156,43 +152,175 @@ static const TargetDescriptor* lookup_target(const char *name) {
     return NULL;
 }
 
-// Generate code for a language target. Returns allocated string or NULL.
-static char* generate_language_code(const char *name, ASTNode *ast, const char *source) {
-    /* C target → use the existing platform codegen (produces C code) */
-    if (strcasecmp(name, "c") == 0) {
-        return codegen_generate(ast, PLATFORM_LINUX);
+// Targets whose output is C: the C-backed platforms and the c language
+static bool target_is_c(const TargetDescriptor *target) {
+    if (target->kind == TARGET_KIND_LANGUAGE) return strcasecmp(target->name, "c") == 0;
+    return target->platform == PLATFORM_LINUX || target->platform == PLATFORM_WINDOWS ||
+           target->platform == PLATFORM_MACOS;
+}
+
+// A run hint is written for a file called "output"; print it for the file
+// actually written, so "node output.js" becomes "node hello.js"
+static void print_run_hint(const char *hint, const char *output_file) {
+    char stem[256];
+    snprintf(stem, sizeof(stem), "%s", output_file);
+    char *dot = strrchr(stem, '.');
+    if (dot && !strpbrk(dot, "/\\")) *dot = '\0';
+    // Compilers such as rustc put the program in the current directory
+    const char *base = stem;
+    for (const char *p = stem; *p; p++)
+        if (*p == '/' || *p == '\\') base = p + 1;
+
+    print_status("  ");
+    for (const char *p = hint; *p; ) {
+        if (strncmp(p, "./output", 8) == 0) {
+            print_status("./%s", base);
+            p += 8;
+        } else if (strncmp(p, "output", 6) == 0) {
+            print_status("%s", stem);
+            p += 6;
+        } else {
+            print_status("%c", *p++);
+        }
+    }
+    print_status("\n");
+}
+
+// Targets that can be built with --crate-type lib
+static bool target_supports_lib(const TargetDescriptor *target) {
+    if (target_is_c(target)) return true;
+    if (target->kind != TARGET_KIND_LANGUAGE) return false;
+    return strcmp(target->extension, ".js") == 0 || strcmp(target->extension, ".ts") == 0 ||
+           strcmp(target->extension, ".py") == 0;
+}
+
+// The header for a C library goes next to its .c file: foo.c gets foo.h,
+// with an include guard named after the file
+static char* generate_c_header(ASTNode *ast, const char *c_file, char *header, size_t n) {
+    snprintf(header, n, "%s", c_file);
+    size_t len = strlen(header);
+    if (len > 2 && strcmp(header + len - 2, ".c") == 0) header[len - 2] = '\0';
+    strncat(header, ".h", n - strlen(header) - 1);
+
+    char guard[256];
+    const char *base = header;
+    for (const char *p = header; *p; p++)
+        if (*p == '/' || *p == '\\') base = p + 1;
+    size_t g = 0;
+    for (const char *p = base; *p && g + 1 < sizeof(guard); p++) {
+        guard[g++] = isalnum((unsigned char)*p) ? (char)toupper((unsigned char)*p) : '_';
+    }
+    guard[g] = '\0';
+
+    return codegen_generate_c_header(ast, guard);
+}
+
+static bool write_c_header(ASTNode *ast, const char *c_file, char *header, size_t n) {
+    char *code = generate_c_header(ast, c_file, header, n);
+    if (!code) return false;
+    write_file(header, code);
+    free(code);
+    return true;
+}
+
+static void json_string(FILE *f, const char *s) {
+    fputc('"', f);
+    for (; *s; s++) {
+        unsigned char c = (unsigned char)*s;
+        if (c == '"' || c == '\\') fprintf(f, "\\%c", c);
+        else if (c < 0x20) fprintf(f, "\\u%04x", c);
+        else fputc(c, f);
     }
+    fputc('"', f);
+}
 
-    /* C++ target → use the dedicated C++ codegen */
-    if (strcasecmp(name, "cpp") == 0 || strcasecmp(name, "c++") == 0) {
-        return codegen_cpp_generate(ast, source);
+static const char* manifest_type(const ASTNode *node) {
+    return node->data_type == TYPE_UNKNOWN ? "auto" : data_type_to_string(node->data_type);
+}
+
+// --emit-manifest: a JSON sidecar next to the output (hello.py gets
+// hello.manifest.json) describing the build for other tools. The exports
+// are the functions and constants a --crate-type lib C header declares,
+// except for internal names that start with '_'.
+static bool write_manifest(const char *output_file, const CompiledArtifact *artifact,
+                           const TargetDescriptor *target, int opt_level, const char *source,
+                           ASTNode *ast) {
+    char path[300];
+    snprintf(path, sizeof(path), "%s", output_file);
+    char *dot = strrchr(path, '.');
+    if (dot && !strpbrk(dot, "/\\")) *dot = '\0';
+    strncat(path, ".manifest.json", sizeof(path) - strlen(path) - 1);
+    FILE *f = fopen(path, "w");
+    if (!f) {
+        fprintf(stderr, "Error: Cannot write manifest %s\n", path);
+        return false;
     }
 
-    /* TypeScript → same codegen as JavaScript */
-    if (strcasecmp(name, "typescript") == 0 || strcasecmp(name, "ts") == 0) {
-        return codegen_javascript(ast, source);
+    // 64-bit FNV-1a of the source text
+    unsigned long long hash = 0xcbf29ce484222325ULL;
+    for (const char *p = source; *p; p++) hash = (hash ^ (unsigned char)*p) * 0x100000001b3ULL;
+
+    fprintf(f, "{\n  \"input\": ");
+    json_string(f, source_path());
+    fprintf(f, ",\n  \"output\": ");
+    json_string(f, output_file);
+    fprintf(f, ",\n  \"target\": ");
+    json_string(f, artifact->target);
+    fprintf(f, ",\n  \"emit\": \"%s\"", emit_kind_name(artifact->emit));
+    fprintf(f, ",\n  \"size\": %zu", artifact->size);
+    fprintf(f, ",\n  \"crate_type\": \"%s\"", crate_type() == CRATE_TYPE_LIB ? "lib" : "bin");
+    // Only C output is optimized, and then compiled with gcc
+    if (target_is_c(target)) fprintf(f, ",\n  \"optimization_level\": %d", opt_level);
+    else fprintf(f, ",\n  \"optimization_level\": null");
+    fprintf(f, ",\n  \"int_width\": %d", int_width());
+    fprintf(f, ",\n  \"compiler_version\": \"%s\"", SUB_VERSION);
+    fprintf(f, ",\n  \"source_hash\": \"fnv1a64:%016llx\"", hash);
+    fprintf(f, ",\n  \"exports\": [");
+    bool first = true;
+    for (ASTNode *stmt = ast->body; stmt; stmt = stmt->next) {
+        if ((stmt->type != AST_FUNCTION_DECL && stmt->type != AST_CONST_DECL) || !stmt->value ||
+            stmt->value[0] == '_') continue;
+        fprintf(f, "%s\n    {\"name\": ", first ? "" : ",");
+        json_string(f, stmt->value);
+        first = false;
+        if (stmt->type == AST_CONST_DECL) {
+            fprintf(f, ", \"kind\": \"const\", \"type\": \"%s\"}", manifest_type(stmt));
+            continue;
+        }
+        fprintf(f, ", \"kind\": \"function\", \"params\": [");
+        bool first_param = true;
+        for (int i = 0; i < stmt->child_count; i++) {
+            ASTNode *param = stmt->children[i];
+            if (param->type != AST_PARAM_DECL) continue;
+            fprintf(f, "%s{\"name\": ", first_param ? "" : ", ");
+            json_string(f, param->value);
+            fprintf(f, ", \"type\": \"%s\"}", manifest_type(param));
+            first_param = false;
+        }
+        fprintf(f, "], \"returns\": \"%s\"}", manifest_type(stmt));
     }
+    fprintf(f, "%s]\n}\n", first ? "" : "\n  ");
+    fclose(f);
+    print_status("\u2713 Manifest written to: %s\n", path);
+    return true;
+}
 
-    /* Dispatch to the matching codegen function */
-    if (strcasecmp(name, "python") == 0     || strcasecmp(name, "py") == 0)     return codegen_python(ast, source);
-    if (strcasecmp(name, "javascript") == 0 || strcasecmp(name, "js") == 0)     return codegen_javascript(ast, source);
-    if (strcasecmp(name, "java") == 0)                                          return codegen_java(ast, source);
-    if (strcasecmp(name, "swift") == 0)                                         return codegen_swift(ast, source);
-    if (strcasecmp(name, "kotlin") == 0     || strcasecmp(name, "kt") == 0)     return codegen_kotlin(ast, source);
-    if (strcasecmp(name, "rust") == 0       || strcasecmp(name, "rs") == 0)     return codegen_rust(ast, source);
-    if (strcasecmp(name, "go") == 0         || strcasecmp(name, "golang") == 0) return codegen_go(ast, source);
-    if (strcasecmp(name, "ruby") == 0       || strcasecmp(name, "rb") == 0)     return codegen_ruby(ast, source);
-    if (strcasecmp(name, "assembly") == 0   || strcasecmp(name, "asm") == 0)    return codegen_assembly(ast, source);
-    if (strcasecmp(name, "css") == 0)                                           return codegen_css(ast, source);
-
-    fprintf(stderr, "Error: No codegen available for language '%s'\n", name);
-    return NULL;
+// --print-size: report what a file would hold instead of writing it
+static void print_size(const char *file, const char *code) {
+    char *formatted = apply_output_newlines(code);
+    const char *text = formatted ? formatted : code;
+    size_t lines = 0;
+    for (const char *p = text; *p; p++) {
+        if (*p == '\n') lines++;
+    }
+    printf("%s: %zu bytes, %zu lines (not written)\n", file, strlen(text), lines);
+    free(formatted);
 }
 
 // Print help / usage
 static void print_help(const char *prog) {
     printf(SUB_LOGO);
-    printf("Usage: %s <input.sb> [target] [--help]\n\n", prog);
+    printf("Usage: %s <input.sb> [target] [output] [--error-limit N] [--help]\n\n", prog);
     printf("Targets can be a PLATFORM or a LANGUAGE:\n\n");
     printf("  Platform targets (generates platform-specific code):\n");
     printf("    android          Java for Android\n");
@@ -222,12 +350,52 @@ static void print_help(const char *prog) {
     printf("  %s hello.sb rust         # Transpile to Rust (hello.rs)\n", prog);
     printf("  %s hello.sb js           # Transpile to JavaScript (hello.js)\n", prog);
     printf("  %s hello.sb --help       # Show this help message\n\n", prog);
+    printf("Options:\n");
+    printf("  --error-limit N          Stop lexing/parsing after N errors (0 = no limit)\n");
+    printf("  --tab-width N            Columns per tab in error excerpts (default 4)\n");
+    printf("  --encoding utf-8|latin-1 Encoding of the source file (default utf-8)\n");
+    printf("  --max-nesting N          Deepest nesting of expressions and blocks the parser\n");
+    printf("                           accepts (default 256)\n");
+    printf("  -W<lint>, -Wno-<lint>    Enable/disable a semantic warning (implicit-widening,\n");
+    printf("                           unused-variable, unused-function, integer-division,\n");
+    printf("                           pointless-statement, unknown-attribute,\n");
+    printf("                           float-precision, float-equality, infinite-loop,\n");
+    printf("                           unused-parameter, shadowing, unreachable-code,\n");
+    printf("                           constant-comparison, ignored-attribute)\n");
+    printf("  --strict                 Enable every warning and treat warnings as errors\n");
+    printf("  --dump-symbols           Print every scope and its bindings after semantic\n");
+    printf("                           analysis\n");
+    printf("  --stats                  Print token counts by kind, AST size and nesting\n");
+    printf("                           depth after parsing\n");
+    printf("  --overflow MODE          Integer overflow in C output: wrap (default), check\n");
+    printf("                           or saturate\n");
+    printf("  --int-width 32|64        Size of int and integer literals (default 64)\n");
+    printf("  --crate-type bin|lib     Build a program (default) or a library with no entry\n");
+    printf("                           point (C with a header, JavaScript, TypeScript, Python)\n");
+    printf("  --entry NAME             Call function NAME after the top-level statements\n");
+    printf("  --prelude FILE           Functions and constants every program may use\n");
+    printf("  --remap-path FROM=TO     Rewrite the FROM prefix of the source path embedded\n");
+    printf("                           in the output\n");
+    printf("  --banner TEXT            Comment line put at the top of the generated code\n");
+    printf("                           (default \"Generated by SUB Language Compiler vX\")\n");
+    printf("  --no-banner              Leave the banner comment out\n");
+    printf("  --output-eol lf|crlf     Line endings of the written files (default lf)\n");
+    printf("  --final-newline          End the output with exactly one line break\n");
+    printf("  --no-final-newline       End the output without a line break\n");
+    printf("  -q, --quiet              Print only diagnostics and requested output; no\n");
+    printf("                           banner, progress or success messages\n");
+    printf("  -O0 .. -O3               Optimization level of C output (default -O2), as in\n");
+    printf("                           subc; gcc gets the same level, at most -O2\n");
+    printf("  --emit-manifest          Also write NAME.manifest.json describing the build:\n");
+    printf("                           input, target, version, source hash and exports\n");
+    printf("  --source-map             Also write OUTPUT.map, a source map back to the .sb\n");
+    printf("                           file (JavaScript and TypeScript only)\n");
+    printf("  --print-size             Run every phase but only report the size of the\n");
+    printf("                           output; no file is written\n\n");
 }
 
 // Main function
 int main(int argc, char *argv[]) {
-    printf(SUB_LOGO);
-
     // Check for --help anywhere in argv
     for (int i = 1; i < argc; i++) {
         if (strcmp(argv[i], "--help") == 0 || strcmp(argv[i], "-h") == 0) {
@@ -236,6 +404,40 @@ int main(int argc, char *argv[]) {
         }
     }
     
+    if (!take_diagnostic_options(&argc, argv)) {
+        return 1;
+    }
+    print_status(SUB_LOGO);
+
+    bool dry_run = false;
+    bool emit_manifest = false;
+    bool source_map = false;
+    int opt_level = 2;
+    int out = 1;
+    for (int i = 1; i < argc; i++) {
+        if (strcmp(argv[i], "--print-size") == 0) {
+            dry_run = true;
+        } else if (strcmp(argv[i], "--emit-manifest") == 0) {
+            emit_manifest = true;
+        } else if (strcmp(argv[i], "--source-map") == 0) {
+            source_map = true;
+        } else if (strncmp(argv[i], "-O", 2) == 0) {
+            if (argv[i][2] < '0' || argv[i][2] > '3' || argv[i][3]) {
+                fprintf(stderr, "Error: unknown optimization level '%s' (use -O0 to -O3)\n", argv[i]);
+                return 1;
+            }
+            opt_level = argv[i][2] - '0';
+        } else {
+            argv[out++] = argv[i];
+        }
+    }
+    argc = out;
+    argv[out] = NULL;
+    // The same passes as subc at each level
+    optimizer_set_licm(opt_level >= 3);
+    optimizer_set_inlining(opt_level >= 2);
+    optimizer_set_unrolling(opt_level >= 3);
+
     if (argc < 2) {
         print_help(argv[0]);
         return 1;
@@ -246,7 +448,11 @@ int main(int argc, char *argv[]) {
 
     // Direct interpreter run
     if (strcasecmp(target_str, "interpret") == 0 || strcasecmp(target_str, "run") == 0) {
-        printf("Interpreting %s...\n\n", input_file);
+        if (crate_type() == CRATE_TYPE_LIB) {
+            fprintf(stderr, "Error: a --crate-type lib program has no entry point to run\n");
+            return 1;
+        }
+        print_status("Interpreting %s...\n\n", input_file);
         extern int interpret_file(const char *path);
         return interpret_file(input_file);
     }
@@ -259,53 +465,41 @@ int main(int argc, char *argv[]) {
         return 1;
     }
     
-    printf("Compiling %s for %s...\n\n", input_file, target_str);
+    if (crate_type() == CRATE_TYPE_LIB && !target_supports_lib(target)) {
+        fprintf(stderr, "Error: --crate-type lib is not supported for target '%s'\n", target_str);
+        fprintf(stderr, "Libraries can be built for C (linux, windows, macos, c), JavaScript, TypeScript and Python.\n");
+        return 1;
+    }
+
+    if (source_map && strcmp(target->extension, ".js") != 0 && strcmp(target->extension, ".ts") != 0) {
+        fprintf(stderr, "Error: --source-map is only supported for JavaScript and TypeScript\n");
+        return 1;
+    }
+
+    print_status("Compiling %s for %s...\n\n", input_file, target_str);
     
     // Phase 1: Read source file
-    printf("[1/5] Reading source file...\n");
+    print_status("[1/2] Reading source file...\n");
     char *source = read_file(input_file);
     if (!source) return 1;
+    set_source_path(input_file);
     
-    // Phase 2: Lexical Analysis
-    printf("[2/5] Lexical analysis...\n");
-    int token_count;
-    Token *tokens = lexer_tokenize(source, &token_count);
-    printf("      Generated %d tokens\n", token_count);
-    
-    // Phase 3: Parsing
-    printf("[3/5] Parsing...\n");
-    ASTNode *ast = parser_parse(tokens, token_count);
-    printf("      AST created\n");
-    
-    // Phase 4: Semantic Analysis
-    printf("[4/5] Semantic analysis...\n");
-    if (!semantic_analyze(ast)) {
-        fprintf(stderr, "Semantic analysis failed\n");
+    // Phase 2: Lexing, parsing, semantic analysis and code generation
+    print_status("[2/2] Compiling for %s...\n", target_str);
+    CompilerContext *ctx = compiler_create(input_file);
+    if (!ctx) {
         free(source);
-        lexer_free_tokens(tokens, token_count);
-        parser_free_ast(ast);
         return 1;
     }
-    printf("      Passed\n");
-    
-    // Phase 5: Code Generation
-    printf("[5/5] Code generation for %s...\n", target_str);
-    
-    char *output_code = NULL;
-    
-    if (target->kind == TARGET_KIND_PLATFORM) {
-        /* Platform target → existing codegen_generate() */
-        output_code = codegen_generate(ast, target->platform);
-    } else {
-        /* Language target → multilang codegen */
-        output_code = generate_language_code(target_str, ast, source);
-    }
-    
-    if (!output_code) {
-        fprintf(stderr, "Code generation failed\n");
+    ctx->target = target_str;
+    ctx->options.source_map = source_map;
+    ctx->options.optimize = opt_level > 0;
+    ctx->options.optimization_level = opt_level;
+    const CompiledArtifact *artifact = compiler_compile_artifact(ctx, source);
+    if (!artifact->bytes) {
+        fprintf(stderr, "Compilation failed\n");
+        compiler_free(ctx);
         free(source);
-        lexer_free_tokens(tokens, token_count);
-        parser_free_ast(ast);
         return 1;
     }
     
@@ -321,35 +515,92 @@ int main(int argc, char *argv[]) {
     } else {
         snprintf(output_file, sizeof(output_file), "%s%s", base_name, target->extension);
     }
-    write_file(output_file, output_code);
+    char map_file[260];
+    snprintf(map_file, sizeof(map_file), "%s.map", output_file);
+
+    if (dry_run) {
+        printf("\n");
+        print_size(output_file, artifact->bytes);
+        if (artifact->source_map) print_size(map_file, artifact->source_map);
+        if (crate_type() == CRATE_TYPE_LIB && target_is_c(target)) {
+            char header[260];
+            char *header_code = generate_c_header(ctx->ast, output_file, header, sizeof(header));
+            if (header_code) print_size(header, header_code);
+            free(header_code);
+        }
+        compiler_free(ctx);
+        free(source);
+        return 0;
+    }
+
+    if (artifact->source_map) {
+        // Point the output at its map, which sits next to it
+        const char *map_name = map_file;
+        for (const char *p = map_file; *p; p++)
+            if (*p == '/' || *p == '\\') map_name = p + 1;
+        size_t n = artifact->size + strlen(map_name) + 32;
+        char *code = malloc(n);
+        if (!code) {
+            compiler_free(ctx);
+            free(source);
+            return 1;
+        }
+        snprintf(code, n, "%s//# sourceMappingURL=%s\n", artifact->bytes, map_name);
+        write_file(output_file, code);
+        free(code);
+        write_file(map_file, artifact->source_map);
+    } else {
+        write_file(output_file, artifact->bytes);
+    }
     
-    printf("\n\u2713 Compilation successful!\n");
-    printf("\u2713 Output written to: %s\n", output_file);
+    print_status("\n\u2713 Compilation successful!\n");
+    print_status("\u2713 Output written to: %s\n", output_file);
+    if (artifact->source_map) print_status("\u2713 Source map written to: %s\n", map_file);
+    if (emit_manifest && !write_manifest(output_file, artifact, target, opt_level, source, ctx->ast)) {
+        compiler_free(ctx);
+        free(source);
+        return 1;
+    }
     
-    // If it is a platform target that compiles to C under the hood, compile to machine code directly
-    if (target->kind == TARGET_KIND_PLATFORM && 
+    if (crate_type() == CRATE_TYPE_LIB) {
+        // A library is not compiled to an executable; C gets a header instead
+        char header[260];
+        if (target_is_c(target)) {
+            if (!write_c_header(ctx->ast, output_file, header, sizeof(header))) {
+                fprintf(stderr, "Error: Failed to generate the library header\n");
+                compiler_free(ctx);
+                free(source);
+                return 1;
+            }
+            print_status("\u2713 Header written to: %s\n", header);
+            print_status("\nNext steps:\n");
+            print_status("  Include %s and compile %s with your program\n", header, output_file);
+        } else {
+            print_status("\nNext steps:\n");
+            print_status("  Import the library from %s\n", output_file);
+        }
+    } else if (target->kind == TARGET_KIND_PLATFORM && 
         (target->platform == PLATFORM_LINUX || target->platform == PLATFORM_WINDOWS || target->platform == PLATFORM_MACOS)) {
         char compile_cmd[1024];
         const char *bin_ext = (target->platform == PLATFORM_WINDOWS) ? ".exe" : "";
-        snprintf(compile_cmd, sizeof(compile_cmd), "gcc -O2 -o %s%s %s", base_name, bin_ext, output_file);
-        printf("\nCompiling intermediate C code to native machine code...\n");
+        snprintf(compile_cmd, sizeof(compile_cmd), "gcc -O%d -o %s%s %s", opt_level > 2 ? 2 : opt_level,
+                 base_name, bin_ext, output_file);
+        print_status("\nCompiling intermediate C code to native machine code...\n");
         int ret = system(compile_cmd);
         if (ret == 0) {
-            printf("\u2705 Machine code compiled successfully: ./%s%s\n", base_name, bin_ext);
+            print_status("\u2705 Machine code compiled successfully: ./%s%s\n", base_name, bin_ext);
         } else {
             fprintf(stderr, "Warning: gcc compilation failed. Make sure gcc is installed.\n");
         }
     } else {
         // Print next steps for other targets
-        printf("\nNext steps:\n");
-        printf("  %s\n", target->run_hint);
+        print_status("\nNext steps:\n");
+        print_run_hint(target->run_hint, output_file);
     }
     
     // Cleanup
+    compiler_free(ctx);
     free(source);
-    lexer_free_tokens(tokens, token_count);
-    parser_free_ast(ast);
-    free(output_code);
     
     return 0;
 }
@@ -39,31 +39,101 @@ static void derive_output_name(const char *input, const char *user_out,
 /* Print usage */
 void print_usage_native(const char *prog_name) {
     printf(SUB_LOGO);
-    printf("Usage: %s <input.sb> [options]\n\n", prog_name);
+    printf("Usage: %s [build] <input.sb> [options]\n", prog_name);
+    printf("       %s [build] <input.sb> <target> [output] [--link] [-o <exe>]\n", prog_name);
+    printf("       %s check <input.sb|dir>... [--keep-going|--fail-fast] [options]\n", prog_name);
+    printf("       %s run <input.sb> [options]\n", prog_name);
+    printf("       %s fmt <input.sb|dir>... [--check|--write]\n", prog_name);
+    printf("       %s repl\n\n", prog_name);
+    printf("Commands:\n");
+    printf("  build              Compile (the default when no command is given)\n");
+    printf("  check              Lex, parse and analyze without writing any output; a\n");
+    printf("                     directory means the .sb files under it. With more than\n");
+    printf("                     one file, --keep-going (default) checks them all and\n");
+    printf("                     --fail-fast stops at the first that fails\n");
+    printf("  run                Interpret the program\n");
+    printf("  fmt                Re-indent the source and print it; --write rewrites\n");
+    printf("                     the files instead, and --check lists the ones that\n");
+    printf("                     would change and fails if there are any\n");
+    printf("  repl               Read statements from standard input and run each one\n");
+    printf("                     as it is entered, printing the value of an\n");
+    printf("                     expression; end with end-of-file (Ctrl-D)\n\n");
     printf("Output Options:\n");
-    printf("  -o <file>          Output filename (default: derived from input)\n\n");
+    printf("  -o <file>          Output filename (default: derived from input)\n");
+    printf("  --link             With a linux/windows/macos target, which also links the\n");
+    printf("                     generated C with cc (or $CC): fail if that fails\n");
+    printf("  --targets A,B,...  Analyze once and write one output per target, named\n");
+    printf("                     after the input (hello.c, hello.html, ...)\n\n");
     printf("Optimization:\n");
     printf("  -O0                No optimization (fast compile)\n");
     printf("  -O1                Basic optimization\n");
-    printf("  -O2                Standard optimization (default)\n");
-    printf("  -O3                Aggressive optimization\n\n");
+    printf("  -O2                Standard optimization (default; inlines small functions)\n");
+    printf("  -O3                Aggressive optimization (also unrolls small constant loops\n");
+    printf("                     and hoists loop-invariant code)\n");
+    printf("  --fast-math        Allow float rewrites that can change results (x + 0.0 -> x)\n");
+    printf("  --no-fast-math     Preserve IEEE float semantics (default)\n\n");
+    printf("Diagnostics:\n");
+    printf("  --error-limit N    Stop lexing/parsing after N errors (0 = no limit)\n");
+    printf("  --tab-width N      Columns per tab in error excerpts (default 4)\n");
+    printf("  --encoding ENC     Source encoding: utf-8 (default) or latin-1\n");
+    printf("  --max-nesting N    Deepest expression/block nesting accepted (default 256)\n");
+    printf("  -W<lint>           Enable a semantic warning (e.g. -Wunused-variable)\n");
+    printf("  -Wno-<lint>        Disable a semantic warning (e.g. -Wno-implicit-widening)\n");
+    printf("  --strict           Enable every warning and treat warnings as errors\n");
+    printf("  --dump-symbols     Print every scope and its bindings to stderr\n");
+    printf("  --stats            Print token counts and tree metrics after parsing\n\n");
+    printf("Runtime:\n");
+    printf("  --overflow MODE    Integer overflow: wrap (default), check or saturate\n");
+    printf("  --int-width 32|64  Size of int and integer literals (default 64)\n");
+    printf("  --entry NAME       Call function NAME after the top-level statements\n");
+    printf("  --prelude FILE     Functions and constants every program may use\n\n");
+    printf("Reproducibility:\n");
+    printf("  --remap-path FROM=TO  Rewrite the FROM prefix of the source path embedded\n");
+    printf("                        in the output\n");
+    printf("  --output-eol lf|crlf  Line endings of the written files (default lf)\n");
+    printf("  --final-newline       End the output with exactly one line break\n");
+    printf("  --no-final-newline    End the output without a line break\n\n");
     printf("Debug:\n");
-    printf("  -v, --verbose      Verbose output\n\n");
+    printf("  -v, --verbose      Verbose output\n");
+    printf("  -q, --quiet        Print only errors and warnings (overrides -v)\n");
+    printf("  --emit ast         Print the optimized AST with source spans and exit\n");
+    printf("                     (unoptimized if semantic analysis fails)\n");
+    printf("  --emit docs        Print a Markdown listing of the public functions and\n");
+    printf("                     their /// doc comments (to the -o file if given),\n");
+    printf("                     and exit\n");
+    printf("  --emit tokens      Print each token with its span and exit\n");
+    printf("  --emit tokens-json Print the tokens as JSON with byte offsets and exit\n");
+    printf("  --emit reachability  Print each statement's span and whether it can run,\n");
+    printf("                     and exit\n\n");
+    printf("Environment:\n");
+    printf("  SUBC_TARGET        Target to use when none is given (e.g. linux); an\n");
+    printf("                     explicit target overrides it\n\n");
     printf("Examples:\n");
     printf("  %s hello.sb                  # Compile to ./hello\n", prog_name);
     printf("  %s hello.sb -O3              # Max optimization\n", prog_name);
-    printf("  %s hello.sb -o myapp         # Custom output name\n\n", prog_name);
+    printf("  %s hello.sb -o myapp         # Custom output name\n", prog_name);
+    printf("  %s check hello.sb            # Report errors only\n", prog_name);
+    printf("  %s fmt --write src/          # Format every .sb file under src/\n\n", prog_name);
+}
+
+/* The C compiler flags every native build uses, before the output and input
+   names. -fwrapv makes plain signed arithmetic wrap, as --overflow wrap
+   promises. -ffile-prefix-map names the source file instead of the C file
+   compiled, so two builds of the same input are byte-identical. */
+static void native_cc_flags(char *buf, size_t n, int opt_level, bool fast_math, const char *c_file) {
+    const char *opt = opt_level >= 2 ? "-O2" : opt_level == 1 ? "-O1" : "-O0";
+    snprintf(buf, n, "%s%s%s -ffile-prefix-map=\"%s=%s\"", opt,
+             fast_math ? " -ffast-math" : "",
+             overflow_mode() == OVERFLOW_WRAP ? " -fwrapv" : "",
+             c_file, source_path());
 }
 
 int compile_to_native(const char *input_file, const char *output_name,
-                      bool verbose, int opt_level) {
+                      bool verbose, int opt_level, bool fast_math) {
     /* ---- Phase 1: Read source ---- */
-    FILE *f = fopen(input_file, "rb");
-    if (!f) { fprintf(stderr, "Cannot open: %s\n", input_file); return 1; }
-    fseek(f, 0, SEEK_END); long sz = ftell(f); fseek(f, 0, SEEK_SET);
-    char *source = malloc(sz + 1);
-    if (!source) { fclose(f); return 1; }
-    fread(source, 1, sz, f); source[sz] = '\0'; fclose(f);
+    char *source = read_file(input_file);
+    if (!source) return 1;
+    set_source_path(input_file);
 
     /* ---- Phase 2: Lex ---- */
     if (verbose) printf("[1/4] Lexing...\n");
@@ -76,8 +146,15 @@ int compile_to_native(const char *input_file, const char *output_name,
 
     /* ---- Phase 4: Semantic ---- */
     if (verbose) printf("[3/4] Semantic analysis...\n");
+#if defined(_WIN32)
+    set_active_target("windows");
+#elif defined(__APPLE__)
+    set_active_target("macos");
+#else
+    set_active_target("linux");
+#endif
     if (!semantic_analyze(ast)) {
-        fprintf(stderr, "Semantic analysis failed.\n");
+        if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed.\n");
         parser_free_ast(ast); lexer_free_tokens(tokens, ntok); free(source);
         return 1;
     }
@@ -99,9 +176,9 @@ int compile_to_native(const char *input_file, const char *output_name,
     fputs(c_code, cf); fclose(cf); free(c_code);
 
     /* ---- Phase 6: Compile with gcc ---- */
-    const char *opt = opt_level >= 2 ? "-O2" : opt_level == 1 ? "-O1" : "-O0";
-    char cmd[1024];
-    snprintf(cmd, sizeof(cmd), "gcc %s -o \"%s\" \"%s\"", opt, output_name, tmp_c);
+    char flags[1024], cmd[2048];
+    native_cc_flags(flags, sizeof(flags), opt_level, fast_math, tmp_c);
+    snprintf(cmd, sizeof(cmd), "gcc %s -o \"%s\" \"%s\"", flags, output_name, tmp_c);
     int ret = system(cmd);
     remove(tmp_c);
 
@@ -109,10 +186,152 @@ int compile_to_native(const char *input_file, const char *output_name,
         fprintf(stderr, "Compilation failed. Make sure gcc is installed.\n");
         return 1;
     }
-    printf("\u2705 Compiled: %s\n", output_name);
+    print_status("\u2705 Compiled: %s\n", output_name);
     return 0;
 }
 
+/* --emit ast: the tree the C backend would compile, after optimization.
+   A program with semantic errors still gets its tree, unoptimized, so the
+   dump can help debug it; the errors are reported and the exit code is 1. */
+static int emit_ast(const char *input_file) {
+    char *source = read_file(input_file);
+    if (!source) return 1;
+
+    int ntok;
+    Token *tokens = lexer_tokenize(source, &ntok);
+    ASTNode *ast = parser_parse(tokens, ntok);
+    int rc = 0;
+    if (!semantic_analyze(ast)) {
+        if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed.\n");
+        rc = 1;
+    } else {
+        optimize_c_output(ast);
+    }
+    print_ast(ast, 0);
+    parser_free_ast(ast); lexer_free_tokens(tokens, ntok); free(source);
+    return rc;
+}
+
+/* --emit docs: see docs_generate(). Printed, or written to `output_file`
+   when -o gives one */
+static int emit_docs(const char *input_file, const char *output_file) {
+    char *source = read_file(input_file);
+    if (!source) return 1;
+
+    int ntok;
+    Token *tokens = lexer_tokenize(source, &ntok);
+    ASTNode *ast = parser_parse(tokens, ntok);
+    int rc = 0;
+    if (!semantic_analyze(ast)) {
+        if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed.\n");
+        rc = 1;
+    } else {
+        set_source_path(input_file);
+        char *docs = docs_generate(ast, source_path());
+        if (!docs) rc = 1;
+        else if (output_file) write_file(output_file, docs);
+        else fputs(docs, stdout);
+        free(docs);
+    }
+    parser_free_ast(ast); lexer_free_tokens(tokens, ntok); free(source);
+    return rc;
+}
+
+/* --emit reachability: each statement's span and whether control can reach
+   it, after semantic analysis; printed even when that fails, like --emit ast */
+static int emit_reachability(const char *input_file) {
+    char *source = read_file(input_file);
+    if (!source) return 1;
+
+    int ntok;
+    Token *tokens = lexer_tokenize(source, &ntok);
+    ASTNode *ast = parser_parse(tokens, ntok);
+    int rc = 0;
+    if (!semantic_analyze(ast)) {
+        if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed.\n");
+        rc = 1;
+    }
+    ReachabilityMap map = semantic_reachability(ast);
+    for (int i = 0; i < map.count; i++) {
+        SourceSpan span = map.items[i].span;
+        printf("%d:%d-%d:%d %s\n", span.line, span.column, span.end_line, span.end_column,
+               map.items[i].reachable ? "reachable" : "unreachable");
+    }
+    reachability_map_free(&map);
+    parser_free_ast(ast); lexer_free_tokens(tokens, ntok); free(source);
+    return rc;
+}
+
+/* The lexer reads "\r\n" as "\n"; walk `source` forward to the byte that
+   the lexer's offset `k` stands for. Offsets must be asked for in order. */
+typedef struct {
+    const char *p;
+    int lexed;
+} OffsetCursor;
+
+static int source_offset(const char *source, OffsetCursor *cursor, int k) {
+    while (cursor->lexed < k && *cursor->p) {
+        if (cursor->p[0] == '\r' && cursor->p[1] == '\n') cursor->p++;
+        cursor->p++;
+        cursor->lexed++;
+    }
+    return (int)(cursor->p - source);
+}
+
+static void json_text(const char *s, int len) {
+    putchar('"');
+    for (int i = 0; i < len; i++) {
+        unsigned char c = (unsigned char)s[i];
+        if (c == '"' || c == '\\') printf("\\%c", c);
+        else if (c < 0x20) printf("\\u%04x", c);
+        else putchar(c);
+    }
+    putchar('"');
+}
+
+/* --emit tokens: one token per line with its span, kind and source text.
+   --emit tokens-json: the same as a JSON array of objects with kind, text,
+   start_byte, end_byte (exclusive), line and column. Offsets count bytes
+   of the file as read, so a "\r\n" line ending is two. */
+static int emit_tokens(const char *input_file, bool json) {
+    char *source = read_file(input_file);
+    if (!source) return 1;
+
+    int ntok;
+    DiagnosticList errors = {0};
+    diagnostic_collect(&errors, NULL);
+    Token *tokens = lexer_tokenize(source, &ntok);
+    diagnostic_collect(NULL, NULL);
+    int rc = errors.count > 0 ? 1 : 0;
+    diagnostic_list_free(&errors);
+
+    OffsetCursor cursor = {source, 0};
+    if (json) printf("[");
+    for (int i = 0; i < ntok; i++) {
+        Token *tok = &tokens[i];
+        int start = source_offset(source, &cursor, tok->start_byte);
+        int end = source_offset(source, &cursor, tok->end_byte);
+        const char *kind = token_type_to_string(tok->type);
+        if (json) {
+            printf("%s\n  {\"kind\": \"%s\", \"text\": ", i ? "," : "", kind);
+            json_text(source + start, end - start);
+            printf(", \"start_byte\": %d, \"end_byte\": %d, \"line\": %d, \"column\": %d}",
+                   start, end, tok->line, tok->column);
+        } else {
+            printf("%d:%d-%d:%d %s", tok->line, tok->column, tok->end_line, tok->end_column, kind);
+            // An unterminated string runs on to the next line; show its first
+            int shown = (int)strcspn(source + start, "\r\n");
+            if (shown > end - start) shown = end - start;
+            if (shown > 0) printf(" %.*s", shown, source + start);
+            printf("\n");
+        }
+    }
+    if (json) printf("\n]\n");
+    lexer_free_tokens(tokens, ntok);
+    free(source);
+    return rc;
+}
+
 /* Target type classification */
 typedef enum {
     TARGET_KIND_PLATFORM,
@@ -169,17 +388,11 @@ static const TargetDescriptor* lookup_target_native(const char *name) {
     return NULL;
 }
 
-extern char* codegen_python(ASTNode *ast, const char *source);
-extern char* codegen_javascript(ASTNode *ast, const char *source);
-extern char* codegen_java(ASTNode *ast, const char *source);
-extern char* codegen_swift(ASTNode *ast, const char *source);
-extern char* codegen_kotlin(ASTNode *ast, const char *source);
-extern char* codegen_rust(ASTNode *ast, const char *source);
-extern char* codegen_go(ASTNode *ast, const char *source);
-extern char* codegen_ruby(ASTNode *ast, const char *source);
-extern char* codegen_assembly(ASTNode *ast, const char *source);
-extern char* codegen_css(ASTNode *ast, const char *source);
 char* read_file(const char *filename) {
+    if (path_is_directory(filename)) {
+        fprintf(stderr, "Error: %s is a directory, not a source file\n", filename);
+        return NULL;
+    }
     FILE *file = fopen(filename, "rb");
     if (!file) {
         fprintf(stderr, "Error: Cannot open file %s\n", filename);
@@ -188,7 +401,7 @@ char* read_file(const char *filename) {
     fseek(file, 0, SEEK_END);
     long size = ftell(file);
     fseek(file, 0, SEEK_SET);
-    char *content = malloc(size + 1);
+    char *content = size >= 0 ? malloc(size + 1) : NULL;
     if (!content) {
         fclose(file);
         return NULL;
@@ -196,34 +409,70 @@ char* read_file(const char *filename) {
     size_t read_size = fread(content, 1, size, file);
     content[read_size] = '\0';
     fclose(file);
-    return content;
+    return decode_source(filename, content, read_size);
 }
 
 void write_file(const char *filename, const char *content) {
-    FILE *file = fopen(filename, "w");
+    char *formatted = apply_output_newlines(content);
+    FILE *file = formatted ? fopen(filename, "wb") : NULL;
     if (!file) {
         fprintf(stderr, "Error: Cannot write to file %s\n", filename);
+        free(formatted);
         return;
     }
-    fputs(content, file);
+    fputs(formatted, file);
     fclose(file);
+    free(formatted);
 }
 
-static char* generate_language_code_native(const char *name, ASTNode *ast, const char *source) {
-    if (strcasecmp(name, "c") == 0) return codegen_generate(ast, PLATFORM_LINUX);
-    if (strcasecmp(name, "cpp") == 0 || strcasecmp(name, "c++") == 0) return codegen_cpp_generate(ast, source);
-    if (strcasecmp(name, "typescript") == 0 || strcasecmp(name, "ts") == 0) return codegen_javascript(ast, source);
-    if (strcasecmp(name, "python") == 0     || strcasecmp(name, "py") == 0)     return codegen_python(ast, source);
-    if (strcasecmp(name, "javascript") == 0 || strcasecmp(name, "js") == 0)     return codegen_javascript(ast, source);
-    if (strcasecmp(name, "java") == 0)                                          return codegen_java(ast, source);
-    if (strcasecmp(name, "swift") == 0)                                         return codegen_swift(ast, source);
-    if (strcasecmp(name, "kotlin") == 0     || strcasecmp(name, "kt") == 0)     return codegen_kotlin(ast, source);
-    if (strcasecmp(name, "rust") == 0       || strcasecmp(name, "rs") == 0)     return codegen_rust(ast, source);
-    if (strcasecmp(name, "go") == 0         || strcasecmp(name, "golang") == 0) return codegen_go(ast, source);
-    if (strcasecmp(name, "ruby") == 0       || strcasecmp(name, "rb") == 0)     return codegen_ruby(ast, source);
-    if (strcasecmp(name, "assembly") == 0   || strcasecmp(name, "asm") == 0)    return codegen_assembly(ast, source);
-    if (strcasecmp(name, "css") == 0)                                           return codegen_css(ast, source);
-    return NULL;
+/* The C compiler --link drives: $CC if set, otherwise cc */
+static const char* link_compiler(void) {
+    const char *cc = getenv("CC");
+    return cc && *cc ? cc : "cc";
+}
+
+static bool link_toolchain_available(const char *cc) {
+    char cmd[512];
+#ifdef _WIN32
+    snprintf(cmd, sizeof(cmd), "%s --version > NUL 2>&1", cc);
+#else
+    snprintf(cmd, sizeof(cmd), "%s --version > /dev/null 2>&1", cc);
+#endif
+    return system(cmd) == 0;
+}
+
+/* Compile the generated C file to an object file with the flags of any
+   native build, then link it into `exe`. The object file is removed
+   afterwards. Problems are errors when `required` (--link), else warnings. */
+static int link_executable(const char *c_file, const char *base_name, const char *exe,
+                           int opt_level, bool fast_math, bool required) {
+    const char *cc = link_compiler();
+    const char *severity = required ? "Error" : "Warning";
+    if (!link_toolchain_available(cc)) {
+        fprintf(stderr, "%s: linking needs a C compiler and linker, but '%s' was not found.\n", severity, cc);
+        fprintf(stderr, "Install gcc or clang, or set CC to the compiler to use.\n");
+        return 1;
+    }
+    char obj[512], flags[1024], cmd[2048];
+    snprintf(obj, sizeof(obj), "%s.o", base_name);
+    native_cc_flags(flags, sizeof(flags), opt_level, fast_math, c_file);
+
+    print_status("\nAssembling %s -> %s\n", c_file, obj);
+    snprintf(cmd, sizeof(cmd), "%s %s -c \"%s\" -o \"%s\"", cc, flags, c_file, obj);
+    if (system(cmd) != 0) {
+        fprintf(stderr, "%s: '%s' failed to compile %s\n", severity, cc, c_file);
+        return 1;
+    }
+    print_status("Linking %s -> %s\n", obj, exe);
+    snprintf(cmd, sizeof(cmd), "%s \"%s\" -o \"%s\"", cc, obj, exe);
+    int ret = system(cmd);
+    remove(obj);
+    if (ret != 0) {
+        fprintf(stderr, "%s: linking %s failed\n", severity, exe);
+        return 1;
+    }
+    print_status("\u2705 Linked executable: %s\n", exe);
+    return 0;
 }
 
 static void get_output_basename_native(const char *input_file, char *out, size_t n) {
@@ -237,43 +486,385 @@ static void get_output_basename_native(const char *input_file, char *out, size_t
         *dot = '\0';
 }
 
-int main(int argc, char *argv[]) {
-    printf(SUB_LOGO);
+/* The file a target's output goes to when none is given: the input's base
+   name plus the target's extension, except Java, whose file must be named
+   after its class */
+static void default_output_name(const TargetDescriptor *target, const char *base_name,
+                                char *out, size_t n) {
+    if (target->kind == TARGET_KIND_LANGUAGE && strcasecmp(target->name, "java") == 0) {
+        snprintf(out, n, "SubProgram%s", target->extension);
+    } else {
+        snprintf(out, n, "%s%s", base_name, target->extension);
+    }
+}
+
+#define MAX_TARGETS 32
+
+/* --targets A,B,...: lex, parse and analyze once, then run each backend on
+   its own copy of the tree (the C backend optimizes it in place). Targets
+   that would write the same file, like linux and macos, are named
+   <base>-<target><ext> instead. */
+static int compile_targets(const char *input_file, const char *list) {
+    const TargetDescriptor *targets[MAX_TARGETS];
+    char names[MAX_TARGETS][32];
+    int count = 0;
+    for (const char *p = list; *p; ) {
+        size_t len = strcspn(p, ",");
+        if (len == 0 || len >= sizeof(names[0])) {
+            fprintf(stderr, "Error: --targets expects a comma-separated list of target names\n");
+            return 1;
+        }
+        if (count == MAX_TARGETS) {
+            fprintf(stderr, "Error: --targets accepts at most %d targets\n", MAX_TARGETS);
+            return 1;
+        }
+        memcpy(names[count], p, len);
+        names[count][len] = '\0';
+        const TargetDescriptor *target = lookup_target_native(names[count]);
+        if (!target) {
+            fprintf(stderr, "Error: Unknown target '%s'\n", names[count]);
+            return 1;
+        }
+        if (strcmp(target->extension, "") == 0) {
+            fprintf(stderr, "Error: '%s' runs the program and cannot be used with --targets\n", names[count]);
+            return 1;
+        }
+        targets[count++] = target;
+        p += len;
+        if (*p == ',') p++;
+    }
+    if (count == 0) {
+        fprintf(stderr, "Error: --targets expects a comma-separated list of target names\n");
+        return 1;
+    }
+
+    char base_name[256];
+    get_output_basename_native(input_file, base_name, sizeof(base_name));
+    char outputs[MAX_TARGETS][512];
+    for (int i = 0; i < count; i++) {
+        default_output_name(targets[i], base_name, outputs[i], sizeof(outputs[i]));
+    }
+    for (int i = 0; i < count; i++) {
+        bool clash = false;
+        for (int j = 0; j < count; j++) {
+            char other[512];
+            default_output_name(targets[j], base_name, other, sizeof(other));
+            if (j != i && strcmp(outputs[i], other) == 0) clash = true;
+        }
+        if (clash) snprintf(outputs[i], sizeof(outputs[i]), "%s-%s%s", base_name, names[i], targets[i]->extension);
+    }
+
+    print_status("Compiling %s for %s...\n\n", input_file, list);
+    char *source = read_file(input_file);
+    if (!source) return 1;
+    set_source_path(input_file);
+
+    int token_count;
+    Token *tokens = lexer_tokenize(source, &token_count);
+    ASTNode *ast = parser_parse(tokens, token_count);
+    int status = 0;
+    // #[target(...)] keeps different functions per target, so such a
+    // program is analyzed once per target instead of once for all of them
+    bool per_target = false;
+    for (ASTNode *stmt = ast ? ast->body : NULL; stmt; stmt = stmt->next) {
+        if (stmt->type == AST_FUNCTION_DECL && ast_has_attribute(stmt, "target")) per_target = true;
+    }
+    if (!per_target && !semantic_analyze(ast)) {
+        if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed\n");
+        status = 1;
+    }
 
+    for (int i = 0; i < count && status == 0; i++) {
+        ASTNode *copy = parser_clone_ast(ast);
+        if (per_target) {
+            set_active_target(names[i]);
+            if (!semantic_analyze(copy)) {
+                if (!parser_had_errors()) fprintf(stderr, "Semantic analysis for %s failed\n", names[i]);
+                parser_free_ast(copy);
+                status = 1;
+                break;
+            }
+        }
+        char *output_code = targets[i]->kind == TARGET_KIND_PLATFORM
+            ? codegen_generate(copy, targets[i]->platform)
+            : codegen_generate_language(names[i], copy, source);
+        parser_free_ast(copy);
+        if (!output_code) {
+            fprintf(stderr, "Code generation for %s failed\n", names[i]);
+            status = 1;
+            break;
+        }
+        write_file(outputs[i], output_code);
+        free(output_code);
+        print_status("\u2713 %s output written to: %s\n", names[i], outputs[i]);
+    }
+
+    free(source);
+    lexer_free_tokens(tokens, token_count);
+    parser_free_ast(ast);
+    return status;
+}
+
+/* subc check: every error the lexer, parser and semantic pass report, and
+   no output. Returns how many there were, or -1 if the file cannot be read. */
+static int check_file(const char *input_file) {
+    char *source = read_file(input_file);
+    if (!source) return -1;
+    set_source_path(input_file);
+
+    DiagnosticList errors = {0};
+    diagnostic_collect(&errors, NULL);
+    int ntok;
+    Token *tokens = lexer_tokenize(source, &ntok);
+    ASTNode *ast = parser_parse(tokens, ntok);
+    bool ok = semantic_analyze(ast) && errors.count == 0;
+    diagnostic_collect(NULL, NULL);
+
+    // A stage can fail without a message of its own (out of memory)
+    int count = errors.count > 0 ? errors.count : 1;
+    if (ok) print_status("\u2713 %s: no errors\n", input_file);
+    else fprintf(stderr, "%s: %d error%s\n", input_file, count, count == 1 ? "" : "s");
+    diagnostic_list_free(&errors);
+    parser_free_ast(ast); lexer_free_tokens(tokens, ntok); free(source);
+    return ok ? 0 : count;
+}
+
+/* The files `inputs` name, each a new string: a directory stands for the
+   .sb files under it. NULL, with a message, if a directory has none. */
+static char** collect_source_files(char **inputs, int ninputs, int *nfiles) {
+    char **files = NULL;
+    *nfiles = 0;
+    for (int i = 0; i < ninputs; i++) {
+        int found = 1;
+        char **dir_files = NULL;
+        if (path_is_directory(inputs[i])) {
+            dir_files = list_source_files(inputs[i], &found);
+            if (found == 0) {
+                fprintf(stderr, "Error: no .sb files in directory %s\n", inputs[i]);
+                for (int k = 0; k < *nfiles; k++) free(files[k]);
+                free(files);
+                return NULL;
+            }
+        }
+        char **grown = realloc(files, sizeof(char *) * (*nfiles + found));
+        if (!grown) {
+            for (int k = 0; k < *nfiles; k++) free(files[k]);
+            free(files);
+            return NULL;
+        }
+        files = grown;
+        for (int k = 0; k < found; k++) files[(*nfiles)++] = dir_files ? dir_files[k] : strdup(inputs[i]);
+        free(dir_files);
+    }
+    return files;
+}
+
+/* subc check <input.sb|dir>...: argv[1..] are the inputs and flags. A
+   directory stands for the .sb files under it. Several files end with a
+   summary of the ones that failed. */
+static int check_command(int argc, char *argv[]) {
+    bool fail_fast = false;
+    int ninputs = 0;
+    for (int i = 1; i < argc; i++) {
+        if (strcmp(argv[i], "--fail-fast") == 0) fail_fast = true;
+        else if (strcmp(argv[i], "--keep-going") == 0) fail_fast = false;
+        else if (argv[i][0] == '-') {
+            fprintf(stderr, "Error: unknown check option '%s'\n", argv[i]);
+            return 1;
+        } else argv[++ninputs] = argv[i];
+    }
+    if (ninputs == 0) {
+        fprintf(stderr, "Error: '%s check' needs an input file\n", argv[0]);
+        return 1;
+    }
+
+    int nfiles;
+    char **files = collect_source_files(argv + 1, ninputs, &nfiles);
+    if (!files) return 1;
+
+    int *counts = calloc(nfiles, sizeof(int));
+    int checked = 0, failed = 0;
+    while (counts && checked < nfiles) {
+        counts[checked] = check_file(files[checked]);
+        fflush(stdout);  // keep each file's status with its errors on stderr
+        if (counts[checked] < 0) fprintf(stderr, "%s: cannot be read\n", files[checked]);
+        if (counts[checked++] == 0) continue;
+        failed++;
+        if (fail_fast) break;
+    }
+
+    if (nfiles > 1 && failed > 0) {
+        fprintf(stderr, "\n%d of %d file%s failed:\n", failed, checked, checked == 1 ? "" : "s");
+        for (int i = 0; i < checked; i++) {
+            if (counts[i] < 0)
+                fprintf(stderr, "  %s: cannot be read\n", files[i]);
+            else if (counts[i] > 0)
+                fprintf(stderr, "  %s: %d error%s\n", files[i], counts[i], counts[i] == 1 ? "" : "s");
+        }
+        if (checked < nfiles)
+            fprintf(stderr, "Stopped early (--fail-fast); %d file%s not checked\n",
+                    nfiles - checked, nfiles - checked == 1 ? "" : "s");
+    }
+    for (int i = 0; i < nfiles; i++) free(files[i]);
+    free(files);
+    bool ok = counts && failed == 0;
+    free(counts);
+    return ok ? 0 : 1;
+}
+
+/* subc fmt <input.sb|dir>... [--check|--write]: format_source() on each
+   file. A file that does not parse is reported and left alone, since its
+   brackets cannot be trusted. */
+static int fmt_command(int argc, char *argv[]) {
+    enum { FMT_PRINT, FMT_CHECK, FMT_WRITE } mode = FMT_PRINT;
+    int ninputs = 0;
+    for (int i = 1; i < argc; i++) {
+        if (strcmp(argv[i], "--check") == 0) mode = FMT_CHECK;
+        else if (strcmp(argv[i], "--write") == 0) mode = FMT_WRITE;
+        else if (argv[i][0] == '-') {
+            fprintf(stderr, "Error: unknown fmt option '%s'\n", argv[i]);
+            return 1;
+        } else argv[++ninputs] = argv[i];
+    }
+    if (ninputs == 0) {
+        fprintf(stderr, "Error: '%s fmt' needs an input file\n", argv[0]);
+        return 1;
+    }
+    int nfiles;
+    char **files = collect_source_files(argv + 1, ninputs, &nfiles);
+    if (!files) return 1;
+
+    int failed = 0;
+    for (int i = 0; i < nfiles; i++) {
+        char *source = read_file(files[i]);
+        if (!source) {
+            failed++;
+            continue;
+        }
+        set_source_path(files[i]);
+        DiagnosticList errors = {0};
+        diagnostic_collect(&errors, NULL);
+        int ntok;
+        Token *tokens = lexer_tokenize(source, &ntok);
+        ASTNode *ast = parser_parse(tokens, ntok);
+        diagnostic_collect(NULL, NULL);
+        bool parsed = ast && errors.count == 0;
+        diagnostic_list_free(&errors);
+        parser_free_ast(ast);
+        lexer_free_tokens(tokens, ntok);
+
+        char *formatted = parsed ? format_source(source) : NULL;
+        if (!formatted) {
+            fprintf(stderr, "%s: not formatted, since it does not parse\n", files[i]);
+            failed++;
+        } else if (mode == FMT_PRINT) {
+            fputs(formatted, stdout);
+        } else if (strcmp(formatted, source) != 0) {
+            if (mode == FMT_CHECK) {
+                printf("%s\n", files[i]);
+                failed++;
+            } else {
+                write_file(files[i], formatted);
+                print_status("Formatted %s\n", files[i]);
+            }
+        }
+        free(formatted);
+        free(source);
+        free(files[i]);
+    }
+    free(files);
+    return failed == 0 ? 0 : 1;
+}
+
+/* SUBC_TARGET names the target to use when the command line gives none.
+   --emit and --targets say what to produce themselves, so they ignore it. */
+static const char* default_target(int argc, char *argv[]) {
+    const char *env = getenv("SUBC_TARGET");
+    if (!env || !*env) return NULL;
+    for (int i = 2; i < argc; i++) {
+        if (strcmp(argv[i], "--emit") == 0 || strcmp(argv[i], "--targets") == 0) return NULL;
+    }
+    return env;
+}
+
+/* subc [build] <input.sb> ...: argv[1] is the input file */
+static int build_command(int argc, char *argv[]) {
     if (argc < 2) {
         print_usage_native(argv[0]);
         return 1;
     }
     
     const char *input_file = argv[1];
-
+    const char *target_str = NULL;
+    bool from_env = false;
     if (argc > 2 && argv[2][0] != '-') {
+        target_str = argv[2];
+    } else {
+        target_str = default_target(argc, argv);
+        from_env = target_str != NULL;
+    }
+
+    if (target_str) {
         // Run transpiler/interpreter driver instead
-        const char *target_str = argv[2];
         
         // Direct interpreter run
         if (strcasecmp(target_str, "interpret") == 0 || strcasecmp(target_str, "run") == 0) {
-            printf("Interpreting %s...\n\n", input_file);
+            print_status("Interpreting %s...\n\n", input_file);
             extern int interpret_file(const char *path);
             return interpret_file(input_file);
         }
         
         const TargetDescriptor *target = lookup_target_native(target_str);
         if (!target) {
-            fprintf(stderr, "Error: Unknown target '%s'\n", target_str);
+            fprintf(stderr, "Error: Unknown target '%s'%s\n", target_str, from_env ? " (from SUBC_TARGET)" : "");
             return 1;
         }
         
-        printf("Compiling %s for %s...\n\n", input_file, target_str);
+        /* After the target: an optional output file, --link, -o <exe> and the
+           optimization flags of a native build */
+        const char *user_file = NULL;
+        const char *exe_out = NULL;
+        bool link = false;
+        int opt_level = 2;
+        bool fast_math = false;
+        for (int i = from_env ? 2 : 3; i < argc; i++) {
+            if (strcmp(argv[i], "--link") == 0) link = true;
+            else if (strcmp(argv[i], "-o") == 0 && i + 1 < argc) exe_out = argv[++i];
+            else if (strncmp(argv[i], "-O", 2) == 0 && argv[i][2] >= '0' && argv[i][2] <= '3' &&
+                     !argv[i][3]) opt_level = argv[i][2] - '0';
+            else if (strcmp(argv[i], "--fast-math") == 0) fast_math = true;
+            else if (strcmp(argv[i], "--no-fast-math") == 0) fast_math = false;
+            else if (strcmp(argv[i], "-v") == 0 || strcmp(argv[i], "--verbose") == 0) continue;
+            else if (argv[i][0] != '-' && !user_file) user_file = argv[i];
+            else {
+                fprintf(stderr, "Error: unexpected argument '%s' after target '%s'\n", argv[i], target_str);
+                return 1;
+            }
+        }
+        bool native = target->kind == TARGET_KIND_PLATFORM &&
+            (target->platform == PLATFORM_LINUX || target->platform == PLATFORM_WINDOWS ||
+             target->platform == PLATFORM_MACOS);
+        if (link && !native) {
+            fprintf(stderr, "Error: --link only works with the linux, windows and macos targets\n");
+            return 1;
+        }
+        optimizer_set_fast_math(fast_math);
+        optimizer_set_licm(opt_level >= 3);
+        optimizer_set_inlining(opt_level >= 2);
+        optimizer_set_unrolling(opt_level >= 3);
+
+        print_status("Compiling %s for %s...\n\n", input_file, target_str);
         char *source = read_file(input_file);
         if (!source) return 1;
+        set_source_path(input_file);
         
         int token_count;
         Token *tokens = lexer_tokenize(source, &token_count);
         ASTNode *ast = parser_parse(tokens, token_count);
         
+        set_active_target(target_str);
         if (!semantic_analyze(ast)) {
-            fprintf(stderr, "Semantic analysis failed\n");
+            if (!parser_had_errors()) fprintf(stderr, "Semantic analysis failed\n");
             free(source);
             lexer_free_tokens(tokens, token_count);
             parser_free_ast(ast);
@@ -284,7 +875,7 @@ int main(int argc, char *argv[]) {
         if (target->kind == TARGET_KIND_PLATFORM) {
             output_code = codegen_generate(ast, target->platform);
         } else {
-            output_code = generate_language_code_native(target_str, ast, source);
+            output_code = codegen_generate_language(target_str, ast, source);
         }
         
         if (!output_code) {
@@ -298,54 +889,55 @@ int main(int argc, char *argv[]) {
         char output_file[256];
         char base_name[256];
         get_output_basename_native(input_file, base_name, sizeof(base_name));
-        if (argc > 3) {
-            snprintf(output_file, sizeof(output_file), "%s", argv[3]);
-        } else if (target->kind == TARGET_KIND_LANGUAGE &&
-            strcasecmp(target_str, "java") == 0) {
-            snprintf(output_file, sizeof(output_file), "SubProgram%s", target->extension);
+        if (user_file) {
+            snprintf(output_file, sizeof(output_file), "%s", user_file);
         } else {
-            snprintf(output_file, sizeof(output_file), "%s%s", base_name, target->extension);
+            default_output_name(target, base_name, output_file, sizeof(output_file));
         }
         write_file(output_file, output_code);
         
-        printf("\n\u2713 Compilation successful!\n");
-        printf("\u2713 Output written to: %s\n", output_file);
+        print_status("\n\u2713 Compilation successful!\n");
+        print_status("\u2713 Output written to: %s\n", output_file);
         
-        // If it is a platform target that compiles to C under the hood, compile to machine code directly
-        if (target->kind == TARGET_KIND_PLATFORM && 
-            (target->platform == PLATFORM_LINUX || target->platform == PLATFORM_WINDOWS || target->platform == PLATFORM_MACOS)) {
-            char compile_cmd[1024];
-            const char *bin_ext = (target->platform == PLATFORM_WINDOWS) ? ".exe" : "";
-            snprintf(compile_cmd, sizeof(compile_cmd), "gcc -O2 -o %s%s %s", base_name, bin_ext, output_file);
-            printf("\nCompiling intermediate C code to native machine code...\n");
-            int ret = system(compile_cmd);
-            if (ret == 0) {
-                printf("\u2705 Machine code compiled successfully: ./%s%s\n", base_name, bin_ext);
-            } else {
-                fprintf(stderr, "Warning: gcc compilation failed. Make sure gcc is installed.\n");
-            }
+        /* A native target also builds the executable. Only with --link is a
+           missing or failing toolchain an error; without it the C file is
+           the result. */
+        int status = 0;
+        if (native) {
+            char exe[512];
+            if (exe_out) snprintf(exe, sizeof(exe), "%s", exe_out);
+            else snprintf(exe, sizeof(exe), "%s%s", base_name,
+                          target->platform == PLATFORM_WINDOWS ? ".exe" : "");
+            status = link_executable(output_file, base_name, exe, opt_level, fast_math, link) != 0 && link;
         } else {
-            printf("\nNext steps:\n");
-            printf("  %s\n", target->run_hint);
+            print_status("\nNext steps:\n");
+            print_status("  %s\n", target->run_hint);
         }
         
         free(source);
         lexer_free_tokens(tokens, token_count);
         parser_free_ast(ast);
         free(output_code);
-        return 0;
+        return status;
     }
 
     const char *user_out = NULL;
+    const char *emit = NULL;
+    const char *target_list = NULL;
     bool verbose = false;
     int opt_level = 2;
+    bool fast_math = false;
     
     // Parse command line options
     for (int i = 2; i < argc; i++) {
         if (strcmp(argv[i], "-v") == 0 || strcmp(argv[i], "--verbose") == 0) {
             verbose = true;
+        } else if (strcmp(argv[i], "--emit") == 0 && i + 1 < argc) {
+            emit = argv[++i];
         } else if (strcmp(argv[i], "-o") == 0 && i + 1 < argc) {
             user_out = argv[++i];
+        } else if (strcmp(argv[i], "--targets") == 0 && i + 1 < argc) {
+            target_list = argv[++i];
         } else if (strcmp(argv[i], "-O0") == 0) {
             opt_level = 0;
         } else if (strcmp(argv[i], "-O1") == 0) {
@@ -354,7 +946,49 @@ int main(int argc, char *argv[]) {
             opt_level = 2;
         } else if (strcmp(argv[i], "-O3") == 0) {
             opt_level = 3;
+        } else if (strcmp(argv[i], "--fast-math") == 0) {
+            fast_math = true;
+        } else if (strcmp(argv[i], "--no-fast-math") == 0) {
+            fast_math = false;
+        } else {
+            fprintf(stderr, "Error: unexpected argument '%s'\n", argv[i]);
+            return 1;
+        }
+    }
+    if (quiet_mode()) verbose = false;
+    optimizer_set_fast_math(fast_math);
+    optimizer_set_licm(opt_level >= 3);
+    optimizer_set_inlining(opt_level >= 2);
+    optimizer_set_unrolling(opt_level >= 3);
+
+    if (emit) {
+        if (strcmp(emit, "docs") == 0) return emit_docs(input_file, user_out);
+        const char *const printed[] = {"ast", "tokens", "tokens-json", "reachability"};
+        bool known = false;
+        for (size_t i = 0; i < sizeof(printed) / sizeof(printed[0]); i++) {
+            if (strcmp(emit, printed[i]) == 0) known = true;
+        }
+        if (!known) {
+            fprintf(stderr, "Error: Unknown --emit kind '%s' (expected 'ast', 'docs', "
+                    "'tokens', 'tokens-json' or 'reachability')\n", emit);
+            return 1;
+        }
+        if (user_out) {
+            fprintf(stderr, "Error: -o cannot be used with --emit %s, which prints to stdout\n", emit);
+            return 1;
         }
+        if (strcmp(emit, "tokens") == 0) return emit_tokens(input_file, false);
+        if (strcmp(emit, "tokens-json") == 0) return emit_tokens(input_file, true);
+        if (strcmp(emit, "reachability") == 0) return emit_reachability(input_file);
+        return emit_ast(input_file);
+    }
+
+    if (target_list) {
+        if (user_out) {
+            fprintf(stderr, "Error: -o cannot be used with --targets; each output is named after the input\n");
+            return 1;
+        }
+        return compile_targets(input_file, target_list);
     }
 
     char output_name[512];
@@ -366,5 +1000,59 @@ int main(int argc, char *argv[]) {
         printf("[Output]  %s\n\n", output_name);
     }
     
-    return compile_to_native(input_file, output_name, verbose, opt_level);
+    return compile_to_native(input_file, output_name, verbose, opt_level, fast_math);
+}
+
+int main(int argc, char *argv[]) {
+    if (!take_diagnostic_options(&argc, argv)) {
+        return 1;
+    }
+    /* fmt prints the formatted source, so it goes before the banner */
+    if (argc > 1 && strcmp(argv[1], "fmt") == 0) {
+        argv[1] = argv[0];
+        return fmt_command(argc - 1, argv + 1);
+    }
+    /* Nor does the banner go in front of what --emit prints */
+    bool emits = false;
+    for (int i = 1; i < argc; i++) {
+        if (strcmp(argv[i], "--emit") == 0) emits = true;
+    }
+    if (!emits) print_status(SUB_LOGO);
+    if (crate_type() == CRATE_TYPE_LIB) {
+        fprintf(stderr, "Error: subc builds executables; use sub with --crate-type lib to build a library\n");
+        return 1;
+    }
+
+    /* A command comes first; without one, subc builds as it always has */
+    const char *command = argc > 1 ? argv[1] : "";
+    if (strcmp(command, "build") == 0) {
+        argv[1] = argv[0];
+        return build_command(argc - 1, argv + 1);
+    }
+    if (strcmp(command, "check") == 0 || strcmp(command, "run") == 0) {
+        if (argc < 3) {
+            fprintf(stderr, "Error: '%s %s' needs an input file\n", argv[0], command);
+            return 1;
+        }
+        if (strcmp(command, "check") == 0) {
+            argv[1] = argv[0];
+            return check_command(argc - 1, argv + 1);
+        }
+        print_status("Interpreting %s...\n\n", argv[2]);
+        extern int interpret_file(const char *path);
+        return interpret_file(argv[2]);
+    }
+    if (strcmp(command, "repl") == 0) {
+        if (argc > 2) {
+            fprintf(stderr, "Error: '%s repl' takes no input file; it reads standard input\n", argv[0]);
+            return 1;
+        }
+        if (prelude_file()) {
+            fprintf(stderr, "Error: --prelude cannot be used with '%s repl'\n", argv[0]);
+            return 1;
+        }
+        extern int interpret_repl(FILE *in);
+        return interpret_repl(stdin);
+    }
+    return build_command(argc, argv);
 }
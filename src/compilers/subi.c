@@ -3,16 +3,97 @@
 #include "interpreter.h"
 #include "logo.h"
 #include <stdio.h>
+#include <stdlib.h>
 #include <string.h>
+#include <errno.h>
+
+/* Remove --max-steps N, --max-memory N and --max-depth N from argv into
+   `limits`. Each takes a positive count. */
+static bool take_limit_options(int *argc, char *argv[], InterpreterLimits *limits) {
+    static const char *names[] = {"--max-steps", "--max-memory", "--max-depth"};
+    bool ok = true;
+    int out = 1;
+    for (int i = 1; i < *argc; i++) {
+        int which = -1;
+        for (int k = 0; k < 3; k++) {
+            if (strcmp(argv[i], names[k]) == 0) which = k;
+        }
+        if (which < 0) {
+            argv[out++] = argv[i];
+            continue;
+        }
+        const char *value = i + 1 < *argc ? argv[++i] : "";
+        char *end;
+        errno = 0;
+        long long n = strtoll(value, &end, 10);
+        if (!*value || *end || errno == ERANGE || n < 1 || (which == 2 && n > 1000000)) {
+            fprintf(stderr, "Error: invalid %s value '%s' (expected a positive count%s)\n",
+                    names[which], value, which == 2 ? " up to 1000000" : "");
+            ok = false;
+        } else if (which == 0) {
+            limits->max_steps = n;
+        } else if (which == 1) {
+            limits->max_memory = n;
+        } else {
+            limits->max_depth = (int)n;
+        }
+    }
+    *argc = out;
+    argv[out] = NULL;
+    return ok;
+}
+
+/* Remove --eval SOURCE (or --eval=SOURCE) from argv; NULL when absent */
+static const char* take_eval_option(int *argc, char *argv[], bool *ok) {
+    const char *source = NULL;
+    int out = 1;
+    for (int i = 1; i < *argc; i++) {
+        if (strncmp(argv[i], "--eval", 6) != 0 || (argv[i][6] && argv[i][6] != '=')) {
+            argv[out++] = argv[i];
+            continue;
+        }
+        if (source) {
+            fprintf(stderr, "Error: --eval given more than once\n");
+            *ok = false;
+        }
+        const char *value = argv[i][6] == '=' ? argv[i] + 7 : (i + 1 < *argc ? argv[++i] : NULL);
+        if (!value) {
+            fprintf(stderr, "Error: --eval expects a program\n");
+            *ok = false;
+        }
+        if (!source) source = value;
+    }
+    *argc = out;
+    argv[out] = NULL;
+    return source;
+}
 
 int main(int argc, char *argv[]) {
-    printf(SUB_LOGO);
-    printf("SUB Interpreter v1.0\n");
-    printf("====================\n\n");
+    InterpreterLimits limits = {0};
+    bool ok = true;
+    const char *eval_source = take_eval_option(&argc, argv, &ok);
+    if (!ok || !take_diagnostic_options(&argc, argv) || !take_limit_options(&argc, argv, &limits)) {
+        return 1;
+    }
+    print_status(SUB_LOGO);
+    print_status("SUB Interpreter v1.0\n");
+    print_status("====================\n\n");
+    if (crate_type() == CRATE_TYPE_LIB) {
+        fprintf(stderr, "Error: a --crate-type lib program has no entry point to run\n");
+        return 1;
+    }
+    if (eval_source) {
+        if (argc > 1) {
+            fprintf(stderr, "Error: --eval takes the program instead of a file\n");
+            return 1;
+        }
+        return interpret_eval(eval_source, &limits);
+    }
     if (argc < 2) {
-        printf("Usage: %s <file.sb>\n", argv[0]);
+        printf("Usage: %s <file.sb> | --eval SOURCE [--error-limit N] [--tab-width N] [--max-nesting N] [--strict] [--dump-symbols] [--stats] [--overflow check|wrap|saturate] [--int-width 32|64] [--entry NAME] [--prelude FILE] [--encoding utf-8|latin-1] [--quiet] [--max-steps N] [--max-memory BYTES] [--max-depth N]\n", argv[0]);
         printf("Example: %s hello.sb\n", argv[0]);
+        printf("         %s --eval \"print(2 + 3)\"\n", argv[0]);
         return 1;
     }
-    return interpret_file(argv[1]);
+    return interpret_file_with_limits(argv[1], &limits);
 }
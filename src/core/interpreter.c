@@ -4,9 +4,60 @@
 #include <stdlib.h>
 #include <string.h>
 #include <math.h>
+#include <stdarg.h>
+#include <ctype.h>
+#include <limits.h>
+#ifdef _WIN32
+#include <io.h>
+#define isatty _isatty
+#define fileno _fileno
+#else
+#include <unistd.h>
+#endif
 
 static SubVal NULL_VAL = {VAL_NULL};
 
+/* Set by runtime_error(); evaluation unwinds and interpret_file() fails */
+static int g_runtime_error = 0;
+
+static void runtime_error(int line, const char *fmt, ...) {
+    va_list args;
+    fprintf(stderr, "[line %d] Runtime error: ", line);
+    va_start(args, fmt);
+    vfprintf(stderr, fmt, args);
+    va_end(args);
+    fprintf(stderr, "\n");
+    g_runtime_error = 1;
+}
+
+static InterpreterLimits g_limits;
+static long long g_steps = 0;
+static long long g_memory = 0;
+static int g_depth = 0;
+
+/* The stack guard: where eval() started and how many bytes of the stack
+   it may use below that, leaving room for the C library */
+static const char *g_stack_base = NULL;
+static size_t g_stack_budget = 0;
+
+static bool stack_exhausted(void) {
+    char here;
+    size_t used = g_stack_base > &here ? (size_t)(g_stack_base - &here) : (size_t)(&here - g_stack_base);
+    return used > g_stack_budget;
+}
+
+/* Count `bytes` against --max-memory; false (with the error reported) once
+   the program would go over it */
+static bool reserve_memory(int line, long long bytes) {
+    g_memory += bytes;
+    if (g_limits.max_memory > 0 && g_memory > g_limits.max_memory) {
+        runtime_error(line, "Resource limit exceeded: allocating %lld bytes goes over --max-memory %lld",
+                      bytes, g_limits.max_memory);
+        return false;
+    }
+    return true;
+}
+
 Env *env_new(Env *parent) {
     Env *e = calloc(1, sizeof(Env));
     e->parent = parent;
@@ -55,6 +106,26 @@ static SubVal make_str(const char *s) {
     SubVal v = {VAL_STRING}; v.sv = strdup(s ? s : ""); return v;
 }
 
+/* Strings are measured and indexed in characters, not bytes: a character
+   is a lead byte and the UTF-8 continuation bytes after it */
+static const char* utf8_next(const char *p) {
+    if (*p) p++;
+    while (((unsigned char)*p & 0xC0) == 0x80) p++;
+    return p;
+}
+
+static long long utf8_length(const char *s) {
+    long long n = 0;
+    for (const char *p = s; *p; p = utf8_next(p)) n++;
+    return n;
+}
+
+/* Start of character `i` of `s`, or the end of `s` past its last one */
+static const char* utf8_offset(const char *s, long long i) {
+    while (i-- > 0 && *s) s = utf8_next(s);
+    return s;
+}
+
 static int is_truthy(SubVal v) {
     switch (v.type) {
         case VAL_BOOL:  return v.bv;
@@ -65,29 +136,214 @@ static int is_truthy(SubVal v) {
     }
 }
 
-static void print_val(SubVal v) {
+static void print_val_inline(SubVal v) {
     switch (v.type) {
-        case VAL_INT:    printf("%lld\n", v.iv); break;
-        case VAL_FLOAT:  printf("%g\n",   v.fv); break;
-        case VAL_BOOL:   printf("%s\n",   v.bv ? "true" : "false"); break;
-        case VAL_STRING: printf("%s\n",   v.sv ? v.sv : ""); break;
-        default:         printf("null\n"); break;
+        case VAL_INT:    printf("%lld", v.iv); break;
+        case VAL_FLOAT:  printf("%g",   v.fv); break;
+        case VAL_BOOL:   printf("%s",   v.bv ? "true" : "false"); break;
+        case VAL_STRING: printf("%s",   v.sv ? v.sv : ""); break;
+        case VAL_ARRAY:
+            printf("[");
+            for (int i = 0; v.av && i < v.av->count; i++) {
+                if (i > 0) printf(", ");
+                print_val_inline(v.av->items[i]);
+            }
+            printf("]");
+            break;
+        case VAL_TUPLE:
+            printf("(");
+            for (int i = 0; v.av && i < v.av->count; i++) {
+                if (i > 0) printf(", ");
+                print_val_inline(v.av->items[i]);
+            }
+            printf(")");
+            break;
+        default:         printf("null"); break;
     }
 }
 
+static void print_val(SubVal v) {
+    print_val_inline(v);
+    printf("\n");
+}
+
 static SubVal eval_block(ASTNode *node, Env *env);
 
+static SubVal eval_string_builtin(ASTNode *node, StringBuiltin builtin, Env *env) {
+    SubVal args[3] = {NULL_VAL, NULL_VAL, NULL_VAL};
+    for (int i = 0; i < node->child_count && i < 3; i++)
+        args[i] = eval(node->children[i], env);
+    if (g_runtime_error) return NULL_VAL;
+    const char *s = args[0].type == VAL_STRING && args[0].sv ? args[0].sv : "";
+    long long slen = utf8_length(s);
+
+    switch (builtin) {
+    case STR_BUILTIN_SUBSTR: {
+        long long start = args[1].iv, len = args[2].iv;
+        if (start < 0 || len < 0 || start > slen || len > slen - start) {
+            runtime_error(node->line, "substr(): start %lld, length %lld is out of range for a string of length %lld",
+                          start, len, slen);
+            return NULL_VAL;
+        }
+        const char *from = utf8_offset(s, start);
+        size_t bytes = (size_t)(utf8_offset(from, len) - from);
+        if (!reserve_memory(node->line, (long long)bytes + 1)) return NULL_VAL;
+        SubVal v = {VAL_STRING};
+        v.sv = strndup(from, bytes);
+        return v;
+    }
+    case STR_BUILTIN_INDEX_OF: {
+        const char *sub = args[1].type == VAL_STRING && args[1].sv ? args[1].sv : "";
+        const char *hit = strstr(s, sub);
+        if (!hit) return make_int(-1);
+        long long at = 0;
+        for (const char *p = s; p < hit; p = utf8_next(p)) at++;
+        return make_int(at);
+    }
+    case STR_BUILTIN_TO_UPPER:
+    case STR_BUILTIN_TO_LOWER: {
+        if (!reserve_memory(node->line, (long long)strlen(s) + 1)) return NULL_VAL;
+        SubVal v = make_str(s);
+        for (char *p = v.sv; *p; p++)
+            *p = (char)(builtin == STR_BUILTIN_TO_UPPER ? toupper((unsigned char)*p) : tolower((unsigned char)*p));
+        return v;
+    }
+    default:
+        return NULL_VAL;
+    }
+}
+
+/* int(x) truncates toward zero, bool(x) is x != 0, string(x) formats like print */
+static SubVal eval_cast(SubVal v, DataType target) {
+    char buf[64];
+    switch (target) {
+    case TYPE_INT:
+        if (v.type==VAL_FLOAT)  return make_int((long long)v.fv);
+        if (v.type==VAL_STRING) return make_int(atoll(v.sv ? v.sv : "0"));
+        if (v.type==VAL_BOOL)   return make_int(v.bv ? 1 : 0);
+        return v;
+    case TYPE_FLOAT:
+        if (v.type==VAL_INT)    return make_float((double)v.iv);
+        if (v.type==VAL_STRING) return make_float(atof(v.sv ? v.sv : "0"));
+        return v;
+    case TYPE_BOOL:
+        if (v.type==VAL_INT)    return make_bool(v.iv != 0);
+        return v;
+    case TYPE_STRING:
+        if (v.type==VAL_STRING) return v;
+        if (v.type==VAL_INT)        snprintf(buf, sizeof(buf), "%lld", v.iv);
+        else if (v.type==VAL_FLOAT) snprintf(buf, sizeof(buf), "%g", v.fv);
+        else if (v.type==VAL_BOOL)  snprintf(buf, sizeof(buf), "%s", v.bv ? "true" : "false");
+        else snprintf(buf, sizeof(buf), "null");
+        return make_str(buf);
+    default:
+        return v;
+    }
+}
+
+/* Integer + - * / under the --overflow mode, at the --int-width. Returns
+   false after reporting a runtime error (overflow in check mode, or
+   division by zero). */
+static bool int_arith(const char *op, long long a, long long b, int line, long long *out) {
+    bool overflow;
+    long long limit;
+    if (strcmp(op, "+") == 0) {
+        overflow = __builtin_add_overflow(a, b, out);
+        limit = b > 0 ? LLONG_MAX : LLONG_MIN;
+    } else if (strcmp(op, "-") == 0) {
+        overflow = __builtin_sub_overflow(a, b, out);
+        limit = b < 0 ? LLONG_MAX : LLONG_MIN;
+    } else if (strcmp(op, "*") == 0) {
+        overflow = __builtin_mul_overflow(a, b, out);
+        limit = (a < 0) != (b < 0) ? LLONG_MIN : LLONG_MAX;
+    } else {
+        if (b == 0) {
+            runtime_error(line, strcmp(op, "/") == 0 ? "Division by zero" : "Modulo by zero");
+            return false;
+        }
+        /* LLONG_MIN / -1 is the only quotient that does not fit */
+        overflow = a == LLONG_MIN && b == -1;
+        limit = LLONG_MAX;
+        if (strcmp(op, "/") == 0) *out = overflow ? LLONG_MIN : a / b;
+        else { *out = overflow ? 0 : a % b; overflow = false; }
+    }
+    if (int_width() == 32 && !overflow && !int_fits(*out)) {
+        /* Operands that fit 32 bits never overflow 64, so *out is exact */
+        overflow = true;
+        limit = *out > 0 ? LLONG_MAX : LLONG_MIN;
+        *out = (int)(unsigned int)*out;
+    }
+    if (!overflow) return true;
+    if (limit == LLONG_MAX) limit = int_max();
+    else if (limit == LLONG_MIN) limit = int_min();
+    switch (overflow_mode()) {
+        case OVERFLOW_CHECK:
+            runtime_error(line, "Integer overflow in %lld %s %lld", a, op, b);
+            return false;
+        case OVERFLOW_SATURATE:
+            *out = limit;
+            return true;
+        default:
+            return true;   /* the builtins already stored the wrapped value */
+    }
+}
+
+/* Fit an int() result to the --int-width under the --overflow mode, like
+   the arithmetic above. Returns false after reporting a check-mode error. */
+static bool int_narrow(long long *value, int line) {
+    if (int_fits(*value)) return true;
+    switch (overflow_mode()) {
+        case OVERFLOW_CHECK:
+            runtime_error(line, "Integer overflow converting %lld to a %d-bit int", *value, int_width());
+            return false;
+        case OVERFLOW_SATURATE:
+            *value = *value > 0 ? int_max() : int_min();
+            return true;
+        default:
+            *value = (int)(unsigned int)*value;
+            return true;
+    }
+}
+
+/* Whether a match value equals a (literal) pattern */
+static bool match_pattern_equal(SubVal v, SubVal p) {
+    if (v.type == VAL_STRING || p.type == VAL_STRING) {
+        return v.type == p.type && strcmp(v.sv ? v.sv : "", p.sv ? p.sv : "") == 0;
+    }
+    if (v.type == VAL_BOOL || p.type == VAL_BOOL) return v.type == p.type && v.bv == p.bv;
+    if (v.type == VAL_NULL || p.type == VAL_NULL) return v.type == p.type;
+    if (v.type == VAL_INT && p.type == VAL_INT) return v.iv == p.iv;
+    double a = v.type == VAL_FLOAT ? v.fv : (double)v.iv;
+    double b = p.type == VAL_FLOAT ? p.fv : (double)p.iv;
+    return a == b;
+}
+
 static SubVal eval_binary(ASTNode *node, Env *env) {
     const char *op = node->value;
     SubVal L = eval(node->left, env);
+
+    /* && and || only evaluate the right operand when the left one does not
+       already decide the result */
+    if (strcmp(op,"&&")==0 || strcmp(op,"||")==0) {
+        bool left = is_truthy(L);
+        if (left == (strcmp(op,"||")==0)) return make_bool(left);
+        return make_bool(is_truthy(eval(node->right, env)));
+    }
     SubVal R = eval(node->right, env);
 
+    /* x == null / x != null */
+    if ((L.type == VAL_NULL || R.type == VAL_NULL) && (strcmp(op,"==")==0 || strcmp(op,"!=")==0)) {
+        bool same = L.type == R.type;
+        return make_bool(strcmp(op,"==")==0 ? same : !same);
+    }
+
     /* string concat */
     if (strcmp(op, "+") == 0 && (L.type == VAL_STRING || R.type == VAL_STRING)) {
         char lb[64], rb[64];
         const char *ls = L.type==VAL_STRING ? L.sv : (snprintf(lb,sizeof(lb),L.type==VAL_INT?"%lld":"%g",L.type==VAL_INT?(double)L.iv:L.fv),lb);
         const char *rs = R.type==VAL_STRING ? R.sv : (snprintf(rb,sizeof(rb),R.type==VAL_INT?"%lld":"%g",R.type==VAL_INT?(double)R.iv:R.fv),rb);
         size_t n = strlen(ls)+strlen(rs)+1;
+        if (!reserve_memory(node->line, (long long)n)) return NULL_VAL;
         char *buf = malloc(n); snprintf(buf, n, "%s%s", ls, rs);
         SubVal res = {VAL_STRING}; res.sv = buf; return res;
     }
@@ -105,6 +361,13 @@ static SubVal eval_binary(ASTNode *node, Env *env) {
         if (strcmp(op,">=")==0) return make_bool(cmp>=0);
     }
 
+    if (L.type == VAL_INT && R.type == VAL_INT &&
+        (strcmp(op,"+")==0 || strcmp(op,"-")==0 || strcmp(op,"*")==0 ||
+         strcmp(op,"/")==0 || strcmp(op,"%")==0)) {
+        long long r;
+        return int_arith(op, L.iv, R.iv, node->line, &r) ? make_int(r) : NULL_VAL;
+    }
+
     double a = L.type==VAL_FLOAT ? L.fv : (double)L.iv;
     double b = R.type==VAL_FLOAT ? R.fv : (double)R.iv;
     int use_float = (L.type==VAL_FLOAT || R.type==VAL_FLOAT);
@@ -120,13 +383,21 @@ static SubVal eval_binary(ASTNode *node, Env *env) {
     if (strcmp(op,"<=")==0) return make_bool(a<=b);
     if (strcmp(op,">")==0)  return make_bool(a>b);
     if (strcmp(op,">=")==0) return make_bool(a>=b);
-    if (strcmp(op,"&&")==0) return make_bool(is_truthy(L)&&is_truthy(R));
-    if (strcmp(op,"||")==0) return make_bool(is_truthy(L)||is_truthy(R));
     return NULL_VAL;
 }
 
 SubVal eval(ASTNode *node, Env *env) {
-    if (!node || env->returning) return NULL_VAL;
+    if (!node || env->returning || g_runtime_error) return NULL_VAL;
+    if (g_limits.max_steps > 0 && ++g_steps > g_limits.max_steps) {
+        runtime_error(node->line, "Resource limit exceeded: more than %lld steps (--max-steps)",
+                      g_limits.max_steps);
+        return NULL_VAL;
+    }
+    if (stack_exhausted()) {
+        runtime_error(node->line, "Resource limit exceeded: the program nests too deeply for the "
+                      "interpreter's stack (%d calls deep; lower --max-depth or raise ulimit -s)", g_depth);
+        return NULL_VAL;
+    }
 
     switch (node->type) {
 
@@ -138,12 +409,35 @@ SubVal eval(ASTNode *node, Env *env) {
         if (node->data_type == TYPE_STRING) return make_str(node->value);
         if (node->data_type == TYPE_BOOL)   return make_bool(node->value && strcmp(node->value,"true")==0);
         if (node->data_type == TYPE_FLOAT)  return make_float(atof(node->value ? node->value : "0"));
-        return make_int(atoll(node->value ? node->value : "0"));
+        if (node->data_type == TYPE_NULL)   return NULL_VAL;
+        const char *digits = node->value ? node->value : "0";
+        const char *p = digits + (*digits == '-' || *digits == '+');
+        bool hex = p[0] == '0' && (p[1] == 'x' || p[1] == 'X');
+        return make_int(strtoll(digits, NULL, hex ? 16 : 10));
     }
 
     case AST_IDENTIFIER:
         return env_get(env, node->value);
 
+    case AST_UNWRAP_EXPR: {
+        SubVal v = eval(node->left, env);
+        if (v.type == VAL_NULL && !g_runtime_error) {
+            runtime_error(node->line, "Unwrapped a null optional value");
+        }
+        return v;
+    }
+
+    case AST_MATCH_EXPR: {
+        SubVal v = eval(node->left, env);
+        for (int i = 0; i < node->child_count; i++) {
+            ASTNode *arm = node->children[i];
+            if (!arm->left || match_pattern_equal(v, eval(arm->left, env))) {
+                return eval(arm->right, env);
+            }
+        }
+        return NULL_VAL;
+    }
+
     case AST_VAR_DECL:
     case AST_CONST_DECL: {
         SubVal val = node->right ? eval(node->right, env) : NULL_VAL;
@@ -161,10 +455,84 @@ SubVal eval(ASTNode *node, Env *env) {
     case AST_BINARY_EXPR:
         return eval_binary(node, env);
 
+    case AST_ARRAY_LITERAL:
+    case AST_TUPLE_EXPR: {
+        /* [value; count] evaluates value once and fills every slot with it */
+        int length = node->type == AST_ARRAY_LITERAL ? ast_array_length(node) : node->child_count;
+        if (!reserve_memory(node->line, (long long)length * (long long)sizeof(SubVal)))
+            return NULL_VAL;
+        SubArray *arr = calloc(1, sizeof(SubArray));
+        arr->items = calloc(length > 0 ? length : 1, sizeof(SubVal));
+        for (int i = 0; i < node->child_count; i++)
+            arr->items[arr->count++] = eval(node->children[i], env);
+        while (arr->count < length) {
+            arr->items[arr->count] = arr->items[0];
+            arr->count++;
+        }
+        return (SubVal){.type = node->type == AST_TUPLE_EXPR ? VAL_TUPLE : VAL_ARRAY, .av = arr};
+    }
+
+    case AST_RANGE_EXPR: {
+        /* a..b as a value: the array [a, a + 1, ..., b - 1] */
+        long long lo = eval(node->left, env).iv;
+        long long hi = eval(node->right, env).iv;
+        if (g_runtime_error) return NULL_VAL;
+        long long count = hi > lo ? hi - lo : 0;
+        if (count > INT_MAX / (long long)sizeof(SubVal)) {
+            runtime_error(node->line, "Range %lld..%lld is too large to hold as an array", lo, hi);
+            return NULL_VAL;
+        }
+        if (!reserve_memory(node->line, count * (long long)sizeof(SubVal))) return NULL_VAL;
+        SubArray *arr = calloc(1, sizeof(SubArray));
+        arr->items = calloc(count > 0 ? (size_t)count : 1, sizeof(SubVal));
+        for (long long i = 0; i < count; i++)
+            arr->items[arr->count++] = make_int(lo + i);
+        return (SubVal){.type = VAL_ARRAY, .av = arr};
+    }
+
+    case AST_DESTRUCTURE_DECL: {
+        SubVal val = eval(node->right, env);
+        for (int i = 0; i < node->child_count; i++) {
+            SubVal item = val.type == VAL_TUPLE && val.av && i < val.av->count ? val.av->items[i] : NULL_VAL;
+            env_define(env, node->children[i]->value, item);
+        }
+        return val;
+    }
+
+    case AST_ARRAY_ACCESS: {
+        SubVal target = eval(node->left, env);
+        long long idx = eval(node->right, env).iv;
+        if (g_runtime_error) return NULL_VAL;
+        if (idx < 0) {
+            runtime_error(node->line, "Negative array index %lld", idx);
+            return NULL_VAL;
+        }
+        if (target.type == VAL_ARRAY && target.av && idx < target.av->count)
+            return target.av->items[idx];
+        if (target.type == VAL_STRING && target.sv && idx < utf8_length(target.sv)) {
+            const char *at = utf8_offset(target.sv, idx);
+            SubVal v = {VAL_STRING};
+            v.sv = strndup(at, (size_t)(utf8_next(at) - at));
+            return v;
+        }
+        runtime_error(node->line, "Index %lld out of range", idx);
+        return NULL_VAL;
+    }
+
+    case AST_CAST_EXPR: {
+        SubVal v = eval_cast(eval(node->right, env), node->data_type);
+        if (node->data_type == TYPE_INT && v.type == VAL_INT && !int_narrow(&v.iv, node->line))
+            return NULL_VAL;
+        return v;
+    }
+
     case AST_UNARY_EXPR: {
         SubVal v = eval(node->right, env);
-        if (node->value && strcmp(node->value, "-")==0)
-            return v.type==VAL_FLOAT ? make_float(-v.fv) : make_int(-v.iv);
+        if (node->value && strcmp(node->value, "-")==0) {
+            if (v.type == VAL_FLOAT) return make_float(-v.fv);
+            long long r;
+            return int_arith("-", 0, v.iv, node->line, &r) ? make_int(r) : NULL_VAL;
+        }
         if (node->value && strcmp(node->value, "!")==0)
             return make_bool(!is_truthy(v));
         return v;
@@ -179,7 +547,7 @@ SubVal eval(ASTNode *node, Env *env) {
 
     case AST_WHILE_STMT: {
         SubVal r = NULL_VAL;
-        while (!env->returning) {
+        while (!env->returning && !g_runtime_error) {
             SubVal c = eval(node->condition, env);
             if (!is_truthy(c)) break;
             Env *loop = env_new(env);
@@ -193,6 +561,36 @@ SubVal eval(ASTNode *node, Env *env) {
 
     case AST_FOR_STMT: {
         if (!node->value) return NULL_VAL;
+        if (node->condition) {
+            /* for x in xs: an indexed loop over the array's elements or the
+               string's characters, evaluated once up front */
+            SubVal iter = eval(node->condition, env);
+            if (g_runtime_error) return NULL_VAL;
+            if (iter.type != VAL_ARRAY && iter.type != VAL_STRING) {
+                runtime_error(node->line, "Cannot iterate over a value that is not an array or a string");
+                return NULL_VAL;
+            }
+            long long count = iter.type == VAL_ARRAY ? (iter.av ? iter.av->count : 0)
+                                                     : utf8_length(iter.sv ? iter.sv : "");
+            const char *at = iter.type == VAL_STRING && iter.sv ? iter.sv : "";
+            for (long long i = 0; i < count && !env->returning && !g_runtime_error; i++) {
+                Env *loop = env_new(env);
+                if (iter.type == VAL_ARRAY) {
+                    env_define(loop, node->value, iter.av->items[i]);
+                } else {
+                    /* One character per step, as len() and s[i] count them */
+                    SubVal c = {VAL_STRING};
+                    c.sv = strndup(at, (size_t)(utf8_next(at) - at));
+                    at = utf8_next(at);
+                    env_define(loop, node->value, c);
+                }
+                eval(node->body, loop);
+                int ret = loop->returning; SubVal rv = loop->ret_val;
+                env_free(loop);
+                if (ret) { env->returning = 1; env->ret_val = rv; break; }
+            }
+            return NULL_VAL;
+        }
         ASTNode *range = (node->children && node->child_count > 0) ? node->children[0] : NULL;
         long long start = 0, end_v = 10;
         if (range && range->type == AST_RANGE_EXPR) {
@@ -203,7 +601,7 @@ SubVal eval(ASTNode *node, Env *env) {
                 end_v = eval(range->left, env).iv;
             }
         }
-        for (long long i = start; i < end_v && !env->returning; i++) {
+        for (long long i = start; i < end_v && !env->returning && !g_runtime_error; i++) {
             Env *loop = env_new(env);
             env_define(loop, node->value, make_int(i));
             eval(node->body, loop);
@@ -220,8 +618,30 @@ SubVal eval(ASTNode *node, Env *env) {
         return NULL_VAL;
     }
 
+    case AST_EXIT_STMT: {
+        SubVal code = node->right ? eval(node->right, env) : make_int(0);
+        if (g_runtime_error) return NULL_VAL;
+        if (code.type != VAL_INT) {
+            runtime_error(node->line, "Exit code must be an int in 0-255");
+            return NULL_VAL;
+        }
+        if (code.iv < 0 || code.iv > 255) {
+            runtime_error(node->line, "Exit code %lld is out of range 0-255", code.iv);
+            return NULL_VAL;
+        }
+        fflush(stdout);
+        exit((int)code.iv);
+    }
+
     case AST_RETURN_STMT: {
         SubVal rv = node->right ? eval(node->right, env) : NULL_VAL;
+        if (g_runtime_error) return NULL_VAL;
+        /* Outside any call this is the program's exit status; semantic
+           analysis only catches a constant out of range */
+        if (g_depth == 0 && rv.type == VAL_INT && (rv.iv < 0 || rv.iv > 255)) {
+            runtime_error(node->line, "Exit code %lld is out of range 0-255", rv.iv);
+            return NULL_VAL;
+        }
         env->returning = 1;
         env->ret_val   = rv;
         return rv;
@@ -231,35 +651,32 @@ SubVal eval(ASTNode *node, Env *env) {
         const char *fn = node->value;
         /* Built-ins: print() and show() are identical */
         if (fn && (strcmp(fn, "print") == 0 || strcmp(fn, "show") == 0)) {
-            for (int i = 0; i < node->child_count; i++)
-                print_val(eval(node->children[i], env));
+            for (int i = 0; i < node->child_count; i++) {
+                SubVal v = eval(node->children[i], env);
+                if (g_runtime_error) break;
+                print_val(v);
+            }
             return NULL_VAL;
         }
-        if (fn && strcmp(fn, "str") == 0 && node->child_count > 0) {
-            SubVal v = eval(node->children[0], env);
-            char buf[64];
-            if (v.type==VAL_INT)         snprintf(buf, sizeof(buf), "%lld", v.iv);
-            else if (v.type==VAL_FLOAT)  snprintf(buf, sizeof(buf), "%g", v.fv);
-            else if (v.type==VAL_STRING) return v;
-            else snprintf(buf, sizeof(buf), "null");
-            return make_str(buf);
-        }
-        if (fn && strcmp(fn, "int") == 0 && node->child_count > 0) {
-            SubVal v = eval(node->children[0], env);
-            if (v.type==VAL_STRING) return make_int(atoll(v.sv ? v.sv : "0"));
-            if (v.type==VAL_FLOAT)  return make_int((long long)v.fv);
-            return v;
-        }
-        if (fn && strcmp(fn, "float") == 0 && node->child_count > 0) {
-            SubVal v = eval(node->children[0], env);
-            if (v.type==VAL_STRING) return make_float(atof(v.sv ? v.sv : "0"));
-            if (v.type==VAL_INT)    return make_float((double)v.iv);
-            return v;
-        }
+        if (fn && strcmp(fn, "str") == 0 && node->child_count > 0)
+            return eval_cast(eval(node->children[0], env), TYPE_STRING);
         if (fn && strcmp(fn, "len") == 0 && node->child_count > 0) {
             SubVal v = eval(node->children[0], env);
-            return make_int(v.type==VAL_STRING ? (long long)strlen(v.sv ? v.sv : "") : 0);
+            if (v.type==VAL_ARRAY) return make_int(v.av ? v.av->count : 0);
+            return make_int(v.type==VAL_STRING ? utf8_length(v.sv ? v.sv : "") : 0);
+        }
+        if (fn && strcmp(fn, "select") == 0 && node->child_count == 3) {
+            /* Unlike ?:, every argument is evaluated */
+            SubVal cond = eval(node->children[0], env);
+            SubVal a = eval(node->children[1], env);
+            SubVal b = eval(node->children[2], env);
+            SubVal picked = is_truthy(cond) ? a : b;
+            /* select(c, 1, 2.5) is a float whichever arm is picked */
+            if (node->data_type == TYPE_FLOAT && picked.type == VAL_INT) return make_float((double)picked.iv);
+            return picked;
         }
+        if (string_builtin_lookup(fn) >= 0)
+            return eval_string_builtin(node, (StringBuiltin)string_builtin_lookup(fn), env);
         if (fn && strcmp(fn, "input") == 0) {
             if (node->child_count > 0) print_val(eval(node->children[0], env));
             char buf[1024];
@@ -275,6 +692,12 @@ SubVal eval(ASTNode *node, Env *env) {
             return NULL_VAL;
         }
         ASTNode *fn_decl = fv.fn;
+        if (g_depth >= g_limits.max_depth) {
+            runtime_error(node->line, "Resource limit exceeded: calls nested more than %d deep (--max-depth)",
+                          g_limits.max_depth);
+            return NULL_VAL;
+        }
+
         Env *fn_env = env_new(env);
         /* Bind parameters */
         if (fn_decl->children) {
@@ -283,7 +706,9 @@ SubVal eval(ASTNode *node, Env *env) {
                 env_define(fn_env, fn_decl->children[i]->value, arg);
             }
         }
+        g_depth++;
         eval(fn_decl->body, fn_env);
+        g_depth--;
         SubVal ret = fn_env->returning ? fn_env->ret_val : NULL_VAL;
         env_free(fn_env);
         return ret;
@@ -303,26 +728,253 @@ static SubVal eval_block(ASTNode *node, Env *env) {
 }
 
 int interpret_file(const char *path) {
-    FILE *f = fopen(path, "rb");
-    if (!f) { fprintf(stderr, "Cannot open: %s\n", path); return 1; }
-    fseek(f, 0, SEEK_END); long sz = ftell(f); fseek(f, 0, SEEK_SET);
-    char *src = malloc(sz + 1);
-    if (!src) { fclose(f); return 1; }
-    fread(src, 1, sz, f); src[sz] = '\0'; fclose(f);
+    return interpret_file_with_limits(path, NULL);
+}
+
+static bool is_expression(const ASTNode *node) {
+    switch (node->type) {
+        case AST_BINARY_EXPR: case AST_UNARY_EXPR: case AST_TERNARY_EXPR:
+        case AST_IDENTIFIER: case AST_LITERAL: case AST_CALL_EXPR:
+        case AST_ARRAY_LITERAL: case AST_MEMBER_ACCESS: case AST_ARRAY_ACCESS:
+        case AST_CAST_EXPR: case AST_UNWRAP_EXPR: case AST_MATCH_EXPR: case AST_TUPLE_EXPR:
+            return true;
+        default:
+            return false;
+    }
+}
+
+/* Reset the limits and counters before a program runs; `stack_base` is in
+   the frame of the caller, which the whole run stays below */
+static void start_run(const InterpreterLimits *limits, const char *stack_base) {
+    g_runtime_error = 0;
+    g_limits = limits ? *limits : (InterpreterLimits){0};
+    if (g_limits.max_depth <= 0) g_limits.max_depth = INTERPRETER_DEFAULT_DEPTH;
+    g_steps = 0;
+    g_memory = 0;
+    g_depth = 0;
+    g_stack_base = stack_base;
+    size_t stack = stack_limit_bytes();
+    g_stack_budget = stack > 1024 * 1024 ? stack - 256 * 1024 : stack / 4 * 3;
+}
+
+/* Run `src` (owned, freed here). With `echo`, the value of a final
+   top-level statement that yields one is printed, as `--eval "2 + 3"` expects. */
+static int interpret_source(char *src, const InterpreterLimits *limits, bool echo) {
     int ntok;
     Token *toks = lexer_tokenize(src, &ntok);
     ASTNode *ast = parser_parse(toks, ntok);
     if (!ast) { free(src); lexer_free_tokens(toks, ntok); return 1; }
+    set_active_target("interpret");
     if (!semantic_analyze(ast)) {
-        fprintf(stderr, "Semantic error\n");
+        if (!parser_had_errors()) fprintf(stderr, "Semantic error\n");
         parser_free_ast(ast); lexer_free_tokens(toks, ntok); free(src);
         return 1;
     }
     Env *global = env_new(NULL);
-    eval(ast, global);
+    char stack_base;
+    start_run(limits, &stack_base);
+    SubVal last = eval(ast, global);
+    if (echo && !g_runtime_error && !global->returning && ast->child_count > 0 &&
+        is_expression(ast->children[ast->child_count - 1]) && last.type != VAL_NULL && last.type != VAL_FUNC) {
+        print_val(last);
+    }
+    /* A top-level `return <int>` sets the exit status */
+    int status = global->returning && global->ret_val.type == VAL_INT ? (int)global->ret_val.iv : 0;
     env_free(global);
     parser_free_ast(ast);
     lexer_free_tokens(toks, ntok);
     free(src);
-    return 0;
+    return g_runtime_error ? 1 : status;
+}
+
+int interpret_file_with_limits(const char *path, const InterpreterLimits *limits) {
+    if (path_is_directory(path)) { fprintf(stderr, "Cannot run a directory: %s\n", path); return 1; }
+    FILE *f = fopen(path, "rb");
+    if (!f) { fprintf(stderr, "Cannot open: %s\n", path); return 1; }
+    fseek(f, 0, SEEK_END); long sz = ftell(f); fseek(f, 0, SEEK_SET);
+    char *src = malloc(sz + 1);
+    if (!src) { fclose(f); return 1; }
+    size_t got = fread(src, 1, sz, f); src[got] = '\0'; fclose(f);
+    src = decode_source(path, src, got);
+    if (!src) return 1;
+    return interpret_source(src, limits, false);
+}
+
+int interpret_eval(const char *source, const InterpreterLimits *limits) {
+    size_t len = strlen(source);
+    char *src = malloc(len + 1);
+    if (!src) return 1;
+    memcpy(src, source, len + 1);
+    src = decode_source("--eval", src, len);
+    if (!src) return 1;
+    // The last value is printed rather than discarded, so it has an effect
+    semantic_set_echo_last(true);
+    int rc = interpret_source(src, limits, true);
+    semantic_set_echo_last(false);
+    return rc;
+}
+
+/* subc repl. The entries that ran are kept: their text, for checking the
+   next entry against, and their trees, which the functions they declared
+   still point into. */
+typedef struct {
+    char *history;
+    size_t history_len;
+    int history_items;     // top-level statements in history
+    ASTNode **trees;
+    int tree_count;
+    Env *global;
+} Repl;
+
+// One line of `in`, however long, without its line break; NULL at the end
+static char* read_repl_line(FILE *in) {
+    size_t len = 0, capacity = 128;
+    char *line = malloc(capacity);
+    if (!line) return NULL;
+    int c;
+    while ((c = fgetc(in)) != EOF && c != '\n') {
+        if (len + 1 == capacity) {
+            char *grown = realloc(line, capacity * 2);
+            if (!grown) break;
+            line = grown;
+            capacity *= 2;
+        }
+        line[len++] = (char)c;
+    }
+    if (c == EOF && len == 0) {
+        free(line);
+        return NULL;
+    }
+    if (len > 0 && line[len - 1] == '\r') len--;
+    line[len] = '\0';
+    return line;
+}
+
+// Check `entry` after the history and run its statements in the global
+// scope. Returns false if it did not get through; then it is forgotten.
+static bool run_repl_entry(Repl *repl, const char *entry, int *status) {
+    int hist_count, entry_count;
+    Token *hist_tokens = lexer_tokenize(repl->history, &hist_count);
+    ASTNode *program = parser_parse(hist_tokens, hist_count);
+    lexer_free_tokens(hist_tokens, hist_count);
+    // The entry is lexed last, so error excerpts and line numbers are its own
+    int errors = diagnostic_error_count();
+    Token *tokens = lexer_tokenize(entry, &entry_count);
+    ASTNode *added = parser_parse(tokens, entry_count);
+    lexer_free_tokens(tokens, entry_count);
+    bool ok = program && added && diagnostic_error_count() == errors;
+    int count = ok ? added->child_count : 0;
+
+    if (ok && count > 0) {
+        ASTNode **children = realloc(program->children, sizeof(ASTNode *) * (program->child_count + count));
+        ok = children != NULL;
+        if (ok) {
+            program->children = children;
+            if (program->child_count > 0) program->children[program->child_count - 1]->next = added->children[0];
+            else program->body = added->children[0];
+            memcpy(children + program->child_count, added->children, sizeof(ASTNode *) * count);
+            program->child_count += count;
+            free(added->children);
+            added->children = NULL;
+            added->child_count = 0;
+            added->body = NULL;
+            ast_number_nodes(program);
+        }
+    }
+    parser_free_ast(added);
+    if (!ok || count == 0) {
+        parser_free_ast(program);
+        return ok;
+    }
+
+    semantic_set_checked_items(repl->history_items);
+    semantic_set_echo_last(true);
+    ok = semantic_analyze(program);
+    semantic_set_echo_last(false);
+    semantic_set_checked_items(0);
+    if (!ok) {
+        parser_free_ast(program);
+        return false;
+    }
+
+    char stack_base;
+    start_run(NULL, &stack_base);
+    SubVal last = NULL_VAL;
+    ASTNode *stmt = NULL;
+    for (int i = program->child_count - count; i < program->child_count; i++) {
+        stmt = program->children[i];
+        last = eval(stmt, repl->global);
+        if (g_runtime_error || repl->global->returning) break;
+    }
+    if (repl->global->returning) {
+        *status = repl->global->ret_val.type == VAL_INT ? (int)repl->global->ret_val.iv : 0;
+    } else if (!g_runtime_error && stmt == program->children[program->child_count - 1] &&
+               is_expression(stmt) && last.type != VAL_NULL && last.type != VAL_FUNC) {
+        print_val(last);
+    }
+    // Keep the output in step with errors on stderr
+    fflush(stdout);
+
+    ASTNode **trees = realloc(repl->trees, sizeof(ASTNode *) * (repl->tree_count + 1));
+    if (trees) {
+        repl->trees = trees;
+        repl->trees[repl->tree_count++] = program;
+    }
+    if (g_runtime_error) return false;
+    size_t len = strlen(entry);
+    char *history = realloc(repl->history, repl->history_len + len + 2);
+    if (!history) return false;
+    memcpy(history + repl->history_len, entry, len);
+    history[repl->history_len + len] = '\n';
+    history[repl->history_len + len + 1] = '\0';
+    repl->history = history;
+    repl->history_len += len + 1;
+    repl->history_items += count;
+    return true;
+}
+
+int interpret_repl(FILE *in) {
+    bool interactive = isatty(fileno(in));
+    Repl repl = {strdup(""), 0, 0, NULL, 0, env_new(NULL)};
+    if (!repl.history || !repl.global) return 1;
+    set_active_target("interpret");
+
+    char *entry = NULL;
+    size_t entry_len = 0;
+    int status = -1;
+    while (status < 0) {
+        if (interactive) {
+            fputs(entry ? "...> " : "sub> ", stdout);
+            fflush(stdout);
+        }
+        char *line = read_repl_line(in);
+        if (!line && !entry) break;
+        if (line) {
+            size_t len = strlen(line);
+            char *grown = realloc(entry, entry_len + len + 2);
+            if (!grown) {
+                free(line);
+                break;
+            }
+            entry = grown;
+            memcpy(entry + entry_len, line, len);
+            entry_len += len;
+            entry[entry_len++] = '\n';
+            entry[entry_len] = '\0';
+            free(line);
+            // Keep reading while a bracket or block is open
+            if (source_open_scopes(entry) > 0) continue;
+        }
+        run_repl_entry(&repl, entry, &status);
+        free(entry);
+        entry = NULL;
+        entry_len = 0;
+    }
+    if (interactive && status < 0) fputs("\n", stdout);
+    free(entry);
+    env_free(repl.global);
+    for (int i = 0; i < repl.tree_count; i++) parser_free_ast(repl.trees[i]);
+    free(repl.trees);
+    free(repl.history);
+    return status < 0 ? 0 : status;
 }
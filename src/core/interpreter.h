@@ -2,7 +2,9 @@
 #define INTERPRETER_H
 #include "sub_compiler.h"
 
-typedef enum { VAL_INT, VAL_FLOAT, VAL_STRING, VAL_BOOL, VAL_NULL, VAL_FUNC } ValType;
+typedef enum { VAL_INT, VAL_FLOAT, VAL_STRING, VAL_BOOL, VAL_NULL, VAL_FUNC, VAL_ARRAY, VAL_TUPLE } ValType;
+
+struct SubArray;
 
 typedef struct SubVal {
     ValType type;
@@ -12,9 +14,15 @@ typedef struct SubVal {
         char      *sv;
         int        bv;
         ASTNode   *fn; /* function AST node */
+        struct SubArray *av; /* arrays and tuples */
     };
 } SubVal;
 
+typedef struct SubArray {
+    int     count;
+    SubVal *items;
+} SubArray;
+
 typedef struct EnvEntry {
     char *name;
     SubVal val;
@@ -34,6 +42,26 @@ SubVal env_get(Env *env, const char *name);
 void   env_set(Env *env, const char *name, SubVal val);
 void   env_define(Env *env, const char *name, SubVal val);
 
+/* Limits for running untrusted programs; 0 leaves a resource unlimited,
+   except max_depth, where 0 means INTERPRETER_DEFAULT_DEPTH. Exceeding one
+   stops the program with "Resource limit exceeded", as does nesting that
+   would overflow the C stack, whatever max_depth allows. */
+typedef struct {
+    long long max_steps;   /* statements and expressions evaluated */
+    long long max_memory;  /* bytes allocated for arrays, tuples and strings built at run time */
+    int       max_depth;   /* nested user function calls */
+} InterpreterLimits;
+
+#define INTERPRETER_DEFAULT_DEPTH 1000
+
 SubVal eval(ASTNode *node, Env *env);
 int    interpret_file(const char *path);
+int    interpret_file_with_limits(const char *path, const InterpreterLimits *limits);
+/* Run a program given as text (subi --eval), printing the value of its last
+   statement if that is an expression */
+int    interpret_eval(const char *source, const InterpreterLimits *limits);
+/* Read entries from `in` and run each in one global scope, printing the
+   value of an expression; a line that leaves a bracket or block open
+   continues on the next. Returns the status of a top-level return, else 0. */
+int    interpret_repl(FILE *in);
 #endif
@@ -12,12 +12,14 @@
 
 /* ── Character Classification ──────────────────────────────── */
 
+/* Bytes >= 0x80 are accepted so UTF-8 names like café lex as identifiers;
+   backends that need ASCII names mangle them (see mangle()). */
 static int is_ident_start(char c) {
-    return isalpha((unsigned char)c) || c == '_';
+    return isalpha((unsigned char)c) || c == '_' || (unsigned char)c >= 0x80;
 }
 
 static int is_ident_cont(char c) {
-    return isalnum((unsigned char)c) || c == '_';
+    return isalnum((unsigned char)c) || c == '_' || (unsigned char)c >= 0x80;
 }
 
 /* ── Dynamic Token Array ───────────────────────────────────── */
@@ -26,11 +28,23 @@ typedef struct {
     Token *data;
     int    count;
     int    capacity;
+    char  *doc;     /* /// lines waiting for the next token */
+    const int *line;    /* The lexer's position, which is where a token */
+    const int *column;  /* ends when it is pushed */
+    const char *source;
+    const char *const *ptr;
+    int start_byte;     /* Where the token being scanned starts */
 } TokenArray;
 
 static void token_array_init(TokenArray *a) {
     a->capacity = 256;
     a->count    = 0;
+    a->doc      = NULL;
+    a->line     = NULL;
+    a->column   = NULL;
+    a->source   = NULL;
+    a->ptr      = NULL;
+    a->start_byte = 0;
     a->data     = malloc(sizeof(Token) * a->capacity);
     if (!a->data) {
         fprintf(stderr, "Fatal: token array allocation failed\n");
@@ -47,6 +61,16 @@ static void token_array_push(TokenArray *a, Token tok) {
             exit(1);
         }
     }
+    /* A doc comment belongs to the first token after it, past any newlines */
+    if (tok.type != TOKEN_NEWLINE) {
+        tok.doc = a->doc;
+        a->doc = NULL;
+    }
+    tok.end_line   = a->line && tok.type != TOKEN_NEWLINE ? *a->line : tok.line;
+    tok.end_column = a->column && tok.type != TOKEN_NEWLINE ? *a->column : tok.column + 1;
+    tok.start_byte = a->start_byte;
+    tok.end_byte   = !a->ptr ? a->start_byte
+                   : tok.type == TOKEN_NEWLINE ? a->start_byte + 1 : (int)(*a->ptr - a->source);
     a->data[a->count++] = tok;
 }
 
@@ -59,6 +83,7 @@ static Token make_token(TokenType type, const char *value,
     t.value  = value ? strdup(value) : NULL;
     t.line   = line;
     t.column = col;
+    t.doc    = NULL;
     return t;
 }
 
@@ -70,6 +95,7 @@ static Token make_token_span(TokenType type, const char *start, int len,
     t.value  = strndup(start, len);
     t.line   = line;
     t.column = col;
+    t.doc    = NULL;
     return t;
 }
 
@@ -81,8 +107,27 @@ typedef struct {
     int         line;
     int         column;
     int         error_count;
+    TokenArray *tokens;
 } Lexer;
 
+/* Maximum number of lexer errors before tokenizing stops (0 = no limit) */
+static int g_lexer_error_limit = 0;
+
+/* Set by lexer_highlight(): comments are recorded here and errors are
+   counted but not printed */
+typedef struct {
+    SourceSpan *data;
+    int count;
+    int capacity;
+} SpanArray;
+
+static SpanArray *g_comment_spans = NULL;
+static bool g_lexer_silent = false;
+
+void lexer_set_error_limit(int limit) {
+    g_lexer_error_limit = limit > 0 ? limit : 0;
+}
+
 static char peek(Lexer *L)      { return *L->ptr; }
 static char peek_next(Lexer *L) { return *L->ptr ? *(L->ptr + 1) : '\0'; }
 
@@ -92,19 +137,39 @@ static char advance(Lexer *L) {
     return c;
 }
 
-static void lex_error(Lexer *L, const char *fmt, ...) {
+static void lex_report(Lexer *L, const char *fmt, va_list ap) {
+    va_list copy;
+    va_copy(copy, ap);
+    diagnostic_record_v(false, NULL, L->line, L->column, fmt, copy);
+    va_end(copy);
     L->error_count++;
+    if (g_lexer_silent) return;
     fprintf(stderr, "[line %d, col %d] Lexer error: ", L->line, L->column);
+    vfprintf(stderr, fmt, ap);
+    fputc('\n', stderr);
+}
+
+static void lex_error(Lexer *L, const char *fmt, ...) {
     va_list ap;
     va_start(ap, fmt);
-    vfprintf(stderr, fmt, ap);
+    lex_report(L, fmt, ap);
     va_end(ap);
-    fputc('\n', stderr);
+    if (!g_lexer_silent) diagnostic_print_excerpt(L->line, L->column);
+}
+
+/* A string or comment that runs to the end of the input: show where it
+   opened (`length` bytes) as well as the end */
+static void lex_error_unclosed(Lexer *L, int line, int column, int length, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    lex_report(L, fmt, ap);
+    va_end(ap);
+    if (!g_lexer_silent) diagnostic_print_unclosed(line, column, length);
 }
 
 /* ── Keyword Table ─────────────────────────────────────────── 
-   Every keyword from the TokenType enum in sub_compiler.h.
-   Sorted roughly by frequency of use for marginal lookup speed. */
+   Every keyword from the TokenType enum in sub_compiler.h. The order
+   does not matter: lookups go through kw_buckets below. */
 
 typedef struct { const char *word; TokenType type; } KWEntry;
 
@@ -171,41 +236,153 @@ static const KWEntry kw_table[] = {
     {"true",        TOKEN_TRUE},
     {"false",       TOKEN_FALSE},
     {"null",        TOKEN_NULL},
+    {"none",        TOKEN_NULL},
 
     {NULL, 0}
 };
 
-static TokenType lookup_keyword(const char *start, int len) {
+/* Open-addressing hash table over kw_table, filled on first use. Every
+   identifier costs one hash and usually a single comparison instead of a
+   walk through the whole list. KW_BUCKETS must be a power of two and well
+   above the number of keywords so probe runs stay short. kw_max_len is
+   the longest keyword, so longer identifiers skip the lookup. */
+#define KW_BUCKETS 256
+
+static const KWEntry *kw_buckets[KW_BUCKETS];
+static int kw_max_len = 0;
+static bool kw_ready = false;
+
+static unsigned kw_hash(const char *s, int len) {
+    unsigned h = 2166136261u;
+    for (int i = 0; i < len; i++) {
+        h ^= (unsigned char)s[i];
+        h *= 16777619u;
+    }
+    return h & (KW_BUCKETS - 1);
+}
+
+static void kw_build(void) {
     for (const KWEntry *e = kw_table; e->word; e++) {
-        if ((int)strlen(e->word) == len &&
-            memcmp(e->word, start, len) == 0)
+        int len = (int)strlen(e->word);
+        if (len > kw_max_len) kw_max_len = len;
+        unsigned h = kw_hash(e->word, len);
+        while (kw_buckets[h]) h = (h + 1) & (KW_BUCKETS - 1);
+        kw_buckets[h] = e;
+    }
+    kw_ready = true;
+}
+
+static TokenType lookup_keyword(const char *start, int len) {
+    if (!kw_ready) kw_build();
+    if (len > kw_max_len) return TOKEN_IDENTIFIER;
+    for (unsigned h = kw_hash(start, len); kw_buckets[h]; h = (h + 1) & (KW_BUCKETS - 1)) {
+        const KWEntry *e = kw_buckets[h];
+        if (strncmp(e->word, start, len) == 0 && e->word[len] == '\0')
             return e->type;
     }
     return TOKEN_IDENTIFIER;
 }
 
+/* ── Directives ────────────────────────────────────────────── 
+   '#' normally starts a line comment; only the names below turn it
+   into a directive token instead. */
+
+static const KWEntry directive_table[] = {
+    {"static_assert", TOKEN_STATIC_ASSERT},
+    {"exit",          TOKEN_EXIT},
+    {NULL, 0}
+};
+
+static TokenType lookup_directive(const char *hash) {
+    const char *start = hash + 1;
+    const char *p = start;
+    while (is_ident_cont(*p)) p++;
+    int len = (int)(p - start);
+    for (const KWEntry *e = directive_table; e->word; e++) {
+        if ((int)strlen(e->word) == len && memcmp(e->word, start, len) == 0)
+            return e->type;
+    }
+    return TOKEN_EOF;
+}
+
+/* '#[name]' or '#[name(...)]', possibly several, then 'function': only
+   then is it an attribute; any other line starting '#[' is a comment. The
+   arguments are checked when the attribute is lexed. */
+static bool attribute_ahead(const char *p) {
+    while (p[0] == '#' && p[1] == '[') {
+        p += 2;
+        if (!is_ident_start(*p)) return false;
+        while (is_ident_cont(*p)) p++;
+        if (*p == '(') {
+            while (*p && *p != ')' && *p != '\n') p++;
+            if (*p++ != ')') return false;
+        }
+        if (*p++ != ']') return false;
+        while (*p == ' ' || *p == '\t' || *p == '\r' || *p == '\n') p++;
+    }
+    return strncmp(p, "function", 8) == 0 && !is_ident_cont(p[8]);
+}
+
 /* ── Whitespace & Comment Skipping ─────────────────────────── */
 
+static void record_comment(Lexer *L, int line, int column) {
+    SpanArray *spans = g_comment_spans;
+    if (!spans) return;
+    if (spans->count == spans->capacity) {
+        int capacity = spans->capacity ? spans->capacity * 2 : 16;
+        SourceSpan *data = realloc(spans->data, capacity * sizeof(SourceSpan));
+        if (!data) return;
+        spans->data = data;
+        spans->capacity = capacity;
+    }
+    spans->data[spans->count++] = (SourceSpan){line, column, L->line, L->column};
+}
+
 static void skip_whitespace_and_comments(Lexer *L) {
     for (;;) {
         char c = peek(L);
+        int line = L->line, column = L->column;
 
-        /* Spaces, tabs, carriage returns */
-        if (c == ' ' || c == '\t' || c == '\r') {
+        /* Spaces and tabs ('\r' is already a newline, see lexer_tokenize) */
+        if (c == ' ' || c == '\t') {
             advance(L);
             continue;
         }
 
+        /* Doc comment: /// ... (but not ////), one line of the doc text */
+        if (c == '/' && strncmp(L->ptr, "///", 3) == 0 && L->ptr[3] != '/') {
+            const char *text = L->ptr + 3;
+            if (*text == ' ') text++;
+            int len = 0;
+            while (text[len] && text[len] != '\n') len++;
+            char **doc = &L->tokens->doc;
+            size_t old = *doc ? strlen(*doc) : 0;
+            char *joined = realloc(*doc, old + (old ? 1 : 0) + len + 1);
+            if (joined) {
+                if (old) joined[old++] = '\n';
+                memcpy(joined + old, text, len);
+                joined[old + len] = '\0';
+                *doc = joined;
+            }
+            while (peek(L) && peek(L) != '\n')
+                advance(L);
+            record_comment(L, line, column);
+            continue;
+        }
+
         /* Single-line comment: // ... or # ... */
-        if ((c == '/' && peek_next(L) == '/') || c == '#') {
+        if ((c == '/' && peek_next(L) == '/') ||
+            (c == '#' && !attribute_ahead(L->ptr) && lookup_directive(L->ptr) == TOKEN_EOF)) {
             while (peek(L) && peek(L) != '\n')
                 advance(L);
+            record_comment(L, line, column);
             continue;
         }
 
         /* Block comment: supports nested slash-star ... star-slash blocks. */
         if (c == '/' && peek_next(L) == '*') {
             int start_line = L->line;
+            int start_col = L->column;
             advance(L);  /* '/' */
             advance(L);  /* '*' */
             int depth = 1;
@@ -225,8 +402,9 @@ static void skip_whitespace_and_comments(Lexer *L) {
                 }
             }
             if (depth > 0)
-                lex_error(L, "Unterminated block comment starting at line %d",
-                          start_line);
+                lex_error_unclosed(L, start_line, start_col, 2,
+                                   "Unterminated block comment starting at line %d", start_line);
+            record_comment(L, line, column);
             continue;
         }
 
@@ -267,6 +445,49 @@ static char* process_escapes(const char *raw, int len, Lexer *L) {
                     }
                     break;
                 }
+                case 'u': {
+                    /* \u{X..XXXXXX} — a Unicode scalar value, stored as UTF-8 */
+                    int digits = 0;
+                    unsigned long cp = 0;
+                    if (i + 1 < len && raw[i+1] == '{') {
+                        while (i + 2 + digits < len && isxdigit((unsigned char)raw[i+2+digits]) && digits < 7) {
+                            cp = cp * 16 + (unsigned long)(isdigit((unsigned char)raw[i+2+digits])
+                                     ? raw[i+2+digits] - '0'
+                                     : tolower((unsigned char)raw[i+2+digits]) - 'a' + 10);
+                            digits++;
+                        }
+                    }
+                    if (digits == 0 || digits > 6 || i + 2 + digits >= len || raw[i+2+digits] != '}') {
+                        lex_error(L, "Invalid Unicode escape; expected \\u{X} with 1 to 6 hex digits");
+                        buf[o++] = 'u';
+                        break;
+                    }
+                    i += 2 + digits;   /* onto the '}' */
+                    if (cp > 0x10FFFF) {
+                        lex_error(L, "Unicode escape \\u{%lX} is above U+10FFFF", cp);
+                        break;
+                    }
+                    if (cp >= 0xD800 && cp <= 0xDFFF) {
+                        lex_error(L, "Unicode escape \\u{%lX} is a surrogate code point, not a scalar value", cp);
+                        break;
+                    }
+                    if (cp < 0x80) {
+                        buf[o++] = (char)cp;
+                    } else if (cp < 0x800) {
+                        buf[o++] = (char)(0xC0 | (cp >> 6));
+                        buf[o++] = (char)(0x80 | (cp & 0x3F));
+                    } else if (cp < 0x10000) {
+                        buf[o++] = (char)(0xE0 | (cp >> 12));
+                        buf[o++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+                        buf[o++] = (char)(0x80 | (cp & 0x3F));
+                    } else {
+                        buf[o++] = (char)(0xF0 | (cp >> 18));
+                        buf[o++] = (char)(0x80 | ((cp >> 12) & 0x3F));
+                        buf[o++] = (char)(0x80 | ((cp >> 6) & 0x3F));
+                        buf[o++] = (char)(0x80 | (cp & 0x3F));
+                    }
+                    break;
+                }
                 default:
                     lex_error(L, "Unknown escape '\\%c'", raw[i]);
                     buf[o++] = raw[i];
@@ -301,8 +522,8 @@ static Token scan_string(Lexer *L) {
     int raw_len = (int)(L->ptr - start);
 
     if (!peek(L)) {
-        lex_error(L, "Unterminated string starting at line %d, col %d",
-                  start_line, start_col);
+        lex_error_unclosed(L, start_line, start_col, 1,
+                           "Unterminated string starting at line %d, col %d", start_line, start_col);
     } else {
         advance(L);                   /* consume closing quote */
     }
@@ -315,19 +536,107 @@ static Token scan_string(Lexer *L) {
     return tok;
 }
 
+/* b"..." holds bytes, not text: printable ASCII and the escapes below,
+   with \xNN for any other byte. The token's value lists the bytes in
+   decimal ("0,255"), and the parser turns it into an [int] array. */
+static Token scan_byte_string(Lexer *L, int start_col) {
+    int start_line = L->line;
+    advance(L);                       /* consume opening quote */
+    const char *start = L->ptr;
+
+    while (peek(L) && peek(L) != '"') {
+        if (peek(L) == '\n') {
+            L->line++;
+            L->column = 0;
+        }
+        if (peek(L) == '\\' && peek_next(L))
+            advance(L);
+        advance(L);
+    }
+    int raw_len = (int)(L->ptr - start);
+
+    if (!peek(L)) {
+        lex_error_unclosed(L, start_line, start_col, 2,
+                           "Unterminated byte string starting at line %d, col %d", start_line, start_col);
+    } else {
+        advance(L);                   /* consume closing quote */
+    }
+
+    char *buf = malloc((size_t)raw_len * 4 + 1);   /* "255," per source byte at most */
+    int o = 0;
+    bool reported_non_ascii = false;
+    for (int i = 0; buf && i < raw_len; i++) {
+        int byte = (unsigned char)start[i];
+        if (byte == '\\' && i + 1 < raw_len) {
+            char e = start[++i];
+            switch (e) {
+                case 'n':  byte = '\n'; break;
+                case 't':  byte = '\t'; break;
+                case 'r':  byte = '\r'; break;
+                case '0':  byte = 0;    break;
+                case '\\': case '\'': case '"': byte = e; break;
+                case 'x':
+                    if (i + 2 < raw_len &&
+                        isxdigit((unsigned char)start[i+1]) &&
+                        isxdigit((unsigned char)start[i+2])) {
+                        char hex[3] = { start[i+1], start[i+2], '\0' };
+                        byte = (int)strtol(hex, NULL, 16);
+                        i += 2;
+                    } else {
+                        lex_error(L, "Invalid hex escape in byte string; expected \\xNN");
+                        continue;
+                    }
+                    break;
+                default:
+                    lex_error(L, "Escape '\\%c' is not allowed in a byte string; "
+                                 "use \\xNN for bytes other than \\n, \\t, \\r and \\0", e);
+                    continue;
+            }
+        } else if (byte >= 0x80) {
+            if (!reported_non_ascii)
+                lex_error(L, "Byte strings may only contain ASCII; write other bytes as \\xNN");
+            reported_non_ascii = true;
+            continue;
+        }
+        o += sprintf(buf + o, "%s%d", o ? "," : "", byte);
+    }
+    if (buf) buf[o] = '\0';
+
+    Token tok = make_token(TOKEN_BYTE_STRING, buf ? buf : "", start_line, start_col);
+    free(buf);
+    return tok;
+}
+
 /* ── Number Scanning ───────────────────────────────────────── */
 
 static Token scan_number(Lexer *L) {
     int start_col    = L->column;
     const char *start = L->ptr;
 
-    /* Hex: 0x... */
+    /* Hex: 0x..., or a hex float 0x1.8p3 whose exponent is a power of 2 */
     if (peek(L) == '0' && (peek_next(L) == 'x' || peek_next(L) == 'X')) {
         advance(L); advance(L);       /* 0x */
         if (!isxdigit((unsigned char)peek(L)))
             lex_error(L, "Expected hex digits after '0x'");
         while (isxdigit((unsigned char)peek(L)))
             advance(L);
+        bool fraction = peek(L) == '.' && isxdigit((unsigned char)peek_next(L));
+        if (fraction) {
+            advance(L);               /* consume '.' */
+            while (isxdigit((unsigned char)peek(L)))
+                advance(L);
+        }
+        if (peek(L) == 'p' || peek(L) == 'P') {
+            advance(L);
+            if (peek(L) == '+' || peek(L) == '-')
+                advance(L);
+            if (!isdigit((unsigned char)peek(L)))
+                lex_error(L, "Expected digit after exponent");
+            while (isdigit((unsigned char)peek(L)))
+                advance(L);
+        } else if (fraction) {
+            lex_error(L, "Hex float literal needs a 'p' exponent, as in 0x1.8p3");
+        }
         int len = (int)(L->ptr - start);
         return make_token_span(TOKEN_NUMBER, start, len, L->line, start_col);
     }
@@ -358,6 +667,15 @@ static Token scan_number(Lexer *L) {
     return make_token_span(TOKEN_NUMBER, start, len, L->line, start_col);
 }
 
+/* A NUMBER token is a float if it has a fraction or an exponent: '.', 'e'
+   or 'E' in decimal, '.' or 'p' in hex (where 'e' is a digit) */
+bool number_literal_is_float(const char *text) {
+    if (*text == '-' || *text == '+') text++;
+    if (text[0] == '0' && (text[1] == 'x' || text[1] == 'X'))
+        return strpbrk(text, ".pP") != NULL;
+    return strpbrk(text, ".eE") != NULL;
+}
+
 /* ── Identifier / Keyword Scanning ─────────────────────────── */
 
 static Token scan_identifier(Lexer *L) {
@@ -368,6 +686,21 @@ static Token scan_identifier(Lexer *L) {
         advance(L);
 
     int len = (int)(L->ptr - start);
+
+    /* r#name is a raw identifier: always a name, even when it spells a
+       keyword, and the token carries the name without the prefix */
+    if (len == 1 && *start == 'r' && peek(L) == '#' && is_ident_start(peek_next(L))) {
+        advance(L);
+        start = L->ptr;
+        while (is_ident_cont(peek(L)))
+            advance(L);
+        return make_token_span(TOKEN_IDENTIFIER, start, (int)(L->ptr - start),
+                               L->line, start_col);
+    }
+
+    if (len == 1 && *start == 'b' && peek(L) == '"')
+        return scan_byte_string(L, start_col);
+
     TokenType type = lookup_keyword(start, len);
 
     return make_token_span(type, start, len, L->line, start_col);
@@ -394,10 +727,14 @@ static Token scan_operator_or_punct(Lexer *L) {
         case ':': return make_token(TOKEN_COLON,     ":", L->line, start_col);
         case '@': return make_token(TOKEN_OPERATOR,  "@", L->line, start_col);
 
-        /* ── Dot or ".." range ── */
+        /* ── Dot, ".." or "..=" range ── */
         case '.':
             if (n == '.') {
                 advance(L);
+                if (peek(L) == '=') {
+                    advance(L);
+                    return make_token(TOKEN_OPERATOR, "..=", L->line, start_col);
+                }
                 return make_token(TOKEN_OPERATOR, "..", L->line, start_col);
             }
             return make_token(TOKEN_DOT, ".", L->line, start_col);
@@ -484,6 +821,33 @@ static int is_operator_or_punct_start(char c) {
             c == '^' || c == '~';
 }
 
+/* ── Line Endings ──────────────────────────────────────────── 
+   "\r\n" and a lone '\r' are both one line break. Sources that contain a
+   '\r' are lexed from a copy with every break rewritten to '\n', so line
+   and column numbers, error excerpts and statement ends come out the same
+   as for the '\n' file. The copy stays alive for the diagnostics of later
+   phases and is replaced by the next call. */
+
+static char *g_normalized_source = NULL;
+
+static const char* normalize_line_endings(const char *source) {
+    if (!strchr(source, '\r')) return source;
+    free(g_normalized_source);
+    g_normalized_source = malloc(strlen(source) + 1);
+    if (!g_normalized_source) return source;
+    char *out = g_normalized_source;
+    for (const char *p = source; *p; p++) {
+        if (*p == '\r') {
+            *out++ = '\n';
+            if (p[1] == '\n') p++;
+        } else {
+            *out++ = *p;
+        }
+    }
+    *out = '\0';
+    return g_normalized_source;
+}
+
 /* ── Main Tokenizer ────────────────────────────────────────── */
 
 Token* lexer_tokenize(const char *source, int *token_count) {
@@ -491,24 +855,37 @@ Token* lexer_tokenize(const char *source, int *token_count) {
         if (token_count) *token_count = 0;
         return NULL;
     }
+    source = normalize_line_endings(source);
+
+    TokenArray arr;
+    token_array_init(&arr);
 
     Lexer L = {
         .source      = source,
         .ptr         = source,
         .line        = 1,
         .column      = 1,
-        .error_count = 0
+        .error_count = 0,
+        .tokens      = &arr
     };
-
-    TokenArray arr;
-    token_array_init(&arr);
+    arr.line   = &L.line;
+    arr.column = &L.column;
+    arr.source = source;
+    arr.ptr    = &L.ptr;
+    diagnostic_set_source(source);
 
     while (peek(&L)) {
+        if (g_lexer_error_limit > 0 && L.error_count >= g_lexer_error_limit) {
+            if (!g_lexer_silent) fprintf(stderr, "too many lexer errors, aborting\n");
+            break;
+        }
+
         /* Skip whitespace and comments first */
         skip_whitespace_and_comments(&L);
         if (!peek(&L)) break;
 
         char c = peek(&L);
+        arr.start_byte = (int)(L.ptr - source);
 
         /* ── Newlines ── */
         if (c == '\n') {
@@ -538,6 +915,76 @@ Token* lexer_tokenize(const char *source, int *token_count) {
             continue;
         }
 
+        /* ── Attributes: #[inline], #[target(linux, web)] ── */
+        if (c == '#' && peek_next(&L) == '[') {
+            int start_col = L.column;
+            advance(&L); advance(&L);     /* #[ */
+            const char *name = L.ptr;
+            while (is_ident_cont(peek(&L)))
+                advance(&L);
+            int len = (int)(L.ptr - name);
+            /* The token's value is the name, then any arguments as
+               "(a b c)": one space between them, whatever the source had */
+            char text[256];
+            int n = snprintf(text, sizeof(text), "%.*s", len, name);
+            bool ok = len > 0 && is_ident_start(*name);
+            if (!ok) {
+                lex_error(&L, "Expected an attribute name after '#['");
+            } else if (peek(&L) == '(') {
+                advance(&L);
+                text[n++] = '(';
+                text[n] = '\0';
+                for (bool first = true; ok; first = false) {
+                    while (peek(&L) == ' ' || peek(&L) == '\t') advance(&L);
+                    const char *arg = L.ptr;
+                    while (is_ident_cont(peek(&L)) || peek(&L) == '+')
+                        advance(&L);
+                    int arg_len = (int)(L.ptr - arg);
+                    while (peek(&L) == ' ' || peek(&L) == '\t') advance(&L);
+                    if (arg_len == 0) {
+                        lex_error(&L, "Expected a name in the arguments of #[%.*s]", len, name);
+                        ok = false;
+                    } else if (n + arg_len + 2 >= (int)sizeof(text)) {
+                        lex_error(&L, "Too many arguments in #[%.*s]", len, name);
+                        ok = false;
+                    } else {
+                        n += snprintf(text + n, sizeof(text) - n, "%s%.*s", first ? "" : " ", arg_len, arg);
+                        if (peek(&L) == ')') { advance(&L); break; }
+                        if (peek(&L) != ',') {
+                            lex_error(&L, "Expected ',' or ')' in the arguments of #[%.*s]", len, name);
+                            ok = false;
+                        } else {
+                            advance(&L);
+                        }
+                    }
+                }
+                if (ok) {
+                    text[n++] = ')';
+                    text[n] = '\0';
+                }
+            }
+            if (ok && peek(&L) != ']') {
+                lex_error(&L, "Expected ']' after attribute name");
+            } else if (ok) {
+                advance(&L);
+            }
+            token_array_push(&arr, make_token_span(TOKEN_ATTRIBUTE, text, (int)strlen(text), L.line, start_col));
+            continue;
+        }
+
+        /* ── Directives: #static_assert, #exit ── */
+        if (c == '#') {
+            TokenType type = lookup_directive(L.ptr);
+            int start_col = L.column;
+            const char *start = L.ptr;
+            advance(&L);
+            while (is_ident_cont(peek(&L)))
+                advance(&L);
+            token_array_push(&arr,
+                make_token_span(type, start, (int)(L.ptr - start), L.line, start_col));
+            continue;
+        }
+
         /* ── Identifiers and keywords ── */
         if (is_ident_start(c)) {
             token_array_push(&arr, scan_identifier(&L));
@@ -557,22 +1004,99 @@ Token* lexer_tokenize(const char *source, int *token_count) {
     }
 
     /* EOF sentinel */
+    arr.start_byte = (int)(L.ptr - source);
     token_array_push(&arr, make_token(TOKEN_EOF, NULL, L.line, L.column));
 
     *token_count = arr.count;
 
-    if (L.error_count > 0)
+    if (L.error_count > 0 && !g_lexer_silent)
         fprintf(stderr, "Lexer finished with %d error(s)\n", L.error_count);
 
     return arr.data;
 }
 
+/* ── Highlighting ──────────────────────────────────────────── */
+
+static HighlightKind highlight_kind(TokenType type) {
+    switch (type) {
+        case TOKEN_IDENTIFIER:     return HIGHLIGHT_IDENTIFIER;
+        case TOKEN_NUMBER:         return HIGHLIGHT_NUMBER;
+        case TOKEN_STRING_LITERAL:
+        case TOKEN_BYTE_STRING:    return HIGHLIGHT_STRING;
+        default:
+            return type >= TOKEN_OPERATOR ? HIGHLIGHT_OPERATOR : HIGHLIGHT_KEYWORD;
+    }
+}
+
+static bool in_lines(SourceSpan span, int first_line, int last_line) {
+    return (first_line <= 0 || span.end_line >= first_line) &&
+           (last_line <= 0 || span.line <= last_line);
+}
+
+HighlightList lexer_highlight(const char *source, int first_line, int last_line) {
+    HighlightList list = {NULL, 0};
+    SpanArray comments = {NULL, 0, 0};
+    g_comment_spans = &comments;
+    g_lexer_silent = true;
+    int count = 0;
+    Token *tokens = lexer_tokenize(source, &count);
+    g_comment_spans = NULL;
+    g_lexer_silent = false;
+
+    list.items = malloc((size_t)(count + comments.count + 1) * sizeof(HighlightToken));
+    if (list.items) {
+        /* Both lists are in source order; merge them */
+        int t = 0, c = 0;
+        while (t < count || c < comments.count) {
+            if (t < count && (tokens[t].type == TOKEN_NEWLINE || tokens[t].type == TOKEN_EOF)) {
+                t++;
+                continue;
+            }
+            bool take_comment = c < comments.count &&
+                (t >= count || comments.data[c].line < tokens[t].line ||
+                 (comments.data[c].line == tokens[t].line && comments.data[c].column < tokens[t].column));
+            HighlightToken item;
+            if (take_comment) {
+                item = (HighlightToken){HIGHLIGHT_COMMENT, TOKEN_EOF, comments.data[c++]};
+            } else {
+                SourceSpan span = {tokens[t].line, tokens[t].column, tokens[t].end_line, tokens[t].end_column};
+                item = (HighlightToken){highlight_kind(tokens[t].type), tokens[t].type, span};
+                t++;
+            }
+            if (in_lines(item.span, first_line, last_line)) list.items[list.count++] = item;
+        }
+    }
+    free(comments.data);
+    lexer_free_tokens(tokens, count);
+    return list;
+}
+
+void highlight_list_free(HighlightList *list) {
+    if (!list) return;
+    free(list->items);
+    list->items = NULL;
+    list->count = 0;
+}
+
+const char* highlight_kind_name(HighlightKind kind) {
+    switch (kind) {
+        case HIGHLIGHT_KEYWORD:    return "keyword";
+        case HIGHLIGHT_IDENTIFIER: return "identifier";
+        case HIGHLIGHT_NUMBER:     return "number";
+        case HIGHLIGHT_STRING:     return "string";
+        case HIGHLIGHT_OPERATOR:   return "operator";
+        case HIGHLIGHT_COMMENT:    return "comment";
+        default:                   return "unknown";
+    }
+}
+
 /* ── Cleanup ───────────────────────────────────────────────── */
 
 void lexer_free_tokens(Token *tokens, int count) {
     if (!tokens) return;
     for (int i = 0; i < count; i++) {
         free(tokens[i].value);   /* free(NULL) is safe per C standard */
+        free(tokens[i].doc);
     }
     free(tokens);
 }
@@ -620,6 +1144,9 @@ const char* token_type_to_string(TokenType type) {
         case TOKEN_ASYNC:          return "ASYNC";
         case TOKEN_AWAIT:          return "AWAIT";
         case TOKEN_YIELD:          return "YIELD";
+        case TOKEN_STATIC_ASSERT:  return "STATIC_ASSERT";
+        case TOKEN_EXIT:           return "EXIT";
+        case TOKEN_ATTRIBUTE:      return "ATTRIBUTE";
         case TOKEN_INT:            return "INT";
         case TOKEN_FLOAT:          return "FLOAT";
         case TOKEN_STRING:         return "STRING_TYPE";
@@ -629,6 +1156,7 @@ const char* token_type_to_string(TokenType type) {
         case TOKEN_IDENTIFIER:     return "IDENTIFIER";
         case TOKEN_NUMBER:         return "NUMBER";
         case TOKEN_STRING_LITERAL: return "STRING_LITERAL";
+        case TOKEN_BYTE_STRING:    return "BYTE_STRING";
         case TOKEN_TRUE:           return "TRUE";
         case TOKEN_FALSE:          return "FALSE";
         case TOKEN_NULL:           return "NULL";
@@ -17,8 +17,43 @@ typedef struct {
     int current;
     int had_error;
     int panic_mode;
+    int error_count;
+    int error_limit;   /* 0 = unlimited */
+    int nesting;       /* open ( and [ in the current expression */
+    int depth;         /* expressions and blocks being parsed, innermost first */
+    int abandoned;     /* gave up; report nothing more while unwinding */
+    const char *stack_base;   /* where parsing started, for the stack guard */
 } ParserState;
 
+/* Maximum number of syntax errors before parsing is abandoned (0 = no limit) */
+static int g_parser_error_limit = 0;
+
+void parser_set_error_limit(int limit) {
+    g_parser_error_limit = limit > 0 ? limit : 0;
+}
+
+/* Set by parser_parse() for the caller to stop before semantic analysis */
+static bool g_parser_had_errors = false;
+
+bool parser_had_errors(void) {
+    return g_parser_had_errors;
+}
+
+/* Deepest nesting of expressions and blocks before the parse is abandoned,
+   well before the recursive descent could overflow the native stack */
+static int g_parser_max_nesting = 256;
+
+void parser_set_max_nesting(int limit) {
+    g_parser_max_nesting = limit;
+}
+
+/* --stats: print token and tree metrics once a file has been parsed */
+static bool g_parser_stats = false;
+
+void parser_set_print_stats(bool enabled) {
+    g_parser_stats = enabled;
+}
+
 /* Forward declarations */
 static ASTNode* parse_statement(ParserState *state);
 static ASTNode* parse_expression(ParserState *state);
@@ -95,6 +130,7 @@ static ASTNode* create_node(ASTNodeType type, const Token *tok, const char *valu
     }
     node->line = tok ? tok->line : 0;
     node->column = tok ? tok->column : 0;
+    node->array_length = -1;
     return node;
 }
 
@@ -132,10 +168,15 @@ static Token* peek_token(ParserState *state, int offset) {
 }
 
 
-/* Advance to next token */
+/* Advance to next token. Inside ( ) or [ ] a newline never ends the
+   expression, so it is skipped here rather than at every call site. */
 static void advance(ParserState *state) {
     if (state && state->current < state->token_count - 1) {
         state->current++;
+        while (state->nesting > 0 && state->current < state->token_count - 1 &&
+               state->tokens[state->current].type == TOKEN_NEWLINE) {
+            state->current++;
+        }
     }
 }
 
@@ -152,7 +193,17 @@ static bool check_operator(ParserState *state, const char *op) {
 
 /* Error reporting */
 static void parser_error(ParserState *state, const char *fmt, ...) {
+    /* Once the limit has been hit the cursor sits on EOF; stay quiet while
+       the recursive descent unwinds. */
+    if (state->error_limit > 0 && state->error_count >= state->error_limit) return;
+    if (state->abandoned) return;
+
+    /* Panic mode: anything reported before the next synchronize() is almost
+       always fallout from the first error, so keep it to ourselves. */
     state->had_error = 1;
+    if (state->panic_mode) return;
+    state->panic_mode = 1;
+    state->error_count++;
     Token *tok = current_token(state);
 
     fprintf(stderr, "[line %d, col %d] Parse error", tok ? tok->line : 0, tok ? tok->column : 0);
@@ -166,20 +217,68 @@ static void parser_error(ParserState *state, const char *fmt, ...) {
     vfprintf(stderr, fmt, args);
     va_end(args);
     fprintf(stderr, "\n");
+    va_start(args, fmt);
+    diagnostic_record_v(false, NULL, tok ? tok->line : 0, tok ? tok->column : 0, fmt, args);
+    va_end(args);
+    if (tok && tok->type != TOKEN_EOF) diagnostic_print_excerpt(tok->line, tok->column);
+
+    if (state->error_limit > 0 && state->error_count >= state->error_limit) {
+        fprintf(stderr, "too many parse errors, aborting\n");
+        state->current = state->token_count - 1;
+    }
+}
 
-    if (!state->panic_mode) {
-        state->panic_mode = 1;
+/* The parser may use a quarter of the C stack, so that the passes that
+   later recurse over the same tree have room too */
+static bool parser_stack_low(const ParserState *state) {
+    char here;
+    if (!state->stack_base) return false;
+    size_t used = state->stack_base > &here ? (size_t)(state->stack_base - &here)
+                                            : (size_t)(&here - state->stack_base);
+    return used > stack_limit_bytes() / 4;
+}
+
+/* Go one level deeper, or give up on the whole input past --max-nesting or
+   once the stack runs low: the cursor jumps to EOF, as for the error limit,
+   so the callers unwind without recursing further or reporting anything
+   else */
+static bool enter_nesting(ParserState *state) {
+    bool low = state->depth < g_parser_max_nesting && parser_stack_low(state);
+    if (state->depth < g_parser_max_nesting && !low) {
+        state->depth++;
+        return true;
+    }
+    if (low) {
+        parser_error(state, "Expression nesting too deep (%d levels fill the parser's stack; "
+                     "lower --max-nesting or raise ulimit -s)", state->depth);
+    } else {
+        parser_error(state, "Expression nesting too deep (more than %d levels; raise the limit with --max-nesting)",
+                     g_parser_max_nesting);
     }
+    state->abandoned = 1;
+    state->current = state->token_count - 1;
+    return false;
 }
 
-static void synchronize(ParserState *state) {
+/* Skip to the next statement boundary after a syntax error. stmt_start is
+   where the failed statement began; if nothing was consumed we step over one
+   token so the caller's statement loop always makes progress. */
+static void synchronize(ParserState *state, int stmt_start) {
     state->panic_mode = 0;
+    if (state->current == stmt_start && !match(state, TOKEN_EOF)) {
+        advance(state);
+    }
     while (!match(state, TOKEN_EOF)) {
         if (match(state, TOKEN_NEWLINE) || match(state, TOKEN_SEMICOLON)) {
             advance(state);
             return;
         }
         switch (current_token(state)->type) {
+            case TOKEN_STATIC_ASSERT:
+            case TOKEN_EXIT:
+            case TOKEN_ATTRIBUTE:
+            case TOKEN_LBRACE:
+            case TOKEN_RBRACE:
             case TOKEN_VAR:
             case TOKEN_CONST:
             case TOKEN_LET:
@@ -208,13 +307,56 @@ static Token* expect(ParserState *state, TokenType type, const char *message) {
     return NULL;
 }
 
+/* Expect the closer of a bracket, brace or paren. At the end of the input
+   the error also shows where `opener` was. */
+static Token* expect_closing(ParserState *state, TokenType type, const char *message, const Token *opener) {
+    if (!opener || !match(state, TOKEN_EOF)) return expect(state, type, message);
+    int reported = state->error_count;
+    parser_error(state, "%s", message);
+    if (state->error_count > reported) diagnostic_print_unclosed(opener->line, opener->column, 1);
+    return NULL;
+}
+
 static void skip_separators(ParserState *state) {
     while (match(state, TOKEN_NEWLINE) || match(state, TOKEN_SEMICOLON)) {
         advance(state);
     }
 }
 
+/* A binary operator at the end of a line continues the expression onto the
+   next one. Called right after the operator has been consumed. */
+static void skip_newlines(ParserState *state) {
+    while (match(state, TOKEN_NEWLINE)) {
+        advance(state);
+    }
+}
+
 /* Type parsing helpers */
+
+/* `int?` after a type name: the value may also be null */
+static bool parse_optional_marker(ParserState *state) {
+    if (!match(state, TOKEN_QUESTION)) return false;
+    advance(state);
+    return true;
+}
+
+/* Whether tok can begin an operand. Decides if a `?` after an expression
+   starts a ternary (`c ? a : b`) or unwraps an optional (`x?`). */
+static bool starts_operand(const Token *tok) {
+    if (!tok) return false;
+    switch (tok->type) {
+        case TOKEN_NUMBER: case TOKEN_STRING_LITERAL: case TOKEN_BYTE_STRING: case TOKEN_TRUE:
+        case TOKEN_FALSE: case TOKEN_NULL: case TOKEN_IDENTIFIER: case TOKEN_INT: case TOKEN_FLOAT:
+        case TOKEN_STRING: case TOKEN_BOOL: case TOKEN_LPAREN: case TOKEN_LBRACKET:
+        case TOKEN_LBRACE:
+            return true;
+        case TOKEN_OPERATOR:
+            return tok->value && (strcmp(tok->value, "-") == 0 || strcmp(tok->value, "!") == 0);
+        default:
+            return false;
+    }
+}
+
 static DataType data_type_from_token(Token *tok) {
     if (!tok) return TYPE_UNKNOWN;
     switch (tok->type) {
@@ -228,6 +370,75 @@ static DataType data_type_from_token(Token *tok) {
     }
 }
 
+/* Array type annotation: [T] is a dynamic array, [T; N] one of exactly N
+   elements. The element type's name is kept in metadata for the backends that
+   declare it; only the length is checked. */
+static void parse_array_type(ParserState *state, ASTNode *decl) {
+    Token *start = current_token(state);
+    advance(state);
+    Token *elem = current_token(state);
+    if (elem && (data_type_from_token(elem) != TYPE_UNKNOWN || elem->type == TOKEN_IDENTIFIER)) {
+        if (elem->value && !decl->metadata) decl->metadata = strdup(elem->value);
+        advance(state);
+    } else {
+        parser_error(state, "Expected element type after '['");
+    }
+    decl->data_type = TYPE_ARRAY;
+    if (match(state, TOKEN_SEMICOLON)) {
+        advance(state);
+        Token *len = current_token(state);
+        char *end = NULL;
+        long n = (len && len->type == TOKEN_NUMBER && len->value) ? strtol(len->value, &end, 10) : -1;
+        if (n < 0 || !end || *end != '\0') {
+            parser_error(state, "Expected array length (a non-negative integer) after ';'");
+        } else {
+            decl->array_length = (int)n;
+            advance(state);
+        }
+    }
+    expect_closing(state, TOKEN_RBRACKET, "Expected ']' after array type", start);
+}
+
+/* Append the tuple type at the cursor, e.g. (int, (string, bool)), to buf
+   in the spelling tuple types have in messages */
+static void append_tuple_type(ParserState *state, char *buf, size_t size) {
+    Token *lparen = current_token(state);
+    int count = 0;
+    state->nesting++;
+    advance(state);
+    strncat(buf, "(", size - strlen(buf) - 1);
+    while (true) {
+        Token *elem = current_token(state);
+        DataType dt = data_type_from_token(elem);
+        if (count > 0) strncat(buf, ", ", size - strlen(buf) - 1);
+        if (match(state, TOKEN_LPAREN)) {
+            append_tuple_type(state, buf, size);
+        } else if (elem && elem->value && (dt == TYPE_INT || dt == TYPE_FLOAT || dt == TYPE_STRING || dt == TYPE_BOOL)) {
+            strncat(buf, elem->value, size - strlen(buf) - 1);
+            advance(state);
+        } else {
+            parser_error(state, "Expected element type in tuple type");
+            break;
+        }
+        count++;
+        if (!match(state, TOKEN_COMMA)) break;
+        advance(state);
+        if (match(state, TOKEN_RPAREN)) break;
+    }
+    state->nesting--;
+    if (count == 1) parser_error(state, "A tuple type needs at least two elements");
+    expect_closing(state, TOKEN_RPAREN, "Expected ')' after tuple type", lparen);
+    strncat(buf, ")", size - strlen(buf) - 1);
+}
+
+/* Tuple type annotation: TYPE_TUPLE, with the type as written kept in
+   metadata for the semantic pass to check the returned tuples against */
+static void parse_tuple_type(ParserState *state, ASTNode *decl) {
+    char type[256] = "";
+    append_tuple_type(state, type, sizeof(type));
+    decl->data_type = TYPE_TUPLE;
+    decl->metadata = strdup(type);
+}
 
 /* ========================================
    Expression Parsing
@@ -235,14 +446,101 @@ static DataType data_type_from_token(Token *tok) {
 
 static ASTNode* parse_expression(ParserState *state);
 
+/* `match` is not a keyword, so it stays usable as a name. It starts a match
+   expression only when followed directly by an operand (`match x {`);
+   `match(x)` is a call and `match = 1` an assignment. */
+static bool starts_match(ParserState *state) {
+    Token *tok = current_token(state);
+    if (!tok || tok->type != TOKEN_IDENTIFIER || !tok->value || strcmp(tok->value, "match") != 0) {
+        return false;
+    }
+    switch (peek_token(state, 1)->type) {
+        case TOKEN_IDENTIFIER: case TOKEN_NUMBER: case TOKEN_STRING_LITERAL:
+        case TOKEN_TRUE: case TOKEN_FALSE:
+            return true;
+        default:
+            return false;
+    }
+}
+
+static ASTNode* parse_primary(ParserState *state);
+
+/* A match pattern: a literal (ints may be negative) or `_` */
+static ASTNode* parse_match_pattern(ParserState *state, bool *wildcard) {
+    Token *tok = current_token(state);
+    *wildcard = false;
+    if (tok->type == TOKEN_IDENTIFIER && tok->value && strcmp(tok->value, "_") == 0) {
+        *wildcard = true;
+        advance(state);
+        return NULL;
+    }
+    if (check_operator(state, "-") && peek_token(state, 1)->type == TOKEN_NUMBER) {
+        Token *num = peek_token(state, 1);
+        char buf[256];
+        snprintf(buf, sizeof(buf), "-%s", num->value);
+        ASTNode *node = create_node(AST_LITERAL, tok, buf);
+        if (!node) return NULL;
+        node->data_type = number_literal_is_float(num->value) ? TYPE_FLOAT : TYPE_INT;
+        advance(state);
+        advance(state);
+        return node;
+    }
+    if (match(state, TOKEN_NUMBER) || match(state, TOKEN_STRING_LITERAL) ||
+        match(state, TOKEN_TRUE) || match(state, TOKEN_FALSE)) {
+        return parse_primary(state);
+    }
+    parser_error(state, "Expected a literal or '_' as a match pattern");
+    return NULL;
+}
+
+/* match x { 1 => "one", 2 => "two", _ => "many" }. Arms are separated by
+   commas or newlines. */
+static ASTNode* parse_match(ParserState *state) {
+    ASTNode *node = create_node(AST_MATCH_EXPR, current_token(state), NULL);
+    if (!node) return NULL;
+    advance(state);
+    node->left = parse_expression(state);
+    Token *lbrace = expect(state, TOKEN_LBRACE, "Expected '{' after match value");
+    if (!lbrace) return node;
+    skip_separators(state);
+    while (!match(state, TOKEN_RBRACE) && !match(state, TOKEN_EOF)) {
+        Token *arm_tok = current_token(state);
+        bool wildcard = false;
+        ASTNode *pattern = parse_match_pattern(state, &wildcard);
+        if (!pattern && !wildcard) break;
+        ASTNode *arm = create_node(AST_MATCH_ARM, arm_tok, NULL);
+        if (!arm || !add_child(node, arm)) {
+            parser_free_ast(pattern);
+            parser_free_ast(arm);
+            break;
+        }
+        arm->left = pattern;
+        if (!expect(state, TOKEN_ARROW, "Expected '=>' after match pattern")) break;
+        skip_newlines(state);
+        arm->right = parse_expression(state);
+        if (!arm->right) {
+            parser_error(state, "Expected expression after '=>'");
+            break;
+        }
+        if (match(state, TOKEN_COMMA)) advance(state);
+        skip_separators(state);
+    }
+    expect_closing(state, TOKEN_RBRACE, "Expected '}' after match arms", lbrace);
+    return node;
+}
+
 static ASTNode* parse_primary(ParserState *state) {
     Token *tok = current_token(state);
     if (!tok) return NULL;
 
+    if (starts_match(state)) {
+        return parse_match(state);
+    }
+
     if (match(state, TOKEN_NUMBER)) {
         ASTNode *node = create_node(AST_LITERAL, tok, tok->value);
         if (!node) return NULL;
-        node->data_type = strchr(tok->value, '.') ? TYPE_FLOAT : TYPE_INT;
+        node->data_type = number_literal_is_float(tok->value) ? TYPE_FLOAT : TYPE_INT;
         advance(state);
         return node;
     }
@@ -255,6 +553,25 @@ static ASTNode* parse_primary(ParserState *state) {
         return node;
     }
 
+    /* b"..." is an [int] array literal of its bytes */
+    if (match(state, TOKEN_BYTE_STRING)) {
+        ASTNode *array = create_node(AST_ARRAY_LITERAL, tok, NULL);
+        if (!array) return NULL;
+        for (const char *p = tok->value; *p; p += strcspn(p, ","), p += *p == ',') {
+            char byte[4];
+            snprintf(byte, sizeof(byte), "%.*s", (int)strcspn(p, ","), p);
+            ASTNode *element = create_node(AST_LITERAL, tok, byte);
+            if (!element || !add_child(array, element)) {
+                parser_free_ast(element);
+                parser_free_ast(array);
+                return NULL;
+            }
+            element->data_type = TYPE_INT;
+        }
+        advance(state);
+        return array;
+    }
+
     if (match(state, TOKEN_TRUE) || match(state, TOKEN_FALSE)) {
         ASTNode *node = create_node(AST_LITERAL, tok, tok->value);
         if (!node) return NULL;
@@ -264,13 +581,31 @@ static ASTNode* parse_primary(ParserState *state) {
     }
 
     if (match(state, TOKEN_NULL)) {
-        ASTNode *node = create_node(AST_LITERAL, tok, tok->value);
+        /* `none` is spelled `null` from here on */
+        ASTNode *node = create_node(AST_LITERAL, tok, "null");
         if (!node) return NULL;
         node->data_type = TYPE_NULL;
         advance(state);
         return node;
     }
 
+    /* Conversion: int(x), float(x), bool(x), string(x) */
+    if ((match(state, TOKEN_INT) || match(state, TOKEN_FLOAT) || match(state, TOKEN_STRING) || match(state, TOKEN_BOOL)) &&
+        peek_token(state, 1)->type == TOKEN_LPAREN) {
+        ASTNode *cast = create_node(AST_CAST_EXPR, tok, tok->value);
+        if (!cast) return NULL;
+        cast->data_type = data_type_from_token(tok);
+        advance(state);
+        Token *lparen = current_token(state);
+        state->nesting++;
+        advance(state);
+        cast->right = parse_expression(state);
+        state->nesting--;
+        if (!cast->right) parser_error(state, "Expected expression in '%s(...)' conversion", cast->value);
+        expect_closing(state, TOKEN_RPAREN, "Expected ')' after conversion operand", lparen);
+        return cast;
+    }
+
     if (match(state, TOKEN_IDENTIFIER) || match(state, TOKEN_INT) || match(state, TOKEN_FLOAT) || match(state, TOKEN_STRING) || match(state, TOKEN_BOOL)) {
         ASTNode *ident = create_node(AST_IDENTIFIER, tok, tok->value);
         if (!ident) return NULL;
@@ -279,17 +614,50 @@ static ASTNode* parse_primary(ParserState *state) {
     }
 
     if (match(state, TOKEN_LPAREN)) {
+        Token *start = tok;
+        state->nesting++;
         advance(state);
         ASTNode *expr = parse_expression(state);
-        expect(state, TOKEN_RPAREN, "Expected ')' after expression");
+        if (expr && match(state, TOKEN_COMMA)) {
+            /* (a, b): a comma after the first element makes a tuple */
+            ASTNode *tuple = create_node(AST_TUPLE_EXPR, start, NULL);
+            if (!tuple || !add_child(tuple, expr)) {
+                parser_free_ast(expr);
+                parser_free_ast(tuple);
+                state->nesting--;
+                return NULL;
+            }
+            while (match(state, TOKEN_COMMA)) {
+                advance(state);
+                skip_separators(state);
+                /* Trailing comma: (1, 2,) */
+                if (match(state, TOKEN_RPAREN)) break;
+                ASTNode *elem = parse_expression(state);
+                if (!elem) break;
+                if (!add_child(tuple, elem)) {
+                    parser_free_ast(elem);
+                    parser_free_ast(tuple);
+                    state->nesting--;
+                    return NULL;
+                }
+            }
+            if (tuple->child_count < 2) parser_error(state, "A tuple needs at least two elements");
+            expr = tuple;
+        }
+        state->nesting--;
+        expect_closing(state, TOKEN_RPAREN, "Expected ')' after expression", start);
         return expr;
     }
 
     if (match(state, TOKEN_LBRACKET)) {
         Token *start = tok;
+        state->nesting++;
         advance(state);
         ASTNode *array = create_node(AST_ARRAY_LITERAL, start, NULL);
-        if (!array) return NULL;
+        if (!array) {
+            state->nesting--;
+            return NULL;
+        }
 
         skip_separators(state);
         if (!match(state, TOKEN_RBRACKET)) {
@@ -299,18 +667,29 @@ static ASTNode* parse_primary(ParserState *state) {
                     if (!add_child(array, elem)) {
                         parser_free_ast(elem);
                         parser_free_ast(array);
+                        state->nesting--;
                         return NULL;
                     }
                 }
+                if (elem && array->child_count == 1 && match(state, TOKEN_SEMICOLON)) {
+                    /* Repeat literal [value; count]; semantic analysis expands it */
+                    advance(state);
+                    array->right = parse_expression(state);
+                    if (!array->right) parser_error(state, "Expected array size after ';'");
+                    break;
+                }
                 if (match(state, TOKEN_COMMA)) {
                     advance(state);
                     skip_separators(state);
+                    /* Trailing comma: [1, 2,] */
+                    if (match(state, TOKEN_RBRACKET)) break;
                     continue;
                 }
                 break;
             }
         }
-        expect(state, TOKEN_RBRACKET, "Expected ']' after array literal");
+        state->nesting--;
+        expect_closing(state, TOKEN_RBRACKET, "Expected ']' after array literal", start);
         return array;
     }
 
@@ -351,12 +730,14 @@ static ASTNode* parse_primary(ParserState *state) {
                 if (match(state, TOKEN_COMMA)) {
                     advance(state);
                     skip_separators(state);
+                    /* Trailing comma: {x: 1, y: 2,} */
+                    if (match(state, TOKEN_RBRACE)) break;
                     continue;
                 }
                 break;
             }
         }
-        expect(state, TOKEN_RBRACE, "Expected '}' after object literal");
+        expect_closing(state, TOKEN_RBRACE, "Expected '}' after object literal", start);
         return obj;
     }
 
@@ -372,18 +753,24 @@ static ASTNode* parse_call(ParserState *state) {
     while (true) {
         if (match(state, TOKEN_LPAREN)) {
             Token *lparen = current_token(state);
+            state->nesting++;
             advance(state);
             ASTNode *call = create_node(AST_CALL_EXPR, lparen, NULL);
             if (!call) {
                 parser_free_ast(expr);
+                state->nesting--;
                 return NULL;
             }
 
             if (expr->type == AST_IDENTIFIER) {
+                /* The callee's name is folded into the call, which starts there */
+                call->line = expr->line;
+                call->column = expr->column;
                 call->value = strdup(expr->value ? expr->value : "");
                 if (!call->value) {
                     parser_free_ast(expr);
                     parser_free_ast(call);
+                    state->nesting--;
                     return NULL;
                 }
             } else {
@@ -397,17 +784,26 @@ static ASTNode* parse_call(ParserState *state) {
                         if (!add_child(call, arg)) {
                             parser_free_ast(arg);
                             parser_free_ast(call);
+                            state->nesting--;
                             return NULL;
                         }
                     }
                     if (match(state, TOKEN_COMMA)) {
                         advance(state);
+                        /* Trailing comma: f(a, b,) */
+                        if (match(state, TOKEN_RPAREN)) break;
                         continue;
                     }
                     break;
                 }
             }
-            expect(state, TOKEN_RPAREN, "Expected ')' after arguments");
+            state->nesting--;
+            Token *rparen = expect_closing(state, TOKEN_RPAREN, "Expected ')' after arguments", lparen);
+            if (rparen) {
+                /* The call's range ends after its ')' */
+                call->end_line = rparen->line;
+                call->end_column = rparen->column + 1;
+            }
             if (expr->type == AST_IDENTIFIER) {
                 parser_free_ast(expr);
             }
@@ -417,9 +813,11 @@ static ASTNode* parse_call(ParserState *state) {
 
         if (match(state, TOKEN_LBRACKET)) {
             Token *start = current_token(state);
+            state->nesting++;
             advance(state);
             ASTNode *index = parse_expression(state);
-            expect(state, TOKEN_RBRACKET, "Expected ']' after index expression");
+            state->nesting--;
+            expect_closing(state, TOKEN_RBRACKET, "Expected ']' after index expression", start);
             ASTNode *access = create_node(AST_ARRAY_ACCESS, start, NULL);
             if (!access) {
                 parser_free_ast(expr);
@@ -432,6 +830,18 @@ static ASTNode* parse_call(ParserState *state) {
             continue;
         }
 
+        if (match(state, TOKEN_QUESTION) && !starts_operand(peek_token(state, 1))) {
+            ASTNode *unwrap = create_node(AST_UNWRAP_EXPR, current_token(state), "?");
+            if (!unwrap) {
+                parser_free_ast(expr);
+                return NULL;
+            }
+            advance(state);
+            unwrap->left = expr;
+            expr = unwrap;
+            continue;
+        }
+
         if (match(state, TOKEN_DOT)) {
             Token *dot = current_token(state);
             advance(state);
@@ -456,7 +866,10 @@ static ASTNode* parse_unary(ParserState *state) {
     if (check_operator(state, "!") || check_operator(state, "-")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
+        if (!enter_nesting(state)) return NULL;
         ASTNode *right = parse_unary(state);
+        state->depth--;
         ASTNode *node = create_node(AST_UNARY_EXPR, op, op->value);
         if (!node) {
             parser_free_ast(right);
@@ -474,6 +887,7 @@ static ASTNode* parse_factor(ParserState *state) {
     while (check_operator(state, "*") || check_operator(state, "/") || check_operator(state, "%")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_unary(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -493,6 +907,7 @@ static ASTNode* parse_term(ParserState *state) {
     while (check_operator(state, "+") || check_operator(state, "-")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_factor(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -513,6 +928,7 @@ static ASTNode* parse_comparison(ParserState *state) {
            check_operator(state, "<=") || check_operator(state, ">=")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_term(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -532,6 +948,7 @@ static ASTNode* parse_equality(ParserState *state) {
     while (check_operator(state, "==") || check_operator(state, "!=")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_comparison(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -551,6 +968,7 @@ static ASTNode* parse_logical_and(ParserState *state) {
     while (check_operator(state, "&&")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_equality(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -570,6 +988,7 @@ static ASTNode* parse_logical_or(ParserState *state) {
     while (check_operator(state, "||")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *right = parse_logical_and(state);
         ASTNode *node = create_node(AST_BINARY_EXPR, op, op->value);
         if (!node) {
@@ -584,13 +1003,38 @@ static ASTNode* parse_logical_or(ParserState *state) {
     return expr;
 }
 
-static ASTNode* parse_ternary(ParserState *state) {
+/* a..b counts up from a and stops before b; a..=b includes b. A range does
+   not chain: the bounds are parsed one level down. */
+static ASTNode* parse_range(ParserState *state) {
     ASTNode *expr = parse_logical_or(state);
+    if (expr && (check_operator(state, "..") || check_operator(state, "..="))) {
+        Token *op = current_token(state);
+        advance(state);
+        skip_newlines(state);
+        ASTNode *end = parse_logical_or(state);
+        if (!end) parser_error(state, "Expected the end of the range after '%s'", op->value);
+        ASTNode *range = create_node(AST_RANGE_EXPR, op, op->value);
+        if (!range) {
+            parser_free_ast(expr);
+            parser_free_ast(end);
+            return NULL;
+        }
+        range->left = expr;
+        range->right = end;
+        return range;
+    }
+    return expr;
+}
+
+static ASTNode* parse_ternary(ParserState *state) {
+    ASTNode *expr = parse_range(state);
     if (match(state, TOKEN_QUESTION)) {
         Token *q = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *then_expr = parse_expression(state);
         expect(state, TOKEN_COLON, "Expected ':' in ternary expression");
+        skip_newlines(state);
         ASTNode *else_expr = parse_expression(state);
         ASTNode *node = create_node(AST_TERNARY_EXPR, q, NULL);
         if (!node) {
@@ -612,6 +1056,7 @@ static ASTNode* parse_assignment(ParserState *state) {
     if (check_operator(state, "=")) {
         Token *op = current_token(state);
         advance(state);
+        skip_newlines(state);
         ASTNode *value = parse_assignment(state);
         ASTNode *assign = create_node(AST_ASSIGN_STMT, op, "=");
         if (!assign) {
@@ -627,7 +1072,10 @@ static ASTNode* parse_assignment(ParserState *state) {
 }
 
 static ASTNode* parse_expression(ParserState *state) {
-    return parse_assignment(state);
+    if (!enter_nesting(state)) return NULL;
+    ASTNode *expr = parse_assignment(state);
+    state->depth--;
+    return expr;
 }
 
 /* ========================================
@@ -646,6 +1094,7 @@ static ASTNode* parse_block_braced(ParserState *state) {
     ASTNode *last_stmt = NULL;
 
     while (!match(state, TOKEN_RBRACE) && !match(state, TOKEN_EOF)) {
+        int stmt_start = state->current;
         ASTNode *stmt = parse_statement(state);
         if (stmt) {
             if (!first_stmt) {
@@ -656,13 +1105,14 @@ static ASTNode* parse_block_braced(ParserState *state) {
                 last_stmt = stmt;
             }
             add_child(block, stmt);
-        } else {
-            synchronize(state);
+        }
+        if (!stmt || state->panic_mode) {
+            synchronize(state, stmt_start);
         }
         skip_separators(state);
     }
 
-    expect(state, TOKEN_RBRACE, "Expected '}' to close block");
+    expect_closing(state, TOKEN_RBRACE, "Expected '}' to close block", start);
     block->body = first_stmt;
     return block;
 }
@@ -683,9 +1133,7 @@ static ASTNode* parse_block_until(ParserState *state, bool stop_on_else) {
         if (stop_on_else && (match(state, TOKEN_ELIF) || match(state, TOKEN_ELSE))) {
             break;
         }
-
-
-
+        int stmt_start = state->current;
         ASTNode *stmt = parse_statement(state);
         if (stmt) {
             if (!first_stmt) {
@@ -696,8 +1144,9 @@ static ASTNode* parse_block_until(ParserState *state, bool stop_on_else) {
                 last_stmt = stmt;
             }
             add_child(block, stmt);
-        } else {
-            synchronize(state);
+        }
+        if (!stmt || state->panic_mode) {
+            synchronize(state, stmt_start);
         }
         skip_separators(state);
     }
@@ -707,10 +1156,11 @@ static ASTNode* parse_block_until(ParserState *state, bool stop_on_else) {
 }
 
 static ASTNode* parse_block(ParserState *state, bool stop_on_else) {
-    if (match(state, TOKEN_LBRACE)) {
-        return parse_block_braced(state);
-    }
-    return parse_block_until(state, stop_on_else);
+    if (!enter_nesting(state)) return NULL;
+    ASTNode *block = match(state, TOKEN_LBRACE) ? parse_block_braced(state)
+                                                : parse_block_until(state, stop_on_else);
+    state->depth--;
+    return block;
 }
 
 static ASTNode* parse_embed_block(ParserState *state) {
@@ -763,10 +1213,47 @@ static ASTNode* parse_embed_block(ParserState *state) {
     return node;
 }
 
+/* var (q, r) = t: each name becomes a decl_type declaration without an
+   initializer, and the tuple to unpack goes in `right` */
+static ASTNode* parse_destructure(ParserState *state, Token *start, ASTNodeType decl_type) {
+    ASTNode *node = create_node(AST_DESTRUCTURE_DECL, start, NULL);
+    if (!node) return NULL;
+
+    Token *lparen = current_token(state);
+    state->nesting++;
+    advance(state);
+    while (true) {
+        Token *name = expect(state, TOKEN_IDENTIFIER, "Expected variable name in destructuring declaration");
+        if (!name) break;
+        ASTNode *target = create_node(decl_type, name, name->value);
+        if (!target || !add_child(node, target)) {
+            parser_free_ast(target);
+            parser_free_ast(node);
+            state->nesting--;
+            return NULL;
+        }
+        if (!match(state, TOKEN_COMMA)) break;
+        advance(state);
+        if (match(state, TOKEN_RPAREN)) break;
+    }
+    state->nesting--;
+    expect_closing(state, TOKEN_RPAREN, "Expected ')' after destructuring targets", lparen);
+
+    if (!check_operator(state, "=")) {
+        parser_error(state, "Expected '=' after destructuring targets");
+        return node;
+    }
+    advance(state);
+    node->right = parse_expression(state);
+    return node;
+}
+
 static ASTNode* parse_var_decl(ParserState *state, ASTNodeType decl_type) {
     Token *start = current_token(state);
     advance(state);
 
+    if (match(state, TOKEN_LPAREN)) return parse_destructure(state, start, decl_type);
+
     Token *name = expect(state, TOKEN_IDENTIFIER, "Expected variable name");
     if (!name) return NULL;
 
@@ -778,7 +1265,9 @@ static ASTNode* parse_var_decl(ParserState *state, ASTNodeType decl_type) {
         Token *type_tok = current_token(state);
         if (type_tok) {
             DataType dt = data_type_from_token(type_tok);
-            if (dt != TYPE_UNKNOWN) {
+            if (type_tok->type == TOKEN_LBRACKET) {
+                parse_array_type(state, decl);
+            } else if (dt != TYPE_UNKNOWN) {
                 decl->data_type = dt;
                 advance(state);
             } else if (match(state, TOKEN_IDENTIFIER)) {
@@ -791,6 +1280,7 @@ static ASTNode* parse_var_decl(ParserState *state, ASTNodeType decl_type) {
             } else {
                 parser_error(state, "Expected type name after ':'");
             }
+            decl->optional = parse_optional_marker(state);
         }
     }
 
@@ -813,8 +1303,9 @@ static ASTNode* parse_function(ParserState *state) {
 
     ASTNode *func = create_node(AST_FUNCTION_DECL, name, name->value);
     if (!func) return NULL;
+    if (start->doc) func->doc = strdup(start->doc);
 
-    expect(state, TOKEN_LPAREN, "Expected '(' after function name");
+    Token *lparen = expect(state, TOKEN_LPAREN, "Expected '(' after function name");
 
     if (!match(state, TOKEN_RPAREN)) {
         while (true) {
@@ -830,7 +1321,9 @@ static ASTNode* parse_function(ParserState *state) {
                 advance(state);
                 Token *type_tok = current_token(state);
                 DataType dt = data_type_from_token(type_tok);
-                if (dt != TYPE_UNKNOWN) {
+                if (match(state, TOKEN_LBRACKET)) {
+                    parse_array_type(state, param);
+                } else if (dt != TYPE_UNKNOWN) {
                     param->data_type = dt;
                     advance(state);
                 } else if (match(state, TOKEN_IDENTIFIER)) {
@@ -844,6 +1337,13 @@ static ASTNode* parse_function(ParserState *state) {
                 } else {
                     parser_error(state, "Expected parameter type after ':'");
                 }
+                param->optional = parse_optional_marker(state);
+            }
+
+            /* Default value: greet(name, greeting = "Hello") */
+            if (check_operator(state, "=")) {
+                advance(state);
+                param->right = parse_expression(state);
             }
 
             if (!add_child(func, param)) {
@@ -860,13 +1360,17 @@ static ASTNode* parse_function(ParserState *state) {
         }
     }
 
-    expect(state, TOKEN_RPAREN, "Expected ')' after parameters");
+    expect_closing(state, TOKEN_RPAREN, "Expected ')' after parameters", lparen);
 
     if (match(state, TOKEN_COLON) || match(state, TOKEN_ARROW)) {
         advance(state);
         Token *type_tok = current_token(state);
         DataType dt = data_type_from_token(type_tok);
-        if (dt != TYPE_UNKNOWN) {
+        if (match(state, TOKEN_LBRACKET)) {
+            parse_array_type(state, func);
+        } else if (match(state, TOKEN_LPAREN)) {
+            parse_tuple_type(state, func);
+        } else if (dt != TYPE_UNKNOWN) {
             func->data_type = dt;
             advance(state);
         } else if (match(state, TOKEN_IDENTIFIER)) {
@@ -879,6 +1383,7 @@ static ASTNode* parse_function(ParserState *state) {
         } else {
             parser_error(state, "Expected return type after ':'");
         }
+        func->optional = parse_optional_marker(state);
     }
 
     skip_separators(state);
@@ -893,6 +1398,33 @@ static ASTNode* parse_function(ParserState *state) {
     return func;
 }
 
+/* #[inline] #[hot] ... function f(): the names are kept, in order, on the
+   function node; the semantic pass reports any it does not know */
+static ASTNode* parse_attributed_function(ParserState *state) {
+    Token *first = current_token(state);
+    StringBuffer names;
+    if (!sb_init(&names)) return NULL;
+    while (match(state, TOKEN_ATTRIBUTE)) {
+        if (names.len) sb_append_char(&names, ',');
+        sb_append(&names, current_token(state)->value);
+        advance(state);
+        skip_newlines(state);
+    }
+    if (!match(state, TOKEN_FUNCTION)) {
+        parser_error(state, "Expected a function after #[%s]", first->value);
+        free(names.data);
+        return NULL;
+    }
+    ASTNode *func = parse_function(state);
+    if (!func) {
+        free(names.data);
+        return NULL;
+    }
+    func->attributes = names.data;
+    if (!func->doc && first->doc) func->doc = strdup(first->doc);
+    return func;
+}
+
 static ASTNode* parse_if(ParserState *state) {
     Token *start = current_token(state);
     advance(state);
@@ -957,6 +1489,8 @@ static ASTNode* parse_for(ParserState *state) {
             iter_expr->child_count = 0;
         }
         parser_free_ast(iter_expr);
+    } else if (iter_expr && iter_expr->type == AST_RANGE_EXPR) {
+        if (!add_child(for_node, iter_expr)) parser_free_ast(iter_expr);
     } else if (iter_expr) {
         for_node->condition = iter_expr;
     }
@@ -1004,6 +1538,55 @@ static ASTNode* parse_return(ParserState *state) {
     return ret;
 }
 
+/* #static_assert(condition, "message") — checked by the semantic pass,
+   never emitted as code. */
+static ASTNode* parse_static_assert(ParserState *state) {
+    Token *start = current_token(state);
+    advance(state);
+
+    if (!expect(state, TOKEN_LPAREN, "Expected '(' after '#static_assert'")) return NULL;
+
+    ASTNode *node = create_node(AST_STATIC_ASSERT, start, NULL);
+    if (!node) return NULL;
+    node->condition = parse_expression(state);
+
+    if (!expect(state, TOKEN_COMMA, "Expected ',' after static assertion condition")) {
+        parser_free_ast(node);
+        return NULL;
+    }
+
+    Token *msg = current_token(state);
+    if (!expect(state, TOKEN_STRING_LITERAL, "Expected message string in '#static_assert'")) {
+        parser_free_ast(node);
+        return NULL;
+    }
+    node->value = strdup(msg->value ? msg->value : "");
+
+    if (!expect(state, TOKEN_RPAREN, "Expected ')' after static assertion message")) {
+        parser_free_ast(node);
+        return NULL;
+    }
+    return node;
+}
+
+/* #exit(code) — ends the program with the given exit status */
+static ASTNode* parse_exit(ParserState *state) {
+    Token *start = current_token(state);
+    advance(state);
+
+    if (!expect(state, TOKEN_LPAREN, "Expected '(' after '#exit'")) return NULL;
+
+    ASTNode *node = create_node(AST_EXIT_STMT, start, NULL);
+    if (!node) return NULL;
+    node->right = parse_expression(state);
+
+    if (!expect(state, TOKEN_RPAREN, "Expected ')' after exit code")) {
+        parser_free_ast(node);
+        return NULL;
+    }
+    return node;
+}
+
 static ASTNode* parse_statement(ParserState *state) {
     skip_separators(state);
 
@@ -1024,6 +1607,10 @@ static ASTNode* parse_statement(ParserState *state) {
         return parse_var_decl(state, AST_VAR_DECL);
     }
 
+    if (match(state, TOKEN_ATTRIBUTE)) {
+        return parse_attributed_function(state);
+    }
+
     if (match(state, TOKEN_FUNCTION)) {
         return parse_function(state);
     }
@@ -1044,6 +1631,14 @@ static ASTNode* parse_statement(ParserState *state) {
         return parse_return(state);
     }
 
+    if (match(state, TOKEN_STATIC_ASSERT)) {
+        return parse_static_assert(state);
+    }
+
+    if (match(state, TOKEN_EXIT)) {
+        return parse_exit(state);
+    }
+
     if (match(state, TOKEN_BREAK)) {
         ASTNode *node = create_node(AST_BREAK_STMT, tok, NULL);
         advance(state);
@@ -1071,7 +1666,9 @@ ASTNode* parser_parse(Token *tokens, int token_count) {
         return NULL;
     }
 
-    ParserState state = {tokens, token_count, 0, 0, 0};
+    ParserState state = {tokens, token_count, 0, 0, 0, 0, g_parser_error_limit, 0, 0, 0, NULL};
+    state.stack_base = (const char *)&state;
+    g_parser_had_errors = false;
     ASTNode *root = create_node(AST_PROGRAM, current_token(&state), "program");
     if (!root) return NULL;
 
@@ -1082,6 +1679,7 @@ ASTNode* parser_parse(Token *tokens, int token_count) {
         skip_separators(&state);
         if (match(&state, TOKEN_EOF)) break;
 
+        int stmt_start = state.current;
         ASTNode *stmt = parse_statement(&state);
         if (stmt) {
             if (!first_stmt) {
@@ -1092,23 +1690,28 @@ ASTNode* parser_parse(Token *tokens, int token_count) {
                 last_stmt = stmt;
             }
             add_child(root, stmt);
-        } else {
-            synchronize(&state);
+        }
+        if (!stmt || state.panic_mode) {
+            synchronize(&state, stmt_start);
         }
     }
 
     root->body = first_stmt;
+    ast_number_nodes(root);
 
+    g_parser_had_errors = state.had_error;
     if (state.had_error) {
         fprintf(stderr, "Parser completed with errors\n");
     }
+    if (g_parser_stats) print_compile_stats(tokens, token_count, root);
 
     return root;
 }
 
 ASTNode* parser_parse_expression(CompilerContext *ctx) {
     if (!ctx || !ctx->tokens) return NULL;
-    ParserState state = {ctx->tokens, ctx->token_count, ctx->current_token, 0, 0};
+    ParserState state = {ctx->tokens, ctx->token_count, ctx->current_token, 0, 0, 0, g_parser_error_limit, 0, 0, 0, NULL};
+    state.stack_base = (const char *)&state;
     ASTNode *expr = parse_expression(&state);
     ctx->current_token = state.current;
     return expr;
@@ -1116,12 +1719,39 @@ ASTNode* parser_parse_expression(CompilerContext *ctx) {
 
 ASTNode* parser_parse_statement(CompilerContext *ctx) {
     if (!ctx || !ctx->tokens) return NULL;
-    ParserState state = {ctx->tokens, ctx->token_count, ctx->current_token, 0, 0};
+    ParserState state = {ctx->tokens, ctx->token_count, ctx->current_token, 0, 0, 0, g_parser_error_limit, 0, 0, 0, NULL};
+    state.stack_base = (const char *)&state;
     ASTNode *stmt = parse_statement(&state);
     ctx->current_token = state.current;
     return stmt;
 }
 
+/* Deep copy of a node and everything it owns, including its next-chain */
+ASTNode* parser_clone_ast(const ASTNode *node) {
+    if (!node) return NULL;
+    ASTNode *copy = calloc(1, sizeof(ASTNode));
+    if (!copy) return NULL;
+    *copy = *node;
+    copy->value = node->value ? strdup(node->value) : NULL;
+    copy->metadata = node->metadata ? strdup(node->metadata) : NULL;
+    copy->doc = node->doc ? strdup(node->doc) : NULL;
+    copy->attributes = node->attributes ? strdup(node->attributes) : NULL;
+    copy->left = parser_clone_ast(node->left);
+    copy->right = parser_clone_ast(node->right);
+    copy->condition = parser_clone_ast(node->condition);
+    copy->body = parser_clone_ast(node->body);
+    copy->next = parser_clone_ast(node->next);
+    copy->children = NULL;
+    if (node->child_count > 0 && node->children) {
+        copy->children = calloc(node->child_count, sizeof(ASTNode*));
+        if (!copy->children) copy->child_count = 0;
+        for (int i = 0; i < copy->child_count; i++) {
+            copy->children[i] = parser_clone_ast(node->children[i]);
+        }
+    }
+    return copy;
+}
+
 /* Free AST — tracks visited nodes to prevent double-free from shared references.
    The next-chain is traversed iteratively to avoid stack overflow on platforms
    with small default stacks (macOS: 512KB vs Linux: 8MB). */
@@ -1155,6 +1785,8 @@ static void free_ast_impl(ASTNode *node, FreeSet *fs) {
 
         free(node->value);
         free(node->metadata);
+        free(node->doc);
+        free(node->attributes);
 
         free_ast_impl(node->left, fs);
         free_ast_impl(node->right, fs);
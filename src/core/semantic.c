@@ -7,24 +7,141 @@
 #include "sub_compiler.h"
 #include "type_system.h"
 #include "windows_compat.h"
+#include <stdarg.h>
+#include <limits.h>
+#include <errno.h>
+#include <math.h>
+#include <float.h>
 
 // Error tracking for semantic_analyze return value
 static int g_semantic_error_count = 0;
 
-// Semantic error reporting wrappers that track error count
+// Set while a prelude is checked on its own
+static bool g_checking_prelude = false;
+// How many of the program's first statements came from the prelude. The
+// prelude's own check reports their warnings, so the program's does not.
+static int g_prelude_item_count = 0;
+static bool g_in_prelude_item = false;
+
+// The file a diagnostic at the current node is in, when it is not the
+// program's: a line in the prelude is named by the prelude's path
+static const char* diagnostic_file(void) {
+    return g_checking_prelude || g_in_prelude_item ? prelude_file() : NULL;
+}
+
+// Semantic error reporting wrappers that track error count. An error in
+// the prelude is left out of the recorded diagnostics, which are all about
+// the program's source.
 static void semantic_report_error(const char *msg, int line) {
+    const char *file = diagnostic_file();
     g_semantic_error_count++;
-    fprintf(stderr, "[line %d] Semantic error: %s\n", line, msg);
+    fprintf(stderr, "[%s%sline %d] Semantic error: %s\n", file ? file : "", file ? ", " : "", line, msg);
+    if (!file) diagnostic_record(false, NULL, line, 0, "%s", msg);
 }
 
 static void semantic_report_error_with_col(const char *msg, int line, int col) {
+    const char *file = diagnostic_file();
+    g_semantic_error_count++;
+    fprintf(stderr, "[%s%sline %d, col %d] Semantic error: %s\n", file ? file : "", file ? ", " : "",
+            line, col, msg);
+    if (!file) diagnostic_record(false, NULL, line, col, "%s", msg);
+}
+
+// An error about the program as a whole, with no line to point at
+static void semantic_report_program_error(const char *msg) {
+    g_semantic_error_count++;
+    fprintf(stderr, "Semantic error: %s\n", msg);
+    diagnostic_record(false, NULL, 0, 0, "%s", msg);
+}
+
+// Report an error on the source range of `node`, and show it underlined
+static void semantic_report_error_at(const char *msg, const ASTNode *node) {
+    SourceSpan span = ast_span(node);
+    const char *file = diagnostic_file();
     g_semantic_error_count++;
-    fprintf(stderr, "[line %d, col %d] Semantic error: %s\n", line, col, msg);
+    if (span.end_line == span.line && span.end_column > span.column + 1) {
+        fprintf(stderr, "[%s%sline %d, col %d-%d] Semantic error: %s\n", file ? file : "", file ? ", " : "",
+                span.line, span.column, span.end_column - 1, msg);
+    } else {
+        fprintf(stderr, "[%s%sline %d, col %d] Semantic error: %s\n", file ? file : "", file ? ", " : "",
+                span.line, span.column, msg);
+    }
+    if (!file) diagnostic_record(false, NULL, span.line, span.column, "%s", msg);
+    // The prelude's own text is the diagnostic source only while it is
+    // checked by itself
+    if (!g_in_prelude_item) diagnostic_print_span(span);
 }
 
 #define compile_error(msg, line) semantic_report_error(msg, line)
 #define compile_error_with_col(msg, line, col) semantic_report_error_with_col(msg, line, col)
 
+// Lint table: command-line name and on/off state for each SemanticLint
+static struct {
+    const char *name;
+    bool enabled;
+} g_lints[LINT_COUNT] = {
+    [LINT_IMPLICIT_WIDENING] = {"implicit-widening", true},
+    [LINT_UNUSED_VARIABLE]   = {"unused-variable", false},
+    [LINT_INTEGER_DIVISION]  = {"integer-division", false},
+    [LINT_UNUSED_FUNCTION]   = {"unused-function", false},
+    [LINT_POINTLESS_STATEMENT] = {"pointless-statement", true},
+    [LINT_UNKNOWN_ATTRIBUTE] = {"unknown-attribute", true},
+    [LINT_FLOAT_PRECISION]   = {"float-precision", false},
+    [LINT_FLOAT_EQUALITY]    = {"float-equality", true},
+    [LINT_INFINITE_LOOP]     = {"infinite-loop", true},
+    [LINT_UNUSED_PARAMETER]  = {"unused-parameter", false},
+    [LINT_SHADOWING]         = {"shadowing", false},
+    [LINT_UNREACHABLE_CODE]  = {"unreachable-code", false},
+    [LINT_CONSTANT_COMPARISON] = {"constant-comparison", false},
+    [LINT_IGNORED_ATTRIBUTE] = {"ignored-attribute", true},
+};
+
+static int g_semantic_warning_count = 0;
+static bool g_warnings_as_errors = false;
+
+void semantic_set_lint(SemanticLint lint, bool enabled) {
+    if (lint >= 0 && lint < LINT_COUNT) g_lints[lint].enabled = enabled;
+}
+
+bool semantic_set_lint_by_name(const char *name, bool enabled) {
+    for (int i = 0; i < LINT_COUNT; i++) {
+        if (strcmp(g_lints[i].name, name) == 0) {
+            g_lints[i].enabled = enabled;
+            return true;
+        }
+    }
+    return false;
+}
+
+void semantic_set_strict(bool strict) {
+    g_warnings_as_errors = strict;
+    if (strict) {
+        for (int i = 0; i < LINT_COUNT; i++) g_lints[i].enabled = true;
+    }
+}
+
+int semantic_warning_count(void) {
+    return g_semantic_warning_count;
+}
+
+static void semantic_report_warning(SemanticLint lint, const char *msg, int line) {
+    if (!g_lints[lint].enabled || g_in_prelude_item) return;
+    // A line in the prelude is named by its file, and left out of the
+    // recorded diagnostics, which are all about the program's source
+    const char *file = diagnostic_file();
+    if (g_warnings_as_errors) {
+        g_semantic_error_count++;
+        fprintf(stderr, "[%s%sline %d] Semantic error: %s [-W%s]\n", file ? file : "", file ? ", " : "",
+                line, msg, g_lints[lint].name);
+        if (!file) diagnostic_record(false, g_lints[lint].name, line, 0, "%s", msg);
+        return;
+    }
+    g_semantic_warning_count++;
+    fprintf(stderr, "[%s%sline %d] Semantic warning: %s [-W%s]\n", file ? file : "", file ? ", " : "",
+            line, msg, g_lints[lint].name);
+    if (!file) diagnostic_record(true, g_lints[lint].name, line, 0, "%s", msg);
+}
+
 // Symbol table entry (local) - enhanced with DataType
 typedef struct LocalSymbolEntry {
     char *name;
@@ -33,10 +150,22 @@ typedef struct LocalSymbolEntry {
     DataType return_type;
     DataType *param_types;
     int param_count;
+    int required_count;  // params before the first one with a default value
+    ASTNode *decl;       // function declaration, for default parameter values; a const's declaration
+    ASTNode *tuple;      // tuple-typed variables and functions: see ASTNode.tuple
+    int array_length;    // fixed-size arrays ([T; N] or a const array literal), else -1
+    DataType element_type;  // arrays: the type of every element when known, else unknown
     int scope_level;
     bool is_initialized;
     bool is_constant;
     bool is_function;
+    bool is_variable;    // declared with var/const/let or by first assignment
+    bool is_parameter;   // a function's parameter (for -Wunused-parameter)
+    bool is_used;        // read at least once (for -Wunused-variable)
+    bool is_optional;    // declared T? (or initialized from one), may hold null
+    bool is_narrowed;    // known to be non-null inside an 'if x != null'
+    int decl_line;       // where the name was declared, for warnings and --dump-symbols
+    int decl_column;
     struct LocalSymbolEntry *next;
 } LocalSymbolEntry;
 
@@ -68,7 +197,8 @@ static LocalSymbolTable* create_symbol_table() {
     return table;
 }
 
-static LocalSymbolEntry* add_symbol(LocalSymbolTable *table, const char *name, const char *type_str, DataType data_type) {
+static LocalSymbolEntry* add_symbol(LocalSymbolTable *table, const char *name, const char *type_str,
+                                    DataType data_type, const ASTNode *at) {
     if (!table || !name) return NULL;
     LocalSymbolEntry *entry = calloc(1, sizeof(LocalSymbolEntry));
     if (!entry) {
@@ -92,10 +222,16 @@ static LocalSymbolEntry* add_symbol(LocalSymbolTable *table, const char *name, c
     entry->return_type = TYPE_UNKNOWN;
     entry->param_types = NULL;
     entry->param_count = 0;
+    entry->required_count = 0;
+    entry->decl = NULL;
+    entry->array_length = -1;
+    entry->element_type = TYPE_UNKNOWN;
     entry->scope_level = table->current_scope;
     entry->is_initialized = false;
     entry->is_constant = false;
     entry->is_function = false;
+    entry->decl_line = at ? at->line : 0;
+    entry->decl_column = at ? at->column : 0;
     entry->next = table->head;
     table->head = entry;
     return entry;
@@ -116,6 +252,95 @@ static LocalSymbolEntry* lookup_symbol(LocalSymbolTable *table, const char *name
     return lookup_symbol_entry(table, name);
 }
 
+// Levenshtein distance between a and b, or a value above limit once it is
+// certain to exceed it
+static int edit_distance(const char *a, const char *b, int limit) {
+    int n = (int)strlen(a), m = (int)strlen(b);
+    if (abs(n - m) > limit || m >= 64) return limit + 1;
+    int row[65];
+    for (int j = 0; j <= m; j++) row[j] = j;
+    for (int i = 1; i <= n; i++) {
+        int diag = row[0], best = row[0] = i;
+        for (int j = 1; j <= m; j++) {
+            int up = row[j];
+            int cost = diag + (a[i - 1] != b[j - 1]);
+            int shorter = (up < row[j - 1] ? up : row[j - 1]) + 1;
+            row[j] = cost < shorter ? cost : shorter;
+            diag = up;
+            if (row[j] < best) best = row[j];
+        }
+        if (best > limit) return limit + 1;
+    }
+    return row[m];
+}
+
+// The in-scope name closest to a misspelled one: a function for a call,
+// anything else for a value. Only a close match counts, about one edit per
+// three characters, and never one that rewrites the whole name (a for b).
+static const char* suggest_symbol(LocalSymbolTable *table, const char *name, bool function) {
+    int limit = (int)strlen(name) / 3;
+    if (limit < 1) limit = 1;
+    const char *best = NULL;
+    int best_distance = limit + 1;
+    for (LocalSymbolEntry *e = table->head; e; e = e->next) {
+        if (e->is_function != function || strcmp(e->name, name) == 0) continue;
+        int longest = (int)(strlen(e->name) > strlen(name) ? strlen(e->name) : strlen(name));
+        int d = edit_distance(name, e->name, limit);
+        if (d < best_distance && d < longest) {
+            best = e->name;
+            best_distance = d;
+        }
+    }
+    return best;
+}
+
+// Two #[target(...)] functions that share no target are never compiled
+// together, which only happens when no target is active (subc check, --emit)
+static bool targets_overlap(const ASTNode *a, const ASTNode *b) {
+    const char *list = a && a->attributes ? strstr(a->attributes, "target(") : NULL;
+    if (!list || !b || !b->attributes || !strstr(b->attributes, "target(")) return true;
+    for (const char *p = list + 7; *p && *p != ')'; ) {
+        size_t len = strcspn(p, " )");
+        char name[32];
+        snprintf(name, sizeof(name), "%.*s", (int)len, p);
+        if (ast_attribute_lists(b, "target", name)) return true;
+        p += len;
+        if (*p == ' ') p++;
+    }
+    return false;
+}
+
+// Functions, constants and variables share one namespace, and each name can
+// be declared once per scope. A clash is reported at `at` with both
+// declarations shown; returns true if `name` was already taken.
+static bool report_redeclaration(LocalSymbolTable *table, const char *name, const ASTNode *at) {
+    LocalSymbolEntry *prev = lookup_symbol_entry(table, name);
+    if (!prev || prev->scope_level != table->current_scope) return false;
+    if (prev->is_function && at->type == AST_FUNCTION_DECL && !targets_overlap(prev->decl, at)) return false;
+    const char *kind = prev->is_function ? "function" : prev->is_constant ? "constant" : "variable";
+    char error_msg[512];
+    snprintf(error_msg, sizeof(error_msg),
+             "'%s' is already declared in this scope, as a %s on line %d",
+             name, kind, prev->decl_line);
+    compile_error_with_col(error_msg, at->line, at->column);
+    diagnostic_print_note(at->line, at->column, "declared again here");
+    diagnostic_print_note(prev->decl_line, prev->decl_column, "first declared here");
+    return true;
+}
+
+// -Wshadowing: a declaration in an inner scope that hides a name from an
+// outer one. Names starting with '_' are exempt, as for the unused lints.
+static void check_shadowing(LocalSymbolTable *table, const char *name, const ASTNode *at) {
+    if (!g_lints[LINT_SHADOWING].enabled || !name || name[0] == '_') return;
+    LocalSymbolEntry *prev = lookup_symbol_entry(table, name);
+    if (!prev || prev->scope_level == table->current_scope) return;
+    const char *kind = prev->is_function ? "function" : prev->is_parameter ? "parameter" :
+                       prev->is_constant ? "constant" : "variable";
+    char msg[512];
+    snprintf(msg, sizeof(msg), "'%s' shadows the %s declared on line %d", name, kind, prev->decl_line);
+    semantic_report_warning(LINT_SHADOWING, msg, at->line);
+}
+
 static void free_symbol_table(LocalSymbolTable *table) {
     LocalSymbolEntry *current = table->head;
     while (current) {
@@ -129,14 +354,146 @@ static void free_symbol_table(LocalSymbolTable *table) {
     free(table);
 }
 
-static void enter_scope(LocalSymbolTable *table) {
+// --dump-symbols: a scope's bindings are only known once it closes, so each
+// scope is written to its own buffer then, and every buffer is printed at
+// the end of the analysis in the order the scopes were opened
+typedef struct {
+    int level;
+    char *text;
+    size_t len;
+} ScopeDump;
+
+static bool g_dump_symbols = false;
+static ScopeDump *g_scope_dumps = NULL;
+static int g_scope_dump_count = 0;
+static int *g_open_dumps = NULL;    // g_scope_dumps index of the open scope at each level
+static int g_open_dump_cap = 0;
+
+void semantic_set_dump_symbols(bool enabled) {
+    g_dump_symbols = enabled;
+}
+
+// Set for subi --eval, which prints the value of the last statement
+static bool g_echo_last = false;
+
+void semantic_set_echo_last(bool enabled) {
+    g_echo_last = enabled;
+}
+
+// subc repl checks each entry after the ones before it; those were checked
+// already, so their warnings are not reported again
+static int g_checked_item_count = 0;
+
+void semantic_set_checked_items(int count) {
+    g_checked_item_count = count;
+}
+
+static void dump_appendf(ScopeDump *dump, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    int n = vsnprintf(NULL, 0, fmt, ap);
+    va_end(ap);
+    if (n < 0) return;
+    char *grown = realloc(dump->text, dump->len + (size_t)n + 1);
+    if (!grown) return;
+    dump->text = grown;
+    va_start(ap, fmt);
+    vsnprintf(dump->text + dump->len, (size_t)n + 1, fmt, ap);
+    va_end(ap);
+    dump->len += (size_t)n;
+}
+
+// `node` is what opened the scope: NULL for the global scope
+static void dump_open_scope(int level, const ASTNode *node) {
+    if (!g_dump_symbols || level < 0) return;
+    if (level >= g_open_dump_cap) {
+        int cap = level + 8;
+        int *grown = realloc(g_open_dumps, (size_t)cap * sizeof(int));
+        if (!grown) return;
+        g_open_dumps = grown;
+        g_open_dump_cap = cap;
+    }
+    ScopeDump *grown = realloc(g_scope_dumps, (size_t)(g_scope_dump_count + 1) * sizeof(ScopeDump));
+    if (!grown) return;
+    g_scope_dumps = grown;
+    ScopeDump *dump = &g_scope_dumps[g_scope_dump_count];
+    dump->level = level;
+    dump->text = NULL;
+    dump->len = 0;
+    g_open_dumps[level] = g_scope_dump_count++;
+
+    if (!node) {
+        dump_appendf(dump, "scope %d: global\n", level);
+    } else if (node->type == AST_FUNCTION_DECL) {
+        dump_appendf(dump, "scope %d: function '%s' (line %d)\n", level,
+                     node->value ? node->value : "?", node->line);
+    } else if (node->type == AST_FOR_STMT) {
+        dump_appendf(dump, "scope %d: for loop (line %d)\n", level, node->line);
+    } else {
+        dump_appendf(dump, "scope %d: block (line %d)\n", level, node->line);
+    }
+}
+
+static void dump_close_scope(LocalSymbolTable *table, int level);
+
+static void dump_print_scopes(void) {
+    if (!g_dump_symbols) return;
+    fprintf(stderr, "Symbols:\n");
+    for (int i = 0; i < g_scope_dump_count; i++) {
+        ScopeDump *dump = &g_scope_dumps[i];
+        for (const char *line = dump->text; line && *line; ) {
+            size_t len = strcspn(line, "\n");
+            fprintf(stderr, "%*s%.*s\n", 2 * dump->level, "", (int)len, line);
+            line += len;
+            if (*line) line++;
+        }
+        free(dump->text);
+    }
+    free(g_scope_dumps);
+    free(g_open_dumps);
+    g_scope_dumps = NULL;
+    g_open_dumps = NULL;
+    g_scope_dump_count = 0;
+    g_open_dump_cap = 0;
+}
+
+static void enter_scope(LocalSymbolTable *table, const ASTNode *node) {
     if (!table) return;
     table->current_scope++;
+    dump_open_scope(table->current_scope, node);
+}
+
+// Warn about the never-read variables and parameters of one scope, in
+// declaration order. Names with a leading underscore are deliberately unused.
+static void report_unused_in_scope(LocalSymbolTable *table, int scope) {
+    if (!g_lints[LINT_UNUSED_VARIABLE].enabled && !g_lints[LINT_UNUSED_PARAMETER].enabled) return;
+    int count = 0;
+    for (LocalSymbolEntry *e = table->head; e; e = e->next) {
+        if (e->scope_level == scope) count++;
+    }
+    if (count == 0) return;
+    LocalSymbolEntry **entries = malloc(count * sizeof(LocalSymbolEntry *));
+    if (!entries) return;
+    int n = 0;
+    for (LocalSymbolEntry *e = table->head; e; e = e->next) {
+        if (e->scope_level == scope) entries[n++] = e;
+    }
+    // The table is newest-first
+    for (int i = n - 1; i >= 0; i--) {
+        LocalSymbolEntry *e = entries[i];
+        if ((!e->is_variable && !e->is_parameter) || e->is_used || e->name[0] == '_') continue;
+        char msg[300];
+        snprintf(msg, sizeof(msg), "%s '%s' is never used", e->is_parameter ? "Parameter" : "Variable", e->name);
+        semantic_report_warning(e->is_parameter ? LINT_UNUSED_PARAMETER : LINT_UNUSED_VARIABLE, msg, e->decl_line);
+    }
+    free(entries);
 }
 
 static void exit_scope(LocalSymbolTable *table) {
     if (!table) return;
     int scope = table->current_scope;
+    report_unused_in_scope(table, scope);
+    dump_close_scope(table, scope);
     LocalSymbolEntry **current = &table->head;
     while (*current) {
         if ((*current)->scope_level == scope) {
@@ -157,7 +514,7 @@ static void exit_scope(LocalSymbolTable *table) {
 // Type Conversion Utilities
 // ========================================
 
-static const char* data_type_to_string(DataType type) {
+const char* data_type_to_string(DataType type) {
     switch (type) {
         case TYPE_INT: return "int";
         case TYPE_FLOAT: return "float";
@@ -169,10 +526,68 @@ static const char* data_type_to_string(DataType type) {
         case TYPE_NULL: return "null";
         case TYPE_VOID: return "void";
         case TYPE_AUTO: return "auto";
+        case TYPE_TUPLE: return "tuple";
         default: return "unknown";
     }
 }
 
+// A tuple type written out from its representative tuple expression,
+// e.g. "(int, string)"
+static void tuple_type_name(const ASTNode *tuple, char *buf, size_t size) {
+    size_t len = (size_t)snprintf(buf, size, "(");
+    for (int i = 0; i < tuple->child_count && len < size; i++) {
+        const ASTNode *elem = tuple->children[i];
+        char inner[256];
+        if (elem->data_type == TYPE_TUPLE && elem->tuple) {
+            tuple_type_name(elem->tuple, inner, sizeof(inner));
+        } else {
+            snprintf(inner, sizeof(inner), "%s", data_type_to_string(elem->data_type));
+        }
+        len += (size_t)snprintf(buf + len, size - len, "%s%s", i > 0 ? ", " : "", inner);
+    }
+    if (len < size) snprintf(buf + len, size - len, ")");
+}
+
+// One line per binding of the scope at `level`, in declaration order:
+//   var total: int  (line 3, col 9)
+static void dump_close_scope(LocalSymbolTable *table, int level) {
+    if (!g_dump_symbols || level < 0 || level >= g_open_dump_cap) return;
+    ScopeDump *dump = &g_scope_dumps[g_open_dumps[level]];
+    int count = 0;
+    for (LocalSymbolEntry *e = table->head; e; e = e->next) {
+        if (e->scope_level == level) count++;
+    }
+    LocalSymbolEntry **entries = count > 0 ? malloc(count * sizeof(LocalSymbolEntry *)) : NULL;
+    if (!entries) return;
+    int n = 0;
+    for (LocalSymbolEntry *e = table->head; e; e = e->next) {
+        if (e->scope_level == level) entries[n++] = e;
+    }
+    for (int i = n - 1; i >= 0; i--) {
+        LocalSymbolEntry *e = entries[i];
+        char type[512];
+        if (e->is_function) {
+            size_t len = (size_t)snprintf(type, sizeof(type), "(");
+            for (int p = 0; p < e->param_count && len < sizeof(type); p++) {
+                DataType param = e->param_types ? e->param_types[p] : TYPE_AUTO;
+                len += (size_t)snprintf(type + len, sizeof(type) - len, "%s%s", p > 0 ? ", " : "",
+                                        data_type_to_string(param));
+            }
+            if (len < sizeof(type)) {
+                snprintf(type + len, sizeof(type) - len, ") -> %s", data_type_to_string(e->return_type));
+            }
+        } else if (e->data_type == TYPE_TUPLE && e->tuple) {
+            tuple_type_name(e->tuple, type, sizeof(type));
+        } else {
+            snprintf(type, sizeof(type), "%s%s", data_type_to_string(e->data_type), e->is_optional ? "?" : "");
+        }
+        const char *kind = e->is_function ? "func" : e->is_constant ? "const" : "var";
+        dump_appendf(dump, "  %s %s: %s  (line %d, col %d)\n", kind, e->name, type,
+                     e->decl_line, e->decl_column);
+    }
+    free(entries);
+}
+
 static DataType data_type_from_string(const char *name) {
     if (!name) return TYPE_UNKNOWN;
     if (strcmp(name, "int") == 0) return TYPE_INT;
@@ -196,10 +611,1076 @@ static bool data_types_are_compatible(DataType expected, DataType actual) {
     return false;
 }
 
+// The type of c ? a : b and select(c, a, b): an int and a float widen to
+// float, as C's ?: does, so neither arm is truncated
+static DataType branch_result_type(DataType a, DataType b) {
+    if ((a == TYPE_INT && b == TYPE_FLOAT) || (a == TYPE_FLOAT && b == TYPE_INT)) return TYPE_FLOAT;
+    return a;
+}
+
+// Two tuple types match when they have the same arity and each pair of
+// element types is compatible
+static bool tuple_types_match(const ASTNode *a, const ASTNode *b) {
+    if (!a || !b) return true;
+    if (a->child_count != b->child_count) return false;
+    for (int i = 0; i < a->child_count; i++) {
+        const ASTNode *x = a->children[i], *y = b->children[i];
+        if (x->data_type == TYPE_TUPLE && y->data_type == TYPE_TUPLE) {
+            if (!tuple_types_match(x->tuple, y->tuple)) return false;
+        } else if (!data_types_are_compatible(x->data_type, y->data_type) &&
+                   x->data_type != TYPE_UNKNOWN && y->data_type != TYPE_UNKNOWN) {
+            return false;
+        }
+    }
+    return true;
+}
+
+static void check_float_division(ASTNode *value, const char *context, int line);
+
+// Check the implicit conversion of `value` (of type `actual`) into a slot of
+// type `expected`: narrowing float -> int is an error, widening a computed
+// int -> float is a lint. Literals widen exactly and are not reported.
+static void check_numeric_conversion(DataType expected, DataType actual, ASTNode *value,
+                                     const char *context, int line) {
+    char msg[512];
+    if (expected == TYPE_INT && actual == TYPE_FLOAT) {
+        snprintf(msg, sizeof(msg),
+                 "Type error: Implicit narrowing conversion from float to int in %s; use int(...) to convert explicitly",
+                 context);
+        compile_error(msg, line);
+    } else if (expected == TYPE_FLOAT && actual == TYPE_INT && value && value->type != AST_LITERAL) {
+        snprintf(msg, sizeof(msg),
+                 "Implicit conversion from int to float in %s may lose precision; use float(...) to make it explicit",
+                 context);
+        semantic_report_warning(LINT_IMPLICIT_WIDENING, msg, line);
+        check_float_division(value, context, line);
+    }
+}
+
+// ========================================
+// Constant Evaluation
+// ========================================
+
+// Compile-time value of a constant expression
+typedef struct {
+    DataType type;   // TYPE_INT, TYPE_FLOAT or TYPE_BOOL
+    long long iv;
+    double fv;
+    bool bv;
+} ConstValue;
+
+static double const_as_float(const ConstValue *v) {
+    return v->type == TYPE_FLOAT ? v->fv : (double)v->iv;
+}
+
+static bool const_eval(ASTNode *node, ConstValue *out);
+
+static bool clear_node_id(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    node->id = 0;
+    return true;
+}
+
+// A copy of a subtree for splicing into the tree. Its nodes get id 0, like
+// any node made after parsing, so the parsed ids stay unique.
+static ASTNode *clone_into_tree(const ASTNode *node) {
+    ASTNode *copy = parser_clone_ast(node);
+    ASTVisitor clear = { .enter = clear_node_id };
+    ast_walk(copy, &clear);
+    return copy;
+}
+
+// A repeat literal [value; count] keeps its one element, and its count is
+// folded to an int literal: the backends evaluate the element once and fill
+// the array with it (see ast_array_length()). The count must be a constant;
+// with 0 or 1 the literal becomes an ordinary one.
+#define MAX_ARRAY_REPEAT 65536
+
+static bool const_eval_in(ASTNode *node, LocalSymbolTable *table, ConstValue *out);
+
+static void fold_array_repeat(ASTNode *node, LocalSymbolTable *table) {
+    ASTNode *count = node->right;
+    node->right = NULL;
+    ConstValue size;
+    char msg[256];
+    if (!const_eval_in(count, table, &size)) {
+        compile_error("Array size must be a constant integer expression", count->line);
+    } else if (size.type != TYPE_INT) {
+        snprintf(msg, sizeof(msg), "Type error: Array size must be an integer, got %s",
+                 data_type_to_string(size.type));
+        compile_error(msg, count->line);
+    } else if (size.iv < 0 || size.iv > MAX_ARRAY_REPEAT) {
+        snprintf(msg, sizeof(msg), "Array size %lld is out of range (0 to %d)", size.iv, MAX_ARRAY_REPEAT);
+        compile_error(msg, count->line);
+    } else if (size.iv == 0) {
+        parser_free_ast(node->children[0]);
+        free(node->children);
+        node->children = NULL;
+        node->child_count = 0;
+    } else if (size.iv > 1) {
+        char digits[32];
+        snprintf(digits, sizeof(digits), "%lld", size.iv);
+        char *value = strdup(digits);
+        if (value) {
+            ASTNode *folded = calloc(1, sizeof(ASTNode));
+            if (folded) {
+                folded->type = AST_LITERAL;
+                folded->data_type = TYPE_INT;
+                folded->value = value;
+                folded->line = count->line;
+                folded->column = count->column;
+                folded->array_length = -1;
+                node->right = folded;
+            } else {
+                free(value);
+            }
+        }
+    }
+    parser_free_ast(count);
+}
+
+// The length of an array expression when it is known statically: an array
+// literal (once its repeat count is folded), or a name bound to a fixed-size
+// array. -1 for dynamic arrays and anything else.
+static int static_array_length(const ASTNode *expr, LocalSymbolTable *table) {
+    if (!expr) return -1;
+    if (expr->type == AST_ARRAY_LITERAL) return ast_array_length(expr);
+    if (expr->type == AST_RANGE_EXPR && expr->left && expr->right) {
+        ConstValue lo, hi;
+        if (const_eval(expr->left, &lo) && const_eval(expr->right, &hi) && lo.type == TYPE_INT &&
+            hi.type == TYPE_INT && hi.iv - lo.iv <= MAX_ARRAY_REPEAT) {
+            return hi.iv > lo.iv ? (int)(hi.iv - lo.iv) : 0;
+        }
+    }
+    if (expr->type == AST_IDENTIFIER && expr->value) {
+        LocalSymbolEntry *entry = lookup_symbol_entry(table, expr->value);
+        return entry ? entry->array_length : -1;
+    }
+    return -1;
+}
+
+// The element type of an already checked array expression when it is known:
+// an array literal's (its elements agree, or it is an error), or that of the
+// array a name was bound to.
+static DataType static_element_type(const ASTNode *expr, LocalSymbolTable *table) {
+    if (!expr) return TYPE_UNKNOWN;
+    if (expr->type == AST_ARRAY_LITERAL && expr->child_count > 0) return expr->children[0]->data_type;
+    if (expr->type == AST_RANGE_EXPR) return TYPE_INT;
+    if (expr->type == AST_IDENTIFIER && expr->value) {
+        LocalSymbolEntry *entry = lookup_symbol_entry(table, expr->value);
+        return entry ? entry->element_type : TYPE_UNKNOWN;
+    }
+    return TYPE_UNKNOWN;
+}
+
+// -Winteger-division: int / int whose operands are constants that do not
+// divide evenly. Called where the division is typed.
+static void check_integer_division(ASTNode *node) {
+    ConstValue l, r;
+    if (!const_eval(node->left, &l) || !const_eval(node->right, &r)) return;
+    if (l.type != TYPE_INT || r.type != TYPE_INT || r.iv == 0 || r.iv == -1 || l.iv % r.iv == 0) return;
+    char msg[256];
+    snprintf(msg, sizeof(msg),
+             "Integer division %lld / %lld discards a remainder of %lld; "
+             "use float(...) on an operand for float division", l.iv, r.iv, l.iv % r.iv);
+    semantic_report_warning(LINT_INTEGER_DIVISION, msg, node->line);
+}
+
+// -Winteger-division: int / int converted to a float. Constant operands
+// were already checked by check_integer_division, so only a division whose
+// remainder is not known at compile time is reported here.
+static void check_float_division(ASTNode *value, const char *context, int line) {
+    if (!value || value->type != AST_BINARY_EXPR || !value->value || strcmp(value->value, "/") != 0 ||
+        value->data_type != TYPE_INT) return;
+    ConstValue l, r;
+    if (const_eval(value->left, &l) && const_eval(value->right, &r)) return;
+    char msg[512];
+    snprintf(msg, sizeof(msg),
+             "Integer division in %s drops any remainder before the result becomes a float; "
+             "use float(...) on an operand for float division", context);
+    semantic_report_warning(LINT_INTEGER_DIVISION, msg, line);
+}
+
+// True if evaluating the expression can do nothing but produce a value: no
+// calls, no x? unwrap or indexing that may trap, nothing that declares or
+// assigns.
+static bool is_impure_node(const ASTNode *node, const void *data) {
+    (void)data;
+    switch (node->type) {
+        case AST_LITERAL:
+        case AST_IDENTIFIER:
+        case AST_BINARY_EXPR:
+        case AST_UNARY_EXPR:
+        case AST_TERNARY_EXPR:
+        case AST_ARRAY_LITERAL:
+        case AST_OBJECT_LITERAL:
+        case AST_RANGE_EXPR:
+        case AST_CAST_EXPR:
+        case AST_TUPLE_EXPR:
+            return false;
+        default:
+            return true;
+    }
+}
+
+static bool expression_is_pure(const ASTNode *node) {
+    return ast_find((ASTNode *)node, is_impure_node, NULL) == NULL;
+}
+
+// Whether running `node` can leave the loop it is in: a break of that loop
+// (not of a nested one), or unless `break_only`, a return or an #exit. A
+// nested function does not count, since declaring it runs none of its body.
+static bool can_leave_loop(const ASTNode *node, bool nested, bool break_only) {
+    if (!node) return false;
+    switch (node->type) {
+        case AST_BREAK_STMT:
+            return !nested;
+        case AST_RETURN_STMT:
+        case AST_EXIT_STMT:
+            return !break_only;
+        case AST_FUNCTION_DECL:
+        case AST_ARROW_FUNCTION:
+            return false;
+        case AST_WHILE_STMT:
+        case AST_DO_WHILE_STMT:
+        case AST_FOR_STMT:
+            nested = true;
+            break;
+        default:
+            break;
+    }
+    if (can_leave_loop(node->left, nested, break_only) || can_leave_loop(node->right, nested, break_only) ||
+        can_leave_loop(node->condition, nested, break_only) ||
+        can_leave_loop(node->body, nested, break_only)) return true;
+    for (int i = 0; i < node->child_count; i++) {
+        if (can_leave_loop(node->children[i], nested, break_only)) return true;
+    }
+    return false;
+}
+
+// The initializer of the const that `name` refers to in `scope`, or NULL
+// when the name is not a const there
+typedef const ASTNode *(*ConstResolver)(void *scope, const char *name);
+
+// Whether an if or loop condition is a compile-time boolean: a literal, a
+// constant expression or a const bound to one. Stores its value in *out.
+static bool constant_condition(const ASTNode *condition, ConstResolver resolve, void *scope, bool *out) {
+    if (!condition) return false;
+    if (condition->type == AST_IDENTIFIER && condition->value) {
+        const ASTNode *bound = resolve(scope, condition->value);
+        if (bound) condition = bound;
+    }
+    ConstValue value;
+    if (!const_eval((ASTNode *)condition, &value) || value.type != TYPE_BOOL) return false;
+    *out = value.bv;
+    return true;
+}
+
+static const ASTNode *resolve_in_table(void *scope, const char *name) {
+    LocalSymbolEntry *entry = lookup_symbol_entry(scope, name);
+    return entry && entry->is_constant && entry->decl ? entry->decl->right : NULL;
+}
+
+// -Winfinite-loop: a while loop whose condition is always true and whose
+// body has no break, return or #exit.
+static void check_infinite_loop(const ASTNode *loop, LocalSymbolTable *table) {
+    if (!g_lints[LINT_INFINITE_LOOP].enabled) return;
+    bool always;
+    if (!constant_condition(loop->condition, resolve_in_table, table, &always) || !always) return;
+    if (can_leave_loop(loop->body, false, false)) return;
+    semantic_report_warning(LINT_INFINITE_LOOP,
+                            "Loop never terminates: its condition is always true and its body "
+                            "has no break, return or #exit", loop->line);
+}
+
+// --emit reachability: whether control can get to each statement. A return,
+// #exit, break or continue ends the statements after it in its block; an if
+// or while whose condition is a compile-time constant only enters the branch
+// it takes, and a while true loop only continues past itself through a break.
+// Conditions name consts the way check_infinite_loop() sees them, through
+// the declarations in scope at each statement.
+typedef struct {
+    ReachabilityEntry *items;
+    int count;
+    int capacity;
+    const ASTNode **decls;   // Declarations in scope, innermost last
+    int decl_count;
+    int decl_capacity;
+} ReachabilityBuilder;
+
+static void reach_declare(ReachabilityBuilder *out, const ASTNode *decl) {
+    if (!decl->value) return;
+    if (out->decl_count == out->decl_capacity) {
+        int capacity = out->decl_capacity ? out->decl_capacity * 2 : 16;
+        const ASTNode **decls = realloc(out->decls, capacity * sizeof(ASTNode *));
+        if (!decls) return;
+        out->decls = decls;
+        out->decl_capacity = capacity;
+    }
+    out->decls[out->decl_count++] = decl;
+}
+
+static const ASTNode *resolve_in_reach(void *scope, const char *name) {
+    ReachabilityBuilder *out = scope;
+    for (int i = out->decl_count - 1; i >= 0; i--) {
+        if (strcmp(out->decls[i]->value, name) == 0) {
+            return out->decls[i]->type == AST_CONST_DECL ? out->decls[i]->right : NULL;
+        }
+    }
+    return NULL;
+}
+
+static bool reach_statement(const ASTNode *stmt, bool reachable, ReachabilityBuilder *out);
+
+static bool reach_statements(const ASTNode *block, bool reachable, ReachabilityBuilder *out) {
+    int outer = out->decl_count;
+    for (int i = 0; i < block->child_count; i++) {
+        if (block->children[i]) reachable = reach_statement(block->children[i], reachable, out);
+    }
+    out->decl_count = outer;
+    return reachable;
+}
+
+// A branch is a block, or for elif an if statement of its own
+static bool reach_branch(const ASTNode *branch, bool reachable, ReachabilityBuilder *out) {
+    if (!branch) return reachable;
+    if (branch->type == AST_BLOCK) return reach_statements(branch, reachable, out);
+    return reach_statement(branch, reachable, out);
+}
+
+static bool reach_statement(const ASTNode *stmt, bool reachable, ReachabilityBuilder *out) {
+    if (out->count == out->capacity) {
+        int capacity = out->capacity ? out->capacity * 2 : 32;
+        ReachabilityEntry *items = realloc(out->items, capacity * sizeof(ReachabilityEntry));
+        if (!items) return reachable;
+        out->items = items;
+        out->capacity = capacity;
+    }
+    // ast_span() already leaves out an if's body; leaving out its else
+    // branch too makes the entry cover `if cond`, and each branch's
+    // statements get entries of their own
+    ASTNode head = *stmt;
+    if (stmt->type == AST_IF_STMT) head.right = NULL;
+    out->items[out->count++] = (ReachabilityEntry){ast_span(&head), stmt->type, reachable};
+
+    bool value = false;
+    bool known = constant_condition(stmt->condition, resolve_in_reach, out, &value);
+    switch (stmt->type) {
+        case AST_RETURN_STMT:
+        case AST_EXIT_STMT:
+        case AST_BREAK_STMT:
+        case AST_CONTINUE_STMT:
+            return false;
+        case AST_CONST_DECL:
+        case AST_VAR_DECL:
+            reach_declare(out, stmt);
+            return reachable;
+        case AST_IF_STMT: {
+            bool then_end = reach_branch(stmt->body, reachable && (!known || value), out);
+            bool else_end = reach_branch(stmt->right, reachable && (!known || !value), out);
+            return then_end || else_end;
+        }
+        case AST_WHILE_STMT:
+        case AST_DO_WHILE_STMT:
+            reach_branch(stmt->body, reachable && (!known || value), out);
+            if (known && value) return reachable && can_leave_loop(stmt->body, false, true);
+            return reachable;
+        case AST_FOR_STMT:
+            reach_branch(stmt->body, reachable, out);
+            return reachable;
+        case AST_FUNCTION_DECL: {
+            // A function can be called from anywhere, so its body starts
+            // reachable; its parameters hide consts of the same name
+            int outer = out->decl_count;
+            for (int i = 0; i < stmt->child_count; i++) {
+                if (stmt->children[i] && stmt->children[i]->type == AST_PARAM_DECL) {
+                    reach_declare(out, stmt->children[i]);
+                }
+            }
+            reach_branch(stmt->body, true, out);
+            out->decl_count = outer;
+            return reachable;
+        }
+        case AST_BLOCK:
+            return reach_statements(stmt, reachable, out);
+        default:
+            return reachable;
+    }
+}
+
+ReachabilityMap semantic_reachability(const ASTNode *ast) {
+    ReachabilityBuilder out = {0};
+    if (ast && ast->type == AST_PROGRAM) reach_statements(ast, true, &out);
+    free(out.decls);
+    return (ReachabilityMap){out.items, out.count};
+}
+
+void reachability_map_free(ReachabilityMap *map) {
+    free(map->items);
+    map->items = NULL;
+    map->count = 0;
+}
+
+// -Wunreachable-code: the first statement of each stretch that the
+// reachability map marks unreachable
+static void report_unreachable_code(const ASTNode *ast) {
+    if (!g_lints[LINT_UNREACHABLE_CODE].enabled) return;
+    // Leave out the items taken in from a prelude
+    ASTNode program = *ast;
+    if (!g_checking_prelude && g_prelude_item_count > 0 && ast->type == AST_PROGRAM) {
+        program.children += g_prelude_item_count;
+        program.child_count -= g_prelude_item_count;
+        program.body = program.child_count > 0 ? program.children[0] : NULL;
+    }
+    ReachabilityMap map = semantic_reachability(&program);
+    for (int i = 0; i < map.count; i++) {
+        if (map.items[i].reachable || (i > 0 && !map.items[i - 1].reachable)) continue;
+        semantic_report_warning(LINT_UNREACHABLE_CODE, "Unreachable code: no path of control gets here",
+                                map.items[i].span.line);
+    }
+    reachability_map_free(&map);
+}
+
+// -Wpointless-statement: an expression statement whose value is thrown away
+// and whose evaluation has no side effects, such as `1 + 2` on its own line.
+static void check_discarded_value(const ASTNode *stmt) {
+    if (!g_lints[LINT_POINTLESS_STATEMENT].enabled || stmt->type == AST_CALL_EXPR ||
+        !expression_is_pure(stmt)) return;
+    semantic_report_warning(LINT_POINTLESS_STATEMENT, "Statement has no effect; its value is discarded",
+                            stmt->line);
+}
+
+// Set while a #static_assert condition is checked, since it is meant to be
+// constant
+static bool g_in_static_assert = false;
+
+// -Wconstant-comparison: a comparison whose result is known before the
+// program runs, because both sides are constants or both are the same
+// variable. Floats only count as constants: x == x is false for NaN.
+static void check_constant_comparison(const ASTNode *node, DataType type) {
+    if (!g_lints[LINT_CONSTANT_COMPARISON].enabled || g_in_static_assert) return;
+    const char *op = node->value;
+    ConstValue value;
+    bool result;
+    if (const_eval((ASTNode *)node, &value) && value.type == TYPE_BOOL) {
+        result = value.bv;
+    } else if (node->left->type == AST_IDENTIFIER && node->right->type == AST_IDENTIFIER &&
+               node->left->value && node->right->value &&
+               strcmp(node->left->value, node->right->value) == 0 &&
+               (type == TYPE_INT || type == TYPE_STRING || type == TYPE_BOOL)) {
+        result = strcmp(op, "==") == 0 || strcmp(op, "<=") == 0 || strcmp(op, ">=") == 0;
+    } else {
+        return;
+    }
+    char msg[128];
+    snprintf(msg, sizeof(msg), "Comparison with '%s' is always %s", op, result ? "true" : "false");
+    semantic_report_warning(LINT_CONSTANT_COMPARISON, msg, node->line);
+}
+
+// -Wpointless-statement: x = x, and x = x + 0, x - 0 or x * 1 on a number
+static void check_self_assignment(const ASTNode *node, DataType type) {
+    const char *name = node->left->value;
+    const ASTNode *value = node->right;
+    if (!value) return;
+    bool no_op = value->type == AST_IDENTIFIER && value->value && strcmp(value->value, name) == 0;
+    if (!no_op && value->type == AST_BINARY_EXPR && value->value && value->left &&
+        value->left->type == AST_IDENTIFIER && value->left->value &&
+        strcmp(value->left->value, name) == 0 && (type == TYPE_INT || type == TYPE_FLOAT)) {
+        ConstValue r;
+        if (const_eval(value->right, &r) && (r.type == TYPE_INT || r.type == TYPE_FLOAT)) {
+            double v = r.type == TYPE_INT ? (double)r.iv : r.fv;
+            no_op = ((strcmp(value->value, "+") == 0 || strcmp(value->value, "-") == 0) && v == 0) ||
+                    (strcmp(value->value, "*") == 0 && v == 1);
+        }
+    }
+    if (!no_op) return;
+    char msg[300];
+    snprintf(msg, sizeof(msg), "Assignment to '%s' has no effect; it already holds that value", name);
+    semantic_report_warning(LINT_POINTLESS_STATEMENT, msg, node->line);
+}
+
+// Optimizer hints on a function: #[inline] / #[no_inline] and #[hot] /
+// #[cold]. An unknown name is a warning; a contradictory pair is an error.
+static void check_function_attributes(const ASTNode *func) {
+    static const char *const known[] = {"inline", "no_inline", "hot", "cold", "target"};
+    if (!func->attributes) return;
+    for (const char *p = func->attributes; *p; p += strcspn(p, ","), p += *p == ',') {
+        int len = (int)strcspn(p, ",(");
+        const char *args = p[len] == '(' ? p + len + 1 : NULL;
+        bool found = false;
+        for (size_t i = 0; i < sizeof(known) / sizeof(known[0]); i++) {
+            if ((int)strlen(known[i]) == len && strncmp(p, known[i], len) == 0) found = true;
+        }
+        bool is_target = len == 6 && strncmp(p, "target", 6) == 0;
+        char error_msg[300];
+        if (!found) {
+            char msg[300];
+            snprintf(msg, sizeof(msg),
+                     "Unknown attribute #[%.*s] on '%s' is ignored "
+                     "(expected inline, no_inline, hot, cold or target)", len, p, func->value);
+            semantic_report_warning(LINT_UNKNOWN_ATTRIBUTE, msg, func->line);
+        } else if (is_target && !args) {
+            snprintf(error_msg, sizeof(error_msg),
+                     "#[target] on '%s' needs the targets it is compiled for, as in #[target(linux, web)]",
+                     func->value);
+            compile_error(error_msg, func->line);
+        } else if (!is_target && args) {
+            snprintf(error_msg, sizeof(error_msg), "#[%.*s] on '%s' takes no arguments", len, p, func->value);
+            compile_error(error_msg, func->line);
+        }
+        for (const char *a = is_target ? args : NULL; a && *a && *a != ')'; ) {
+            int arg_len = (int)strcspn(a, " )");
+            char arg[32];
+            snprintf(arg, sizeof(arg), "%.*s", arg_len, a);
+            if (!canonical_target_name(arg)) {
+                snprintf(error_msg, sizeof(error_msg),
+                         "Unknown target '%s' in #[target(...)] on '%s'", arg, func->value);
+                compile_error(error_msg, func->line);
+            }
+            a += arg_len;
+            if (*a == ' ') a++;
+        }
+    }
+    const char *pairs[][2] = {{"inline", "no_inline"}, {"hot", "cold"}};
+    for (size_t i = 0; i < sizeof(pairs) / sizeof(pairs[0]); i++) {
+        if (ast_has_attribute(func, pairs[i][0]) && ast_has_attribute(func, pairs[i][1])) {
+            char error_msg[300];
+            snprintf(error_msg, sizeof(error_msg),
+                     "Function '%s' cannot be both #[%s] and #[%s]",
+                     func->value, pairs[i][0], pairs[i][1]);
+            compile_error(error_msg, func->line);
+        }
+    }
+}
+
+// The inliner only handles a body that is a single `return expr` of a
+// plain value, so #[inline] on anything else would be silently dropped.
+// Runs after the body is checked, once an inferred tuple return is known.
+static void check_inline_body(const ASTNode *func) {
+    if (!func->value || !ast_has_attribute(func, "inline") || !func->body) return;
+    const ASTNode *block = func->body;
+    const ASTNode *stmt = block->body ? block->body : (block->child_count > 0 ? block->children[0] : NULL);
+    if (stmt && !stmt->next && block->child_count <= 1 && stmt->type == AST_RETURN_STMT &&
+        stmt->right && !stmt->optional && !func->optional && !func->tuple) return;
+    char msg[300];
+    snprintf(msg, sizeof(msg),
+             "#[inline] on '%s' is ignored; only a function whose body is a single 'return' of a plain value can be inlined",
+             func->value);
+    semantic_report_warning(LINT_IGNORED_ATTRIBUTE, msg, func->line);
+}
+
+// #[target(...)] functions dropped because they do not list the active
+// target, kept by name so that a use of one can say why it is missing
+typedef struct {
+    char *name;
+    char *targets;  // "linux, web"
+} ExcludedFunction;
+
+static ExcludedFunction *g_excluded = NULL;
+static int g_excluded_count = 0;
+
+static void clear_excluded_functions(void) {
+    for (int i = 0; i < g_excluded_count; i++) {
+        free(g_excluded[i].name);
+        free(g_excluded[i].targets);
+    }
+    free(g_excluded);
+    g_excluded = NULL;
+    g_excluded_count = 0;
+}
+
+static const char* excluded_function_targets(const char *name) {
+    for (int i = 0; i < g_excluded_count; i++) {
+        if (strcmp(g_excluded[i].name, name) == 0) return g_excluded[i].targets;
+    }
+    return NULL;
+}
+
+static void record_excluded_function(const ASTNode *func) {
+    ExcludedFunction *list = realloc(g_excluded, sizeof(ExcludedFunction) * (g_excluded_count + 1));
+    if (!list) return;
+    g_excluded = list;
+    const char *args = strstr(func->attributes, "target(") + 7;
+    size_t len = strcspn(args, ")");
+    char *targets = malloc(len * 2 + 1);
+    if (!targets) return;
+    size_t n = 0;
+    for (size_t i = 0; i < len; i++) {
+        if (args[i] == ' ') targets[n++] = ',';
+        targets[n++] = args[i];
+    }
+    targets[n] = '\0';
+    g_excluded[g_excluded_count].name = strdup(func->value);
+    g_excluded[g_excluded_count].targets = targets;
+    g_excluded_count++;
+}
+
+// Drop the top-level functions whose #[target(...)] does not list the
+// active target, so no backend sees them. Without an active target (--emit,
+// subc check) every function stays.
+static void exclude_target_functions(ASTNode *ast) {
+    const char *target = active_target();
+    if (!target || ast->type != AST_PROGRAM) return;
+    ASTNode *prev = NULL;
+    int kept = 0;
+    for (int i = 0; i < ast->child_count; i++) {
+        ASTNode *stmt = ast->children[i];
+        if (stmt->type == AST_FUNCTION_DECL && stmt->value && ast_has_attribute(stmt, "target") &&
+            strstr(stmt->attributes, "target(") && !ast_attribute_lists(stmt, "target", target)) {
+            check_function_attributes(stmt);
+            record_excluded_function(stmt);
+            if (prev) prev->next = stmt->next;
+            else ast->body = stmt->next;
+            stmt->next = NULL;
+            parser_free_ast(stmt);
+            continue;
+        }
+        ast->children[kept++] = stmt;
+        prev = stmt;
+    }
+    ast->child_count = kept;
+}
+
+// The scope whose consts const_eval() may read, while const_eval_in() runs
+static LocalSymbolTable *g_const_scope = NULL;
+
+// Fold an expression built only from literals (and, under const_eval_in(),
+// consts). Returns false if its value is not known at compile time
+// (variables, calls, strings, division by zero).
+static bool const_eval(ASTNode *node, ConstValue *out) {
+    if (!node) return false;
+
+    switch (node->type) {
+        case AST_IDENTIFIER: {
+            const ASTNode *bound = g_const_scope && node->value ? resolve_in_table(g_const_scope, node->value) : NULL;
+            return bound && const_eval((ASTNode *)bound, out);
+        }
+
+        case AST_LITERAL: {
+            if (!node->value || node->data_type == TYPE_STRING) return false;
+            if (strcmp(node->value, "true") == 0 || strcmp(node->value, "false") == 0) {
+                out->type = TYPE_BOOL;
+                out->bv = strcmp(node->value, "true") == 0;
+                return true;
+            }
+            bool is_hex = node->value[0] == '0' && (node->value[1] == 'x' || node->value[1] == 'X');
+            char *end = NULL;
+            if (number_literal_is_float(node->value)) {
+                out->type = TYPE_FLOAT;
+                out->fv = strtod(node->value, &end);
+            } else {
+                out->type = TYPE_INT;
+                out->iv = strtoll(node->value, &end, is_hex ? 16 : 10);
+            }
+            return end && *end == '\0';
+        }
+
+        case AST_UNARY_EXPR: {
+            ConstValue v;
+            if (!node->value || !const_eval(node->right, &v)) return false;
+            if (strcmp(node->value, "!") == 0 && v.type == TYPE_BOOL) {
+                out->type = TYPE_BOOL;
+                out->bv = !v.bv;
+                return true;
+            }
+            if (strcmp(node->value, "-") == 0 && v.type != TYPE_BOOL) {
+                if (v.type == TYPE_INT && (v.iv == LLONG_MIN || !int_fits(-v.iv))) return false;
+                *out = v;
+                if (v.type == TYPE_FLOAT) out->fv = -v.fv;
+                else out->iv = -v.iv;
+                return true;
+            }
+            return false;
+        }
+
+        case AST_BINARY_EXPR: {
+            ConstValue l, r;
+            if (!node->value || !const_eval(node->left, &l) || !const_eval(node->right, &r)) return false;
+            const char *op = node->value;
+
+            if (l.type == TYPE_BOOL || r.type == TYPE_BOOL) {
+                if (l.type != r.type) return false;
+                out->type = TYPE_BOOL;
+                if (strcmp(op, "&&") == 0) out->bv = l.bv && r.bv;
+                else if (strcmp(op, "||") == 0) out->bv = l.bv || r.bv;
+                else if (strcmp(op, "==") == 0) out->bv = l.bv == r.bv;
+                else if (strcmp(op, "!=") == 0) out->bv = l.bv != r.bv;
+                else return false;
+                return true;
+            }
+
+            bool use_float = l.type == TYPE_FLOAT || r.type == TYPE_FLOAT;
+            double a = const_as_float(&l), b = const_as_float(&r);
+
+            if (strcmp(op, "==") == 0 || strcmp(op, "!=") == 0 ||
+                strcmp(op, "<") == 0 || strcmp(op, ">") == 0 ||
+                strcmp(op, "<=") == 0 || strcmp(op, ">=") == 0) {
+                int cmp = use_float ? (a < b ? -1 : a > b ? 1 : 0)
+                                    : (l.iv < r.iv ? -1 : l.iv > r.iv ? 1 : 0);
+                out->type = TYPE_BOOL;
+                if (strcmp(op, "==") == 0) out->bv = cmp == 0;
+                else if (strcmp(op, "!=") == 0) out->bv = cmp != 0;
+                else if (strcmp(op, "<") == 0) out->bv = cmp < 0;
+                else if (strcmp(op, ">") == 0) out->bv = cmp > 0;
+                else if (strcmp(op, "<=") == 0) out->bv = cmp <= 0;
+                else out->bv = cmp >= 0;
+                return true;
+            }
+
+            if (use_float) {
+                out->type = TYPE_FLOAT;
+                if (strcmp(op, "+") == 0) out->fv = a + b;
+                else if (strcmp(op, "-") == 0) out->fv = a - b;
+                else if (strcmp(op, "*") == 0) out->fv = a * b;
+                else if (strcmp(op, "/") == 0 && b != 0.0) out->fv = a / b;
+                else return false;
+                return true;
+            }
+
+            // An expression that overflows the --int-width is not a
+            // constant: its value depends on the runtime --overflow mode
+            out->type = TYPE_INT;
+            if (strcmp(op, "+") == 0) return !__builtin_add_overflow(l.iv, r.iv, &out->iv) && int_fits(out->iv);
+            if (strcmp(op, "-") == 0) return !__builtin_sub_overflow(l.iv, r.iv, &out->iv) && int_fits(out->iv);
+            if (strcmp(op, "*") == 0) return !__builtin_mul_overflow(l.iv, r.iv, &out->iv) && int_fits(out->iv);
+            if (r.iv == 0 || (l.iv == int_min() && r.iv == -1)) return false;
+            if (strcmp(op, "/") == 0) out->iv = l.iv / r.iv;
+            else if (strcmp(op, "%") == 0) out->iv = l.iv % r.iv;
+            else return false;
+            return true;
+        }
+
+        case AST_CAST_EXPR: {
+            ConstValue v;
+            if (!const_eval(node->right, &v)) return false;
+            if (node->data_type == TYPE_INT && v.type != TYPE_BOOL) {
+                out->type = TYPE_INT;
+                out->iv = v.type == TYPE_FLOAT ? (long long)v.fv : v.iv;
+                return true;
+            }
+            if (node->data_type == TYPE_FLOAT && v.type != TYPE_BOOL) {
+                out->type = TYPE_FLOAT;
+                out->fv = const_as_float(&v);
+                return true;
+            }
+            if (node->data_type == TYPE_BOOL && v.type != TYPE_FLOAT) {
+                out->type = TYPE_BOOL;
+                out->bv = v.type == TYPE_BOOL ? v.bv : v.iv != 0;
+                return true;
+            }
+            return false;
+        }
+
+        // len() of an ASCII literal without escapes; other strings count bytes in
+        // some backends and characters in others
+        case AST_CALL_EXPR: {
+            if (!node->value || strcmp(node->value, "len") != 0 || node->child_count != 1) return false;
+            const ASTNode *arg = node->children[0];
+            if (arg->type != AST_LITERAL || arg->data_type != TYPE_STRING || !ast_literal_is_verbatim(arg)) return false;
+            for (const char *p = arg->value; *p; p++) {
+                if ((unsigned char)*p >= 0x80) return false;
+            }
+            out->type = TYPE_INT;
+            out->iv = (long long)strlen(arg->value);
+            return true;
+        }
+
+        default:
+            return false;
+    }
+}
+
+// const_eval() for the places that need a compile-time value (a repeat
+// count, a #static_assert condition), where a const in `table` may stand in
+// for its value
+static bool const_eval_in(ASTNode *node, LocalSymbolTable *table, ConstValue *out) {
+    LocalSymbolTable *outer = g_const_scope;
+    g_const_scope = table;
+    bool folded = const_eval(node, out);
+    g_const_scope = outer;
+    return folded;
+}
+
 // ========================================
 // Expression Type Checking
 // ========================================
 
+// substr / index_of / to_upper / to_lower: fixed arity, string and int operands
+// ========================================
+// Optional Values
+// ========================================
+
+// An optional value (node->optional) may be null, so it cannot be used where
+// a value is required until it has been checked or unwrapped with `x?`
+static void require_non_optional(ASTNode *expr, const char *context) {
+    if (!expr || !expr->optional) return;
+    char error_msg[512];
+    if (expr->type == AST_IDENTIFIER && expr->value) {
+        snprintf(error_msg, sizeof(error_msg),
+                 "Type error: Optional value '%s' used as %s without a null check; "
+                 "test 'if %s != null' first or unwrap it with '%s?'",
+                 expr->value, context, expr->value, expr->value);
+    } else {
+        snprintf(error_msg, sizeof(error_msg),
+                 "Type error: Optional value used as %s without a null check; unwrap it with '?'",
+                 context);
+    }
+    compile_error(error_msg, expr->line);
+}
+
+// Range bounds (a..b, a..=b, range(n), range(a, b)) must be ints. a..=b is
+// then rewritten to a..(b + 1), so backends only lower the exclusive form.
+static void check_range_bounds(ASTNode *range, LocalSymbolTable *table) {
+    ASTNode *bounds[2] = {range->left, range->right};
+    for (int i = 0; i < 2; i++) {
+        if (!bounds[i]) continue;
+        DataType type = check_expression_type(bounds[i], table);
+        require_non_optional(bounds[i], "a range bound");
+        if (type != TYPE_INT && type != TYPE_UNKNOWN && type != TYPE_AUTO) {
+            char error_msg[256];
+            snprintf(error_msg, sizeof(error_msg), "Type error: Range bounds must be int, got %s",
+                     data_type_to_string(type));
+            semantic_report_error_at(error_msg, bounds[i]);
+        }
+    }
+    if (!range->value || strcmp(range->value, "..=") != 0 || !range->right) return;
+    ASTNode *one = calloc(1, sizeof(ASTNode));
+    ASTNode *plus = calloc(1, sizeof(ASTNode));
+    char *dots = strdup("..");
+    if (!one || !plus || !dots || !(one->value = strdup("1")) || !(plus->value = strdup("+"))) {
+        if (one) free(one->value);
+        free(one);
+        free(plus);
+        free(dots);
+        return;
+    }
+    one->type = AST_LITERAL;
+    one->data_type = TYPE_INT;
+    one->line = range->right->line;
+    one->column = range->right->column;
+    one->array_length = -1;
+    plus->type = AST_BINARY_EXPR;
+    plus->data_type = TYPE_INT;
+    plus->left = range->right;
+    plus->right = one;
+    plus->line = range->right->line;
+    plus->column = range->right->column;
+    plus->array_length = -1;
+    range->right = plus;
+    free(range->value);
+    range->value = dots;
+}
+
+// If `cond` being `when_true` proves an optional variable non-null
+// (`x != null` is true, or `x == null` is false), return that variable
+static LocalSymbolEntry* null_checked_entry(ASTNode *cond, LocalSymbolTable *table, bool when_true) {
+    if (!cond || cond->type != AST_BINARY_EXPR || !cond->value) return NULL;
+    if (strcmp(cond->value, when_true ? "!=" : "==") != 0) return NULL;
+    ASTNode *var = NULL;
+    if (cond->right && cond->right->data_type == TYPE_NULL) var = cond->left;
+    else if (cond->left && cond->left->data_type == TYPE_NULL) var = cond->right;
+    if (!var || var->type != AST_IDENTIFIER || !var->value) return NULL;
+    LocalSymbolEntry *entry = lookup_symbol(table, var->value);
+    return entry && entry->is_optional ? entry : NULL;
+}
+
+static DataType check_string_builtin(ASTNode *node, const char *fn_name, LocalSymbolTable *table) {
+    static const DataType params[STR_BUILTIN_COUNT][3] = {
+        [STR_BUILTIN_SUBSTR]   = {TYPE_STRING, TYPE_INT, TYPE_INT},
+        [STR_BUILTIN_INDEX_OF] = {TYPE_STRING, TYPE_STRING},
+        [STR_BUILTIN_TO_UPPER] = {TYPE_STRING},
+        [STR_BUILTIN_TO_LOWER] = {TYPE_STRING},
+    };
+    int builtin = string_builtin_lookup(fn_name);
+    int arity = string_builtin_arity(builtin);
+
+    if (node->child_count != arity) {
+        char error_msg[256];
+        snprintf(error_msg, sizeof(error_msg),
+                 "Function '%s' expects %d arguments, got %d", fn_name, arity, node->child_count);
+        compile_error(error_msg, node->line);
+    }
+    for (int i = 0; i < node->child_count; i++) {
+        DataType arg_type = check_expression_type(node->children[i], table);
+        {
+            char context[300];
+            snprintf(context, sizeof(context), "an argument to '%s'", fn_name);
+            require_non_optional(node->children[i], context);
+        }
+        if (i < arity && arg_type != params[builtin][i] &&
+            arg_type != TYPE_UNKNOWN && arg_type != TYPE_AUTO) {
+            char error_msg[512];
+            snprintf(error_msg, sizeof(error_msg),
+                     "Type error: Argument %d to '%s' expects %s, got %s",
+                     i + 1, fn_name, data_type_to_string(params[builtin][i]),
+                     data_type_to_string(arg_type));
+            compile_error(error_msg, node->line);
+        }
+    }
+    return builtin == STR_BUILTIN_INDEX_OF ? TYPE_INT : TYPE_STRING;
+}
+
+// match x { ... }: every pattern must have the scrutinee's type, no arm may
+// repeat an earlier pattern or follow `_`, and the arms must cover every
+// value (a `_` arm, or both `true` and `false` for a bool). The match has
+// the type of its arms.
+static DataType check_match(ASTNode *node, LocalSymbolTable *table) {
+    char error_msg[512];
+    DataType subject = check_expression_type(node->left, table);
+    require_non_optional(node->left, "a match value");
+    bool wildcard = false, seen_true = false, seen_false = false;
+    DataType result = TYPE_UNKNOWN;
+
+    if (node->child_count == 0) {
+        compile_error("Match expression has no arms", node->line);
+    }
+    for (int i = 0; i < node->child_count; i++) {
+        ASTNode *arm = node->children[i];
+        ASTNode *pattern = arm->left;
+        if (wildcard) {
+            compile_error("Unreachable match arm after '_'", arm->line);
+        }
+        if (!pattern) {
+            wildcard = true;
+        } else {
+            DataType pattern_type = check_expression_type(pattern, table);
+            const char *quote = pattern_type == TYPE_STRING ? "\"" : "";
+            bool numeric = (subject == TYPE_INT || subject == TYPE_FLOAT) &&
+                           (pattern_type == TYPE_INT || pattern_type == TYPE_FLOAT);
+            if (pattern_type != subject && !numeric && subject != TYPE_UNKNOWN && subject != TYPE_AUTO) {
+                snprintf(error_msg, sizeof(error_msg),
+                         "Type error: Match pattern %s%s%s is %s, but the matched value is %s",
+                         quote, pattern->value, quote,
+                         data_type_to_string(pattern_type), data_type_to_string(subject));
+                compile_error(error_msg, arm->line);
+            }
+            for (int j = 0; j < i; j++) {
+                ASTNode *earlier = node->children[j]->left;
+                if (earlier && earlier->data_type == pattern_type &&
+                    strcmp(earlier->value, pattern->value) == 0) {
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Unreachable match arm: pattern %s%s%s already matched on line %d",
+                             quote, pattern->value, quote, node->children[j]->line);
+                    compile_error(error_msg, arm->line);
+                    break;
+                }
+            }
+            if (pattern_type == TYPE_BOOL) {
+                if (strcmp(pattern->value, "true") == 0) seen_true = true;
+                else seen_false = true;
+            }
+        }
+
+        DataType arm_type = check_expression_type(arm->right, table);
+        if (arm->right && arm->right->optional) node->optional = true;
+        if (result == TYPE_UNKNOWN || result == TYPE_NULL) {
+            result = arm_type;
+        } else if (result == TYPE_INT && arm_type == TYPE_FLOAT) {
+            result = TYPE_FLOAT;
+        } else if (arm_type == TYPE_NULL) {
+            node->optional = true;
+        } else if (!data_types_are_compatible(result, arm_type) && arm_type != TYPE_UNKNOWN) {
+            snprintf(error_msg, sizeof(error_msg),
+                     "Type error: Match arm has type %s, but earlier arms have type %s",
+                     data_type_to_string(arm_type), data_type_to_string(result));
+            compile_error(error_msg, arm->line);
+        }
+    }
+    if (result == TYPE_NULL) node->optional = true;
+
+    if (!wildcard && !(subject == TYPE_BOOL && seen_true && seen_false) && node->child_count > 0) {
+        if (subject == TYPE_BOOL) {
+            snprintf(error_msg, sizeof(error_msg),
+                     "Non-exhaustive match: missing case %s", seen_true ? "false" : "true");
+        } else {
+            snprintf(error_msg, sizeof(error_msg),
+                     "Non-exhaustive match on %s: add a '_' arm for the remaining values",
+                     data_type_to_string(subject));
+        }
+        compile_error(error_msg, node->line);
+    }
+
+    node->data_type = result;
+    return result;
+}
+
+// Set while checking the operand of a unary minus, which may be one past
+// the largest int so that the smallest one can be written
+static bool g_negated_literal = false;
+
+// An integer literal must fit in an int of the --int-width
+static void check_int_literal(const ASTNode *node) {
+    const char *digits = node->value;
+    bool negative = *digits == '-';
+    if (*digits == '-' || *digits == '+') digits++;
+    bool hex = digits[0] == '0' && (digits[1] == 'x' || digits[1] == 'X');
+    errno = 0;
+    unsigned long long magnitude = strtoull(digits, NULL, hex ? 16 : 10);
+    unsigned long long limit = (unsigned long long)int_max() + (negative || g_negated_literal ? 1 : 0);
+    if (errno == ERANGE || magnitude > limit) {
+        char error_msg[256];
+        snprintf(error_msg, sizeof(error_msg),
+                 "Integer literal %s does not fit in a %d-bit int (the largest is %lld)",
+                 node->value, int_width(), int_max());
+        semantic_report_error_at(error_msg, node);
+    }
+}
+
+// A float literal, decimal or hex, too large for a double is an error
+// rather than a silent infinity
+static bool check_float_range(const ASTNode *node) {
+    if (!isinf(strtod(node->value, NULL))) return true;
+    char error_msg[256];
+    snprintf(error_msg, sizeof(error_msg),
+             "Float literal %.64s is too large for a 64-bit float", node->value);
+    semantic_report_error_at(error_msg, node);
+    return false;
+}
+
+// Most targets have no hex float literals, so 0x1.8p3 becomes the decimal
+// that reads back as the same double (12.0)
+static void rewrite_hex_float(ASTNode *node) {
+    double value = strtod(node->value, NULL);
+    char buf[64];
+    snprintf(buf, sizeof(buf), "%.17g", value);
+    if (!strpbrk(buf, ".e")) strcat(buf, ".0");
+    free(node->value);
+    node->value = strdup(buf);
+}
+
+// -Wfloat-precision: a decimal float literal whose significant digits do
+// not all survive the trip through a 64-bit double (0.1 reads back as 0.1,
+// 3.14159265358979323846 does not)
+static void check_float_precision(const ASTNode *node) {
+    if (!g_lints[LINT_FLOAT_PRECISION].enabled) return;
+    char digits[512];
+    size_t n = 0;
+    for (const char *p = node->value; *p && *p != 'e' && *p != 'E' && n + 1 < sizeof(digits); p++) {
+        if (!isdigit((unsigned char)*p) || (n == 0 && *p == '0')) continue;
+        digits[n++] = *p;
+    }
+    while (n > 0 && digits[n - 1] == '0') n--;
+    digits[n] = '\0';
+    if (n <= DBL_DIG) return;
+
+    char buf[600];
+    snprintf(buf, sizeof(buf), "%.*e", (int)n - 1, fabs(strtod(node->value, NULL)));
+    char kept[512];
+    size_t k = 0;
+    for (const char *p = buf; *p && *p != 'e' && k + 1 < sizeof(kept); p++) {
+        if (isdigit((unsigned char)*p)) kept[k++] = *p;
+    }
+    while (k > 0 && kept[k - 1] == '0') k--;
+    kept[k] = '\0';
+    if (strcmp(digits, kept) == 0) return;
+
+    char warning_msg[256];
+    snprintf(warning_msg, sizeof(warning_msg),
+             "Float literal %.64s loses precision as a 64-bit float (it reads back as %.17g)",
+             node->value, strtod(node->value, NULL));
+    semantic_report_warning(LINT_FLOAT_PRECISION, warning_msg, node->line);
+}
+
 static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
     if (!node) return TYPE_UNKNOWN;
     
@@ -226,28 +1707,23 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 return TYPE_NULL;
             }
             
-            // Check if numeric
-            bool has_dot = false;
-            bool is_number = true;
-            const char *p = node->value;
-            
-            if (*p == '-' || *p == '+') p++;
-            
-            while (*p) {
-                if (isdigit(*p)) {
-                    // OK
-                } else if (*p == '.' && !has_dot) {
-                    has_dot = true;
-                } else {
-                    is_number = false;
-                    break;
+            // Numbers were checked by the lexer: decimal or hex, with an
+            // optional fraction and exponent
+            {
+                const char *p = node->value;
+                if (*p == '-' || *p == '+') p++;
+                if (isdigit((unsigned char)*p) || (*p == '.' && isdigit((unsigned char)p[1]))) {
+                    if (!number_literal_is_float(node->value)) {
+                        node->data_type = TYPE_INT;
+                        check_int_literal(node);
+                        return TYPE_INT;
+                    }
+                    node->data_type = TYPE_FLOAT;
+                    if (!check_float_range(node)) return TYPE_FLOAT;
+                    if (p[0] == '0' && (p[1] == 'x' || p[1] == 'X')) rewrite_hex_float(node);
+                    else check_float_precision(node);
+                    return TYPE_FLOAT;
                 }
-                p++;
-            }
-            
-            if (is_number && strlen(node->value) > 0) {
-                node->data_type = has_dot ? TYPE_FLOAT : TYPE_INT;
-                return node->data_type;
             }
             
             node->data_type = TYPE_UNKNOWN;
@@ -263,11 +1739,22 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 LocalSymbolEntry *entry = lookup_symbol(table, node->value);
                 if (!entry) {
                     char error_msg[256];
-                    snprintf(error_msg, sizeof(error_msg), 
-                             "Undefined variable '%s'", node->value);
-                    compile_error(error_msg, node->line);
+                    const char *suggestion = suggest_symbol(table, node->value, false);
+                    const char *targets = excluded_function_targets(node->value);
+                    if (targets) {
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Function '%.64s' is only compiled for #[target(%.64s)], not for %s",
+                                 node->value, targets, active_target());
+                    } else if (suggestion) {
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Undefined variable '%.64s'; did you mean '%.64s'?", node->value, suggestion);
+                    } else {
+                        snprintf(error_msg, sizeof(error_msg), "Undefined variable '%.64s'", node->value);
+                    }
+                    semantic_report_error_at(error_msg, node);
                     return TYPE_UNKNOWN;
                 }
+                entry->is_used = true;
                 if (!entry->is_initialized) {
                     char error_msg[256];
                     snprintf(error_msg, sizeof(error_msg),
@@ -276,6 +1763,23 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 }
                 
                 node->data_type = entry->data_type;
+                node->tuple = entry->tuple;
+                if (entry->is_optional) {
+                    if (!entry->is_narrowed) {
+                        node->optional = true;
+                        return entry->data_type;
+                    }
+                    // Checked against null already: read it through an
+                    // implicit unwrap so the backends see a plain value
+                    ASTNode *inner = clone_into_tree(node);
+                    if (inner) {
+                        inner->optional = true;
+                        node->type = AST_UNWRAP_EXPR;
+                        node->left = inner;
+                        free(node->value);
+                        node->value = NULL;
+                    }
+                }
                 return entry->data_type;
             }
             
@@ -286,9 +1790,36 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             }
             
             left_type = check_expression_type(node->left, table);
-            right_type = check_expression_type(node->right, table);
+            {
+                // `x != null && x > 0`: the right side only runs once x is known
+                LocalSymbolEntry *narrow = NULL;
+                if (strcmp(node->value, "&&") == 0) narrow = null_checked_entry(node->left, table, true);
+                else if (strcmp(node->value, "||") == 0) narrow = null_checked_entry(node->left, table, false);
+                bool was_narrowed = narrow && narrow->is_narrowed;
+                if (narrow) narrow->is_narrowed = true;
+                right_type = check_expression_type(node->right, table);
+                if (narrow) narrow->is_narrowed = was_narrowed;
+            }
             
             const char *op = node->value;
+
+            // Comparing an optional with null is how it gets checked
+            if ((strcmp(op, "==") == 0 || strcmp(op, "!=") == 0) &&
+                (left_type == TYPE_NULL || right_type == TYPE_NULL)) {
+                ASTNode *other = left_type == TYPE_NULL ? node->right : node->left;
+                DataType other_type = left_type == TYPE_NULL ? right_type : left_type;
+                if (other->optional || other->type == AST_UNWRAP_EXPR || other_type == TYPE_NULL ||
+                    other_type == TYPE_UNKNOWN || other_type == TYPE_AUTO) {
+                    node->data_type = TYPE_BOOL;
+                    return TYPE_BOOL;
+                }
+            }
+            {
+                char context[64];
+                snprintf(context, sizeof(context), "an operand of '%s'", op);
+                require_non_optional(node->left, context);
+                require_non_optional(node->right, context);
+            }
             
             // Arithmetic operators
             if (strcmp(op, "+") == 0 || strcmp(op, "-") == 0 ||
@@ -314,6 +1845,7 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 if (left_type == TYPE_INT || left_type == TYPE_FLOAT) {
                     if (right_type == TYPE_INT || right_type == TYPE_FLOAT) {
                         node->data_type = (left_type == TYPE_FLOAT || right_type == TYPE_FLOAT) ? TYPE_FLOAT : TYPE_INT;
+                        if (node->data_type == TYPE_INT && strcmp(op, "/") == 0) check_integer_division(node);
                         return node->data_type;
                     }
                 }
@@ -340,6 +1872,7 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             if (strcmp(op, "==") == 0 || strcmp(op, "!=") == 0 ||
                 strcmp(op, "<") == 0 || strcmp(op, ">") == 0 ||
                 strcmp(op, "<=") == 0 || strcmp(op, ">=") == 0) {
+                check_constant_comparison(node, left_type);
                 
                 // String comparison
                 if (left_type == TYPE_STRING && right_type == TYPE_STRING) {
@@ -350,6 +1883,15 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 // Numeric comparison
                 if ((left_type == TYPE_INT || left_type == TYPE_FLOAT) &&
                     (right_type == TYPE_INT || right_type == TYPE_FLOAT)) {
+                    // -Wfloat-equality: rounding makes 0.1 + 0.2 != 0.3
+                    if (left_type == TYPE_FLOAT && right_type == TYPE_FLOAT &&
+                        (strcmp(op, "==") == 0 || strcmp(op, "!=") == 0)) {
+                        char msg[256];
+                        snprintf(msg, sizeof(msg),
+                                 "Comparing floats with '%s' is exact and sensitive to rounding; "
+                                 "check that their difference is within a small tolerance instead", op);
+                        semantic_report_warning(LINT_FLOAT_EQUALITY, msg, node->line);
+                    }
                     node->data_type = TYPE_BOOL;
                     return TYPE_BOOL;
                 }
@@ -405,7 +1947,15 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 return TYPE_UNKNOWN;
             }
             
+            g_negated_literal = strcmp(node->value, "-") == 0 && node->right &&
+                                node->right->type == AST_LITERAL;
             right_type = check_expression_type(node->right, table);
+            g_negated_literal = false;
+            {
+                char context[64];
+                snprintf(context, sizeof(context), "the operand of '%s'", node->value);
+                require_non_optional(node->right, context);
+            }
             
             if (strcmp(node->value, "!") == 0 || strcmp(node->value, "not") == 0) {
                 if (right_type != TYPE_BOOL && right_type != TYPE_UNKNOWN) {
@@ -436,6 +1986,20 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             
         case AST_CALL_EXPR:
             if (!node->value && (!node->left || node->left->type != AST_IDENTIFIER)) {
+                // Calls through an expression are not checked (methods have
+                // no types yet), except that a value known not to be a
+                // function cannot be called
+                if (node->left && node->left->type != AST_MEMBER_ACCESS) {
+                    DataType callee_type = check_expression_type(node->left, table);
+                    if (callee_type != TYPE_UNKNOWN && callee_type != TYPE_AUTO &&
+                        callee_type != TYPE_FUNCTION && callee_type != TYPE_OBJECT) {
+                        char error_msg[256];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: A value of type %s is not a function and cannot be called",
+                                 data_type_to_string(callee_type));
+                        semantic_report_error_at(error_msg, node);
+                    }
+                }
                 node->data_type = TYPE_UNKNOWN;
                 return TYPE_UNKNOWN;
             }
@@ -456,25 +2020,116 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                     node->data_type = TYPE_STRING;
                     return TYPE_STRING;
                 }
-                if (fn_name && (strcmp(fn_name, "len") == 0 || strcmp(fn_name, "int") == 0 ||
-                                strcmp(fn_name, "sizeof") == 0)) {
+                if (fn_name && strcmp(fn_name, "len") == 0) {
+                    if (node->child_count != 1) {
+                        char error_msg[256];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Function 'len' expects 1 argument, got %d", node->child_count);
+                        compile_error(error_msg, node->line);
+                    }
+                    for (int i = 0; i < node->child_count; i++) {
+                        DataType arg_type = check_expression_type(node->children[i], table);
+                        require_non_optional(node->children[i], "an argument to 'len'");
+                        if (i == 0 && arg_type != TYPE_STRING && arg_type != TYPE_ARRAY &&
+                            arg_type != TYPE_UNKNOWN && arg_type != TYPE_AUTO) {
+                            char error_msg[512];
+                            snprintf(error_msg, sizeof(error_msg),
+                                     "Type error: len() expects a string or array, got %s",
+                                     data_type_to_string(arg_type));
+                            compile_error(error_msg, node->line);
+                        }
+                    }
+                    node->data_type = TYPE_INT;
+                    return TYPE_INT;
+                }
+                if (fn_name && strcmp(fn_name, "select") == 0) {
+                    /* select(c, a, b) types like c ? a : b, but evaluates
+                       both a and b */
+                    if (node->child_count != 3) {
+                        char error_msg[256];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Function 'select' expects 3 arguments, got %d", node->child_count);
+                        compile_error(error_msg, node->line);
+                        for (int i = 0; i < node->child_count; i++)
+                            check_expression_type(node->children[i], table);
+                        node->data_type = TYPE_UNKNOWN;
+                        return TYPE_UNKNOWN;
+                    }
+                    result_type = check_expression_type(node->children[0], table);
+                    require_non_optional(node->children[0], "the condition of 'select'");
+                    if (result_type != TYPE_BOOL && result_type != TYPE_UNKNOWN) {
+                        char error_msg[512];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: select() condition must be boolean, got %s",
+                                 data_type_to_string(result_type));
+                        compile_error(error_msg, node->line);
+                    }
+                    left_type = check_expression_type(node->children[1], table);
+                    right_type = check_expression_type(node->children[2], table);
+                    if (!data_types_are_compatible(left_type, right_type) &&
+                        left_type != TYPE_UNKNOWN && right_type != TYPE_UNKNOWN) {
+                        char error_msg[512];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: select() arguments have incompatible types %s and %s",
+                                 data_type_to_string(left_type), data_type_to_string(right_type));
+                        compile_error(error_msg, node->line);
+                    }
+                    node->data_type = branch_result_type(left_type, right_type);
+                    node->optional = node->children[1]->optional || node->children[2]->optional;
+                    return node->data_type;
+                }
+                if (fn_name && strcmp(fn_name, "typeof") == 0) {
+                    /* typeof(x) is the static type name of x, so the call is
+                       replaced by a string literal and x is never evaluated */
+                    if (node->child_count != 1) {
+                        char error_msg[256];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Function 'typeof' expects 1 argument, got %d", node->child_count);
+                        compile_error(error_msg, node->line);
+                        node->data_type = TYPE_STRING;
+                        return TYPE_STRING;
+                    }
+                    ASTNode *arg = node->children[0];
+                    DataType arg_type = check_expression_type(arg, table);
+                    char type_name[300];
+                    if (arg_type == TYPE_TUPLE && arg->tuple) {
+                        tuple_type_name(arg->tuple, type_name, sizeof(type_name));
+                    } else {
+                        snprintf(type_name, sizeof(type_name), "%s%s",
+                                 data_type_to_string(arg_type), arg->optional ? "?" : "");
+                    }
+                    parser_free_ast(node->left);
+                    parser_free_ast(arg);
+                    free(node->children);
+                    free(node->value);
+                    node->left = NULL;
+                    node->children = NULL;
+                    node->child_count = 0;
+                    node->value = strdup(type_name);
+                    node->type = AST_LITERAL;
+                    node->optional = false;
+                    node->data_type = TYPE_STRING;
+                    return TYPE_STRING;
+                }
+                if (string_builtin_lookup(fn_name) >= 0) {
+                    node->data_type = check_string_builtin(node, fn_name, table);
+                    return node->data_type;
+                }
+                if (fn_name && strcmp(fn_name, "sizeof") == 0) {
                     for (int i = 0; i < node->child_count; i++)
                         check_expression_type(node->children[i], table);
                     node->data_type = TYPE_INT;
                     return TYPE_INT;
                 }
                 if (fn_name && (strcmp(fn_name, "str") == 0 || strcmp(fn_name, "type") == 0)) {
-                    for (int i = 0; i < node->child_count; i++)
+                    for (int i = 0; i < node->child_count; i++) {
                         check_expression_type(node->children[i], table);
+                        if (strcmp(fn_name, "str") == 0)
+                            require_non_optional(node->children[i], "an argument to 'str'");
+                    }
                     node->data_type = TYPE_STRING;
                     return TYPE_STRING;
                 }
-                if (fn_name && strcmp(fn_name, "float") == 0) {
-                    for (int i = 0; i < node->child_count; i++)
-                        check_expression_type(node->children[i], table);
-                    node->data_type = TYPE_FLOAT;
-                    return TYPE_FLOAT;
-                }
                 if (fn_name && strcmp(fn_name, "range") == 0) {
                     for (int i = 0; i < node->child_count; i++)
                         check_expression_type(node->children[i], table);
@@ -482,27 +2137,81 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                     return TYPE_ARRAY;
                 }
                 
-                LocalSymbolEntry *entry = lookup_symbol(table, fn_name);
+                LocalSymbolEntry *entry = fn_name ? lookup_symbol(table, fn_name) : NULL;
                 if (!entry || !entry->is_function) {
-                    char error_msg[256];
-                    snprintf(error_msg, sizeof(error_msg),
-                             "Undefined function '%s'", fn_name ? fn_name : "<anonymous>");
-                    compile_error(error_msg, node->line);
+                    // Only function declarations are callable; anything else
+                    // that is in scope names a value
+                    char error_msg[512];
+                    if (entry) {
+                        entry->is_used = true;
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: '%s' is a variable of type %s, not a function, and cannot be called",
+                                 fn_name, data_type_to_string(entry->data_type));
+                    } else if (fn_name && excluded_function_targets(fn_name)) {
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Function '%.64s' is only compiled for #[target(%.64s)], not for %s",
+                                 fn_name, excluded_function_targets(fn_name), active_target());
+                    } else {
+                        const char *suggestion = fn_name ? suggest_symbol(table, fn_name, true) : NULL;
+                        snprintf(error_msg, sizeof(error_msg), "Undefined function '%.64s'%s%.64s%s",
+                                 fn_name ? fn_name : "<anonymous>", suggestion ? "; did you mean '" : "",
+                                 suggestion ? suggestion : "", suggestion ? "'?" : "");
+                    }
+                    for (int i = 0; i < node->child_count; i++) {
+                        check_expression_type(node->children[i], table);
+                    }
+                    semantic_report_error_at(error_msg, node);
                     node->data_type = TYPE_UNKNOWN;
                     return TYPE_UNKNOWN;
                 }
 
                 if (entry->param_count >= 0 && entry->param_types) {
-                    if (node->child_count != entry->param_count) {
+                    if (node->child_count < entry->required_count ||
+                        node->child_count > entry->param_count) {
                         char error_msg[256];
-                        snprintf(error_msg, sizeof(error_msg),
-                                 "Function '%s' expects %d arguments, got %d",
-                                 fn_name, entry->param_count, node->child_count);
+                        if (entry->required_count == entry->param_count) {
+                            snprintf(error_msg, sizeof(error_msg),
+                                     "Function '%s' expects %d arguments, got %d",
+                                     fn_name, entry->param_count, node->child_count);
+                        } else {
+                            snprintf(error_msg, sizeof(error_msg),
+                                     "Function '%s' expects %d to %d arguments, got %d",
+                                     fn_name, entry->required_count, entry->param_count,
+                                     node->child_count);
+                        }
                         compile_error(error_msg, node->line);
                     } else {
+                        // Omitted trailing arguments take the declared defaults,
+                        // so every backend sees a call with the full arity
+                        for (int i = node->child_count; i < entry->param_count; i++) {
+                            ASTNode *arg = clone_into_tree(entry->decl->children[i]->right);
+                            if (!arg) break;
+                            ASTNode **grown = realloc(node->children, sizeof(ASTNode*) * (i + 1));
+                            if (!grown) {
+                                parser_free_ast(arg);
+                                break;
+                            }
+                            node->children = grown;
+                            node->children[i] = arg;
+                            node->child_count = i + 1;
+                        }
                         for (int i = 0; i < node->child_count; i++) {
                             DataType arg_type = check_expression_type(node->children[i], table);
                             DataType param_type = entry->param_types[i];
+                            if (entry->decl && entry->decl->children[i]->optional) {
+                                // An optional parameter takes null or a plain value
+                                if (arg_type == TYPE_NULL) continue;
+                            } else {
+                                char context[300];
+                                snprintf(context, sizeof(context), "an argument to '%s'", fn_name);
+                                require_non_optional(node->children[i], context);
+                            }
+                            {
+                                char context[300];
+                                snprintf(context, sizeof(context), "argument %d to '%s'", i + 1, fn_name);
+                                check_numeric_conversion(param_type, arg_type, node->children[i],
+                                                         context, node->line);
+                            }
                             if (param_type != TYPE_UNKNOWN && param_type != TYPE_AUTO &&
                                 !data_types_are_compatible(param_type, arg_type) &&
                                 arg_type != TYPE_UNKNOWN) {
@@ -514,6 +2223,15 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                                          data_type_to_string(arg_type));
                                 compile_error(error_msg, node->line);
                             }
+                            int length = static_array_length(node->children[i], table);
+                            int expected = entry->decl ? entry->decl->children[i]->array_length : -1;
+                            if (expected >= 0 && length >= 0 && length != expected) {
+                                char error_msg[512];
+                                snprintf(error_msg, sizeof(error_msg),
+                                         "Type error: Argument %d to '%s' expects an array of length %d, got %d element%s",
+                                         i + 1, fn_name, expected, length, length == 1 ? "" : "s");
+                                compile_error(error_msg, node->line);
+                            }
                         }
                     }
                 } else {
@@ -523,11 +2241,14 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 }
 
                 node->data_type = entry->return_type != TYPE_UNKNOWN ? entry->return_type : TYPE_UNKNOWN;
+                node->tuple = entry->tuple;
+                node->optional = entry->decl && entry->decl->optional;
                 return node->data_type;
             }
             
         case AST_ARRAY_LITERAL:
             node->data_type = TYPE_ARRAY;
+            if (node->right) fold_array_repeat(node, table);
             if (node->child_count > 0 && node->children) {
                 DataType elem_type = check_expression_type(node->children[0], table);
                 for (int i = 1; i < node->child_count; i++) {
@@ -544,6 +2265,12 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             }
             return TYPE_ARRAY;
             
+        case AST_RANGE_EXPR:
+            // Outside a for loop a range is the array of the ints it counts
+            check_range_bounds(node, table);
+            node->data_type = TYPE_ARRAY;
+            return TYPE_ARRAY;
+            
         case AST_OBJECT_LITERAL:
             node->data_type = TYPE_OBJECT;
             if (node->children) {
@@ -559,6 +2286,8 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
         case AST_ARRAY_ACCESS:
             left_type = check_expression_type(node->left, table);
             right_type = check_expression_type(node->right, table);
+            require_non_optional(node->left, "an indexed value");
+            require_non_optional(node->right, "an index");
             
             if (left_type != TYPE_ARRAY && left_type != TYPE_STRING && left_type != TYPE_UNKNOWN) {
                 char error_msg[512];
@@ -576,6 +2305,32 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 compile_error(error_msg, node->line);
             }
             
+            // Indices are never negative, and stay below a known length;
+            // catch the constant case now
+            {
+                ConstValue index;
+                int length = left_type == TYPE_ARRAY ? static_array_length(node->left, table) : -1;
+                // A string literal holds at least as many bytes as characters,
+                // so an index past its bytes is out of range on every target
+                bool string_literal = node->left->type == AST_LITERAL && node->left->data_type == TYPE_STRING &&
+                                      ast_literal_is_verbatim(node->left);
+                if (string_literal) length = (int)strlen(node->left->value);
+                if (const_eval(node->right, &index) && index.type == TYPE_INT && index.iv < 0) {
+                    char error_msg[128];
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Array index %lld is negative", index.iv);
+                    compile_error(error_msg, node->line);
+                } else if (length >= 0 && const_eval(node->right, &index) && index.type == TYPE_INT &&
+                           index.iv >= length) {
+                    char error_msg[160];
+                    snprintf(error_msg, sizeof(error_msg),
+                             string_literal ? "String index %lld is out of bounds for a string of length %d"
+                                            : "Array index %lld is out of bounds for an array of length %d",
+                             index.iv, length);
+                    compile_error(error_msg, node->line);
+                }
+            }
+            
             // For string indexing, result is string; for array, element type (unknown for now)
             node->data_type = (left_type == TYPE_STRING) ? TYPE_STRING : TYPE_UNKNOWN;
             return node->data_type;
@@ -585,6 +2340,46 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             node->data_type = TYPE_UNKNOWN;
             return TYPE_UNKNOWN;
             
+        case AST_CAST_EXPR:
+            // Legal conversions: int <-> float, string -> int/float (parse),
+            // int/bool -> bool, bool -> int, anything -> string
+            right_type = check_expression_type(node->right, table);
+            {
+                char context[64];
+                snprintf(context, sizeof(context), "the operand of '%s(...)'",
+                         node->value ? node->value : "cast");
+                require_non_optional(node->right, context);
+            }
+            {
+                bool legal = true;
+                if (right_type != TYPE_UNKNOWN && right_type != TYPE_AUTO) {
+                    switch (node->data_type) {
+                        case TYPE_INT:
+                            legal = right_type == TYPE_INT || right_type == TYPE_FLOAT ||
+                                    right_type == TYPE_STRING || right_type == TYPE_BOOL;
+                            break;
+                        case TYPE_FLOAT:
+                            legal = right_type == TYPE_INT || right_type == TYPE_FLOAT ||
+                                    right_type == TYPE_STRING;
+                            break;
+                        case TYPE_BOOL:
+                            legal = right_type == TYPE_BOOL || right_type == TYPE_INT;
+                            break;
+                        default:
+                            legal = true;
+                            break;
+                    }
+                }
+                if (!legal) {
+                    char error_msg[512];
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Type error: Cannot convert %s to %s",
+                             data_type_to_string(right_type), data_type_to_string(node->data_type));
+                    compile_error(error_msg, node->line);
+                }
+            }
+            return node->data_type;
+            
         case AST_TERNARY_EXPR:
             if (!node->condition) {
                 compile_error("Ternary expression missing condition", node->line);
@@ -592,6 +2387,7 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
             }
             
             result_type = check_expression_type(node->condition, table);
+            require_non_optional(node->condition, "a condition");
             if (result_type != TYPE_BOOL && result_type != TYPE_UNKNOWN) {
                 char error_msg[512];
                 snprintf(error_msg, sizeof(error_msg),
@@ -612,8 +2408,28 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
                 compile_error(error_msg, node->line);
             }
             
-            node->data_type = left_type;
-            return left_type;
+            node->data_type = branch_result_type(left_type, right_type);
+            node->optional = node->left->optional || node->right->optional;
+            return node->data_type;
+
+        case AST_UNWRAP_EXPR:
+            // x? is the value of x; the runtime stops if it is null
+            node->data_type = check_expression_type(node->left, table);
+            node->optional = false;
+            return node->data_type;
+
+        case AST_MATCH_EXPR:
+            return check_match(node, table);
+
+        case AST_TUPLE_EXPR:
+            // A tuple is its own type description
+            for (int i = 0; i < node->child_count; i++) {
+                check_expression_type(node->children[i], table);
+                require_non_optional(node->children[i], "a tuple element");
+            }
+            node->data_type = TYPE_TUPLE;
+            node->tuple = node;
+            return TYPE_TUPLE;
             
         default:
             node->data_type = TYPE_UNKNOWN;
@@ -625,6 +2441,72 @@ static DataType check_expression_type(ASTNode *node, LocalSymbolTable *table) {
 // Statement Type Checking
 // ========================================
 
+// Exit codes, from #exit or a top-level return, are ints in 0-255
+static void check_exit_code(ASTNode *expr, DataType type, int line) {
+    char error_msg[256];
+    if (type != TYPE_INT && type != TYPE_UNKNOWN && type != TYPE_AUTO) {
+        snprintf(error_msg, sizeof(error_msg),
+                 "Type error: Exit code must be int, got %s", data_type_to_string(type));
+        compile_error(error_msg, line);
+        return;
+    }
+    ConstValue code;
+    if (const_eval(expr, &code) && code.type == TYPE_INT && (code.iv < 0 || code.iv > 255)) {
+        snprintf(error_msg, sizeof(error_msg), "Exit code %lld is out of range 0-255", code.iv);
+        compile_error(error_msg, line);
+    }
+}
+
+// Check default parameter values and return how many parameters a call
+// must pass. Defaults must be constant and may only be given to trailing
+// parameters.
+static int check_param_defaults(ASTNode *func, LocalSymbolTable *table) {
+    int required = -1;
+    for (int i = 0; i < func->child_count; i++) {
+        ASTNode *param = func->children[i];
+        if (!param) continue;
+        if (!param->right) {
+            if (required >= 0) {
+                char error_msg[256];
+                snprintf(error_msg, sizeof(error_msg),
+                         "Parameter '%s' without a default follows a parameter with one",
+                         param->value ? param->value : "?");
+                compile_error(error_msg, param->line);
+            }
+            continue;
+        }
+        if (required < 0) required = i;
+
+        DataType default_type = check_expression_type(param->right, table);
+        ConstValue folded;
+        if (param->right->type != AST_LITERAL && !const_eval(param->right, &folded)) {
+            char error_msg[256];
+            snprintf(error_msg, sizeof(error_msg),
+                     "Default value for parameter '%s' must be a constant expression",
+                     param->value ? param->value : "?");
+            compile_error(error_msg, param->right->line);
+            continue;
+        }
+        DataType param_type = param->data_type;
+        if (param_type == TYPE_UNKNOWN && param->metadata) {
+            param_type = data_type_from_string(param->metadata);
+        }
+        if (param_type == TYPE_UNKNOWN && default_type != TYPE_NULL) {
+            // An untyped parameter takes the type of its default
+            param->data_type = default_type;
+        } else if (param_type != TYPE_UNKNOWN && param_type != TYPE_AUTO &&
+                   !data_types_are_compatible(param_type, default_type)) {
+            char error_msg[512];
+            snprintf(error_msg, sizeof(error_msg),
+                     "Type error: Default value for parameter '%s' expects %s, got %s",
+                     param->value ? param->value : "?",
+                     data_type_to_string(param_type), data_type_to_string(default_type));
+            compile_error(error_msg, param->right->line);
+        }
+    }
+    return required < 0 ? func->child_count : required;
+}
+
 static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSymbolEntry *current_function) {
     if (!node) return;
     
@@ -639,39 +2521,131 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                 return;
             }
             
-            entry = lookup_symbol_entry(table, node->value);
-            if (entry && entry->scope_level == table->current_scope) {
-                char error_msg[256];
-                snprintf(error_msg, sizeof(error_msg),
-                         "Variable '%s' already declared in this scope",
-                         node->value);
-                compile_error(error_msg, node->line);
-                return;
-            }
+            if (report_redeclaration(table, node->value, node)) return;
+            check_shadowing(table, node->value, node);
             
             // Add symbol to table
             {
                 DataType declared = node->data_type != TYPE_UNKNOWN ? node->data_type : TYPE_AUTO;
-                add_symbol(table, node->value, NULL, declared);
+                entry = add_symbol(table, node->value, NULL, declared, node);
+                if (entry) {
+                    entry->array_length = node->array_length;
+                    entry->is_variable = true;
+                    entry->is_optional = node->optional;
+                    // An optional without an initializer starts out null
+                    entry->is_initialized = node->optional;
+                }
             }
             
             // Check initializer if present
             if (node->right) {
                 expr_type = check_expression_type(node->right, table);
                 
+                if (node->optional) {
+                    // `var x: int? = null` keeps its declared type
+                    if (expr_type == TYPE_NULL)
+                        expr_type = node->data_type != TYPE_UNKNOWN ? node->data_type : TYPE_AUTO;
+                } else if (expr_type == TYPE_NULL && node->data_type != TYPE_UNKNOWN &&
+                           node->data_type != TYPE_AUTO) {
+                    char error_msg[512];
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Type error: Cannot assign null to non-optional %s '%s'; declare it as %s?",
+                             data_type_to_string(node->data_type), node->value,
+                             data_type_to_string(node->data_type));
+                    compile_error(error_msg, node->line);
+                } else if (node->right->optional) {
+                    if (node->data_type != TYPE_UNKNOWN) {
+                        char context[300];
+                        snprintf(context, sizeof(context), "the initializer of non-optional '%s'", node->value);
+                        require_non_optional(node->right, context);
+                    } else {
+                        // `var y = find(...)` is optional like its initializer
+                        node->optional = true;
+                        entry = lookup_symbol_entry(table, node->value);
+                        if (entry) entry->is_optional = true;
+                    }
+                }
+                
+                // Annotated declarations keep their declared numeric type
+                if (node->data_type == TYPE_INT || node->data_type == TYPE_FLOAT) {
+                    char context[300];
+                    snprintf(context, sizeof(context), "initializer of '%s'", node->value);
+                    check_numeric_conversion(node->data_type, expr_type, node->right, context, node->line);
+                    if (expr_type == TYPE_INT || expr_type == TYPE_FLOAT) expr_type = node->data_type;
+                }
+                
+                // [T; N] fixes the length; a const array literal keeps its own
+                int length = static_array_length(node->right, table);
+                if (node->array_length >= 0 && length >= 0 && length != node->array_length) {
+                    char error_msg[512];
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Type error: Cannot initialize '%s' of length %d with %d element%s",
+                             node->value, node->array_length, length, length == 1 ? "" : "s");
+                    compile_error(error_msg, node->line);
+                }
+                
                 // Update the symbol's type
                 entry = lookup_symbol_entry(table, node->value);
                 if (entry) {
+                    if (node->array_length >= 0) entry->array_length = node->array_length;
+                    else if (node->type == AST_CONST_DECL) entry->array_length = length;
+                    entry->element_type = static_element_type(node->right, table);
                     entry->data_type = expr_type;
+                    entry->tuple = node->right->tuple;
                     entry->is_initialized = true;
                     entry->is_constant = (node->type == AST_CONST_DECL);
+                    if (entry->is_constant) entry->decl = node;
                     node->data_type = expr_type;
+                    node->tuple = node->right->tuple;
                 }
             } else if (node->type == AST_CONST_DECL) {
                 compile_error("Const declaration requires initializer", node->line);
             }
             break;
             
+        case AST_DESTRUCTURE_DECL: {
+            // var (a, b) = t declares one variable per element of t
+            if (!node->right) {
+                compile_error("Destructuring declaration requires an initializer", node->line);
+                return;
+            }
+            expr_type = check_expression_type(node->right, table);
+            require_non_optional(node->right, "a destructuring declaration");
+            ASTNode *tuple = expr_type == TYPE_TUPLE ? node->right->tuple : NULL;
+            if (expr_type != TYPE_TUPLE && expr_type != TYPE_UNKNOWN) {
+                char error_msg[512];
+                snprintf(error_msg, sizeof(error_msg),
+                         "Type error: Cannot destructure %s; only a tuple can be unpacked into variables",
+                         data_type_to_string(expr_type));
+                compile_error(error_msg, node->line);
+            } else if (tuple && tuple->child_count != node->child_count) {
+                char type_name[256], error_msg[600];
+                tuple_type_name(tuple, type_name, sizeof(type_name));
+                snprintf(error_msg, sizeof(error_msg),
+                         "Type error: Cannot destructure a %d-element tuple %s into %d variables",
+                         tuple->child_count, type_name, node->child_count);
+                compile_error(error_msg, node->line);
+                tuple = NULL;
+            }
+
+            for (int i = 0; i < node->child_count; i++) {
+                ASTNode *target = node->children[i];
+                if (report_redeclaration(table, target->value, target)) continue;
+                check_shadowing(table, target->value, target);
+                ASTNode *elem = tuple ? tuple->children[i] : NULL;
+                target->data_type = elem ? elem->data_type : TYPE_UNKNOWN;
+                target->tuple = elem ? elem->tuple : NULL;
+                entry = add_symbol(table, target->value, NULL, target->data_type, target);
+                if (entry) {
+                    entry->tuple = target->tuple;
+                    entry->is_variable = true;
+                    entry->is_initialized = true;
+                    entry->is_constant = (target->type == AST_CONST_DECL);
+                }
+            }
+            break;
+        }
+
         case AST_ASSIGN_STMT:
             if (!node->left) {
                 compile_error("Assignment missing target", node->line);
@@ -681,9 +2655,10 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             if (node->left->type == AST_IDENTIFIER) {
                 entry = lookup_symbol(table, node->left->value);
                 if (!entry) {
-                    entry = add_symbol(table, node->left->value, NULL, TYPE_AUTO);
+                    entry = add_symbol(table, node->left->value, NULL, TYPE_AUTO, node->left);
                     if (entry) {
                         entry->is_initialized = true;
+                        entry->is_variable = true;
                     }
                     node->type = AST_VAR_DECL;
                     node->value = strdup(node->left->value);
@@ -693,6 +2668,10 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                         if (entry) {
                             entry->data_type = expr_type;
                             node->data_type = expr_type;
+                            if (node->right->optional && expr_type != TYPE_NULL) {
+                                entry->is_optional = true;
+                                node->optional = true;
+                            }
                         }
                     }
                     break;
@@ -714,9 +2693,27 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             }
             
             expr_type = check_expression_type(node->right, table);
+
+            if (entry && entry->is_optional) {
+                // The target may hold null; a possibly-null value undoes a check
+                node->optional = true;
+                if (expr_type == TYPE_NULL || node->right->optional) entry->is_narrowed = false;
+                if (expr_type == TYPE_NULL) {
+                    node->data_type = entry->data_type;
+                    break;
+                }
+            } else if (entry) {
+                char context[300];
+                snprintf(context, sizeof(context), "the value assigned to non-optional '%s'", node->left->value);
+                require_non_optional(node->right, context);
+            }
             
             // Check type compatibility
             if (entry) {
+                check_self_assignment(node, entry->data_type);
+                char context[300];
+                snprintf(context, sizeof(context), "assignment to '%s'", node->left->value);
+                check_numeric_conversion(entry->data_type, expr_type, node->right, context, node->line);
                 if (!data_types_are_compatible(entry->data_type, expr_type) &&
                     entry->data_type != TYPE_AUTO && expr_type != TYPE_UNKNOWN) {
                     char error_msg[512];
@@ -726,6 +2723,27 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                     compile_error(error_msg, node->line);
                     return;
                 }
+                if (entry->array_length >= 0) {
+                    int length = static_array_length(node->right, table);
+                    if (length >= 0 && length != entry->array_length) {
+                        char error_msg[512];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: Cannot assign %d element%s to '%s' of length %d",
+                                 length, length == 1 ? "" : "s", node->left->value, entry->array_length);
+                        compile_error(error_msg, node->line);
+                        return;
+                    }
+                }
+                if (expr_type == TYPE_TUPLE && entry->tuple &&
+                    !tuple_types_match(entry->tuple, node->right->tuple)) {
+                    char expected[256], actual[256], error_msg[600];
+                    tuple_type_name(entry->tuple, expected, sizeof(expected));
+                    tuple_type_name(node->right->tuple, actual, sizeof(actual));
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Type error: Cannot assign %s to variable of type %s", actual, expected);
+                    compile_error(error_msg, node->line);
+                    return;
+                }
             }
             
             // Update variable type if it was auto
@@ -733,6 +2751,11 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                 if (entry->data_type == TYPE_AUTO) {
                     entry->data_type = expr_type;
                 }
+                // Keep the element type only while every array assigned agrees
+                if (entry->element_type != static_element_type(node->right, table)) {
+                    entry->element_type = TYPE_UNKNOWN;
+                }
+                if (!entry->tuple) entry->tuple = node->right->tuple;
                 entry->is_initialized = true;
             }
             node->data_type = expr_type;
@@ -745,6 +2768,7 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             }
             
             expr_type = check_expression_type(node->condition, table);
+            require_non_optional(node->condition, "a condition");
             if (expr_type != TYPE_BOOL && expr_type != TYPE_UNKNOWN) {
                 char error_msg[512];
                 snprintf(error_msg, sizeof(error_msg),
@@ -753,8 +2777,21 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                 compile_error(error_msg, node->line);
             }
             
-            check_statement_type(node->body, table, current_function);
-            if (node->right) check_statement_type(node->right, table, current_function);  // else/elif
+            {
+                // `if x != null` proves x non-null in the body, `if x == null`
+                // in the else branch
+                LocalSymbolEntry *narrow = null_checked_entry(node->condition, table, true);
+                bool was_narrowed = narrow && narrow->is_narrowed;
+                if (narrow) narrow->is_narrowed = true;
+                check_statement_type(node->body, table, current_function);
+                if (narrow) narrow->is_narrowed = was_narrowed;
+
+                narrow = null_checked_entry(node->condition, table, false);
+                was_narrowed = narrow && narrow->is_narrowed;
+                if (narrow) narrow->is_narrowed = true;
+                if (node->right) check_statement_type(node->right, table, current_function);  // else/elif
+                if (narrow) narrow->is_narrowed = was_narrowed;
+            }
             break;
             
         case AST_WHILE_STMT:
@@ -765,6 +2802,7 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             }
             
             expr_type = check_expression_type(node->condition, table);
+            require_non_optional(node->condition, "a condition");
             if (expr_type != TYPE_BOOL && expr_type != TYPE_UNKNOWN) {
                 char error_msg[512];
                 snprintf(error_msg, sizeof(error_msg),
@@ -774,61 +2812,145 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             }
             
             check_statement_type(node->body, table, current_function);
+            check_infinite_loop(node, table);
             break;
             
-        case AST_FOR_STMT:
-            enter_scope(table);
+        case AST_FOR_STMT: {
+            // range(...) counts in ints; an array gives its elements and a
+            // string its one-character strings. The iterable is checked
+            // before the loop variable is in scope.
+            DataType elem_type = TYPE_INT;
+            if (node->children && node->child_count > 0) {
+                ASTNode *range = node->children[0];
+                if (range && range->type == AST_RANGE_EXPR) check_range_bounds(range, table);
+            } else if (node->condition) {
+                expr_type = check_expression_type(node->condition, table);
+                require_non_optional(node->condition, "the value a for loop iterates over");
+                if (expr_type == TYPE_ARRAY) {
+                    elem_type = static_element_type(node->condition, table);
+                } else if (expr_type == TYPE_STRING) {
+                    elem_type = TYPE_STRING;
+                } else if (expr_type == TYPE_UNKNOWN || expr_type == TYPE_AUTO) {
+                    elem_type = TYPE_UNKNOWN;
+                } else {
+                    char error_msg[512];
+                    snprintf(error_msg, sizeof(error_msg),
+                             "Type error: Cannot iterate over %s; a for loop needs an array, a string or range(...)",
+                             data_type_to_string(expr_type));
+                    semantic_report_error_at(error_msg, node->condition);
+                    elem_type = TYPE_UNKNOWN;
+                }
+            }
+
+            enter_scope(table, node);
 
             if (node->value) {
-                LocalSymbolEntry *loop_var = add_symbol(table, node->value, NULL, TYPE_INT);
+                check_shadowing(table, node->value, node);
+                LocalSymbolEntry *loop_var = add_symbol(table, node->value, NULL, elem_type, node);
                 if (loop_var) {
                     loop_var->is_initialized = true;
                 }
             }
 
-            if (node->children && node->child_count > 0) {
-                ASTNode *range = node->children[0];
-                if (range && range->type == AST_RANGE_EXPR) {
-                    if (range->left) check_expression_type(range->left, table);
-                    if (range->right) check_expression_type(range->right, table);
-                }
-            } else if (node->condition) {
-                check_expression_type(node->condition, table);
-            }
-
             check_statement_type(node->body, table, current_function);
             exit_scope(table);
             break;
+        }
             
         case AST_RETURN_STMT:
             // Would need to check against function return type
             if (node->right) {
                 DataType return_type = check_expression_type(node->right, table);
+                ASTNode *func_decl = current_function ? current_function->decl : NULL;
+                if (func_decl && !func_decl->optional && func_decl->data_type == TYPE_UNKNOWN &&
+                    (node->right->optional || return_type == TYPE_NULL)) {
+                    // An untyped function returning an optional is optional itself
+                    func_decl->optional = true;
+                }
+                if (func_decl && func_decl->optional) {
+                    node->optional = true;
+                    if (return_type == TYPE_NULL) break;
+                } else if (current_function) {
+                    char context[300];
+                    snprintf(context, sizeof(context), "the return value of non-optional '%s'",
+                             current_function->name);
+                    require_non_optional(node->right, context);
+                }
                 if (current_function) {
+                    // A (T, ...) annotation is checked as written
+                    char actual[256] = "";
+                    if (return_type == TYPE_TUPLE && node->right->tuple) {
+                        tuple_type_name(node->right->tuple, actual, sizeof(actual));
+                    }
+                    if (*actual && func_decl && func_decl->data_type == TYPE_TUPLE && func_decl->metadata &&
+                        strcmp(actual, func_decl->metadata) != 0) {
+                        char error_msg[600];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: Return type %s does not match function return type %s",
+                                 actual, (const char *)func_decl->metadata);
+                        compile_error(error_msg, node->line);
+                    } else if (return_type == TYPE_TUPLE && !current_function->tuple) {
+                        // The first tuple returned fixes the function's tuple type
+                        current_function->tuple = node->right->tuple;
+                        if (func_decl) func_decl->tuple = node->right->tuple;
+                    } else if (return_type == TYPE_TUPLE &&
+                               !tuple_types_match(current_function->tuple, node->right->tuple)) {
+                        char expected[256], actual[256], error_msg[600];
+                        tuple_type_name(current_function->tuple, expected, sizeof(expected));
+                        tuple_type_name(node->right->tuple, actual, sizeof(actual));
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: Return type %s does not match function return type %s",
+                                 actual, expected);
+                        compile_error(error_msg, node->line);
+                    }
+                    int length = static_array_length(node->right, table);
+                    if (func_decl && func_decl->array_length >= 0 && length >= 0 &&
+                        length != func_decl->array_length) {
+                        char error_msg[512];
+                        snprintf(error_msg, sizeof(error_msg),
+                                 "Type error: '%s' returns an array of length %d, not %d element%s",
+                                 current_function->name, func_decl->array_length, length, length == 1 ? "" : "s");
+                        compile_error(error_msg, node->line);
+                    }
                     if (current_function->return_type == TYPE_UNKNOWN || current_function->return_type == TYPE_AUTO) {
                         current_function->return_type = return_type;
                     } else if (!data_types_are_compatible(current_function->return_type, return_type) &&
                                return_type != TYPE_UNKNOWN) {
                         char error_msg[512];
+                        bool annotated = func_decl && func_decl->data_type == TYPE_TUPLE && func_decl->metadata;
                         snprintf(error_msg, sizeof(error_msg),
                                  "Type error: Return type %s does not match function return type %s",
                                  data_type_to_string(return_type),
-                                 data_type_to_string(current_function->return_type));
+                                 annotated ? (const char *)func_decl->metadata
+                                           : data_type_to_string(current_function->return_type));
                         compile_error(error_msg, node->line);
                     }
                 }
+                if (!current_function) {
+                    // A top-level return ends the program like #exit
+                    check_exit_code(node->right, return_type, node->line);
+                }
             } else if (current_function && current_function->return_type == TYPE_UNKNOWN) {
                 current_function->return_type = TYPE_VOID;
             }
             break;
+
+        case AST_EXIT_STMT:
+            if (node->right) {
+                check_exit_code(node->right, check_expression_type(node->right, table), node->line);
+            }
+            break;
             
         case AST_FUNCTION_DECL:
-            if (node->value) {
-                LocalSymbolEntry *func_entry = add_symbol(table, node->value, "function", TYPE_FUNCTION);
+            check_function_attributes(node);
+            if (node->value && !report_redeclaration(table, node->value, node)) {
+                LocalSymbolEntry *func_entry = add_symbol(table, node->value, "function", TYPE_FUNCTION, node);
                 if (func_entry) {
                     func_entry->is_function = true;
                     func_entry->return_type = node->data_type != TYPE_UNKNOWN ? node->data_type : TYPE_UNKNOWN;
                     func_entry->param_count = node->child_count;
+                    func_entry->required_count = check_param_defaults(node, table);
+                    func_entry->decl = node;
                     if (node->child_count > 0) {
                         func_entry->param_types = calloc(node->child_count, sizeof(DataType));
                         if (!func_entry->param_types) {
@@ -848,15 +2970,19 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                     }
                 }
 
-                enter_scope(table);
+                enter_scope(table, node);
                 if (node->children) {
                     for (int i = 0; i < node->child_count; i++) {
                         ASTNode *param = node->children[i];
                         if (param && param->value) {
+                            check_shadowing(table, param->value, param);
                             LocalSymbolEntry *param_entry = add_symbol(table, param->value, NULL,
-                                                                       param->data_type != TYPE_UNKNOWN ? param->data_type : TYPE_AUTO);
+                                                                       param->data_type != TYPE_UNKNOWN ? param->data_type : TYPE_AUTO,
+                                                                       param);
                             if (param_entry) {
+                                param_entry->array_length = param->array_length;
                                 param_entry->is_initialized = true;
+                                param_entry->is_parameter = true;
                             }
                         }
                     }
@@ -866,14 +2992,16 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
                     check_statement_type(node->body, table, func_entry);
                 }
                 exit_scope(table);
+                check_inline_body(node);
             }
             break;
             
         case AST_BLOCK:
-            enter_scope(table);
+            enter_scope(table, node);
             {
                 ASTNode *child = node->body ? node->body : (node->children ? node->children[0] : NULL);
                 while (child) {
+                    check_discarded_value(child);
                     check_statement_type(child, table, current_function);
                     child = child->next;
                 }
@@ -884,10 +3012,13 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
         case AST_PROGRAM:
             {
                 ASTNode *stmt = node->body ? node->body : (node->children ? node->children[0] : NULL);
-                while (stmt) {
+                for (int i = 0; stmt; i++) {
+                    g_in_prelude_item = i < g_prelude_item_count + g_checked_item_count && !g_checking_prelude;
+                    if (!(g_echo_last && !g_checking_prelude && !stmt->next)) check_discarded_value(stmt);
                     check_statement_type(stmt, table, current_function);
                     stmt = stmt->next;
                 }
+                g_in_prelude_item = false;
             }
             break;
             
@@ -895,6 +3026,24 @@ static void check_statement_type(ASTNode *node, LocalSymbolTable *table, LocalSy
             /* Type-check function call arguments in expression context */
             check_expression_type(node, table);
             break;
+
+        case AST_STATIC_ASSERT:
+            g_in_static_assert = true;
+            check_expression_type(node->condition, table);
+            g_in_static_assert = false;
+            {
+                ConstValue value;
+                if (!const_eval_in(node->condition, table, &value) || value.type != TYPE_BOOL) {
+                    compile_error("#static_assert condition is not a compile-time constant boolean",
+                                  node->line);
+                } else if (!value.bv) {
+                    char error_msg[512];
+                    snprintf(error_msg, sizeof(error_msg), "Static assertion failed: %s",
+                             node->value ? node->value : "");
+                    compile_error(error_msg, node->line);
+                }
+            }
+            break;
             
         default:
             // Recursively check child nodes
@@ -946,6 +3095,339 @@ static int analyze_node(ASTNode *node, LocalSymbolTable *table) {
 // Main Entry Points
 // ========================================
 
+// --crate-type lib: a library has no entry point to run top-level code in,
+// so only functions, constants with compile-time values and static
+// assertions may appear outside a function
+static void check_library_items(ASTNode *ast) {
+    ASTNode *stmt = ast->body ? ast->body : (ast->children ? ast->children[0] : NULL);
+    char msg[256];
+    for (; stmt; stmt = stmt->next) {
+        if (stmt->type == AST_FUNCTION_DECL || stmt->type == AST_STATIC_ASSERT) continue;
+        if (stmt->type == AST_CONST_DECL) {
+            ConstValue folded;
+            ASTNode *value = stmt->right;
+            if (!value || (!(value->type == AST_LITERAL && value->value) && !const_eval(value, &folded))) {
+                snprintf(msg, sizeof(msg),
+                         "Constant '%s' in a library must have a value known at compile time",
+                         stmt->value ? stmt->value : "?");
+                compile_error(msg, stmt->line);
+            }
+            continue;
+        }
+        compile_error("Top-level statements are not allowed with --crate-type lib; "
+                      "move them into a function", stmt->line);
+    }
+}
+
+// Mark every top-level function that `node` names (calls it or uses it as
+// a value), other than `self`; newly reached ones are queued on `queue`
+typedef struct {
+    ASTNode **funcs;
+    int count;
+    int self;
+    bool *marked;
+    int *queue;
+    int *queued;
+} FunctionRefs;
+
+static bool mark_function_ref(ASTVisitor *visitor, ASTNode *node) {
+    FunctionRefs *refs = visitor->data;
+    if (!node->value) return true;
+    for (int i = 0; i < refs->count; i++) {
+        if (i != refs->self && !refs->marked[i] && strcmp(refs->funcs[i]->value, node->value) == 0) {
+            refs->marked[i] = true;
+            if (refs->queue) refs->queue[(*refs->queued)++] = i;
+        }
+    }
+    return true;
+}
+
+static void mark_function_refs(ASTNode *node, ASTNode **funcs, int count, int self,
+                               bool *marked, int *queue, int *queued) {
+    FunctionRefs refs = {funcs, count, self, marked, queue, queued};
+    ASTVisitor marker = { .data = &refs };
+    marker.visit[AST_CALL_EXPR] = mark_function_ref;
+    marker.visit[AST_IDENTIFIER] = mark_function_ref;
+    ast_walk(node, &marker);
+}
+
+// -Wunused-function: a function is live if the top-level code reaches it,
+// directly or through other live functions. Every function of a library is
+// exported, so none of them is reported; names with a leading underscore
+// are deliberately unused, as for variables.
+static void report_unused_functions(ASTNode *ast) {
+    if (!g_lints[LINT_UNUSED_FUNCTION].enabled || crate_type() == CRATE_TYPE_LIB) return;
+    ASTNode *first = ast->body ? ast->body : (ast->children ? ast->children[0] : NULL);
+    int count = 0;
+    for (ASTNode *stmt = first; stmt; stmt = stmt->next) {
+        if (stmt->type == AST_FUNCTION_DECL && stmt->value) count++;
+    }
+    if (count == 0) return;
+    ASTNode **funcs = malloc(count * sizeof(ASTNode *));
+    bool *live = calloc(count, sizeof(bool));
+    bool *named = calloc(count, sizeof(bool));
+    int *queue = malloc(count * sizeof(int));
+    if (funcs && live && named && queue) {
+        int n = 0, queued = 0;
+        for (ASTNode *stmt = first; stmt; stmt = stmt->next) {
+            if (stmt->type == AST_FUNCTION_DECL && stmt->value) funcs[n++] = stmt;
+        }
+        for (ASTNode *stmt = first; stmt; stmt = stmt->next) {
+            if (stmt->type != AST_FUNCTION_DECL) mark_function_refs(stmt, funcs, count, -1, live, queue, &queued);
+        }
+        for (int q = 0; q < queued; q++) {
+            int f = queue[q];
+            mark_function_refs(funcs[f], funcs, count, f, live, queue, &queued);
+        }
+        // Which dead functions are still called, from other dead ones
+        for (int i = 0; i < count; i++) {
+            if (!live[i]) mark_function_refs(funcs[i], funcs, count, i, named, NULL, NULL);
+        }
+        for (int i = 0; i < count; i++) {
+            if (live[i] || funcs[i]->value[0] == '_') continue;
+            char msg[300];
+            snprintf(msg, sizeof(msg), named[i] ? "Function '%s' is only called from functions that are never called"
+                                                : "Function '%s' is never called", funcs[i]->value);
+            semantic_report_warning(LINT_UNUSED_FUNCTION, msg, funcs[i]->line);
+        }
+    }
+    free(funcs);
+    free(live);
+    free(named);
+    free(queue);
+}
+
+static bool is_local_decl_of(const ASTNode *node, const void *name) {
+    return (node->type == AST_PARAM_DECL || node->type == AST_VAR_DECL || node->type == AST_CONST_DECL ||
+            node->type == AST_FOR_STMT) && node->value && strcmp(node->value, name) == 0;
+}
+
+typedef struct {
+    const char *from;
+    const char *to;
+} Rename;
+
+// A function that declares the name itself keeps its own meaning of it
+static bool rename_in_function(ASTVisitor *visitor, ASTNode *node) {
+    const Rename *rename = visitor->data;
+    return !ast_find(node, is_local_decl_of, rename->from);
+}
+
+static bool rename_ref(ASTVisitor *visitor, ASTNode *node) {
+    const Rename *rename = visitor->data;
+    if (node->value && strcmp(node->value, rename->from) == 0) {
+        char *to = strdup(rename->to);
+        if (to) {
+            free(node->value);
+            node->value = to;
+        }
+    }
+    return true;
+}
+
+static bool top_level_declares(const ASTNode *block, int count, const char *name) {
+    for (int i = 0; i < count; i++) {
+        const ASTNode *stmt = block->children[i];
+        if (stmt && (stmt->type == AST_FUNCTION_DECL || stmt->type == AST_CONST_DECL ||
+                     stmt->type == AST_VAR_DECL) && stmt->value && strcmp(stmt->value, name) == 0) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// Give a shadowed prelude item that other prelude items still use a name
+// of its own, prelude_<name>, and point those uses at it
+static void rename_shadowed_item(ASTNode *item, ASTNode **items, int n, const ASTNode *ast,
+                                 const ASTNode *prelude) {
+    char name[300];
+    snprintf(name, sizeof(name), "prelude_%s", item->value);
+    for (int k = 2; top_level_declares(ast, ast->child_count, name) ||
+                    top_level_declares(prelude, prelude->child_count, name); k++) {
+        snprintf(name, sizeof(name), "prelude_%s_%d", item->value, k);
+    }
+    char *from = item->value;
+    Rename rename = {from, name};
+    ASTVisitor renamer = { .data = &rename };
+    renamer.visit[AST_FUNCTION_DECL] = rename_in_function;
+    renamer.visit[AST_ARROW_FUNCTION] = rename_in_function;
+    renamer.visit[AST_CALL_EXPR] = rename_ref;
+    renamer.visit[AST_IDENTIFIER] = rename_ref;
+    for (int i = 0; i < n; i++) ast_walk(items[i], &renamer);
+    item->value = strdup(name);
+    if (item->value) free(from);
+    else item->value = from;
+}
+
+// --prelude: put the prelude's functions and constants that the program
+// reaches in front of its statements, so every backend sees them. The
+// prelude is checked by itself first, so its errors point into its own
+// text. A top-level name the program declares shadows the prelude's for
+// the program; prelude items that use the prelude's own definition keep
+// it, under a name of its own.
+static void add_prelude(ASTNode *ast) {
+    const char *path = prelude_file();
+    if (!path || ast->type != AST_PROGRAM) return;
+    const char *program_source = diagnostic_source();
+    int errors = diagnostic_error_count();
+    char *source;
+    ASTNode *prelude = prelude_parse(&source);
+    char msg[600];
+    if (!prelude || diagnostic_error_count() > errors) {
+        snprintf(msg, sizeof(msg), prelude ? "Prelude '%s' has errors" : "Cannot read prelude '%s'", path);
+        semantic_report_program_error(msg);
+        diagnostic_set_source(program_source);
+        parser_free_ast(prelude);
+        free(source);
+        return;
+    }
+    for (int i = 0; i < prelude->child_count; i++) {
+        ASTNode *item = prelude->children[i];
+        if (item->type != AST_FUNCTION_DECL && item->type != AST_CONST_DECL) {
+            compile_error_with_col("A prelude may only declare functions and constants", item->line, item->column);
+        }
+    }
+    if (g_semantic_error_count == 0) {
+        // Warnings come from this check, against the prelude's own lines;
+        // the program's check leaves out the prelude items it takes in
+        bool was_dumping = g_dump_symbols;
+        g_dump_symbols = false;
+        ASTNode *copy = parser_clone_ast(prelude);
+        g_checking_prelude = true;
+        semantic_analyze(copy);
+        g_checking_prelude = false;
+        parser_free_ast(copy);
+        g_dump_symbols = was_dumping;
+    }
+    if (g_semantic_error_count > 0) {
+        snprintf(msg, sizeof(msg), "Prelude '%s' has errors", path);
+        semantic_report_program_error(msg);
+    }
+    diagnostic_set_source(program_source);
+
+    int count = prelude->child_count;
+    ASTNode **items = malloc((count ? count : 1) * sizeof(ASTNode *));
+    bool *reached = calloc(count ? count : 1, sizeof(bool));
+    bool *shadowed = calloc(count ? count : 1, sizeof(bool));
+    int *queue = malloc((count ? count : 1) * sizeof(int));
+    ASTNode **children = realloc(ast->children, sizeof(ASTNode *) * (ast->child_count + count + 1));
+    if (children) ast->children = children;
+    int n = 0;
+    if (g_semantic_error_count == 0 && items && reached && shadowed && queue && children) {
+        for (int i = 0; i < count; i++) {
+            ASTNode *item = prelude->children[i];
+            if (!item->value) continue;
+            shadowed[n] = top_level_declares(ast, ast->child_count, item->value);
+            items[n++] = item;
+        }
+        // The program's own uses of a shadowed name are of its definition,
+        // so only other prelude items can reach the prelude's
+        int queued = 0;
+        for (int i = 0; i < n; i++) reached[i] = shadowed[i];
+        for (int i = 0; i < ast->child_count; i++) {
+            mark_function_refs(ast->children[i], items, n, -1, reached, queue, &queued);
+        }
+        for (int i = 0; i < n; i++) reached[i] = reached[i] && !shadowed[i];
+        for (int q = 0; q < queued; q++) {
+            mark_function_refs(items[queue[q]], items, n, queue[q], reached, queue, &queued);
+        }
+        int kept = 0;
+        for (int i = 0; i < n; i++) {
+            if (!reached[i]) continue;
+            shadowed[kept] = shadowed[i];
+            items[kept++] = items[i];
+        }
+        n = kept;
+        for (int i = 0; i < n; i++) {
+            if (shadowed[i]) rename_shadowed_item(items[i], items, n, ast, prelude);
+        }
+    } else {
+        n = 0;
+    }
+
+    // Move the reached items out of the prelude's tree and in front of the
+    // program's statements, both in children and in the next chain
+    for (int i = 0; i < prelude->child_count; i++) {
+        prelude->children[i]->next = NULL;
+        for (int k = 0; k < n; k++) {
+            if (items[k] == prelude->children[i]) prelude->children[i] = NULL;
+        }
+    }
+    prelude->body = NULL;
+    if (n > 0) {
+        memmove(ast->children + n, ast->children, sizeof(ASTNode *) * ast->child_count);
+        ASTVisitor clear = { .enter = clear_node_id };
+        for (int k = 0; k < n; k++) {
+            ast->children[k] = items[k];
+            items[k]->next = k + 1 < n ? items[k + 1] : ast->body;
+            ast_walk(items[k], &clear);
+        }
+        ast->child_count += n;
+        ast->body = items[0];
+    }
+    g_prelude_item_count = n;
+    free(items);
+    free(reached);
+    free(shadowed);
+    free(queue);
+    parser_free_ast(prelude);
+    free(source);
+}
+
+// --entry: end the program with a call to the named top-level function, so
+// every backend and the interpreter run it after the top-level statements.
+// It must exist and take no arguments; whatever it returns is discarded.
+static void add_entry_call(ASTNode *ast) {
+    const char *name = entry_function();
+    if (!name || ast->type != AST_PROGRAM) return;
+    if (crate_type() == CRATE_TYPE_LIB) {
+        semantic_report_program_error("--entry cannot be used with --crate-type lib, which has no entry point");
+        return;
+    }
+    ASTNode *entry = NULL;
+    char available[512] = "";
+    int len = 0;
+    for (int i = 0; i < ast->child_count; i++) {
+        ASTNode *stmt = ast->children[i];
+        if (stmt->type != AST_FUNCTION_DECL || !stmt->value) continue;
+        if (strcmp(stmt->value, name) == 0) entry = stmt;
+        if (len < (int)sizeof(available)) {
+            len += snprintf(available + len, sizeof(available) - len, "%s%s", len ? ", " : "", stmt->value);
+        }
+    }
+    char msg[800];
+    if (!entry) {
+        if (len) snprintf(msg, sizeof(msg), "Entry function '%s' is not defined; the program defines: %s", name, available);
+        else snprintf(msg, sizeof(msg), "Entry function '%s' is not defined; the program defines no functions", name);
+        semantic_report_program_error(msg);
+        return;
+    }
+    int params = 0;
+    for (int i = 0; i < entry->child_count; i++) {
+        if (entry->children[i] && entry->children[i]->type == AST_PARAM_DECL) params++;
+    }
+    if (params > 0) {
+        snprintf(msg, sizeof(msg), "Entry function '%s' must take no parameters, but takes %d", name, params);
+        compile_error_with_col(msg, entry->line, entry->column);
+        return;
+    }
+
+    ASTNode *call = calloc(1, sizeof(ASTNode));
+    ASTNode **children = realloc(ast->children, sizeof(ASTNode *) * (ast->child_count + 1));
+    if (!call || !children || !(call->value = strdup(name))) {
+        free(call);
+        if (children) ast->children = children;
+        return;
+    }
+    call->type = AST_CALL_EXPR;
+    call->line = entry->line;
+    call->column = entry->column;
+    ast->children = children;
+    if (ast->child_count > 0) ast->children[ast->child_count - 1]->next = call;
+    else ast->body = call;
+    ast->children[ast->child_count++] = call;
+}
+
 int semantic_analyze(ASTNode *ast) {
     if (!ast) {
         fprintf(stderr, "Semantic error: NULL AST\n");
@@ -953,10 +3435,32 @@ int semantic_analyze(ASTNode *ast) {
     }
     
     g_semantic_error_count = 0;
+    g_semantic_warning_count = 0;
+    // A tree with syntax errors is missing pieces; checking it would only
+    // report their fallout (a discarded value, a wrong return type, ...)
+    if (parser_had_errors()) return 0;
     
+    clear_excluded_functions();
+    if (!g_checking_prelude) {
+        g_prelude_item_count = 0;
+        add_prelude(ast);
+        if (g_semantic_error_count > 0) return 0;
+    }
+    exclude_target_functions(ast);
+    if (crate_type() == CRATE_TYPE_LIB && ast->type == AST_PROGRAM && !g_checking_prelude) {
+        check_library_items(ast);
+    }
+    if (!g_checking_prelude) add_entry_call(ast);
     LocalSymbolTable *table = create_symbol_table();
     if (!table) return 0;
+    dump_open_scope(0, NULL);
     check_statement_type(ast, table, NULL);
+    // Top-level variables never go through exit_scope()
+    report_unused_in_scope(table, 0);
+    if (ast->type == AST_PROGRAM && !g_checking_prelude) report_unused_functions(ast);
+    report_unreachable_code(ast);
+    dump_close_scope(table, 0);
+    dump_print_scopes();
     free_symbol_table(table);
     
     return g_semantic_error_count == 0 ? 1 : 0;
@@ -40,15 +40,17 @@ TypeInfo* type_info_create(SubType base_type) {
     TypeInfo *info = calloc(1, sizeof(TypeInfo));
     if (!info) return NULL;
     info->base_type = base_type;
+    info->array_length = -1;
     info->is_const = false;
     info->is_nullable = false;
     return info;
 }
 
-TypeInfo* type_info_create_array(TypeInfo *element_type) {
+TypeInfo* type_info_create_array(TypeInfo *element_type, int length) {
     TypeInfo *info = type_info_create(SUB_TYPE_ARRAY);
     if (!info) return NULL;
     info->element_type = element_type;
+    info->array_length = length;
     return info;
 }
 
@@ -81,6 +83,7 @@ TypeInfo* type_info_copy(const TypeInfo *info) {
     
     copy->is_const = info->is_const;
     copy->is_nullable = info->is_nullable;
+    copy->array_length = info->array_length;
     
     if (info->element_type) {
         copy->element_type = type_info_copy(info->element_type);
@@ -183,7 +186,8 @@ TypeInfo* type_infer_from_node(ASTNode *node) {
             return type_info_create(SUB_TYPE_ANY);
             
         case AST_ARRAY_LITERAL:
-            return type_info_create(SUB_TYPE_ARRAY);
+            // A repeat literal [value; count] has no fixed length until its count is folded
+            return type_info_create_array(NULL, ast_array_length(node));
             
         case AST_OBJECT_LITERAL:
             return type_info_create(SUB_TYPE_OBJECT);
@@ -202,6 +206,7 @@ TypeInfo* type_infer_expression(ASTNode *expr) {
    ======================================== */
 
 const char* type_map_to(SubType type, SubTargetLanguage target) {
+    if (type == SUB_TYPE_INT && target == TARGET_RUST && int_width() == 32) return "i32";
     for (size_t i = 0; i < TYPE_MAPPINGS_COUNT; i++) {
         if (TYPE_MAPPINGS[i].sub_type == type) {
             switch (target) {
@@ -263,8 +268,9 @@ bool types_are_equal(TypeInfo *a, TypeInfo *b) {
     
     if (a->base_type != b->base_type) return false;
     
-    // For arrays, check element types
+    // For arrays, check element types, and lengths when both are known
     if (a->base_type == SUB_TYPE_ARRAY) {
+        if (a->array_length >= 0 && b->array_length >= 0 && a->array_length != b->array_length) return false;
         return types_are_equal(a->element_type, b->element_type);
     }
     
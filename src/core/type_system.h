@@ -54,6 +54,7 @@ typedef enum {
 typedef struct TypeInfo {
     SubType base_type;
     struct TypeInfo *element_type;  // For arrays: type of elements
+    int array_length;               // For arrays: element count when known statically, else -1
     struct TypeInfo *return_type;   // For functions: return type
     struct TypeInfo **param_types;  // For functions: parameter types
     int param_count;
@@ -90,7 +91,7 @@ extern const size_t TYPE_MAPPINGS_COUNT;
 
 /* Creation and destruction */
 TypeInfo* type_info_create(SubType base_type);
-TypeInfo* type_info_create_array(TypeInfo *element_type);
+TypeInfo* type_info_create_array(TypeInfo *element_type, int length);
 TypeInfo* type_info_create_function(TypeInfo *return_type, TypeInfo **params, int param_count);
 void type_info_free(TypeInfo *info);
 TypeInfo* type_info_copy(const TypeInfo *info);
@@ -7,7 +7,16 @@
 #include "sub_compiler.h"
 #include "codegen_cpp.h"
 #include "windows_compat.h"
+#include "logo.h"
 #include <stdarg.h>
+#include <limits.h>
+#include <sys/stat.h>
+#ifdef _WIN32
+#include <io.h>
+#else
+#include <sys/resource.h>
+#include <dirent.h>
+#endif
 
 // Utility: Print compilation error
 void compile_error(const char *message, int line) {
@@ -41,11 +50,244 @@ void print_token(Token *token) {
     printf(" at line %d, col %d\n", token->line, token->column);
 }
 
-// Print AST (for debugging)
-void print_ast(ASTNode *node, int depth) {
+static void span_include(SourceSpan *span, int line, int column, int end_line, int end_column) {
+    if (line <= 0) return;
+    if (span->line == 0 || line < span->line || (line == span->line && column < span->column)) {
+        span->line = line;
+        span->column = column;
+    }
+    if (end_line > span->end_line || (end_line == span->end_line && end_column > span->end_column)) {
+        span->end_line = end_line;
+        span->end_column = end_column;
+    }
+}
+
+static void span_walk(const ASTNode *node, SourceSpan *span) {
+    if (!node) return;
+    int end_line = node->end_line, end_column = node->end_column;
+    if (end_line == 0) {
+        // Only the start is recorded; the node's own token ends after its text
+        int len = node->value ? (int)strlen(node->value) : 1;
+        if (node->type == AST_LITERAL && node->data_type == TYPE_STRING) len += 2;
+        end_line = node->line;
+        end_column = node->column + len;
+    }
+    span_include(span, node->line, node->column, end_line, end_column);
+    span_walk(node->left, span);
+    span_walk(node->right, span);
+    span_walk(node->condition, span);
+    for (int i = 0; i < node->child_count; i++) {
+        span_walk(node->children[i], span);
+    }
+}
+
+bool ast_literal_is_verbatim(const ASTNode *node) {
+    const char *p = diagnostic_source();
+    if (!p || !node->value || node->line < 1 || node->column < 1) return false;
+    for (int line = 1; line < node->line && *p; p++) {
+        if (*p == '\n') line++;
+    }
+    for (int col = 1; col < node->column && *p && *p != '\n'; col++) p++;
+    if (*p != '"') return false;
+    size_t len = strcspn(p + 1, "\"\\\n");
+    return p[1 + len] == '"' && len == strlen(node->value);
+}
+
+int ast_array_length(const ASTNode *node) {
+    if (!node->right) return node->child_count;
+    if (node->right->type != AST_LITERAL || node->right->data_type != TYPE_INT) return -1;
+    return atoi(node->right->value);
+}
+
+bool ast_is_array_repeat(const ASTNode *node) {
+    return node && node->type == AST_ARRAY_LITERAL && node->right && node->child_count == 1;
+}
+
+// Source range covered by an expression and all of its operands
+SourceSpan ast_span(const ASTNode *node) {
+    SourceSpan span = {0, 0, 0, 0};
+    span_walk(node, &span);
+    return span;
+}
+
+// node->attributes is "inline,target(linux web)": names separated by
+// commas, each with its arguments, if any, in parentheses
+static const char* find_attribute(const ASTNode *node, const char *name) {
+    if (!node || !node->attributes) return NULL;
+    size_t n = strlen(name);
+    for (const char *p = node->attributes; *p; p += strcspn(p, ","), p += *p == ',') {
+        if (strncmp(p, name, n) == 0 && (p[n] == ',' || p[n] == '(' || p[n] == '\0')) return p + n;
+    }
+    return NULL;
+}
+
+bool ast_has_attribute(const ASTNode *node, const char *name) {
+    return find_attribute(node, name) != NULL;
+}
+
+bool ast_attribute_lists(const ASTNode *node, const char *name, const char *item) {
+    const char *p = find_attribute(node, name);
+    if (!p || *p != '(') return false;
+    const char *want = canonical_target_name(item);
+    if (!want) want = item;
+    for (p++; *p && *p != ')'; ) {
+        size_t len = strcspn(p, " )");
+        char arg[32];
+        snprintf(arg, sizeof(arg), "%.*s", (int)len, p);
+        const char *have = canonical_target_name(arg);
+        if (strcmp(have ? have : arg, want) == 0) return true;
+        p += len;
+        if (*p == ' ') p++;
+    }
+    return false;
+}
+
+void ast_walk(ASTNode *node, ASTVisitor *visitor) {
+    if (!node) return;
+    ASTVisitFn visit = visitor->visit[node->type] ? visitor->visit[node->type] : visitor->enter;
+    if (!visit || visit(visitor, node)) ast_walk_operands(node, visitor);
+    if (visitor->leave) visitor->leave(visitor, node);
+}
+
+void ast_walk_operands(ASTNode *node, ASTVisitor *visitor) {
+    if (!node) return;
+    ast_walk(node->left, visitor);
+    ast_walk(node->right, visitor);
+    ast_walk(node->condition, visitor);
+    // A block's body only points at its first statement
+    if (node->type != AST_BLOCK && node->type != AST_PROGRAM) ast_walk(node->body, visitor);
+    for (int i = 0; i < node->child_count; i++) {
+        ast_walk(node->children[i], visitor);
+    }
+}
+
+static bool number_node(ASTVisitor *visitor, ASTNode *node) {
+    node->id = ++*(int *)visitor->data;
+    return true;
+}
+
+int ast_number_nodes(ASTNode *root) {
+    int count = 0;
+    ASTVisitor numberer = { .enter = number_node, .data = &count };
+    ast_walk(root, &numberer);
+    return count;
+}
+
+typedef struct {
+    ASTMatchFn match;
+    const void *data;
+    ASTNode *found;
+} NodeSearch;
+
+static bool match_node(ASTVisitor *visitor, ASTNode *node) {
+    NodeSearch *search = visitor->data;
+    if (search->found) return false;
+    if (search->match(node, search->data)) search->found = node;
+    return !search->found;
+}
+
+ASTNode* ast_find(ASTNode *root, ASTMatchFn match, const void *data) {
+    NodeSearch search = {match, data, NULL};
+    ASTVisitor finder = { .enter = match_node, .data = &search };
+    ast_walk(root, &finder);
+    return search.found;
+}
+
+static bool has_id(const ASTNode *node, const void *data) {
+    return node->id == *(const NodeId *)data;
+}
+
+ASTNode* ast_find_node(ASTNode *root, NodeId id) {
+    if (id <= 0) return NULL;
+    return ast_find(root, has_id, &id);
+}
+
+static bool has_type(const ASTNode *node, const void *data) {
+    return node->type == *(const ASTNodeType *)data;
+}
+
+bool ast_contains_type(ASTNode *root, ASTNodeType type) {
+    return ast_find(root, has_type, &type) != NULL;
+}
+
+static bool is_call_to(const ASTNode *node, const void *data) {
+    return node->type == AST_CALL_EXPR && node->value && strcmp(node->value, data) == 0;
+}
+
+bool ast_calls_function(ASTNode *root, const char *name) {
+    return ast_find(root, is_call_to, name) != NULL;
+}
+
+/* ========================================
+   StringBuilder Helpers
+   ======================================== */
+
+typedef struct {
+    char *buffer;
+    size_t size;
+    size_t capacity;
+} StringBuilder;
+
+static StringBuilder* sb_create(void) {
+    StringBuilder *sb = malloc(sizeof(StringBuilder));
+    if (!sb) return NULL;
+    sb->capacity = 4096;
+    sb->size = 0;
+    sb->buffer = malloc(sb->capacity);
+    if (!sb->buffer) {
+        free(sb);
+        return NULL;
+    }
+    sb->buffer[0] = '\0';
+    return sb;
+}
+
+static void sb_append(StringBuilder *sb, const char *fmt, ...) {
+    if (!sb || !fmt) return;
+
+    va_list args;
+    va_start(args, fmt);
+
+    va_list args_copy;
+    va_copy(args_copy, args);
+    int needed = vsnprintf(NULL, 0, fmt, args_copy);
+    va_end(args_copy);
+
+    if (needed < 0) {
+        va_end(args);
+        return;
+    }
+
+    while (sb->size + needed + 1 > sb->capacity) {
+        sb->capacity *= 2;
+        char *new_buffer = realloc(sb->buffer, sb->capacity);
+        if (!new_buffer) {
+            va_end(args);
+            return;
+        }
+        sb->buffer = new_buffer;
+    }
+
+    vsnprintf(sb->buffer + sb->size, needed + 1, fmt, args);
+    sb->size += needed;
+    va_end(args);
+}
+
+static char* sb_to_string(StringBuilder *sb) {
+    if (!sb) return NULL;
+    char *result = strdup(sb->buffer);
+    free(sb->buffer);
+    free(sb);
+    return result;
+}
+
+static void dump_ast_list(StringBuilder *sb, ASTNode *node, int depth);
+
+// One node with its span, then its operands one level deeper
+static void dump_ast_node(StringBuilder *sb, ASTNode *node, int depth) {
     if (!node) return;
     
-    for (int i = 0; i < depth; i++) printf("  ");
+    for (int i = 0; i < depth; i++) sb_append(sb, "  ");
     
     const char *node_names[] = {
         "PROGRAM", "VAR_DECL", "CONST_DECL", "FUNCTION_DECL",
@@ -57,22 +299,356 @@ void print_ast(ASTNode *node, int depth) {
         "BLOCK", "UI_COMPONENT", "EMBED_CODE", "EMBED_CPP",
         "EMBED_C", "ARRAY_LITERAL", "OBJECT_LITERAL", "MEMBER_ACCESS",
         "ARRAY_ACCESS", "NEW_EXPR", "RANGE_EXPR", "ARRAY_ITERATION",
-        "PARAM_DECL"
+        "PARAM_DECL", "STATIC_ASSERT", "CAST_EXPR", "EXIT_STMT",
+        "UNWRAP_EXPR",
+        "MATCH_EXPR",
+        "MATCH_ARM",
+        "TUPLE_EXPR",
+        "DESTRUCTURE_DECL"
     };
     
     if (node->type < sizeof(node_names) / sizeof(char*)) {
-        printf("%s", node_names[node->type]);
+        sb_append(sb, "%s", node_names[node->type]);
     } else {
-        printf("UNKNOWN(%d)", node->type);
+        sb_append(sb, "UNKNOWN(%d)", node->type);
     }
     if (node->value) {
-        printf(": %s", node->value);
+        sb_append(sb, ": %s", node->value);
     }
-    printf("\n");
+    if (node->type != AST_PROGRAM && node->type != AST_BLOCK) {
+        SourceSpan span = ast_span(node);
+        sb_append(sb, " [%d:%d-%d:%d]", span.line, span.column, span.end_line, span.end_column);
+    }
+    sb_append(sb, "\n");
     
-    if (node->left) print_ast(node->left, depth + 1);
-    if (node->right) print_ast(node->right, depth + 1);
-    if (node->next) print_ast(node->next, depth);
+    if (node->condition) dump_ast_list(sb, node->condition, depth + 1);
+    if (node->left) dump_ast_list(sb, node->left, depth + 1);
+    if (node->right) dump_ast_list(sb, node->right, depth + 1);
+    // Block statements are chained through body/next; everything else
+    // (arguments, parameters, elements) lives in children
+    if (node->type != AST_PROGRAM && node->type != AST_BLOCK) {
+        for (int i = 0; i < node->child_count; i++) {
+            dump_ast_node(sb, node->children[i], depth + 1);
+        }
+    }
+    if (node->body) dump_ast_list(sb, node->body, depth + 1);
+}
+
+// A node, then its siblings along ->next
+static void dump_ast_list(StringBuilder *sb, ASTNode *node, int depth) {
+    for (; node; node = node->next) {
+        dump_ast_node(sb, node, depth);
+    }
+}
+
+char* ast_dump(ASTNode *node) {
+    StringBuilder *sb = sb_create();
+    if (!sb) return NULL;
+    dump_ast_list(sb, node, 0);
+    return sb_to_string(sb);
+}
+
+// Print AST (for debugging)
+void print_ast(ASTNode *node, int depth) {
+    StringBuilder *sb = sb_create();
+    if (!sb) return;
+    dump_ast_list(sb, node, depth);
+    fputs(sb->buffer, stdout);
+    free(sb_to_string(sb));
+}
+
+char* docs_generate(ASTNode *ast, const char *title) {
+    StringBuilder *sb = sb_create();
+    if (!sb) return NULL;
+    sb_append(sb, "# %s\n", title);
+    for (ASTNode *stmt = ast->body; stmt; stmt = stmt->next) {
+        if (stmt->type != AST_FUNCTION_DECL || !stmt->value || stmt->value[0] == '_') continue;
+        sb_append(sb, "\n## %s(", stmt->value);
+        bool first = true;
+        for (int i = 0; i < stmt->child_count; i++) {
+            if (stmt->children[i]->type != AST_PARAM_DECL) continue;
+            sb_append(sb, "%s%s", first ? "" : ", ", stmt->children[i]->value);
+            first = false;
+        }
+        sb_append(sb, ")\n");
+        if (stmt->doc) sb_append(sb, "\n%s\n", stmt->doc);
+    }
+    return sb_to_string(sb);
+}
+
+typedef struct {
+    int nodes;
+    int max_expr_depth;
+    int functions;
+    int variables;
+    int constants;
+} TreeStats;
+
+static bool is_expression_node(const ASTNode *node) {
+    switch (node->type) {
+        case AST_CALL_EXPR: case AST_BINARY_EXPR: case AST_UNARY_EXPR:
+        case AST_TERNARY_EXPR: case AST_IDENTIFIER: case AST_LITERAL:
+        case AST_ARRAY_LITERAL: case AST_OBJECT_LITERAL: case AST_MEMBER_ACCESS:
+        case AST_ARRAY_ACCESS: case AST_NEW_EXPR: case AST_RANGE_EXPR:
+        case AST_CAST_EXPR: case AST_UNWRAP_EXPR: case AST_MATCH_EXPR:
+        case AST_MATCH_ARM: case AST_TUPLE_EXPR: case AST_ARROW_FUNCTION:
+            return true;
+        default:
+            return false;
+    }
+}
+
+// One walk over the tree; `depth` counts the expression nodes above `node`
+static void stats_walk(const ASTNode *node, int depth, TreeStats *stats) {
+    if (!node) return;
+    stats->nodes++;
+    depth = is_expression_node(node) ? depth + 1 : 0;
+    if (depth > stats->max_expr_depth) stats->max_expr_depth = depth;
+    if (node->type == AST_FUNCTION_DECL) stats->functions++;
+    else if (node->type == AST_VAR_DECL) stats->variables++;
+    else if (node->type == AST_CONST_DECL) stats->constants++;
+    else if (node->type == AST_DESTRUCTURE_DECL) stats->variables += node->child_count;
+
+    stats_walk(node->condition, depth, stats);
+    stats_walk(node->left, depth, stats);
+    stats_walk(node->right, depth, stats);
+    for (int i = 0; i < node->child_count; i++) {
+        stats_walk(node->children[i], depth, stats);
+    }
+    // A block's body is its first statement, already among its children
+    if (node->type != AST_PROGRAM && node->type != AST_BLOCK) stats_walk(node->body, depth, stats);
+}
+
+void print_compile_stats(const Token *tokens, int token_count, const ASTNode *ast) {
+    int counts[TOKEN_EOF + 1] = {0};
+    for (int i = 0; i < token_count; i++) {
+        if (tokens[i].type >= 0 && tokens[i].type <= TOKEN_EOF) counts[tokens[i].type]++;
+    }
+    TreeStats stats = {0, 0, 0, 0, 0};
+    stats_walk(ast, 0, &stats);
+
+    fprintf(stderr, "Stats:\n");
+    fprintf(stderr, "  tokens: %d\n", token_count);
+    // Most frequent kind first; ties keep the TokenType order
+    for (;;) {
+        int best = -1;
+        for (int t = 0; t <= TOKEN_EOF; t++) {
+            if (counts[t] > 0 && (best < 0 || counts[t] > counts[best])) best = t;
+        }
+        if (best < 0) break;
+        fprintf(stderr, "    %-14s %d\n", token_type_to_string((TokenType)best), counts[best]);
+        counts[best] = 0;
+    }
+    fprintf(stderr, "  AST nodes: %d\n", stats.nodes);
+    fprintf(stderr, "  max expression depth: %d\n", stats.max_expr_depth);
+    fprintf(stderr, "  functions: %d\n", stats.functions);
+    fprintf(stderr, "  variables: %d\n", stats.variables);
+    fprintf(stderr, "  constants: %d\n", stats.constants);
+}
+
+/* ========================================
+   Source Formatting
+   ======================================== */
+
+/* What a line is nested in: a bracket, or a block that a bare `end` closes
+   (`if x` ... `end`). Of the brackets a line leaves open only the first
+   indents, so the lines under `f([` are one level in, not two. */
+typedef struct {
+    bool end_block;
+    bool indents;
+} FormatScope;
+
+typedef struct {
+    FormatScope *items;
+    int count;
+    int capacity;
+} FormatStack;
+
+static void scope_push(FormatStack *stack, bool end_block, bool indents) {
+    if (stack->count == stack->capacity) {
+        int capacity = stack->capacity ? stack->capacity * 2 : 16;
+        FormatScope *items = realloc(stack->items, capacity * sizeof(FormatScope));
+        if (!items) return;
+        stack->items = items;
+        stack->capacity = capacity;
+    }
+    stack->items[stack->count++] = (FormatScope){end_block, indents};
+}
+
+static bool in_end_block(const FormatStack *stack) {
+    return stack->count > 0 && stack->items[stack->count - 1].end_block;
+}
+
+// A closing bracket also closes the `end` blocks still open inside it, as
+// the parser ends a bare block at the brace after it
+static void scope_close_bracket(FormatStack *stack) {
+    while (in_end_block(stack)) stack->count--;
+    if (stack->count > 0) stack->count--;
+}
+
+static int scope_indent(const FormatStack *stack) {
+    int indent = 0;
+    for (int i = 0; i < stack->count; i++) {
+        if (stack->items[i].indents) indent++;
+    }
+    return indent;
+}
+
+static bool is_open_bracket(TokenType type) {
+    return type == TOKEN_LBRACE || type == TOKEN_LPAREN || type == TOKEN_LBRACKET;
+}
+
+static bool is_close_bracket(TokenType type) {
+    return type == TOKEN_RBRACE || type == TOKEN_RPAREN || type == TOKEN_RBRACKET;
+}
+
+// Track the brackets and `end` keywords among the code tokens [from, to)
+// of a line
+static void scan_scopes(FormatStack *scopes, const HighlightToken *tokens, int from, int to,
+                        bool *in_embed) {
+    int lowest = scopes->count;
+    for (int i = from; i < to; i++) {
+        TokenType type = tokens[i].type;
+        if (tokens[i].kind == HIGHLIGHT_COMMENT) continue;
+        if (is_open_bracket(type)) {
+            scope_push(scopes, false, false);
+        } else if (is_close_bracket(type)) {
+            scope_close_bracket(scopes);
+        } else if (type == TOKEN_END) {
+            if (in_end_block(scopes)) scopes->count--;
+        } else if (type == TOKEN_EMBED) {
+            *in_embed = true;
+        }
+        if (scopes->count < lowest) lowest = scopes->count;
+    }
+    if (scopes->count > lowest) scopes->items[lowest].indents = true;
+}
+
+static void append_line(StringBuilder *out, int *blank_lines, int indent, const char *text, size_t len) {
+    if (!out) return;
+    if (*blank_lines > 0) sb_append(out, "\n");
+    for (int i = 0; i < indent; i++) sb_append(out, "    ");
+    sb_append(out, "%.*s\n", (int)len, text);
+}
+
+// Re-indent `source` into `out` (which may be NULL); returns how many
+// scopes are still open at the end
+static int format_text(const char *source, StringBuilder *out) {
+    HighlightList list = lexer_highlight(source, 0, 0);
+    const HighlightToken *tokens = list.items;
+    FormatStack scopes = {NULL, 0, 0};
+    int next = 0;
+    int covered_to = 0;     // last line of a token that started on an earlier line
+    bool in_embed = false;
+    bool maybe_braced = false;  // the last line opened a bare block; `{` may follow
+    bool wrote = false;
+    int blank_lines = 0;
+
+    const char *p = source;
+    for (int line = 1; *p; line++) {
+        const char *eol = strchr(p, '\n');
+        size_t len = eol ? (size_t)(eol - p) : strlen(p);
+        if (len > 0 && p[len - 1] == '\r') len--;
+        int first = next;
+        while (next < list.count && tokens[next].span.line == line) next++;
+        bool inside_token = covered_to >= line;
+        for (int i = first; i < next; i++) {
+            if (tokens[i].span.end_line > covered_to) covered_to = tokens[i].span.end_line;
+        }
+        int code = first;
+        while (code < next && tokens[code].kind == HIGHLIGHT_COMMENT) code++;
+        if (in_embed && code < next && tokens[code].type == TOKEN_ENDEMBED) in_embed = false;
+
+        if (in_embed) {
+            append_line(out, &blank_lines, 0, p, len);
+            blank_lines = 0;
+            wrote = true;
+        } else if (inside_token) {
+            // The rest of a string or block comment, kept as written
+            append_line(out, &blank_lines, 0, p, len);
+            blank_lines = 0;
+            wrote = true;
+            scan_scopes(&scopes, tokens, first, next, &in_embed);
+            if (code < next) maybe_braced = false;
+        } else {
+            size_t start = strspn(p, " \t");
+            if (start > len) start = len;
+            size_t end = len;
+            if (covered_to <= line) {
+                while (end > start && (p[end - 1] == ' ' || p[end - 1] == '\t')) end--;
+            }
+
+            bool has_brace = false;
+            for (int i = code; i < next; i++) {
+                if (tokens[i].type == TOKEN_LBRACE) has_brace = true;
+            }
+            int dedent = 0;
+            int k = code;
+            if (k < next && maybe_braced && tokens[k].type == TOKEN_LBRACE) {
+                // `function f()` with its brace on the next line
+                scopes.count--;
+            }
+            while (k < next && is_close_bracket(tokens[k].type)) {
+                scope_close_bracket(&scopes);
+                k++;
+            }
+            if (k < next && tokens[k].type == TOKEN_END) {
+                if (in_end_block(&scopes)) scopes.count--;
+                k++;
+            } else if (k < next && in_end_block(&scopes) &&
+                       (tokens[k].type == TOKEN_ELIF || tokens[k].type == TOKEN_ELSE)) {
+                // A bare elif/else lines up with its if; one with a brace
+                // closes the bare block and opens its own
+                if (has_brace) scopes.count--;
+                else dedent = 1;
+            }
+            int indent = scope_indent(&scopes) - dedent;
+            if (indent < 0) indent = 0;
+
+            int depth = scopes.count;
+            scan_scopes(&scopes, tokens, k, next, &in_embed);
+            if (code < next) maybe_braced = false;
+
+            // `if x`, `for ...`, `while ...` or `function f()` without a
+            // brace opens a block that runs to its `end`
+            int head = code;
+            while (head < next && tokens[head].type == TOKEN_ATTRIBUTE) head++;
+            TokenType keyword = head < next ? tokens[head].type : TOKEN_EOF;
+            int last = next - 1;
+            while (last >= code && tokens[last].kind == HIGHLIGHT_COMMENT) last--;
+            if ((keyword == TOKEN_IF || keyword == TOKEN_FOR || keyword == TOKEN_WHILE ||
+                 keyword == TOKEN_FUNCTION) && !has_brace && scopes.count == depth &&
+                tokens[last].type != TOKEN_END) {
+                scope_push(&scopes, true, true);
+                maybe_braced = true;
+            }
+
+            if (end == start) {
+                if (wrote) blank_lines = 1;
+            } else {
+                append_line(out, &blank_lines, indent, p + start, end - start);
+                blank_lines = 0;
+                wrote = true;
+            }
+        }
+        p = eol ? eol + 1 : p + strlen(p);
+    }
+    int open = scopes.count;
+    free(scopes.items);
+    highlight_list_free(&list);
+    return open;
+}
+
+char* format_source(const char *source) {
+    if (!source) return NULL;
+    StringBuilder *sb = sb_create();
+    if (!sb) return NULL;
+    format_text(source, sb);
+    return sb_to_string(sb);
+}
+
+int source_open_scopes(const char *source) {
+    return source ? format_text(source, NULL) : 0;
 }
 
 /* ========================================
@@ -366,6 +942,10 @@ void compiler_free(CompilerContext *ctx) {
         cls = next;
     }
     
+    diagnostic_list_free(&ctx->errors);
+    diagnostic_list_free(&ctx->warnings);
+    free(ctx->output);
+    free(ctx->source_map);
     free(ctx->output_path);
     free(ctx->source_file);
     free(ctx);
@@ -397,115 +977,121 @@ bool compiler_compile(CompilerContext *ctx) {
         return false;
     }
 
+    bool ok = compiler_compile_source(ctx, source);
+
+    // Write output
+    if (ok && ctx->output_path) {
+        write_file(ctx->output_path, ctx->artifact.bytes);
+    }
+
+    free(source);
+    return ok;
+}
+
+// The canonical name of what ctx compiles for
+static const char* compile_target(const CompilerContext *ctx) {
+    if (!ctx->target) return platform_to_string(ctx->target_platform);
+    const char *name = canonical_target_name(ctx->target);
+    return name ? name : ctx->target;
+}
+
+static bool platform_from_name(const char *name, Platform *out) {
+    for (Platform p = PLATFORM_ANDROID; p <= PLATFORM_WASM; p++) {
+        if (strcmp(name, platform_to_string(p)) == 0) {
+            *out = p;
+            return true;
+        }
+    }
+    return false;
+}
+
+// Compile source text already in memory. Errors and warnings are still
+// printed, and are also kept apart in ctx->errors and ctx->warnings: a
+// program that only has warnings compiles, and its warnings stay available.
+static bool compile_stages(CompilerContext *ctx, const char *source) {
     // Lexical analysis
     ctx->tokens = lexer_tokenize(source, &ctx->token_count);
-    if (!ctx->tokens) {
-        ctx->error_count++;
-        free(source);
-        return false;
-    }
+    if (!ctx->tokens || ctx->errors.count > 0) return false;
 
     // Parsing
     ctx->ast = parser_parse(ctx->tokens, ctx->token_count);
-    if (!ctx->ast) {
-        ctx->error_count++;
-        free(source);
-        return false;
-    }
+    if (!ctx->ast || ctx->errors.count > 0) return false;
 
     // Semantic analysis
-    if (!semantic_analyze(ctx->ast)) {
-        ctx->error_count++;
-        free(source);
-        return false;
+    set_active_target(compile_target(ctx));
+    if (!semantic_analyze(ctx->ast)) return false;
+
+    if (ctx->emit == EMIT_KIND_AST) {
+        optimize_c_output(ctx->ast);
+        ctx->output = ast_dump(ctx->ast);
+        return ctx->output != NULL;
+    }
+    if (ctx->emit == EMIT_KIND_DOCS) {
+        const char *title = source_path() ? source_path() : ctx->source_file;
+        ctx->output = docs_generate(ctx->ast, title ? title : "program");
+        return ctx->output != NULL;
     }
 
     // Code generation
-    char *output = codegen_generate(ctx->ast, ctx->target_platform);
-    if (!output) {
-        ctx->error_count++;
-        free(source);
+    const char *target = compile_target(ctx);
+    Platform platform;
+    codegen_set_source_map(ctx->options.source_map);
+    if (platform_from_name(target, &platform)) {
+        ctx->output = codegen_generate(ctx->ast, platform);
+    } else {
+        ctx->output = codegen_generate_language(target, ctx->ast, source);
+    }
+    ctx->source_map = codegen_take_source_map();
+    codegen_set_source_map(false);
+    return ctx->output != NULL;
+}
+
+bool compiler_compile_source(CompilerContext *ctx, const char *source) {
+    if (!ctx || !source) {
         return false;
     }
 
-    // Write output
-    if (ctx->output_path) {
-        write_file(ctx->output_path, output);
+    diagnostic_collect(&ctx->errors, &ctx->warnings);
+    bool ok = compile_stages(ctx, source);
+    // A stage can fail without a message of its own (out of memory)
+    if (!ok && ctx->errors.count == 0) {
+        diagnostic_record(false, NULL, 0, 0, "compilation failed");
     }
+    diagnostic_collect(NULL, NULL);
 
-    free(output);
-    free(source);
+    ctx->error_count = ctx->errors.count;
+    ctx->warning_count = ctx->warnings.count;
 
-    return ctx->error_count == 0;
+    ctx->artifact = (CompiledArtifact){
+        .bytes = ok ? ctx->output : NULL,
+        .size = ok ? strlen(ctx->output) : 0,
+        .target = compile_target(ctx),
+        .emit = ctx->emit,
+        .source_map = ok ? ctx->source_map : NULL,
+        .errors = &ctx->errors,
+        .warnings = &ctx->warnings,
+    };
+    return ok;
 }
 
-// Get compiler output
-char* compiler_get_output(CompilerContext *ctx) {
-    (void)ctx;
-    return NULL;
+const CompiledArtifact* compiler_compile_artifact(CompilerContext *ctx, const char *source) {
+    if (!ctx || !source) return NULL;
+    compiler_compile_source(ctx, source);
+    return &ctx->artifact;
 }
 
-/* ========================================
-   StringBuilder Helpers
-   ======================================== */
-
-typedef struct {
-    char *buffer;
-    size_t size;
-    size_t capacity;
-} StringBuilder;
-
-static StringBuilder* sb_create(void) {
-    StringBuilder *sb = malloc(sizeof(StringBuilder));
-    if (!sb) return NULL;
-    sb->capacity = 4096;
-    sb->size = 0;
-    sb->buffer = malloc(sb->capacity);
-    if (!sb->buffer) {
-        free(sb);
-        return NULL;
-    }
-    sb->buffer[0] = '\0';
-    return sb;
-}
-
-static void sb_append(StringBuilder *sb, const char *fmt, ...) {
-    if (!sb || !fmt) return;
-
-    va_list args;
-    va_start(args, fmt);
-
-    va_list args_copy;
-    va_copy(args_copy, args);
-    int needed = vsnprintf(NULL, 0, fmt, args_copy);
-    va_end(args_copy);
-
-    if (needed < 0) {
-        va_end(args);
-        return;
-    }
-
-    while (sb->size + needed + 1 > sb->capacity) {
-        sb->capacity *= 2;
-        char *new_buffer = realloc(sb->buffer, sb->capacity);
-        if (!new_buffer) {
-            va_end(args);
-            return;
-        }
-        sb->buffer = new_buffer;
+const char* emit_kind_name(EmitKind emit) {
+    switch (emit) {
+        case EMIT_KIND_CODE: return "code";
+        case EMIT_KIND_AST: return "ast";
+        case EMIT_KIND_DOCS: return "docs";
     }
-
-    vsnprintf(sb->buffer + sb->size, needed + 1, fmt, args);
-    sb->size += needed;
-    va_end(args);
+    return "unknown";
 }
 
-static char* sb_to_string(StringBuilder *sb) {
-    if (!sb) return NULL;
-    char *result = strdup(sb->buffer);
-    free(sb->buffer);
-    free(sb);
-    return result;
+// Get compiler output: the generated code once compilation has succeeded
+char* compiler_get_output(CompilerContext *ctx) {
+    return ctx ? ctx->output : NULL;
 }
 
 /* ========================================
@@ -602,4 +1188,849 @@ const char* platform_get_compiler(Platform platform, bool use_cpp) {
     }
 }
 
+/* ========================================
+   Command-Line Helpers
+   ======================================== */
+
+static const struct {
+    const char *name;
+    int arity;
+} g_string_builtins[STR_BUILTIN_COUNT] = {
+    [STR_BUILTIN_SUBSTR]   = {"substr", 3},
+    [STR_BUILTIN_INDEX_OF] = {"index_of", 2},
+    [STR_BUILTIN_TO_UPPER] = {"to_upper", 1},
+    [STR_BUILTIN_TO_LOWER] = {"to_lower", 1},
+};
+
+int string_builtin_lookup(const char *name) {
+    if (!name) return -1;
+    for (int i = 0; i < STR_BUILTIN_COUNT; i++) {
+        if (strcmp(g_string_builtins[i].name, name) == 0) return i;
+    }
+    return -1;
+}
+
+int string_builtin_arity(StringBuiltin builtin) {
+    return g_string_builtins[builtin].arity;
+}
+
+static OverflowMode g_overflow_mode = OVERFLOW_WRAP;
+
+OverflowMode overflow_mode(void) {
+    return g_overflow_mode;
+}
+
+void set_overflow_mode(OverflowMode mode) {
+    g_overflow_mode = mode;
+}
+
+static bool parse_overflow_mode(const char *value) {
+    if (strcmp(value, "wrap") == 0) set_overflow_mode(OVERFLOW_WRAP);
+    else if (strcmp(value, "check") == 0) set_overflow_mode(OVERFLOW_CHECK);
+    else if (strcmp(value, "saturate") == 0) set_overflow_mode(OVERFLOW_SATURATE);
+    else {
+        fprintf(stderr, "Error: invalid --overflow mode '%s' (expected check, wrap or saturate)\n", value);
+        return false;
+    }
+    return true;
+}
+
+static int g_int_width = 64;
+
+int int_width(void) {
+    return g_int_width;
+}
+
+void set_int_width(int bits) {
+    g_int_width = bits;
+}
+
+long long int_max(void) {
+    return g_int_width == 32 ? INT_MAX : LLONG_MAX;
+}
+
+long long int_min(void) {
+    return g_int_width == 32 ? INT_MIN : LLONG_MIN;
+}
+
+bool int_fits(long long value) {
+    return value >= int_min() && value <= int_max();
+}
+
+static bool parse_int_width(const char *value) {
+    if (strcmp(value, "32") == 0) set_int_width(32);
+    else if (strcmp(value, "64") == 0) set_int_width(64);
+    else {
+        fprintf(stderr, "Error: invalid --int-width '%s' (expected 32 or 64)\n", value);
+        return false;
+    }
+    return true;
+}
+
+static CrateType g_crate_type = CRATE_TYPE_BIN;
+
+CrateType crate_type(void) {
+    return g_crate_type;
+}
+
+void set_crate_type(CrateType type) {
+    g_crate_type = type;
+}
+
+static bool parse_crate_type(const char *value) {
+    if (strcmp(value, "bin") == 0) set_crate_type(CRATE_TYPE_BIN);
+    else if (strcmp(value, "lib") == 0) set_crate_type(CRATE_TYPE_LIB);
+    else {
+        fprintf(stderr, "Error: invalid --crate-type '%s' (expected bin or lib)\n", value);
+        return false;
+    }
+    return true;
+}
+
+static const char *g_entry_function = NULL;
+
+const char* entry_function(void) {
+    return g_entry_function;
+}
+
+void set_entry_function(const char *name) {
+    g_entry_function = name;
+}
+
+static const char *g_prelude_file = NULL;
+
+const char* prelude_file(void) {
+    return g_prelude_file;
+}
+
+void set_prelude_file(const char *path) {
+    g_prelude_file = path;
+}
+
+ASTNode* prelude_parse(char **source) {
+    *source = NULL;
+    if (!g_prelude_file || path_is_directory(g_prelude_file)) return NULL;
+    FILE *f = fopen(g_prelude_file, "rb");
+    if (!f) return NULL;
+    fseek(f, 0, SEEK_END);
+    long size = ftell(f);
+    fseek(f, 0, SEEK_SET);
+    char *text = size >= 0 ? malloc(size + 1) : NULL;
+    if (!text) {
+        fclose(f);
+        return NULL;
+    }
+    size_t got = fread(text, 1, size, f);
+    text[got] = '\0';
+    fclose(f);
+    text = decode_source(g_prelude_file, text, got);
+    if (!text) return NULL;
+    // The lexer keeps one buffer for line-ending conversion, which the
+    // program's own text may be using; convert the prelude in place instead
+    char *out = text;
+    for (const char *p = text; *p; p++) {
+        if (*p != '\r') *out++ = *p;
+        else if (p[1] != '\n') *out++ = '\n';
+    }
+    *out = '\0';
+
+    int count;
+    Token *tokens = lexer_tokenize(text, &count);
+    ASTNode *ast = tokens ? parser_parse(tokens, count) : NULL;
+    lexer_free_tokens(tokens, count);
+    *source = text;
+    return ast;
+}
+
+bool path_is_directory(const char *path) {
+    struct stat st;
+    return stat(path, &st) == 0 && (st.st_mode & S_IFMT) == S_IFDIR;
+}
+
+typedef struct {
+    char **paths;
+    int count;
+    int capacity;
+} PathList;
+
+static void add_source_files(const char *dir, PathList *list) {
+    size_t dir_len = strlen(dir);
+    bool slash = dir_len > 0 && (dir[dir_len - 1] == '/' || dir[dir_len - 1] == '\\');
+#ifdef _WIN32
+    char *pattern = malloc(dir_len + 4);
+    if (!pattern) return;
+    sprintf(pattern, "%s%s*", dir, slash ? "" : "\\");
+    struct _finddata_t found;
+    intptr_t handle = _findfirst(pattern, &found);
+    free(pattern);
+    if (handle == -1) return;
+    do {
+        const char *name = found.name;
+#else
+    DIR *d = opendir(dir);
+    if (!d) return;
+    struct dirent *entry;
+    while ((entry = readdir(d)) != NULL) {
+        const char *name = entry->d_name;
+#endif
+        if (name[0] == '.') continue;
+        char *path = malloc(dir_len + strlen(name) + 2);
+        if (!path) continue;
+        sprintf(path, "%s%s%s", dir, slash ? "" : "/", name);
+        const char *dot = strrchr(name, '.');
+        if (path_is_directory(path)) {
+            add_source_files(path, list);
+        } else if (dot && strcmp(dot, ".sb") == 0) {
+            if (list->count == list->capacity) {
+                int capacity = list->capacity ? list->capacity * 2 : 8;
+                char **grown = realloc(list->paths, sizeof(char *) * capacity);
+                if (!grown) {
+                    free(path);
+                    continue;
+                }
+                list->paths = grown;
+                list->capacity = capacity;
+            }
+            list->paths[list->count++] = path;
+            continue;
+        }
+        free(path);
+#ifdef _WIN32
+    } while (_findnext(handle, &found) == 0);
+    _findclose(handle);
+#else
+    }
+    closedir(d);
+#endif
+}
+
+static int compare_paths(const void *a, const void *b) {
+    return strcmp(*(char *const *)a, *(char *const *)b);
+}
+
+char** list_source_files(const char *dir, int *count) {
+    PathList list = {NULL, 0, 0};
+    add_source_files(dir, &list);
+    if (list.count > 1) qsort(list.paths, list.count, sizeof(char *), compare_paths);
+    *count = list.count;
+    return list.paths;
+}
+
+size_t stack_limit_bytes(void) {
+#ifdef _WIN32
+    return 1024 * 1024;   /* the default reserve MSVC and MinGW link with */
+#else
+    struct rlimit rl;
+    if (getrlimit(RLIMIT_STACK, &rl) != 0 || rl.rlim_cur == RLIM_INFINITY) return 8 * 1024 * 1024;
+    return (size_t)rl.rlim_cur;
+#endif
+}
+
+static bool g_quiet = false;
+
+bool quiet_mode(void) {
+    return g_quiet;
+}
+
+void set_quiet_mode(bool quiet) {
+    g_quiet = quiet;
+}
+
+void print_status(const char *fmt, ...) {
+    if (g_quiet) return;
+    va_list args;
+    va_start(args, fmt);
+    vprintf(fmt, args);
+    va_end(args);
+}
+
+static const char *g_banner = "Generated by SUB Language Compiler v" SUB_VERSION;
+
+const char* codegen_banner(void) {
+    return g_banner;
+}
+
+void set_codegen_banner(const char *text) {
+    g_banner = text;
+}
+
+static bool parse_banner(const char *text) {
+    if (strchr(text, '\n') || strstr(text, "*/")) {
+        fprintf(stderr, "Error: --banner text must be a single line without '*/'\n");
+        return false;
+    }
+    set_codegen_banner(text);
+    return true;
+}
+
+static bool g_latin1_input = false;
+
+static bool parse_encoding(const char *value) {
+    if (strcasecmp(value, "utf-8") == 0 || strcasecmp(value, "utf8") == 0) {
+        g_latin1_input = false;
+    } else if (strcasecmp(value, "latin-1") == 0 || strcasecmp(value, "latin1") == 0 ||
+               strcasecmp(value, "iso-8859-1") == 0) {
+        g_latin1_input = true;
+    } else {
+        fprintf(stderr, "Error: invalid --encoding '%s' (expected utf-8 or latin-1)\n", value);
+        return false;
+    }
+    return true;
+}
+
+// Length of the UTF-8 sequence at `p`, or 0 if it is not a valid one
+// (overlong forms, surrogates and code points past U+10FFFF included)
+static size_t utf8_sequence_length(const unsigned char *p, size_t left) {
+    if (p[0] < 0x80) return 1;
+    size_t len;
+    unsigned min;
+    unsigned cp;
+    if ((p[0] & 0xE0) == 0xC0) { len = 2; min = 0x80; cp = p[0] & 0x1F; }
+    else if ((p[0] & 0xF0) == 0xE0) { len = 3; min = 0x800; cp = p[0] & 0x0F; }
+    else if ((p[0] & 0xF8) == 0xF0) { len = 4; min = 0x10000; cp = p[0] & 0x07; }
+    else return 0;
+    if (len > left) return 0;
+    for (size_t i = 1; i < len; i++) {
+        if ((p[i] & 0xC0) != 0x80) return 0;
+        cp = (cp << 6) | (p[i] & 0x3F);
+    }
+    if (cp < min || cp > 0x10FFFF || (cp >= 0xD800 && cp <= 0xDFFF)) return 0;
+    return len;
+}
+
+char* decode_source(const char *filename, char *content, size_t size) {
+    if (!content) return NULL;
+    if (g_latin1_input) {
+        size_t extra = 0;
+        for (size_t i = 0; i < size; i++) {
+            if ((unsigned char)content[i] >= 0x80) extra++;
+        }
+        if (extra == 0) return content;
+        char *utf8 = malloc(size + extra + 1);
+        if (!utf8) {
+            fprintf(stderr, "Error: Out of memory reading %s\n", filename);
+            free(content);
+            return NULL;
+        }
+        size_t n = 0;
+        for (size_t i = 0; i < size; i++) {
+            unsigned char c = (unsigned char)content[i];
+            if (c < 0x80) {
+                utf8[n++] = (char)c;
+            } else {
+                utf8[n++] = (char)(0xC0 | (c >> 6));
+                utf8[n++] = (char)(0x80 | (c & 0x3F));
+            }
+        }
+        utf8[n] = '\0';
+        free(content);
+        return utf8;
+    }
+    const unsigned char *bytes = (const unsigned char *)content;
+    int line = 1;
+    size_t line_start = 0;
+    for (size_t i = 0; i < size; ) {
+        size_t len = utf8_sequence_length(bytes + i, size - i);
+        if (len == 0) {
+            fprintf(stderr, "Error: %s is not valid UTF-8: byte 0x%02X at offset %zu (line %d, column %zu) "
+                    "does not start a valid sequence; use --encoding latin-1 to read it as Latin-1\n",
+                    filename, bytes[i], i, line, i - line_start + 1);
+            free(content);
+            return NULL;
+        }
+        if (bytes[i] == '\n') {
+            line++;
+            line_start = i + 1;
+        }
+        i += len;
+    }
+    return content;
+}
+
+static bool g_output_crlf = false;
+static int g_final_newline = -1;  // -1 leaves the backend's ending alone
+
+void set_output_eol(bool crlf) {
+    g_output_crlf = crlf;
+}
+
+void set_final_newline(int mode) {
+    g_final_newline = mode;
+}
+
+static bool parse_output_eol(const char *value) {
+    if (strcmp(value, "lf") == 0) {
+        set_output_eol(false);
+    } else if (strcmp(value, "crlf") == 0) {
+        set_output_eol(true);
+    } else {
+        fprintf(stderr, "Error: invalid --output-eol '%s' (expected lf or crlf)\n", value);
+        return false;
+    }
+    return true;
+}
+
+char* apply_output_newlines(const char *code) {
+    size_t len = strlen(code);
+    size_t end = len;
+    if (g_final_newline >= 0) {
+        while (end > 0 && (code[end - 1] == '\n' || code[end - 1] == '\r')) end--;
+    }
+    size_t lines = 1;
+    for (size_t i = 0; i < end; i++) {
+        if (code[i] == '\n') lines++;
+    }
+    char *out = malloc(end + lines + 3);
+    if (!out) return NULL;
+    size_t n = 0;
+    for (size_t i = 0; i < end; i++) {
+        if (code[i] == '\n' && g_output_crlf && (i == 0 || code[i - 1] != '\r')) out[n++] = '\r';
+        out[n++] = code[i];
+    }
+    if (g_final_newline == 1 && end > 0) {
+        if (g_output_crlf) out[n++] = '\r';
+        out[n++] = '\n';
+    }
+    out[n] = '\0';
+    return out;
+}
+
+static const char *g_diag_source = NULL;
+static int g_tab_width = 4;
+
+void diagnostic_set_source(const char *source) {
+    g_diag_source = source;
+}
+
+const char* diagnostic_source(void) {
+    return g_diag_source;
+}
+
+void diagnostic_set_tab_width(int width) {
+    g_tab_width = width;
+}
+
+// Print the source line and a caret under `column`, followed by tildes up
+// to `end_column`:
+//      3 |     var x = @
+//        |             ^
+// Both lines expand tabs the same way, so the caret lines up whatever the
+// terminal's tab stops are. UTF-8 continuation bytes take no width.
+static void print_excerpt(int line, int column, int end_column, const char *label) {
+    if (!g_diag_source || line < 1 || column < 1) return;
+    const char *start = g_diag_source;
+    for (int l = 1; l < line; l++) {
+        start = strchr(start, '\n');
+        if (!start) return;
+        start++;
+    }
+    size_t len = strcspn(start, "\r\n");
+    if (len == 0) return;
+
+    char gutter[16];
+    int gutter_width = snprintf(gutter, sizeof(gutter), "%5d", line);
+    fprintf(stderr, "%s | ", gutter);
+    int visual = 0, caret = -1, caret_end = -1;
+    for (size_t i = 0; i < len; i++) {
+        if ((int)i == column - 1) caret = visual;
+        if ((int)i == end_column - 1) caret_end = visual;
+        unsigned char c = (unsigned char)start[i];
+        if (c == '\t') {
+            int spaces = g_tab_width - visual % g_tab_width;
+            fprintf(stderr, "%*s", spaces, "");
+            visual += spaces;
+        } else {
+            fputc(c, stderr);
+            if ((c & 0xC0) != 0x80) visual++;
+        }
+    }
+    if (caret < 0) caret = visual;
+    if (caret_end < 0) caret_end = visual;
+    fprintf(stderr, "\n%*s | %*s^", gutter_width, "", caret, "");
+    for (int i = caret + 1; i < caret_end; i++) fputc('~', stderr);
+    if (label) fprintf(stderr, " %s", label);
+    fputc('\n', stderr);
+}
+
+void diagnostic_print_excerpt(int line, int column) {
+    print_excerpt(line, column, column + 1, NULL);
+}
+
+void diagnostic_print_note(int line, int column, const char *label) {
+    print_excerpt(line, column, column + 1, label);
+}
+
+// Spans over several lines are marked from their start to the line's end
+void diagnostic_print_span(SourceSpan span) {
+    print_excerpt(span.line, span.column, span.end_line == span.line ? span.end_column : INT_MAX, NULL);
+}
+
+// The opener is marked where it stands, and the end of the input just past
+// the last character on a line with any text, wherever the scanner stopped
+// (often a blank line after the final newline, which has nothing to show)
+void diagnostic_print_unclosed(int line, int column, int length) {
+    if (!g_diag_source) return;
+    print_excerpt(line, column, column + length, "opened here");
+    int end_line = 0, end_column = 0, current = 1;
+    for (const char *p = g_diag_source; *p; current++) {
+        size_t len = strcspn(p, "\n");
+        size_t text = len;
+        while (text > 0 && isspace((unsigned char)p[text - 1])) text--;
+        if (text > 0) {
+            end_line = current;
+            end_column = (int)text + 1;
+        }
+        p += len;
+        if (*p) p++;
+    }
+    if (end_line > 0) print_excerpt(end_line, end_column, end_column + 1, "expected closing here");
+}
+
+static DiagnosticList *g_collect_errors = NULL;
+static DiagnosticList *g_collect_warnings = NULL;
+
+void diagnostic_collect(DiagnosticList *errors, DiagnosticList *warnings) {
+    g_collect_errors = errors;
+    g_collect_warnings = warnings;
+}
+
+static int g_diagnostic_errors = 0;
+
+int diagnostic_error_count(void) {
+    return g_diagnostic_errors;
+}
+
+void diagnostic_record_v(bool warning, const char *lint, int line, int column, const char *fmt, va_list ap) {
+    if (!warning) g_diagnostic_errors++;
+    DiagnosticList *list = warning ? g_collect_warnings : g_collect_errors;
+    if (!list) return;
+    if (list->count == list->capacity) {
+        int capacity = list->capacity ? list->capacity * 2 : 8;
+        Diagnostic *items = realloc(list->items, capacity * sizeof(Diagnostic));
+        if (!items) return;
+        list->items = items;
+        list->capacity = capacity;
+    }
+    char message[512];
+    vsnprintf(message, sizeof(message), fmt, ap);
+    Diagnostic *d = &list->items[list->count++];
+    d->line = line;
+    d->column = column;
+    d->lint = lint;
+    d->message = strdup(message);
+}
+
+void diagnostic_record(bool warning, const char *lint, int line, int column, const char *fmt, ...) {
+    va_list ap;
+    va_start(ap, fmt);
+    diagnostic_record_v(warning, lint, line, column, fmt, ap);
+    va_end(ap);
+}
+
+void diagnostic_list_free(DiagnosticList *list) {
+    if (!list) return;
+    for (int i = 0; i < list->count; i++) free(list->items[i].message);
+    free(list->items);
+    list->items = NULL;
+    list->count = list->capacity = 0;
+}
+
+#define MAX_PATH_REMAPS 16
+
+typedef struct {
+    char *from;
+    char *to;
+} PathRemap;
+
+static PathRemap g_path_remaps[MAX_PATH_REMAPS];
+static int g_path_remap_count = 0;
+static const char *g_source_path = NULL;
+static char g_remapped_path[1024];
+
+void set_source_path(const char *path) {
+    g_source_path = path;
+}
+
+// Like rustc's --remap-path-prefix, the last matching remap wins
+const char* source_path(void) {
+    if (!g_source_path) return NULL;
+    for (int i = g_path_remap_count - 1; i >= 0; i--) {
+        size_t n = strlen(g_path_remaps[i].from);
+        if (strncmp(g_source_path, g_path_remaps[i].from, n) == 0) {
+            snprintf(g_remapped_path, sizeof(g_remapped_path), "%s%s",
+                     g_path_remaps[i].to, g_source_path + n);
+            return g_remapped_path;
+        }
+    }
+    return g_source_path;
+}
+
+const char* canonical_target_name(const char *name) {
+    static const char *const targets[][2] = {
+        {"interpret", "interpret"}, {"run", "interpret"},
+        {"android", "android"}, {"ios", "ios"}, {"web", "web"},
+        {"windows", "windows"}, {"macos", "macos"}, {"linux", "linux"},
+        {"python", "python"}, {"py", "python"},
+        {"javascript", "javascript"}, {"js", "javascript"},
+        {"typescript", "typescript"}, {"ts", "typescript"},
+        {"java", "java"}, {"c", "c"}, {"cpp", "cpp"}, {"c++", "cpp"},
+        {"rust", "rust"}, {"rs", "rust"}, {"go", "go"}, {"golang", "go"},
+        {"swift", "swift"}, {"kotlin", "kotlin"}, {"kt", "kotlin"},
+        {"ruby", "ruby"}, {"rb", "ruby"}, {"assembly", "assembly"}, {"asm", "assembly"},
+        {"css", "css"},
+    };
+    if (!name) return NULL;
+    for (size_t i = 0; i < sizeof(targets) / sizeof(targets[0]); i++) {
+        if (strcasecmp(name, targets[i][0]) == 0) return targets[i][1];
+    }
+    return NULL;
+}
+
+static const char *g_active_target = NULL;
+
+const char* active_target(void) {
+    return g_active_target;
+}
+
+void set_active_target(const char *name) {
+    g_active_target = canonical_target_name(name);
+}
+
+static bool parse_path_remap(const char *value) {
+    const char *eq = strchr(value, '=');
+    if (!eq || eq == value) {
+        fprintf(stderr, "Error: invalid --remap-path '%s' (expected FROM=TO)\n", value);
+        return false;
+    }
+    if (g_path_remap_count >= MAX_PATH_REMAPS) {
+        fprintf(stderr, "Error: too many --remap-path options (at most %d)\n", MAX_PATH_REMAPS);
+        return false;
+    }
+    PathRemap *remap = &g_path_remaps[g_path_remap_count++];
+    remap->from = strndup(value, (size_t)(eq - value));
+    remap->to = strdup(eq + 1);
+    return true;
+}
+
+// Remove the diagnostic options shared by all drivers from argv and apply
+// them: "--error-limit N" (or "--error-limit=N") for the lexer and parser,
+// "-W<lint>" / "-Wno-<lint>" for semantic warnings, "--strict" to turn
+// every lint on as an error, "--dump-symbols" to print the symbol table
+// after semantic analysis, "--stats" for token and tree metrics after
+// parsing, "--overflow <check|wrap|saturate>" for
+// runtime integer overflow, "--int-width <32|64>" for the size of int,
+// "--crate-type <bin|lib>" for what is built, "--entry NAME" for the
+// function called after the top-level statements, "--max-nesting N" for
+// the deepest nesting the parser accepts,
+// "--remap-path FROM=TO" for the source path embedded in output,
+// "-q" / "--quiet" to drop banners and progress messages,
+// "--banner TEXT" / "--no-banner" for the comment atop generated code,
+// "--output-eol <lf|crlf>" and "--final-newline" / "--no-final-newline" for
+// the line endings of written files, "--encoding <utf-8|latin-1>" for how
+// source files are read and
+// "--tab-width N" for source excerpts. Returns false if any of them is
+// malformed.
+bool take_diagnostic_options(int *argc, char *argv[]) {
+    int out = 1;
+    bool ok = true;
+    for (int i = 1; i < *argc; i++) {
+        const char *value = NULL;
+        if (strncmp(argv[i], "-W", 2) == 0 && argv[i][2]) {
+            bool enable = strncmp(argv[i] + 2, "no-", 3) != 0;
+            const char *name = enable ? argv[i] + 2 : argv[i] + 5;
+            if (!semantic_set_lint_by_name(name, enable)) {
+                fprintf(stderr, "Error: unknown warning option '%s'\n", argv[i]);
+                ok = false;
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--strict") == 0) {
+            semantic_set_strict(true);
+            continue;
+        }
+        if (strcmp(argv[i], "-q") == 0 || strcmp(argv[i], "--quiet") == 0) {
+            set_quiet_mode(true);
+            continue;
+        }
+        if (strcmp(argv[i], "--no-banner") == 0) {
+            set_codegen_banner(NULL);
+            continue;
+        }
+        if (strcmp(argv[i], "--encoding") == 0 || strncmp(argv[i], "--encoding=", 11) == 0) {
+            const char *name = argv[i][10] == '=' ? argv[i] + 11 : (i + 1 < *argc ? argv[++i] : NULL);
+            if (!name) {
+                fprintf(stderr, "Error: --encoding requires utf-8 or latin-1\n");
+                ok = false;
+            } else if (!parse_encoding(name)) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--final-newline") == 0) {
+            set_final_newline(1);
+            continue;
+        }
+        if (strcmp(argv[i], "--no-final-newline") == 0) {
+            set_final_newline(0);
+            continue;
+        }
+        if (strcmp(argv[i], "--output-eol") == 0 || strncmp(argv[i], "--output-eol=", 13) == 0) {
+            const char *eol = argv[i][12] == '=' ? argv[i] + 13 : (i + 1 < *argc ? argv[++i] : NULL);
+            if (!eol) {
+                fprintf(stderr, "Error: --output-eol requires lf or crlf\n");
+                ok = false;
+            } else if (!parse_output_eol(eol)) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--banner") == 0 || strncmp(argv[i], "--banner=", 9) == 0) {
+            const char *text = argv[i][8] == '=' ? argv[i] + 9 : (i + 1 < *argc ? argv[++i] : NULL);
+            if (!text) {
+                fprintf(stderr, "Error: --banner requires the comment text\n");
+                ok = false;
+            } else if (!parse_banner(text)) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--dump-symbols") == 0) {
+            semantic_set_dump_symbols(true);
+            continue;
+        }
+        if (strcmp(argv[i], "--stats") == 0) {
+            parser_set_print_stats(true);
+            continue;
+        }
+        if (strcmp(argv[i], "--overflow") == 0) {
+            if (i + 1 >= *argc) {
+                fprintf(stderr, "Error: --overflow requires a mode\n");
+                ok = false;
+            } else if (!parse_overflow_mode(argv[++i])) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strncmp(argv[i], "--overflow=", 11) == 0) {
+            if (!parse_overflow_mode(argv[i] + 11)) ok = false;
+            continue;
+        }
+        if (strcmp(argv[i], "--int-width") == 0) {
+            if (i + 1 >= *argc) {
+                fprintf(stderr, "Error: --int-width requires 32 or 64\n");
+                ok = false;
+            } else if (!parse_int_width(argv[++i])) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strncmp(argv[i], "--int-width=", 12) == 0) {
+            if (!parse_int_width(argv[i] + 12)) ok = false;
+            continue;
+        }
+        if (strcmp(argv[i], "--crate-type") == 0) {
+            if (i + 1 >= *argc) {
+                fprintf(stderr, "Error: --crate-type requires bin or lib\n");
+                ok = false;
+            } else if (!parse_crate_type(argv[++i])) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strncmp(argv[i], "--crate-type=", 13) == 0) {
+            if (!parse_crate_type(argv[i] + 13)) ok = false;
+            continue;
+        }
+        if (strcmp(argv[i], "--entry") == 0 || strncmp(argv[i], "--entry=", 8) == 0) {
+            const char *name = argv[i][7] == '=' ? argv[i] + 8 : (i + 1 < *argc ? argv[++i] : NULL);
+            if (!name || !*name) {
+                fprintf(stderr, "Error: --entry requires a function name\n");
+                ok = false;
+            } else {
+                set_entry_function(name);
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--prelude") == 0 || strncmp(argv[i], "--prelude=", 10) == 0) {
+            const char *path = argv[i][9] == '=' ? argv[i] + 10 : (i + 1 < *argc ? argv[++i] : NULL);
+            if (!path || !*path) {
+                fprintf(stderr, "Error: --prelude requires a file\n");
+                ok = false;
+            } else {
+                set_prelude_file(path);
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--remap-path") == 0) {
+            if (i + 1 >= *argc) {
+                fprintf(stderr, "Error: --remap-path requires FROM=TO\n");
+                ok = false;
+            } else if (!parse_path_remap(argv[++i])) {
+                ok = false;
+            }
+            continue;
+        }
+        if (strncmp(argv[i], "--remap-path=", 13) == 0) {
+            if (!parse_path_remap(argv[i] + 13)) ok = false;
+            continue;
+        }
+        if (strcmp(argv[i], "--max-nesting") == 0 || strncmp(argv[i], "--max-nesting=", 14) == 0) {
+            const char *depth = argv[i][13] == '=' ? argv[i] + 14 : (i + 1 < *argc ? argv[++i] : NULL);
+            char *end = NULL;
+            long n = depth ? strtol(depth, &end, 10) : 0;
+            // Every pass recurses once per level; the deepest use about 3 KB
+            // of stack each, so allow one level per 4 KB (2048 on 8 MB)
+            long most = (long)(stack_limit_bytes() / 4096);
+            if (most < 1) most = 1;
+            if (!depth || !*depth || *end || n < 1 || n > most) {
+                fprintf(stderr, "Error: invalid --max-nesting value '%s' (expected 1-%ld; the stack "
+                        "size, ulimit -s, sets the maximum)\n", depth ? depth : "", most);
+                ok = false;
+            } else {
+                parser_set_max_nesting((int)n);
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--tab-width") == 0 || strncmp(argv[i], "--tab-width=", 12) == 0) {
+            const char *width = argv[i][11] == '=' ? argv[i] + 12 : (i + 1 < *argc ? argv[++i] : NULL);
+            char *end = NULL;
+            long n = width ? strtol(width, &end, 10) : 0;
+            if (!width || !*width || *end || n < 1 || n > 32) {
+                fprintf(stderr, "Error: invalid --tab-width value '%s' (expected 1-32)\n",
+                        width ? width : "");
+                ok = false;
+            } else {
+                diagnostic_set_tab_width((int)n);
+            }
+            continue;
+        }
+        if (strcmp(argv[i], "--error-limit") == 0) {
+            if (i + 1 >= *argc) {
+                fprintf(stderr, "Error: --error-limit requires a value\n");
+                ok = false;
+                continue;
+            }
+            value = argv[++i];
+        } else if (strncmp(argv[i], "--error-limit=", 14) == 0) {
+            value = argv[i] + 14;
+        } else {
+            argv[out++] = argv[i];
+            continue;
+        }
+
+        char *end = NULL;
+        long limit = strtol(value, &end, 10);
+        if (!*value || *end || limit < 0 || limit > INT_MAX) {
+            fprintf(stderr, "Error: invalid --error-limit value '%s'\n", value);
+            ok = false;
+            continue;
+        }
+        lexer_set_error_limit((int)limit);
+        parser_set_error_limit((int)limit);
+    }
+    argv[out] = NULL;
+    *argc = out;
+    return ok;
+}
+
 /* C++ CodeGen helper functions are now in codegen_cpp.c */
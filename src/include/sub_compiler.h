@@ -14,6 +14,7 @@
 #include <string.h>
 #include <ctype.h>
 #include <stdbool.h>
+#include <stdarg.h>
 
 /* Token Types for Lexical Analysis */
 typedef enum {
@@ -69,6 +70,11 @@ typedef enum {
     TOKEN_AWAIT,          // await
     TOKEN_YIELD,          // yield
     
+    // Compile-time directives
+    TOKEN_STATIC_ASSERT,  // #static_assert
+    TOKEN_EXIT,           // #exit
+    TOKEN_ATTRIBUTE,      // #[name]; the token's value is the name
+    
     // Type Keywords
     TOKEN_INT,            // int
     TOKEN_FLOAT,          // float
@@ -81,6 +87,7 @@ typedef enum {
     TOKEN_IDENTIFIER,     // variable names
     TOKEN_NUMBER,         // numeric literals
     TOKEN_STRING_LITERAL, // string literals
+    TOKEN_BYTE_STRING,    // b"...": value is the bytes in decimal, comma-separated
     TOKEN_TRUE,           // true
     TOKEN_FALSE,          // false
     TOKEN_NULL,           // null
@@ -113,6 +120,11 @@ typedef struct {
     char *value;
     int line;
     int column;
+    int end_line;   // Just past the token's last character
+    int end_column;
+    int start_byte; // Byte offsets of the token's text in the source, with
+    int end_byte;   // line endings read as "\n"; end_byte is exclusive
+    char *doc;      // Text of the /// lines directly above the token, else NULL
 } Token;
 
 /* Data Types */
@@ -128,7 +140,8 @@ typedef enum {
     TYPE_NULL,
     TYPE_AUTO,
     TYPE_VOID,
-    TYPE_GENERIC
+    TYPE_GENERIC,
+    TYPE_TUPLE
 } DataType;
 
 /* Abstract Syntax Tree Node Types */
@@ -162,19 +175,34 @@ typedef enum {
     AST_EMBED_CODE,
     AST_EMBED_CPP,
     AST_EMBED_C,
-    AST_ARRAY_LITERAL,
+    AST_ARRAY_LITERAL,    // [a, b]: children = elements; [v; n] until analysis: children = {v}, right = n
     AST_OBJECT_LITERAL,
     AST_MEMBER_ACCESS,
     AST_ARRAY_ACCESS,
     AST_NEW_EXPR,         // new ClassName()
     AST_RANGE_EXPR,       // range(n) or range(start, end)
     AST_ARRAY_ITERATION,  // for item in collection
-    AST_PARAM_DECL        // Function parameter declaration
+    AST_PARAM_DECL,       // Function parameter declaration
+    AST_STATIC_ASSERT,    // #static_assert(condition, "message")
+    AST_CAST_EXPR,        // int(x), float(x), bool(x), string(x)
+    AST_EXIT_STMT,        // #exit(code)
+    AST_UNWRAP_EXPR,      // x? (value "?"), or a use of x after a null check (NULL)
+    AST_MATCH_EXPR,       // match x { ... }: left = scrutinee, children = arms
+    AST_MATCH_ARM,        // pattern => value: left = pattern (NULL for _), right = value
+    AST_TUPLE_EXPR,       // (a, b): children = elements
+    AST_DESTRUCTURE_DECL, // var (a, b) = t: children = one VAR_DECL/CONST_DECL per name, right = t
+    AST_NODE_TYPE_COUNT   // number of node types, not a node type
 } ASTNodeType;
 
+/* Position of a node in its tree, for referring to it without a pointer */
+typedef int NodeId;
+
 /* AST Node Structure */
 typedef struct ASTNode {
     ASTNodeType type;
+    NodeId id;          // Pre-order position from parser_parse(), from 1 (a
+                        // cloned tree keeps them); 0 for nodes the later
+                        // passes create or copy into the tree
     char *value;
     DataType data_type;
     struct ASTNode *left;
@@ -187,8 +215,27 @@ typedef struct ASTNode {
     void *metadata;
     int line;
     int column;
+    int end_line;       // End of the source range, set for calls (after the ')')
+    int end_column;     // and when the node replaces a larger expression (e.g.
+                        // a folded constant); else 0
+    bool optional;      // Declarations: type written as T?. Expressions: may be
+                        // null (set by the semantic pass).
+    struct ASTNode *tuple;  // Tuple-typed nodes: the tuple expression whose
+                            // elements give the element types (not owned)
+    char *doc;              // Functions: the /// comment above them, else NULL
+    int array_length;       // Declarations typed [T; N]: N. Else -1 (dynamic).
+    char *attributes;       // Functions: the #[...] names above them, comma-
+                            // separated in source order, else NULL
 } ASTNode;
 
+/* Source range of a node including its operands; end_column is exclusive */
+typedef struct {
+    int line;
+    int column;
+    int end_line;
+    int end_column;
+} SourceSpan;
+
 /* Target Platform Enum */
 typedef enum {
     PLATFORM_ANDROID,
@@ -211,6 +258,7 @@ typedef struct {
     bool use_cpp;               // Use C++ compiler instead of C
     bool enable_simd;           // Enable SIMD optimizations
     bool parallel_compile;      // Parallel compilation
+    bool source_map;            // Also build a source map (JavaScript, TypeScript)
 } CompilationOptions;
 
 /* Symbol Table Entry */
@@ -247,6 +295,44 @@ typedef struct ClassDef {
     struct ClassDef *next;
 } ClassDef;
 
+/* A message reported while compiling; `lint` is the -W name of the lint
+   behind it (also for ones --strict turns into errors), NULL otherwise */
+typedef struct {
+    int line;
+    int column;
+    const char *lint;
+    char *message;
+} Diagnostic;
+
+typedef struct {
+    Diagnostic *items;
+    int count;
+    int capacity;
+} DiagnosticList;
+
+/* What a compilation produced */
+typedef enum {
+    EMIT_KIND_CODE,   // source for the target
+    EMIT_KIND_AST,    // --emit ast
+    EMIT_KIND_DOCS    // --emit docs
+} EmitKind;
+
+/* The result of compiling with the library, for tools that want the output
+   together with what it is and how it went. The artifact belongs to the
+   context it came from: bytes is NULL when compilation failed, source_map
+   is NULL for targets that do not produce one, and errors and warnings
+   point at the context's lists. target is the canonical name of what was
+   compiled for: a platform ("linux") or a language ("python"). */
+typedef struct {
+    const char *bytes;
+    size_t size;
+    const char *target;
+    EmitKind emit;
+    const char *source_map;
+    const DiagnosticList *errors;
+    const DiagnosticList *warnings;
+} CompiledArtifact;
+
 /* Compiler Context */
 typedef struct {
     Token *tokens;
@@ -256,31 +342,141 @@ typedef struct {
     SymbolTable *symbol_table;
     ClassDef *classes;
     Platform target_platform;
+    const char *target;       // platform or language name; NULL uses target_platform
+    EmitKind emit;            // what compilation produces; code by default
     CompilationOptions options;
     char *output_path;
     char *source_file;
+    char *output;
+    char *source_map;
     int error_count;
     int warning_count;
+    DiagnosticList errors;    // lexer, parser and semantic errors
+    DiagnosticList warnings;  // enabled lints; never make compilation fail
+    CompiledArtifact artifact;
 } CompilerContext;
 
 /* Function Declarations */
 
 // Lexical Analysis
+/* The stream always ends in TOKEN_EOF, so empty input yields one token */
 Token* lexer_tokenize(const char *source, int *token_count);
 void lexer_free_tokens(Token *tokens, int count);
+void lexer_set_error_limit(int limit);
 const char* token_type_to_string(TokenType type);
 
+/* Tokens for syntax highlighting: every token and comment of the source in
+   order, newlines left out, each with its span and one of the kinds below.
+   Lines first_line to last_line (1-based, inclusive; 0 for either end means
+   unbounded) select the tokens that overlap them. The whole source is
+   still lexed, so a string or comment opened above the range is classified
+   correctly. Lexer errors are not printed, and an unterminated string or
+   comment still comes out as one token. */
+typedef enum {
+    HIGHLIGHT_KEYWORD,     // keywords, type names, true/false/null, #directives, #[attributes]
+    HIGHLIGHT_IDENTIFIER,
+    HIGHLIGHT_NUMBER,
+    HIGHLIGHT_STRING,      // string and byte string literals
+    HIGHLIGHT_OPERATOR,    // operators and punctuation
+    HIGHLIGHT_COMMENT      // //, #, /* */ and /// comments
+} HighlightKind;
+
+typedef struct {
+    HighlightKind kind;
+    TokenType type;        // TOKEN_EOF for comments
+    SourceSpan span;
+} HighlightToken;
+
+typedef struct {
+    HighlightToken *items;
+    int count;
+} HighlightList;
+
+HighlightList lexer_highlight(const char *source, int first_line, int last_line);
+void highlight_list_free(HighlightList *list);
+const char* highlight_kind_name(HighlightKind kind);
+
+/* subc fmt: `source` re-indented by four spaces per open brace, bracket or
+   bare `if`/`for`/`while`/`function` block (one closed by `end`), with
+   trailing whitespace and blank lines at either end removed, runs of blank
+   lines cut to one, and a single final newline. Nothing within a line is
+   respaced, and the inside of a string, block comment or embed block is
+   kept as written. Formatting formatted text changes nothing. */
+char* format_source(const char *source);
+/* How many of those brackets and blocks are still open at the end of
+   `source`; subc repl reads another line while any are */
+int source_open_scopes(const char *source);
+
+/* Whether NUMBER token text (optionally signed) is a float literal */
+bool number_literal_is_float(const char *text);
+
 // Parser
+/* Empty or comment-only input parses to an AST_PROGRAM with no children */
 ASTNode* parser_parse(Token *tokens, int token_count);
+/* Whether the last parser_parse() reported a syntax error; its tree is then
+   partial, and semantic_analyze() refuses it */
+bool parser_had_errors(void);
 void parser_free_ast(ASTNode *node);
+ASTNode* parser_clone_ast(const ASTNode *node);
 ASTNode* parser_parse_expression(CompilerContext *ctx);
 ASTNode* parser_parse_statement(CompilerContext *ctx);
+void parser_set_error_limit(int limit);
+/* --max-nesting: deepest expression/block nesting accepted (default 256) */
+void parser_set_max_nesting(int limit);
+void parser_set_print_stats(bool enabled);
 
 // Semantic Analysis
 int semantic_analyze(ASTNode *ast);
 int semantic_check_types(ASTNode *ast);
+
+/* Every statement in source order, nested ones after the statement that
+   holds them, with whether control can reach it: a return, #exit, break or
+   continue makes the rest of its block unreachable, and so does a constant
+   if or while condition for the branch it never takes. Function bodies are
+   reachable wherever the function is declared. */
+typedef struct {
+    SourceSpan span;
+    ASTNodeType type;
+    bool reachable;
+} ReachabilityEntry;
+
+typedef struct {
+    ReachabilityEntry *items;
+    int count;
+} ReachabilityMap;
+
+ReachabilityMap semantic_reachability(const ASTNode *ast);
+void reachability_map_free(ReachabilityMap *map);
 DataType semantic_infer_type(ASTNode *node);
 
+// Semantic lints: reported as warnings, toggled with -W<name> / -Wno-<name>
+typedef enum {
+    LINT_IMPLICIT_WIDENING,   // implicit-widening: int silently converted to float
+    LINT_UNUSED_VARIABLE,     // unused-variable: declared but never read (off by default)
+    LINT_INTEGER_DIVISION,    // integer-division: int / int that drops a remainder (off by default)
+    LINT_UNUSED_FUNCTION,     // unused-function: never reached from the program (off by default)
+    LINT_POINTLESS_STATEMENT, // pointless-statement: a statement that changes nothing, like x = x or 1 + 2
+    LINT_UNKNOWN_ATTRIBUTE,   // unknown-attribute: a #[name] the compiler does not know
+    LINT_FLOAT_PRECISION,     // float-precision: a float literal with more digits than a double keeps (off by default)
+    LINT_FLOAT_EQUALITY,      // float-equality: == or != between two floats
+    LINT_INFINITE_LOOP,       // infinite-loop: an always-true condition and no way out of the body
+    LINT_UNUSED_PARAMETER,    // unused-parameter: a function parameter the body never reads (off by default)
+    LINT_SHADOWING,           // shadowing: a declaration that hides an outer one of the same name (off by default)
+    LINT_UNREACHABLE_CODE,    // unreachable-code: a statement no path of control reaches (off by default)
+    LINT_CONSTANT_COMPARISON, // constant-comparison: a comparison that is always true or always false (off by default)
+    LINT_IGNORED_ATTRIBUTE,   // ignored-attribute: a #[inline] the inliner cannot act on
+    LINT_COUNT
+} SemanticLint;
+
+void semantic_set_lint(SemanticLint lint, bool enabled);
+bool semantic_set_lint_by_name(const char *name, bool enabled);
+void semantic_set_strict(bool strict);   // enable every lint and report them as errors
+void semantic_set_dump_symbols(bool enabled);   // print every scope's bindings to stderr
+void semantic_set_echo_last(bool enabled);   // the last top-level statement's value is used, not discarded
+void semantic_set_checked_items(int count);  // the first `count` top-level statements report no warnings
+const char* data_type_to_string(DataType type);  // "int", "string", ... as typeof() spells them
+int semantic_warning_count(void);
+
 // Symbol Table
 SymbolTable* symbol_table_create(int size);
 void symbol_table_free(SymbolTable *table);
@@ -298,13 +494,38 @@ void class_def_free(ClassDef *cls);
 // Code Generation
 char* codegen_generate(ASTNode *ast, Platform platform);
 char* codegen_generate_c(ASTNode *ast, Platform platform);
+char* codegen_generate_c_header(ASTNode *ast, const char *guard);
 char* codegen_embed_cpp(const char *cpp_code);
 char* codegen_embed_c(const char *c_code);
 void optimize_c_output(ASTNode *node);
+void optimizer_set_fast_math(bool enabled);
+void optimizer_set_licm(bool enabled);
+void optimizer_set_inlining(bool enabled);
+void optimizer_set_unrolling(bool enabled);
+
+/* Identifier mangling for targets whose reserved words or character set
+   differ from SUB's. A name that is a target keyword, contains non-ASCII
+   bytes or starts with the reserved "sub_" prefix becomes "sub_" plus an
+   escaped copy ('_' -> "__", other bytes -> "_xHH"); any other name is
+   returned unchanged. The mapping is injective and the result stays valid
+   for the rest of the process, so a name always mangles the same way. */
+typedef enum {
+    MANGLE_C,
+    MANGLE_JS,
+    MANGLE_ASM
+} MangleTarget;
+
+const char* mangle(const char *name, MangleTarget target);
+
+/* Reports every construct in `ast` that the named backend ("C", "Python",
+   ...) cannot lower yet, with its source span, and returns false if there
+   was one. The generators call this before emitting anything. */
+bool codegen_check_support(const ASTNode *ast, const char *backend);
 
 /* Multi-language transpilation */
 char* codegen_python(ASTNode *ast, const char *source);
 char* codegen_javascript(ASTNode *ast, const char *source);
+char* codegen_typescript(ASTNode *ast, const char *source);
 char* codegen_java(ASTNode *ast, const char *source);
 char* codegen_swift(ASTNode *ast, const char *source);
 char* codegen_kotlin(ASTNode *ast, const char *source);
@@ -312,22 +533,229 @@ char* codegen_rust(ASTNode *ast, const char *source);
 char* codegen_go(ASTNode *ast, const char *source);
 char* codegen_ruby(ASTNode *ast, const char *source);
 char* codegen_assembly(ASTNode *ast, const char *source);
+char* codegen_css(ASTNode *ast, const char *source);
+/* The generator for a language target ("python" or "py", "typescript", "c",
+   ...); NULL for a name that is not one, or when generation fails */
+char* codegen_generate_language(const char *name, ASTNode *ast, const char *source);
+/* With a source map enabled, the JavaScript and TypeScript generators also
+   build a version 3 source map of the output. codegen_take_source_map()
+   hands over the one the last of them built (malloc'd), or NULL. */
+void codegen_set_source_map(bool enabled);
+char* codegen_take_source_map(void);
 
 // Utility Functions
 char* read_file(const char *filename);
+/* Check that the `size` bytes read from `filename` are UTF-8, or transcode
+   them from Latin-1 under --encoding latin-1. Returns the text to compile
+   (possibly a new allocation), or NULL after reporting the offset of the
+   first invalid sequence; `content` is freed in that case. */
+char* decode_source(const char *filename, char *content, size_t size);
 void write_file(const char *filename, const char *content);
 void compile_error(const char *message, int line);
 void compile_error_with_col(const char *message, int line, int column);
+void print_ast(ASTNode *node, int depth);
+/* What print_ast() prints, as a malloc'd string (--emit ast) */
+char* ast_dump(ASTNode *node);
+/* --emit docs: Markdown headed `title`, listing every public function (a
+   name without a leading underscore) with its parameters and /// doc
+   comment; a malloc'd string */
+char* docs_generate(ASTNode *ast, const char *title);
+/* --stats: token counts by kind, AST node count, deepest expression and the
+   number of functions, variables and constants, printed to stderr */
+void print_compile_stats(const Token *tokens, int token_count, const ASTNode *ast);
+SourceSpan ast_span(const ASTNode *node);
+/* Whether a string literal is written as plain characters between quotes,
+   with no escapes. Only these have a len() and indices worth folding: an
+   escape such as \0 decodes differently from one backend to the next. */
+bool ast_literal_is_verbatim(const ASTNode *node);
+/* Element count of an array literal. A repeat [value; count] keeps one
+   child and, once semantic analysis folds it, an int literal count in
+   node->right; -1 while that count is not folded yet. */
+int ast_array_length(const ASTNode *node);
+/* True for a repeat literal [value; count] with its count folded */
+bool ast_is_array_repeat(const ASTNode *node);
+/* Whether a function was marked #[name] or #[name(...)] */
+bool ast_has_attribute(const ASTNode *node, const char *name);
+/* Whether #[name(...)] on a function lists `item`; target names are compared
+   by canonical_target_name() */
+bool ast_attribute_lists(const ASTNode *node, const char *name, const char *item);
+
+/* AST traversal. ast_walk() calls the node's visit[] entry, or enter when that
+   is NULL, before its operands; returning false skips them. leave runs after
+   the operands, so it may rewrite the node bottom-up. Every callback may be
+   NULL, in which case the walk just carries on. */
+typedef struct ASTVisitor ASTVisitor;
+typedef bool (*ASTVisitFn)(ASTVisitor *visitor, ASTNode *node);
+struct ASTVisitor {
+    ASTVisitFn visit[AST_NODE_TYPE_COUNT];
+    ASTVisitFn enter;
+    void (*leave)(ASTVisitor *visitor, ASTNode *node);
+    void *data;
+};
+void ast_walk(ASTNode *node, ASTVisitor *visitor);
+/* Walk left, right, condition, body and children; what a visit entry calls
+   when it wants to handle the operands itself */
+void ast_walk_operands(ASTNode *node, ASTVisitor *visitor);
+/* Number the nodes 1, 2, ... in ast_walk() order; returns how many there are.
+   parser_parse() does this, so one source always gives the same ids. */
+int ast_number_nodes(ASTNode *root);
+/* The first node under `root`, in ast_walk() order, that `match` accepts;
+   `data` is passed through to it */
+typedef bool (*ASTMatchFn)(const ASTNode *node, const void *data);
+ASTNode* ast_find(ASTNode *root, ASTMatchFn match, const void *data);
+/* The node with `id` under `root`, or NULL */
+ASTNode* ast_find_node(ASTNode *root, NodeId id);
+/* Whether anything under `root` is a `type` node */
+bool ast_contains_type(ASTNode *root, ASTNodeType type);
+/* Whether anything under `root` calls `name` */
+bool ast_calls_function(ASTNode *root, const char *name);
 
 // Compiler Interface
 CompilerContext* compiler_create(const char *source_file);
 void compiler_free(CompilerContext *ctx);
 bool compiler_compile(CompilerContext *ctx);
+bool compiler_compile_source(CompilerContext *ctx, const char *source);
 char* compiler_get_output(CompilerContext *ctx);
+/* compiler_compile_source, returning the artifact; NULL only without a
+   context or source */
+const CompiledArtifact* compiler_compile_artifact(CompilerContext *ctx, const char *source);
+const char* emit_kind_name(EmitKind emit);
 
 // Platform-specific helpers
 const char* platform_to_string(Platform platform);
 bool platform_supports_cpp(Platform platform);
 const char* platform_get_compiler(Platform platform, bool use_cpp);
 
+// String builtins, shared by semantic analysis, the interpreter and codegen
+typedef enum {
+    STR_BUILTIN_SUBSTR,     // substr(s, start, len) -> string
+    STR_BUILTIN_INDEX_OF,   // index_of(s, sub) -> int, -1 if absent
+    STR_BUILTIN_TO_UPPER,   // to_upper(s) -> string
+    STR_BUILTIN_TO_LOWER,   // to_lower(s) -> string
+    STR_BUILTIN_COUNT
+} StringBuiltin;
+
+int string_builtin_lookup(const char *name);   // StringBuiltin, or -1
+int string_builtin_arity(StringBuiltin builtin);
+
+// Runtime integer overflow behaviour for + - * / (and unary -), chosen
+// with --overflow and honoured by the interpreter and the C backend
+typedef enum {
+    OVERFLOW_WRAP,          // two's-complement wraparound (default)
+    OVERFLOW_CHECK,         // stop with a runtime error
+    OVERFLOW_SATURATE       // clamp to the int bounds of the --int-width
+} OverflowMode;
+
+OverflowMode overflow_mode(void);
+void set_overflow_mode(OverflowMode mode);
+
+// Width in bits of `int` and unsuffixed integer literals, 32 or 64 (the
+// default), chosen with --int-width. Literals must fit it, constants fold
+// within it and the interpreter, C and Rust backends overflow at it.
+int int_width(void);
+void set_int_width(int bits);
+long long int_max(void);
+long long int_min(void);
+bool int_fits(long long value);
+
+// What the transpiler builds, chosen with --crate-type. A library has no
+// entry point: only functions and constants may appear at the top level,
+// and the C, JavaScript/TypeScript and Python backends export them.
+typedef enum {
+    CRATE_TYPE_BIN,         // a program with an entry point (default)
+    CRATE_TYPE_LIB          // a library of exported functions and constants
+} CrateType;
+
+CrateType crate_type(void);
+void set_crate_type(CrateType type);
+
+// Function chosen with --entry, called once the top-level statements have
+// run; NULL (the default) when the program is just its top-level statements.
+const char* entry_function(void);
+void set_entry_function(const char *name);
+
+// --prelude FILE: functions and constants every program may use without
+// defining them; NULL (the default) for no prelude. prelude_parse() reads
+// and parses it, leaving the diagnostic source on its text (returned in
+// `source`, to free after the tree); NULL if the file cannot be read.
+const char* prelude_file(void);
+void set_prelude_file(const char *path);
+ASTNode* prelude_parse(char **source);
+
+// True if `path` names a directory. read_file() and the other readers
+// refuse one, since it opens like an empty or endless file.
+bool path_is_directory(const char *path);
+// The .sb files under directory `dir`, its subdirectories included, sorted
+// by path: a malloc'd array of malloc'd strings, or NULL (*count 0) if the
+// directory cannot be read.
+char** list_source_files(const char *dir, int *count);
+
+// Bytes of C stack the program may use (RLIMIT_STACK, or the platform's
+// default). The parser and interpreter stop nesting before reaching it.
+size_t stack_limit_bytes(void);
+
+// -q/--quiet: drivers print only diagnostics and the output that was asked
+// for. print_status() is printf() for banners, progress and success lines,
+// and prints nothing in quiet mode.
+bool quiet_mode(void);
+void set_quiet_mode(bool quiet);
+void print_status(const char *fmt, ...);
+
+// The comment line backends put at the top of generated code, written in
+// the target's comment syntax. --banner replaces the text and --no-banner
+// makes this NULL, in which case no banner is written.
+const char* codegen_banner(void);
+void set_codegen_banner(const char *text);
+
+// Line endings of generated files, applied as they are written so every
+// backend follows them. --output-eol crlf turns each "\n" into "\r\n";
+// --final-newline (mode 1) ends the file with exactly one line break and
+// --no-final-newline (mode 0) with none. Mode -1, the default, keeps the
+// backend's ending. Returns a new string.
+void set_output_eol(bool crlf);
+void set_final_newline(int mode);
+char* apply_output_newlines(const char *code);
+
+// Path of the file being compiled, as it should appear in generated output
+// and binaries: the path given on the command line with any --remap-path
+// prefix rewritten. NULL until a driver sets it.
+void set_source_path(const char *path);
+const char* source_path(void);
+
+// The target being compiled for, by its canonical name (py -> python,
+// run -> interpret). Semantic analysis drops #[target(...)] functions that
+// do not list it. NULL, the default, keeps every function.
+const char* canonical_target_name(const char *name);
+const char* active_target(void);
+void set_active_target(const char *name);
+
+// Source excerpts under lexer, parser and some semantic errors. The lexer
+// registers the text it tokenizes; columns are 1-based bytes, and tabs are
+// expanded to the --tab-width (default 4) when the line and its caret are
+// printed. A span is underlined from `column` up to `end_column` (exclusive).
+// A construct still open at the end of the input (a string, block comment,
+// bracket or brace) gets two excerpts: its `length`-byte opener marked
+// "opened here", and the end of the input marked "expected closing here".
+// A note is an excerpt whose caret carries a label, such as "first declared
+// here" under the earlier of two clashing declarations.
+void diagnostic_set_source(const char *source);
+const char* diagnostic_source(void);
+void diagnostic_set_tab_width(int width);
+void diagnostic_print_excerpt(int line, int column);
+void diagnostic_print_note(int line, int column, const char *label);
+void diagnostic_print_span(SourceSpan span);
+void diagnostic_print_unclosed(int line, int column, int length);
+
+// While collection is on, every error and warning is also appended to the
+// given lists (either may be NULL); pass NULL, NULL to stop collecting
+void diagnostic_collect(DiagnosticList *errors, DiagnosticList *warnings);
+void diagnostic_record(bool warning, const char *lint, int line, int column, const char *fmt, ...);
+void diagnostic_record_v(bool warning, const char *lint, int line, int column, const char *fmt, va_list ap);
+void diagnostic_list_free(DiagnosticList *list);
+// Errors recorded so far, whether or not they are being collected
+int diagnostic_error_count(void);
+
+// Command-line helpers shared by the drivers
+bool take_diagnostic_options(int *argc, char *argv[]);
+
 #endif /* SUB_COMPILER_H */
@@ -0,0 +1,204 @@
+/* ========================================
+   SUB Language - Pipeline Benchmarks
+   Times each compiler stage on generated programs
+   File: tests/bench/pipeline_bench.c
+
+   Build and run with `make bench`. Every stage is timed on its own input
+   (tokens for the parser, a fresh AST for the semantic pass, and so on),
+   so set-up and freeing are not counted. A last run reports the lexer's
+   throughput on words that all go through the keyword lookup.
+   ======================================== */
+
+#define _POSIX_C_SOURCE 199309L
+#include "sub_compiler.h"
+#include <stdarg.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <time.h>
+
+/* Program sizes in lines; the last one is the large-input baseline */
+static const int SIZES[] = {100, 1000, 10000};
+
+typedef struct {
+    char *data;
+    size_t len;
+    size_t cap;
+    int lines;
+} Source;
+
+static void src_printf(Source *src, const char *fmt, ...) {
+    char line[256];
+    va_list args;
+    va_start(args, fmt);
+    int n = vsnprintf(line, sizeof(line), fmt, args);
+    va_end(args);
+    if (n < 0) return;
+    if (src->len + (size_t)n + 1 > src->cap) {
+        src->cap = (src->cap + (size_t)n + 1) * 2;
+        src->data = realloc(src->data, src->cap);
+        if (!src->data) {
+            fprintf(stderr, "bench: out of memory\n");
+            exit(1);
+        }
+    }
+    memcpy(src->data + src->len, line, (size_t)n + 1);
+    src->len += (size_t)n;
+    for (int i = 0; i < n; i++) {
+        if (line[i] == '\n') src->lines++;
+    }
+}
+
+/* A valid program of about `lines` lines: functions with locals, branches
+   and loops, followed by a call to each of them */
+static char* generate_program(int lines, int *actual_lines) {
+    Source src = {NULL, 0, 0, 0};
+    int funcs = 0;
+    while (src.lines + funcs + 2 < lines) {
+        int i = funcs++;
+        src_printf(&src, "function f%d(a: int, b: int): int {\n", i);
+        src_printf(&src, "    var total = a + b * %d\n", i % 7 + 1);
+        src_printf(&src, "    if total > %d {\n", i);
+        src_printf(&src, "        total = total - %d\n", i % 5);
+        src_printf(&src, "    } else {\n");
+        src_printf(&src, "        total = total + 1\n");
+        src_printf(&src, "    }\n");
+        src_printf(&src, "    var j = 0\n");
+        src_printf(&src, "    while j < 3 {\n");
+        src_printf(&src, "        total = total + j * (2 + 3)\n");
+        src_printf(&src, "        j = j + 1\n");
+        src_printf(&src, "    }\n");
+        src_printf(&src, "    return total\n");
+        src_printf(&src, "}\n");
+    }
+    src_printf(&src, "var acc = 0\n");
+    for (int i = 0; i < funcs; i++) {
+        src_printf(&src, "acc = acc + f%d(%d, 2)\n", i, i);
+    }
+    src_printf(&src, "print(acc)\n");
+    *actual_lines = src.lines;
+    return src.data;
+}
+
+static double now_ms(void) {
+    struct timespec ts;
+    clock_gettime(CLOCK_MONOTONIC, &ts);
+    return ts.tv_sec * 1000.0 + ts.tv_nsec / 1e6;
+}
+
+typedef struct {
+    double total;
+    double best;
+} Timing;
+
+static void record(Timing *t, double elapsed) {
+    t->total += elapsed;
+    if (t->best == 0 || elapsed < t->best) t->best = elapsed;
+}
+
+static ASTNode* parse_source(const char *source) {
+    int count = 0;
+    Token *tokens = lexer_tokenize(source, &count);
+    ASTNode *ast = tokens ? parser_parse(tokens, count) : NULL;
+    lexer_free_tokens(tokens, count);
+    return ast;
+}
+
+static void bench_size(int target_lines) {
+    int lines = 0;
+    char *source = generate_program(target_lines, &lines);
+    int iterations = target_lines >= 10000 ? 3 : 20000 / target_lines;
+    Timing tokenize = {0, 0}, parse = {0, 0}, semantic = {0, 0};
+    Timing optimize = {0, 0}, codegen = {0, 0}, compile = {0, 0};
+
+    for (int it = 0; it < iterations; it++) {
+        double start;
+        int count = 0;
+
+        start = now_ms();
+        Token *tokens = lexer_tokenize(source, &count);
+        record(&tokenize, now_ms() - start);
+
+        start = now_ms();
+        ASTNode *ast = parser_parse(tokens, count);
+        record(&parse, now_ms() - start);
+        lexer_free_tokens(tokens, count);
+
+        start = now_ms();
+        int ok = semantic_analyze(ast);
+        record(&semantic, now_ms() - start);
+        if (!ok) {
+            fprintf(stderr, "bench: generated program failed semantic analysis\n");
+            exit(1);
+        }
+
+        start = now_ms();
+        optimize_c_output(ast);
+        record(&optimize, now_ms() - start);
+
+        /* The C backend runs the optimizer again; on an optimized tree that
+           pass finds nothing to do */
+        start = now_ms();
+        char *code = codegen_generate_c(ast, PLATFORM_LINUX);
+        record(&codegen, now_ms() - start);
+        free(code);
+        parser_free_ast(ast);
+
+        start = now_ms();
+        ast = parse_source(source);
+        semantic_analyze(ast);
+        code = codegen_generate_c(ast, PLATFORM_LINUX);
+        record(&compile, now_ms() - start);
+        free(code);
+        parser_free_ast(ast);
+    }
+
+    const struct { const char *name; Timing *t; } rows[] = {
+        {"tokenize", &tokenize}, {"parse", &parse}, {"semantic", &semantic},
+        {"optimize", &optimize}, {"codegen (C)", &codegen}, {"compile (end-to-end)", &compile},
+    };
+    for (size_t i = 0; i < sizeof(rows) / sizeof(rows[0]); i++) {
+        printf("%-22s %7d %6d %12.3f %12.3f\n", rows[i].name, lines, iterations,
+               rows[i].t->total / iterations, rows[i].t->best);
+    }
+    printf("\n");
+    free(source);
+}
+
+/* Keywords and names of every length, long and short, one after another:
+   the case where keyword classification dominates tokenizing */
+static void bench_lexer_throughput(void) {
+    static const char *words[] = {
+        "var", "total", "while", "javascript", "implements", "count", "return",
+        "protected", "value", "none", "endembed", "index", "x", "function_name",
+    };
+    const int nwords = (int)(sizeof(words) / sizeof(words[0]));
+    Source src = {NULL, 0, 0, 0};
+    for (int i = 0; src.lines < 50000; i += 4) {
+        src_printf(&src, "%s %s %s %s\n", words[i % nwords], words[(i + 1) % nwords],
+                   words[(i + 2) % nwords], words[(i + 3) % nwords]);
+    }
+
+    const int iterations = 5;
+    double best = 0;
+    int count = 0;
+    for (int it = 0; it < iterations; it++) {
+        double start = now_ms();
+        Token *tokens = lexer_tokenize(src.data, &count);
+        double elapsed = now_ms() - start;
+        if (best == 0 || elapsed < best) best = elapsed;
+        lexer_free_tokens(tokens, count);
+    }
+    printf("lexer throughput: %.1f MB/s, %.1f M tokens/s (%d lines, %d tokens, best of %d)\n",
+           src.len / 1e6 / (best / 1000.0), count / 1e6 / (best / 1000.0), src.lines, count, iterations);
+    free(src.data);
+}
+
+int main(void) {
+    printf("%-22s %7s %6s %12s %12s\n", "stage", "lines", "iters", "mean ms", "best ms");
+    for (size_t i = 0; i < sizeof(SIZES) / sizeof(SIZES[0]); i++) {
+        bench_size(SIZES[i]);
+    }
+    bench_lexer_throughput();
+    return 0;
+}
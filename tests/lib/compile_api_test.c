@@ -0,0 +1,383 @@
+/* ========================================
+   SUB Language - Library API Tests
+   Checks compiler_compile_source(), compiler_compile_artifact(),
+   ast_walk(), node ids and the lexer's keyword and highlighting entry
+   points through the public header
+   File: tests/lib/compile_api_test.c
+
+   Build and run with `make libtest`. Each case compiles a small program
+   in memory and checks the result, the output and the two diagnostic
+   lists; the compiler's own messages still go to stderr.
+   ======================================== */
+
+#include "sub_compiler.h"
+#include <stdio.h>
+#include <string.h>
+
+static int g_failures = 0;
+
+static void expect(bool condition, const char *label) {
+    printf("%s %s\n", condition ? "ok  " : "FAIL", label);
+    if (!condition) g_failures++;
+}
+
+static CompilerContext* compile(const char *source, bool *ok) {
+    CompilerContext *ctx = compiler_create(NULL);
+    *ok = compiler_compile_source(ctx, source);
+    return ctx;
+}
+
+static void test_warning_only(void) {
+    bool ok;
+    semantic_set_lint(LINT_UNUSED_VARIABLE, true);
+    CompilerContext *ctx = compile("function main() {\n    var unused = 1\n}\n", &ok);
+    semantic_set_lint(LINT_UNUSED_VARIABLE, false);
+
+    expect(ok, "unused variable: compiles");
+    expect(compiler_get_output(ctx) != NULL, "unused variable: has output");
+    expect(ctx->errors.count == 0, "unused variable: no errors");
+    expect(ctx->warnings.count == 1, "unused variable: one warning");
+    if (ctx->warnings.count == 1) {
+        Diagnostic *d = &ctx->warnings.items[0];
+        expect(d->line == 2, "unused variable: warning is on line 2");
+        expect(d->lint && strcmp(d->lint, "unused-variable") == 0,
+               "unused variable: warning names its lint");
+        expect(strstr(d->message, "unused") != NULL, "unused variable: warning message");
+    }
+    compiler_free(ctx);
+}
+
+static void test_error_and_warning(void) {
+    bool ok;
+    semantic_set_lint(LINT_UNUSED_VARIABLE, true);
+    CompilerContext *ctx = compile("function main() {\n    var unused = 1\n    print(missing)\n}\n", &ok);
+    semantic_set_lint(LINT_UNUSED_VARIABLE, false);
+
+    expect(!ok, "undefined name: fails");
+    expect(compiler_get_output(ctx) == NULL, "undefined name: no output");
+    expect(ctx->errors.count == 1, "undefined name: one error");
+    expect(ctx->warnings.count == 1, "undefined name: the warning is kept apart");
+    if (ctx->errors.count == 1) {
+        expect(ctx->errors.items[0].lint == NULL, "undefined name: errors carry no lint");
+        expect(ctx->errors.items[0].line == 3, "undefined name: error is on line 3");
+    }
+    compiler_free(ctx);
+}
+
+static void test_parse_error(void) {
+    bool ok;
+    CompilerContext *ctx = compile("var x = (1 +\n", &ok);
+    expect(!ok, "parse error: fails");
+    expect(ctx->errors.count > 0, "parse error: reported as an error");
+    expect(ctx->warnings.count == 0, "parse error: no warnings");
+    compiler_free(ctx);
+}
+
+static void test_artifact(void) {
+    CompilerContext *ctx = compiler_create(NULL);
+    ctx->target_platform = PLATFORM_WINDOWS;
+    const CompiledArtifact *artifact = compiler_compile_artifact(ctx, "print(\"hi\")\n");
+    expect(artifact && artifact->bytes, "artifact: has bytes");
+    if (artifact && artifact->bytes) {
+        expect(strcmp(artifact->target, "windows") == 0, "artifact: records its target");
+        expect(artifact->emit == EMIT_KIND_CODE, "artifact: is code");
+        expect(artifact->size == strlen(compiler_get_output(ctx)), "artifact: size is the output length");
+        expect(artifact->source_map == NULL, "artifact: no source map");
+        expect(artifact->errors->count == 0 && artifact->warnings == &ctx->warnings,
+               "artifact: diagnostics are the context's");
+    }
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    artifact = compiler_compile_artifact(ctx, "print(missing)\n");
+    expect(artifact && !artifact->bytes && artifact->size == 0, "artifact: failure has no bytes");
+    expect(artifact && artifact->errors->count == 1, "artifact: failure keeps its error");
+    compiler_free(ctx);
+}
+
+static void test_artifact_targets(void) {
+    CompilerContext *ctx = compiler_create(NULL);
+    ctx->target = "py";
+    const CompiledArtifact *artifact = compiler_compile_artifact(ctx, "print(\"hi\")\n");
+    expect(artifact && artifact->bytes && strstr(artifact->bytes, "print(") != NULL,
+           "language artifact: python code");
+    expect(artifact && strcmp(artifact->target, "python") == 0,
+           "language artifact: target is the canonical name");
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    ctx->target = "ts";
+    artifact = compiler_compile_artifact(ctx, "var n: int = 1\nprint(n)\n");
+    expect(artifact && artifact->bytes && strstr(artifact->bytes, "number") != NULL,
+           "language artifact: typescript code");
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    ctx->emit = EMIT_KIND_AST;
+    artifact = compiler_compile_artifact(ctx, "print(\"hi\")\n");
+    expect(artifact && artifact->emit == EMIT_KIND_AST && artifact->bytes &&
+           strstr(artifact->bytes, "PROGRAM") != NULL, "ast artifact: the tree dump");
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    ctx->emit = EMIT_KIND_DOCS;
+    artifact = compiler_compile_artifact(ctx, "/// Adds one\nfunction inc(x: int): int {\n    return x + 1\n}\n");
+    expect(artifact && artifact->emit == EMIT_KIND_DOCS && artifact->bytes &&
+           strstr(artifact->bytes, "inc") != NULL && strstr(artifact->bytes, "Adds one") != NULL,
+           "docs artifact: documents the function");
+    compiler_free(ctx);
+}
+
+static void test_source_map(void) {
+    CompilerContext *ctx = compiler_create(NULL);
+    ctx->target = "js";
+    ctx->options.source_map = true;
+    const CompiledArtifact *artifact = compiler_compile_artifact(ctx, "var a = 1\nprint(a)\n");
+    expect(artifact && artifact->source_map && strstr(artifact->source_map, "\"mappings\"") != NULL,
+           "source map: built for javascript");
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    ctx->options.source_map = true;
+    artifact = compiler_compile_artifact(ctx, "print(\"hi\")\n");
+    expect(artifact && artifact->bytes && artifact->source_map == NULL, "source map: none for C");
+    compiler_free(ctx);
+
+    ctx = compiler_create(NULL);
+    ctx->target = "js";
+    artifact = compiler_compile_artifact(ctx, "print(\"hi\")\n");
+    expect(artifact && artifact->source_map == NULL, "source map: only when asked for");
+    compiler_free(ctx);
+}
+
+static bool count_print(ASTVisitor *visitor, ASTNode *node) {
+    if (node->value && strcmp(node->value, "print") == 0) (*(int *)visitor->data)++;
+    return true;
+}
+
+static bool skip_function(ASTVisitor *visitor, ASTNode *node) {
+    (void)visitor;
+    (void)node;
+    return false;
+}
+
+static bool is_return(const ASTNode *node, const void *data) {
+    (void)data;
+    return node->type == AST_RETURN_STMT;
+}
+
+static void test_visitor(void) {
+    const char *source =
+        "var x = 1\n"
+        "print(x)\n"
+        "if x > 0 {\n"
+        "    print(x + 1)\n"
+        "}\n"
+        "function f(a) {\n"
+        "    print(a)\n"
+        "    return a\n"
+        "}\n";
+    int token_count;
+    Token *tokens = lexer_tokenize(source, &token_count);
+    ASTNode *ast = parser_parse(tokens, token_count);
+
+    int prints = 0;
+    ASTVisitor counter = { .data = &prints };
+    counter.visit[AST_CALL_EXPR] = count_print;
+    ast_walk(ast, &counter);
+    expect(prints == 3, "visitor: counts every print call");
+
+    prints = 0;
+    counter.visit[AST_FUNCTION_DECL] = skip_function;
+    ast_walk(ast, &counter);
+    expect(prints == 2, "visitor: false skips a node's operands");
+
+    ASTNode *ret = ast_find(ast, is_return, NULL);
+    expect(ret && ret->type == AST_RETURN_STMT, "visitor: ast_find returns the matching node");
+    expect(ast_contains_type(ast, AST_IF_STMT) && !ast_contains_type(ast, AST_WHILE_STMT),
+           "visitor: ast_contains_type looks for a node type");
+    expect(ast_calls_function(ast, "print") && !ast_calls_function(ast, "f"),
+           "visitor: ast_calls_function finds calls only");
+
+    parser_free_ast(ast);
+    lexer_free_tokens(tokens, token_count);
+}
+
+static bool collect_id(ASTVisitor *visitor, ASTNode *node) {
+    int *ids = visitor->data;
+    if (ids[0] < 63) ids[++ids[0]] = node->id;
+    return true;
+}
+
+static void test_node_ids(void) {
+    const char *source = "var x = 1 + 2\nif x > 2 {\n    print(x)\n}\n";
+    int token_count;
+    Token *tokens = lexer_tokenize(source, &token_count);
+    ASTNode *ast = parser_parse(tokens, token_count);
+
+    int ids[64] = {0};
+    ASTVisitor collector = { .enter = collect_id, .data = ids };
+    ast_walk(ast, &collector);
+    bool in_order = ids[0] > 1;
+    for (int i = 1; i <= ids[0]; i++) {
+        if (ids[i] != i) in_order = false;
+    }
+    expect(in_order, "node ids: unique and numbered in walk order");
+
+    ASTNode *again = parser_parse(tokens, token_count);
+    bool same = true;
+    for (int i = 1; i <= ids[0]; i++) {
+        ASTNode *a = ast_find_node(ast, i);
+        ASTNode *b = ast_find_node(again, i);
+        if (!a || !b || a->id != i || a->type != b->type || a->line != b->line || a->column != b->column) {
+            same = false;
+        }
+    }
+    expect(same, "node ids: the same source gives the same ids");
+    expect(ast_find_node(ast, 1) == ast, "node ids: the program is node 1");
+    expect(ast_find_node(ast, ids[0] + 1) == NULL && ast_find_node(ast, 0) == NULL,
+           "node ids: an unknown id finds nothing");
+
+    ASTNode *clone = parser_clone_ast(ast);
+    ASTNode *print_call = NULL;
+    for (int i = 1; i <= ids[0]; i++) {
+        ASTNode *node = ast_find_node(ast, i);
+        if (node->type == AST_CALL_EXPR) print_call = node;
+    }
+    ASTNode *cloned_call = print_call ? ast_find_node(clone, print_call->id) : NULL;
+    expect(cloned_call && cloned_call != print_call && cloned_call->type == AST_CALL_EXPR,
+           "node ids: a clone keeps them");
+
+    parser_free_ast(clone);
+    parser_free_ast(again);
+    parser_free_ast(ast);
+    lexer_free_tokens(tokens, token_count);
+
+    /* Default arguments copy the declared value into each call */
+    tokens = lexer_tokenize("function f(a, b = 2) {\n    return a + b\n}\nprint(f(1))\nprint(f(1))\n",
+                            &token_count);
+    ast = parser_parse(tokens, token_count);
+    int parsed = ast_number_nodes(ast);
+    semantic_analyze(ast);
+    memset(ids, 0, sizeof(ids));
+    ast_walk(ast, &collector);
+    bool unique = ids[0] > parsed;
+    for (int i = 1; i <= ids[0]; i++) {
+        for (int k = i + 1; k <= ids[0]; k++) {
+            if (ids[i] != 0 && ids[i] == ids[k]) unique = false;
+        }
+    }
+    expect(unique, "node ids: copies made by semantic analysis do not repeat them");
+    parser_free_ast(ast);
+    lexer_free_tokens(tokens, token_count);
+}
+
+static void test_doc_comment(void) {
+    int token_count;
+    Token *tokens = lexer_tokenize("/// Doubles n.\n/// Any int.\nfunction twice(n) {\n    return n * 2\n}\n"
+                                   "// plain comment\nfunction bare() {\n}\n", &token_count);
+    ASTNode *ast = parser_parse(tokens, token_count);
+    expect(ast && ast->child_count == 2, "doc comment: two functions parsed");
+    if (ast && ast->child_count == 2) {
+        const char *doc = ast->children[0]->doc;
+        expect(doc && strcmp(doc, "Doubles n.\nAny int.") == 0, "doc comment: /// lines are kept on the function");
+        expect(ast->children[1]->doc == NULL, "doc comment: // is not a doc comment");
+    }
+    parser_free_ast(ast);
+    lexer_free_tokens(tokens, token_count);
+}
+
+static void test_keywords(void) {
+    static const struct { const char *word; TokenType type; } keywords[] = {
+        {"var", TOKEN_VAR}, {"const", TOKEN_CONST}, {"let", TOKEN_LET},
+        {"function", TOKEN_FUNCTION}, {"return", TOKEN_RETURN}, {"if", TOKEN_IF},
+        {"elif", TOKEN_ELIF}, {"else", TOKEN_ELSE}, {"for", TOKEN_FOR},
+        {"while", TOKEN_WHILE}, {"do", TOKEN_DO}, {"end", TOKEN_END},
+        {"break", TOKEN_BREAK}, {"continue", TOKEN_CONTINUE}, {"try", TOKEN_TRY},
+        {"catch", TOKEN_CATCH}, {"finally", TOKEN_FINALLY}, {"throw", TOKEN_THROW},
+        {"embed", TOKEN_EMBED}, {"endembed", TOKEN_ENDEMBED}, {"cpp", TOKEN_CPP},
+        {"python", TOKEN_PYTHON}, {"javascript", TOKEN_JAVASCRIPT}, {"rust", TOKEN_RUST},
+        {"ui", TOKEN_UI}, {"class", TOKEN_CLASS}, {"extends", TOKEN_EXTENDS},
+        {"implements", TOKEN_IMPLEMENTS}, {"new", TOKEN_NEW}, {"this", TOKEN_THIS},
+        {"super", TOKEN_SUPER}, {"static", TOKEN_STATIC}, {"private", TOKEN_PRIVATE},
+        {"public", TOKEN_PUBLIC}, {"protected", TOKEN_PROTECTED}, {"async", TOKEN_ASYNC},
+        {"await", TOKEN_AWAIT}, {"yield", TOKEN_YIELD}, {"int", TOKEN_INT},
+        {"float", TOKEN_FLOAT}, {"string", TOKEN_STRING}, {"bool", TOKEN_BOOL},
+        {"auto", TOKEN_AUTO}, {"void", TOKEN_VOID}, {"true", TOKEN_TRUE},
+        {"false", TOKEN_FALSE}, {"null", TOKEN_NULL}, {"none", TOKEN_NULL},
+        /* Prefixes, extensions and other casings of keywords are names */
+        {"variable", TOKEN_IDENTIFIER}, {"fo", TOKEN_IDENTIFIER}, {"Var", TOKEN_IDENTIFIER},
+        {"javascripts", TOKEN_IDENTIFIER}, {"implementsx", TOKEN_IDENTIFIER}, {"e", TOKEN_IDENTIFIER},
+    };
+    size_t count = sizeof(keywords) / sizeof(keywords[0]);
+    bool all = true;
+    for (size_t i = 0; i < count; i++) {
+        int token_count;
+        Token *tokens = lexer_tokenize(keywords[i].word, &token_count);
+        if (!tokens || token_count < 1 || tokens[0].type != keywords[i].type) {
+            printf("     '%s' lexed as %d, expected %d\n", keywords[i].word,
+                   tokens && token_count > 0 ? (int)tokens[0].type : -1, (int)keywords[i].type);
+            all = false;
+        }
+        lexer_free_tokens(tokens, token_count);
+    }
+    expect(all, "keywords: every keyword gets its token type and near misses stay identifiers");
+}
+
+/* "kind line:column-end_line:end_column" for every highlight token, one per line */
+static void describe_highlight(const HighlightList *list, char *out, size_t size) {
+    size_t n = 0;
+    out[0] = '\0';
+    for (int i = 0; i < list->count && n < size; i++) {
+        const HighlightToken *t = &list->items[i];
+        n += snprintf(out + n, size - n, "%s %d:%d-%d:%d\n", highlight_kind_name(t->kind),
+                      t->span.line, t->span.column, t->span.end_line, t->span.end_column);
+    }
+}
+
+static void test_highlight(void) {
+    char described[512];
+    HighlightList list = lexer_highlight("#var x = \"hi\" // c", 0, 0);
+    describe_highlight(&list, described, sizeof(described));
+    expect(strcmp(described, "comment 1:1-1:19\n") == 0, "highlight: '#' starts a comment that runs to the end of the line");
+    highlight_list_free(&list);
+
+    list = lexer_highlight("var x = \"hi\" // c", 0, 0);
+    describe_highlight(&list, described, sizeof(described));
+    expect(strcmp(described, "keyword 1:1-1:4\nidentifier 1:5-1:6\noperator 1:7-1:8\n"
+                             "string 1:9-1:13\ncomment 1:14-1:18\n") == 0,
+           "highlight: keyword, identifier, operator, string and comment");
+    highlight_list_free(&list);
+
+    list = lexer_highlight("var n = 42\n/* a\nb */ n = n + 1.5\nprint(n)\n", 3, 3);
+    describe_highlight(&list, described, sizeof(described));
+    expect(strcmp(described, "comment 2:1-3:5\nidentifier 3:6-3:7\noperator 3:8-3:9\nidentifier 3:10-3:11\n"
+                             "operator 3:12-3:13\nnumber 3:14-3:17\n") == 0,
+           "highlight: a line range keeps the tokens overlapping it");
+    highlight_list_free(&list);
+
+    list = lexer_highlight("var s = \"open\n", 0, 0);
+    expect(list.count == 4 && list.items[3].kind == HIGHLIGHT_STRING,
+           "highlight: an unterminated string is still a string");
+    highlight_list_free(&list);
+}
+
+int main(void) {
+    test_warning_only();
+    test_error_and_warning();
+    test_parse_error();
+    test_artifact();
+    test_artifact_targets();
+    test_source_map();
+    test_visitor();
+    test_node_ids();
+    test_doc_comment();
+    test_keywords();
+    test_highlight();
+    if (g_failures > 0) {
+        printf("%d check(s) failed\n", g_failures);
+        return 1;
+    }
+    printf("All library checks passed.\n");
+    return 0;
+}
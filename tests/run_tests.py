@@ -7,14 +7,21 @@ Runs a small set of canonical .sb programs through all three tools
 an expected value, not just that the tool exited 0. Each tool mixes its
 own banner/log lines into stdout ahead of the program's own output, so
 program output is checked as a suffix of what the tool printed.
+
+The golden programs in tests/programs/ are stricter: each one's interpreter
+stdout must equal its adjacent .expected file.
 """
+import json
 import os
+import re
+import shutil
 import subprocess
 import sys
 
 SCRIPT_DIR = os.path.dirname(os.path.abspath(__file__))
 ROOT_DIR = os.path.dirname(SCRIPT_DIR)
 EXAMPLES_DIR = os.path.join(ROOT_DIR, "examples")
+PROGRAMS_DIR = os.path.join(SCRIPT_DIR, "programs")
 
 EXE = ".exe" if sys.platform == "win32" else ""
 SUB = os.path.join(ROOT_DIR, "sub" + EXE)
@@ -62,9 +69,9 @@ def check_suffix(actual, expected, label):
     return False
 
 
-def run(cmd, cwd=None):
+def run(cmd, cwd=None, env=None, input=None):
     try:
-        result = subprocess.run(cmd, cwd=cwd, stdout=subprocess.PIPE,
+        result = subprocess.run(cmd, cwd=cwd, env=env, input=input, stdout=subprocess.PIPE,
                                  stderr=subprocess.STDOUT, text=True,
                                  encoding="utf-8", errors="replace", timeout=30)
         return result.returncode, result.stdout
@@ -87,6 +94,17 @@ def tool_available(name):
         return False
 
 
+def test_library_api():
+    """The C API checks in tests/lib/ (make libtest) run with the rest."""
+    label = "library: make libtest"
+    if not tool_available("make"):
+        print(f"  SKIP {label} (make not found)")
+        return
+    rc, out = run(["make", "-s", "libtest"], cwd=ROOT_DIR)
+    failed = [line for line in out.splitlines() if line.startswith("FAIL")]
+    check_result(label, rc == 0 and "All library checks passed." in out, f"rc={rc} {failed or out}")
+
+
 def test_interpreter(sb_file, expected):
     rc, out = run([SUBI, sb_file])
     label = f"subi: {os.path.basename(sb_file)}"
@@ -97,13 +115,13 @@ def test_interpreter(sb_file, expected):
     check_suffix(out, expected, label)
 
 
-def test_native_compile(sb_file, expected):
+def test_native_compile(sb_file, expected, extra_args=()):
     label = f"subc: {os.path.basename(sb_file)}"
     if not tool_available("gcc"):
         print(f"  SKIP {label} (gcc not found)")
         return
     out_name = os.path.join(ROOT_DIR, "_regtest_bin")
-    rc, out = run([SUBC, sb_file, "-o", out_name])
+    rc, out = run([SUBC, sb_file, "-o", out_name, *extra_args])
     if rc != 0:
         print(f"  FAIL {label} (compile exit {rc})\n       {out}")
         failures.append(label)
@@ -147,6 +165,2062 @@ def test_transpile_and_run(sb_file, expected, lang, ext, runner):
     check_suffix(run_out, expected, label)
 
 
+def test_transpile_and_build(sb_file, expected, lang, ext, compiler):
+    """Like test_transpile_and_run, for targets compiled before they run."""
+    label = f"sub {lang}(compiled): {os.path.basename(sb_file)}"
+    if not tool_available(compiler[0]):
+        print(f"  SKIP {label} ('{compiler[0]}' not available)")
+        return
+    stem = os.path.join(ROOT_DIR, os.path.splitext(os.path.basename(sb_file))[0])
+    out_file, bin_path = stem + ext, stem + "_bin" + EXE
+    rc, out = run([SUB, sb_file, lang, out_file, "-q"])
+    if rc == 0:
+        rc, out = run(compiler + [out_file, "-o", bin_path])
+    if rc == 0:
+        rc, out = run([bin_path])
+    for path in (out_file, bin_path):
+        if os.path.exists(path):
+            os.remove(path)
+    if rc != 0:
+        print(f"  FAIL {label} (exit {rc})\n       {out}")
+        failures.append(label)
+        return
+    check_suffix(out, expected, label)
+
+
+def interpreter_stdout(sb_file):
+    """Program output from subi: stdout only, with the banner stripped."""
+    try:
+        result = subprocess.run([SUBI, sb_file], stdout=subprocess.PIPE,
+                                stderr=subprocess.PIPE, text=True,
+                                encoding="utf-8", errors="replace", timeout=30)
+    except (subprocess.TimeoutExpired, OSError) as e:
+        return 1, str(e)
+    _, sep, body = result.stdout.partition("====================\n\n")
+    return result.returncode, body if sep else result.stdout
+
+
+def test_link():
+    """`subc file.sb linux --link -o app` builds an executable with cc."""
+    label = "subc --link: linked executable runs"
+    if not tool_available("cc"):
+        print(f"  SKIP {label} (cc not found)")
+        return
+    sb_file = write_snippet("link", 'print("linked")\n')
+    c_file = os.path.join(ROOT_DIR, "_regtest_link.c")
+    exe = os.path.join(ROOT_DIR, "_regtest_link_app" + EXE)
+    target = "windows" if EXE else "linux"
+    rc, out = run([SUBC, sb_file, target, c_file, "--link", "-o", exe], cwd=ROOT_DIR)
+    if rc != 0 or not os.path.exists(exe):
+        print(f"  FAIL {label} (exit {rc})\n       {out}")
+        failures.append(label)
+    else:
+        rc, run_out = run([exe])
+        check_suffix(run_out, "linked", label)
+    wrap = write_snippet("link_wrap", "function bump(x: int): int {\n    return x + 1\n}\n"
+                         "var big = 9223372036854775807\nprint(bump(big) < 0)\n")
+    wrap_exe = os.path.join(ROOT_DIR, "_regtest_link_wrap" + EXE)
+    results = []
+    for cmd in ([SUBC, wrap, target, "--link", "-o", exe], [SUBC, wrap, "-o", exe], [SUBC, wrap, target]):
+        rc, out = run(cmd, cwd=ROOT_DIR)
+        built = exe if "-o" in cmd else wrap_exe
+        results.append(run([built])[1].strip() if rc == 0 and os.path.exists(built) else f"rc={rc} {out}")
+    label = "subc --link: same gcc flags (-fwrapv) as a plain native build"
+    check_result(label, len(set(results)) == 1 and results[0] == "1", results)
+    rc, out = run([SUBC, sb_file, target, "--bogus"], cwd=ROOT_DIR)
+    label = "subc: an unknown option after the target is an error"
+    check_result(label, rc != 0 and "unexpected argument '--bogus'" in out, f"rc={rc} {out}")
+    env = dict(os.environ, SUBC_TARGET=target)
+    for label, cmd, env_used, bad in [
+            ("subc: an unknown option without a target is an error", [SUBC, sb_file, "--bogus-flag"], None,
+             "--bogus-flag"),
+            ("subc: an option of sub's only is an error", [SUBC, sb_file, "--emit-manifest"], None, "--emit-manifest"),
+            ("subc build: an unknown option is an error", [SUBC, "build", sb_file, "--bogus"], None, "--bogus"),
+            ("subc: an unknown option with SUBC_TARGET is an error", [SUBC, sb_file, "--bogus"], env, "--bogus")]:
+        rc, out = run(cmd, cwd=ROOT_DIR, env=env_used)
+        check_result(label, rc != 0 and f"unexpected argument '{bad}'" in out, f"rc={rc} {out}")
+    saved = os.environ.get("CC")
+    os.environ["CC"] = "_regtest_no_such_cc"
+    rc, out = run([SUBC, sb_file, target, c_file, "--link", "-o", exe], cwd=ROOT_DIR)
+    if saved is None:
+        os.environ.pop("CC")
+    else:
+        os.environ["CC"] = saved
+    label = "subc --link: missing toolchain is reported"
+    check_result(label, rc != 0 and "Error: linking needs a C compiler and linker" in out, out)
+    for path in (sb_file, c_file, exe, wrap, wrap_exe, os.path.join(ROOT_DIR, "_regtest_link_wrap.c")):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def check_generated(label, path, present=(), absent=()):
+    """Check the file sub wrote at `path` contains and omits the given text."""
+    data = read_bytes(path)
+    text = data.decode("utf-8") if data is not None else ""
+    missing = [s for s in present if s not in text]
+    unwanted = [s for s in absent if s in text]
+    check_result(label, data is not None and not missing and not unwanted,
+                 f"(missing {missing}, unexpected {unwanted}) {text}")
+
+
+def test_crate_type():
+    """--crate-type lib exports the functions and leaves out the entry point."""
+    sb_file = write_snippet("crate", "function add(a: int, b: int): int {\n    return a + b\n}\n")
+    c_file = os.path.join(ROOT_DIR, "_regtest_crate.c")
+    h_file = os.path.join(ROOT_DIR, "_regtest_crate.h")
+    js_file = os.path.join(ROOT_DIR, "_regtest_crate.js")
+    run([SUB, sb_file, "c", c_file, "--crate-type", "lib"])
+    check_generated("crate-type lib: C has no main", c_file, ["long add(long a, long b) {"], ["int main("])
+    check_generated("crate-type lib: C header declares the function", h_file,
+                    ["#ifndef _REGTEST_CRATE_H", "long add(long a, long b);"])
+    with open(sb_file, "w", encoding="utf-8", newline="\n") as f:
+        f.write("function _priv(): int {\n    return 41\n}\nfunction answer(): int {\n    return _priv() + 1\n}\n")
+    run([SUB, sb_file, "c", c_file, "--crate-type", "lib"])
+    check_generated("crate-type lib: C header prototypes a function with no parameters as (void)"
+                    " and leaves out _ names", h_file, ["long answer(void);"], ["_priv", "answer()"])
+    if tool_available("gcc"):
+        main_file = os.path.join(ROOT_DIR, "_regtest_crate_main.c")
+        exe_file = os.path.join(ROOT_DIR, "_regtest_crate_main" + EXE)
+        with open(main_file, "w") as f:
+            f.write('#include <stdio.h>\n#include "_regtest_crate.h"\n'
+                    'int main(void) { printf("%ld\\n", answer()); return 0; }\n')
+        rc, out = run(["gcc", "-Wall", "-Wstrict-prototypes", "-Werror", main_file, c_file, "-o", exe_file])
+        if rc == 0:
+            rc, out = run([exe_file])
+        check_result("crate-type lib: the header links against the library under -Wstrict-prototypes",
+                     rc == 0 and out.strip() == "42", out)
+        for path in (main_file, exe_file):
+            if os.path.exists(path):
+                os.remove(path)
+    with open(sb_file, "w", encoding="utf-8", newline="\n") as f:
+        f.write("function add(a: int, b: int): int {\n    return a + b\n}\n")
+    run([SUB, sb_file, "c", c_file, "--crate-type=bin"])
+    check_generated("crate-type bin: C has main", c_file, ["long add(long a, long b) {", "int main("])
+    run([SUB, sb_file, "js", js_file, "--crate-type", "lib"])
+    check_generated("crate-type lib: JS exports the function", js_file, ["export function add(a, b)"])
+    run([SUB, sb_file, "js", js_file])
+    check_generated("crate-type bin: JS has no exports", js_file, ["function add(a, b)"], ["export"])
+
+    with open(sb_file, "w", encoding="utf-8", newline="\n") as f:
+        f.write('function add(a: int, b: int): int {\n    return a + b\n}\nprint(add(1, 2))\n')
+    rc, out = run([SUB, sb_file, "js", js_file, "--crate-type", "lib"])
+    label = "crate-type lib: top-level statements are rejected"
+    check_result(label, rc != 0 and "[line 4] Semantic error: Top-level statements are not allowed" in out,
+                 out)
+    rc, out = run([SUB, sb_file, "rust", "--crate-type", "lib"])
+    label = "crate-type lib: unsupported target is reported"
+    check_result(label, rc != 0 and "--crate-type lib is not supported for target 'rust'" in out, out)
+    for path in (sb_file, c_file, h_file, js_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_print_size():
+    """--print-size reports the output size and writes nothing."""
+    sb_file = write_snippet("size", "function add(a: int, b: int): int {\n    return a + b\n}\nprint(add(1, 2))\n")
+    c_file = os.path.join(ROOT_DIR, "_regtest_size.c")
+    binary = os.path.join(ROOT_DIR, "_regtest_size")
+    rc, out = run([SUB, sb_file, "c", c_file, "--print-size"])
+    match = re.search(r"_regtest_size\.c: (\d+) bytes, (\d+) lines \(not written\)", out)
+    label = "print-size: reports a byte count"
+    check_result(label, rc == 0 and match and int(match.group(1)) > 0, out)
+    label = "print-size: leaves no file behind"
+    check_result(label, not os.path.exists(c_file) and not os.path.exists(binary), "")
+    for path in (sb_file, c_file, binary):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_quiet():
+    """--quiet leaves stdout empty on success but keeps diagnostics."""
+    sb_file = write_snippet("quiet", "print(42)\n")
+    py_file = os.path.join(ROOT_DIR, "_regtest_quiet.py")
+    binary = os.path.join(ROOT_DIR, "_regtest_quiet")
+    for label, cmd, expected in [
+            ("quiet: sub prints nothing on success", [SUB, sb_file, "python", py_file, "--quiet"], ""),
+            ("quiet: subc -q prints nothing on success", [SUBC, sb_file, "-o", binary, "-q"], ""),
+            ("quiet: subi prints only the program output", [SUBI, sb_file, "-q"], "42")]:
+        proc = subprocess.run(cmd, capture_output=True, text=True, cwd=ROOT_DIR)
+        check_result(label, proc.returncode == 0 and proc.stdout.strip() == expected,
+                     f"rc={proc.returncode} stdout={proc.stdout!r}")
+    bad_file = write_snippet("quiet_bad", "print(missing)\n")
+    proc = subprocess.run([SUB, bad_file, "python", py_file, "-q"], capture_output=True, text=True)
+    label = "quiet: errors still reach stderr"
+    check_result(label, proc.returncode != 0 and "missing" in proc.stderr,
+                 f"rc={proc.returncode} stderr={proc.stderr!r}")
+    for path in (sb_file, bad_file, py_file, binary):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_banner():
+    sb_file = write_snippet("banner", "print(1)\n")
+    js_file = os.path.join(ROOT_DIR, "_regtest_banner.js")
+    py_file = os.path.join(ROOT_DIR, "_regtest_banner.py")
+    for label, cmd, path, check in [
+            ("banner: --no-banner output starts with code",
+             [SUB, sb_file, "js", js_file, "--no-banner"], js_file,
+             lambda text: text.startswith("console.log(1);")),
+            ("banner: a custom banner is written verbatim",
+             [SUB, sb_file, "python", py_file, "--banner", "Copyright ACME, do not edit"], py_file,
+             lambda text: text.splitlines()[1] == "# Copyright ACME, do not edit"),
+            ("banner: the default names the compiler version",
+             [SUB, sb_file, "js", js_file], js_file,
+             lambda text: text.startswith("// Generated by SUB Language Compiler v"))]:
+        rc, out = run(cmd)
+        text = open(path).read() if rc == 0 and os.path.exists(path) else ""
+        check_result(label, check(text), f"rc={rc} output={text[:80]!r}")
+    rc, out = run([SUB, sb_file, "c", os.path.join(ROOT_DIR, "_regtest_banner.c"), "--banner", "a */ b"])
+    label = "banner: text that would end a C comment is rejected"
+    check_result(label, rc != 0 and "single line without '*/'" in out, f"rc={rc} {out}")
+    for path in (sb_file, js_file, py_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_output_newlines():
+    """--output-eol picks the line endings of written files and
+    --final-newline / --no-final-newline how they end."""
+    sb_file = write_snippet("eol", "print(1)\nprint(2)\n")
+    out_file = os.path.join(ROOT_DIR, "_regtest_eol.js")
+    for label, args, check in [
+            ("newlines: the default is LF", [],
+             lambda data: b"\n" in data and b"\r" not in data),
+            ("newlines: --output-eol crlf ends every line with CRLF", ["--output-eol", "crlf"],
+             lambda data: data.count(b"\r\n") == data.count(b"\n") > 0),
+            ("newlines: --no-final-newline drops the trailing line break", ["--no-final-newline"],
+             lambda data: data.endswith(b"console.log(2);")),
+            ("newlines: --final-newline ends with exactly one", ["--final-newline"],
+             lambda data: data.endswith(b"console.log(2);\n")),
+            ("newlines: --final-newline uses the chosen line ending", ["--final-newline", "--output-eol=crlf"],
+             lambda data: data.endswith(b"console.log(2);\r\n"))]:
+        rc, out = run([SUB, sb_file, "js", out_file, *args])
+        data = read_bytes(out_file) if rc == 0 else None
+        check_result(label, data is not None and check(data),
+                     f"rc={rc} output={data[-40:] if data else out!r}")
+    rc, out = run([SUB, sb_file, "js", out_file, "--output-eol", "cr"])
+    label = "newlines: an unknown line ending is rejected"
+    check_result(label, rc != 0 and "expected lf or crlf" in out, f"rc={rc} {out}")
+    for path in (sb_file, out_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_source_encoding():
+    """Invalid UTF-8 is reported with its offset; --encoding latin-1 reads it."""
+    sb_file = os.path.join(ROOT_DIR, "_regtest_encoding.sb")
+    with open(sb_file, "wb") as f:
+        f.write(b'print(1)\nprint("caf\xe9")\n')
+    message = "is not valid UTF-8: byte 0xE9 at offset 19 (line 2, column 11)"
+    for label, cmd in [("encoding: subi reports the first invalid byte", [SUBI, sb_file]),
+                       ("encoding: subc reports the first invalid byte", [SUBC, "check", sb_file]),
+                       ("encoding: sub reports the first invalid byte", [SUB, sb_file, "python"])]:
+        rc, out = run(cmd)
+        check_result(label, rc != 0 and message in out, f"(exit {rc}) {out}")
+    rc, out = run([SUBI, sb_file, "--encoding", "latin-1"])
+    check_suffix(out, "1\ncaf\u00e9", "encoding: --encoding latin-1 transcodes the file")
+    os.remove(sb_file)
+    sb_file = write_snippet("encoding", 'print("caf\u00e9 \u2713 \U0001F600")\n')
+    test_interpreter(sb_file, "caf\u00e9 \u2713 \U0001F600")
+    os.remove(sb_file)
+
+
+def test_typescript():
+    """The TypeScript target annotates declarations and signatures with their types."""
+    sb_file = write_snippet("ts", "function add(a: int, b: float): float {\n    return a + b\n}\n"
+                            "function show(v) {\n    print(v)\n}\n"
+                            "var n = 5\nvar xs = [1, 2]\nvar maybe: string? = null\nshow(add(n, 0.5))\n"
+                            "var later: int\nlater = 1\nvar empty: [int] = []\nshow(later)\nshow(empty)\n")
+    ts_file = os.path.join(ROOT_DIR, "_regtest_ts.ts")
+    rc, out = run([SUB, sb_file, "ts", ts_file])
+    text = open(ts_file).read() if rc == 0 and os.path.exists(ts_file) else ""
+    for label, needle in [
+            ("typescript: a typed function gets a full signature",
+             "function add(a: number, b: number): number {"),
+            ("typescript: an untyped parameter is any", "function show(v: any) {"),
+            ("typescript: an int variable is a number", "let n: number = 5;"),
+            ("typescript: an array of ints is number[]", "let xs: number[] = [1, 2];"),
+            ("typescript: an optional is T | null", "let maybe: string | null = null;"),
+            ("typescript: a declaration without a value may be null", "let later: number | null = null;"),
+            ("typescript: an empty array takes its annotated element type", "let empty: number[] = [];")]:
+        check_result(label, needle in text, f"rc={rc} output={text!r}")
+    for path in (sb_file, ts_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_check_keep_going():
+    """subc check with several files: --keep-going summarizes, --fail-fast stops."""
+    good = write_snippet("many_good", "print(1)\n")
+    bad = write_snippet("many_bad", "print(nope)\nprint(nada)\n")
+    for label, cmd, needles, absent in [
+            ("check: --keep-going checks every file and lists failures",
+             [SUBC, "check", bad, good, "--keep-going"],
+             ["many_good.sb: no errors", "1 of 2 files failed", "_regtest_many_bad.sb: 2 errors"], None),
+            ("check: keep-going is the default",
+             [SUBC, "check", bad, good], ["many_good.sb: no errors", "1 of 2 files failed"], None),
+            ("check: --fail-fast stops at the first failure",
+             [SUBC, "check", "--fail-fast", bad, good],
+             ["1 of 1 file failed", "1 file not checked"], "many_good.sb: no errors")]:
+        rc, out = run(cmd, cwd=ROOT_DIR)
+        check_result(label, rc != 0 and all(n in out for n in needles) and not (absent and absent in out),
+                     f"rc={rc} {out}")
+    tree = os.path.join(ROOT_DIR, "_regtest_check_dir")
+    empty = os.path.join(tree, "empty")
+    os.makedirs(os.path.join(tree, "nested"), exist_ok=True)
+    os.makedirs(empty, exist_ok=True)
+    with open(os.path.join(tree, "a.sb"), "w") as f:
+        f.write("print(1)\n")
+    with open(os.path.join(tree, "nested", "b.sb"), "w") as f:
+        f.write("print(nope)\n")
+    with open(os.path.join(tree, "notes.txt"), "w") as f:
+        f.write("not a program\n")
+    missing = os.path.join(ROOT_DIR, "_regtest_missing.sb")
+    for label, cmd, needles in [
+            ("check: a directory stands for the .sb files under it", [SUBC, "check", tree],
+             ["a.sb: no errors", "1 of 2 files failed", os.path.join("nested", "b.sb") + ": 1 error"]),
+            ("check: an unreadable input is reported by name", [SUBC, "check", missing, good],
+             ["_regtest_missing.sb: cannot be read", "1 of 2 files failed"]),
+            ("check: a directory with no .sb files fails", [SUBC, "check", empty],
+             ["no .sb files in directory"]),
+            ("build: a directory is refused, not read", [SUBC, tree, "-q"], ["is a directory"])]:
+        rc, out = run(cmd, cwd=ROOT_DIR)
+        check_result(label, rc != 0 and all(n in out for n in needles) and "notes.txt" not in out,
+                     f"rc={rc} {out}")
+    shutil.rmtree(tree)
+    for path in (good, bad):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_subcommands():
+    """subc build/check/run; a bare file still builds."""
+    good = write_snippet("cmd_good", "print(6 * 7)\n")
+    bad = write_snippet("cmd_bad", "print(missing)\n")
+    c_file = os.path.join(ROOT_DIR, "_regtest_cmd_good.c")
+    for label, cmd, ok, needle in [
+            ("subcommands: check accepts a valid program", [SUBC, "check", good], True, "no errors"),
+            ("subcommands: check reports a semantic error", [SUBC, "check", bad], False,
+             "Undefined variable 'missing'"),
+            ("subcommands: run interprets the program", [SUBC, "run", good], True, "42"),
+            ("subcommands: repl takes no input file", [SUBC, "repl", good], False, "reads standard input")]:
+        rc, out = run(cmd, cwd=ROOT_DIR)
+        check_result(label, (rc == 0) == ok and needle in out, f"rc={rc} {out}")
+    for label, cmd in [("subcommands: build compiles for a target", [SUBC, "build", good, "linux"]),
+                       ("subcommands: a bare file and target still build", [SUBC, good, "linux"])]:
+        if os.path.exists(c_file):
+            os.remove(c_file)
+        rc, out = run(cmd, cwd=ROOT_DIR)
+        check_result(label, rc == 0 and os.path.exists(c_file), f"rc={rc} {out}")
+    for path in (good, bad, c_file, c_file[:-2] + EXE):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def check_result(label, ok, detail):
+    if ok:
+        print(f"  OK   {label}")
+    else:
+        print(f"  FAIL {label}\n       {detail}")
+        failures.append(label)
+
+
+def test_fmt():
+    """subc fmt re-indents, and --check / --write act on the files."""
+    messy = ("\n\nfunction add(a, b) {\nreturn a + b   \n}\n"
+             "function one()\n{\nreturn 1\n}\n"
+             "if add(1, 2) > 2\nprint(\"yes\")\nelif one() == 1\n  print(\"one\")\nelse\n  print(\"no\")\nend\n\n\n\n"
+             "var xs = [1,\n2]\nfor x in xs {\n        # each\n  print(x)  # with a comment\n}\n"
+             "embed c\n  int keep = 1;\nendembed\n\n")
+    tidy = ("function add(a, b) {\n    return a + b\n}\n"
+            "function one()\n{\n    return 1\n}\n"
+            "if add(1, 2) > 2\n    print(\"yes\")\nelif one() == 1\n    print(\"one\")\nelse\n    print(\"no\")\nend\n\n"
+            "var xs = [1,\n    2]\nfor x in xs {\n    # each\n    print(x)  # with a comment\n}\n"
+            "embed c\n  int keep = 1;\nendembed\n")
+    messy_file = write_snippet("fmt_messy", messy)
+    tidy_file = write_snippet("fmt_tidy", tidy)
+    broken_file = write_snippet("fmt_broken", "print(1\n")
+
+    rc, out = run([SUBC, "fmt", messy_file], cwd=ROOT_DIR)
+    check_result("fmt: re-indents blocks, brackets and bare if/end", rc == 0 and out == tidy, repr(out))
+    rc, out = run([SUBC, "fmt", tidy_file], cwd=ROOT_DIR)
+    check_result("fmt: formatted source stays as it is", rc == 0 and out == tidy, repr(out))
+    rc, out = run([SUBC, "fmt", "--check", messy_file, tidy_file], cwd=ROOT_DIR)
+    check_result("fmt: --check lists the files that would change and fails",
+                 rc != 0 and messy_file in out and tidy_file not in out, f"rc={rc} {out}")
+    rc, out = run([SUBC, "fmt", "-q", "--write", messy_file], cwd=ROOT_DIR)
+    written = (read_bytes(messy_file) or b"").decode("utf-8")
+    check_result("fmt: --write rewrites the file", rc == 0 and written == tidy, repr(written))
+    rc, out = run([SUBC, "fmt", "--check", messy_file], cwd=ROOT_DIR)
+    check_result("fmt: --check passes once the file is formatted", rc == 0, f"rc={rc} {out}")
+    rc, out = run([SUBC, "fmt", broken_file], cwd=ROOT_DIR)
+    check_result("fmt: a file that does not parse is left alone",
+                 rc != 0 and "does not parse" in out and read_bytes(broken_file) == b"print(1\n", f"rc={rc} {out}")
+    for path in (messy_file, tidy_file, broken_file):
+        os.remove(path)
+
+
+def test_repl():
+    """subc repl runs each entry in one scope and prints expression values."""
+    for label, entries, rc_expected, expected in [
+            ("repl: entries share variables and functions",
+             "var x = 2\nx + 3\nfunction sq(n) {\n    return n * n\n}\nsq(x)\n", 0, "5\n4\n"),
+            ("repl: a bare if block continues until end",
+             "var x = 2\nif x > 1\n    print(\"big\")\nend\n", 0, "big\n"),
+            ("repl: a failed entry is reported and forgotten",
+             "print(missing)\nvar missing = 1\nmissing\n", 0, "1\n"),
+            ("repl: a top-level return ends it with that status", "return 3\nprint(1)\n", 3, "")]:
+        rc, out = run([SUBC, "repl", "-q"], cwd=ROOT_DIR, input=entries)
+        shown = "".join(line + "\n" for line in out.splitlines()
+                        if not line.startswith(("[line", " ", "Parser")))
+        check_result(label, rc == rc_expected and shown == expected, f"rc={rc} {out!r}")
+    rc, out = run([SUBC, "repl", "-q"], cwd=ROOT_DIR, input="print(missing)\nprint(2)\n")
+    check_result("repl: an error names the entry's own line", "[line 1," in out and out.endswith("2\n"), repr(out))
+    rc, out = run([SUBC, "repl", "-q"], cwd=ROOT_DIR, input="var f = 0.5\nprint(f == 0.5)\nprint(2)\n")
+    check_result("repl: an earlier entry's warning is not repeated",
+                 rc == 0 and out.count("float-equality") == 1, repr(out))
+
+
+def test_target_from_environment():
+    """SUBC_TARGET supplies the target when the command line names none."""
+    sb_file = write_snippet("env_target", "print(1)\n")
+    base = os.path.join(ROOT_DIR, "_regtest_env_target")
+    outputs = [base + ".c", base + ".py"]
+    for label, target, args, path in [
+            ("SUBC_TARGET: a target-less invocation uses it", "linux", [], base + ".c"),
+            ("SUBC_TARGET: an explicit target wins", "linux", ["python"], base + ".py")]:
+        for output in outputs:
+            if os.path.exists(output):
+                os.remove(output)
+        env = dict(os.environ, SUBC_TARGET=target)
+        rc, out = run([SUBC, sb_file, *args], cwd=ROOT_DIR, env=env)
+        made = [output for output in outputs if os.path.exists(output)]
+        check_result(label, rc == 0 and made == [path], f"rc={rc} wrote={made} {out}")
+    app = os.path.join(ROOT_DIR, "_regtest_env_app" + EXE)
+    if os.path.exists(base + EXE):
+        os.remove(base + EXE)
+    rc, out = run([SUBC, sb_file, "-o", app, "-O0"], cwd=ROOT_DIR,
+                  env=dict(os.environ, SUBC_TARGET="linux"))
+    label = "SUBC_TARGET: -o and -O still apply to the build"
+    ran = run([app])[1].strip() if os.path.exists(app) else ""
+    check_result(label, rc == 0 and ran == "1" and not os.path.exists(base + EXE),
+                 f"rc={rc} ran={ran!r} {out}")
+    rc, out = run([SUBC, sb_file], cwd=ROOT_DIR, env=dict(os.environ, SUBC_TARGET="wasm"))
+    label = "SUBC_TARGET: an unknown value is reported"
+    check_result(label, rc != 0 and "Unknown target 'wasm' (from SUBC_TARGET)" in out, f"rc={rc} {out}")
+    for path in [sb_file, base + EXE, app] + outputs:
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_multiple_targets():
+    """subc --targets analyzes once and writes one output per target."""
+    sb_file = write_snippet("targets", "function sq(x: int): int {\n    return x * x\n}\nprint(sq(4))\n")
+    base = os.path.join(ROOT_DIR, "_regtest_targets")
+    rc, out = run([SUBC, sb_file, "--targets", "python,web"], cwd=ROOT_DIR)
+    label = "targets: two targets write two files named after the input"
+    outputs = [base + ".py", base + ".html"]
+    check_result(label, rc == 0 and all(os.path.exists(path) for path in outputs), f"rc={rc} {out}")
+    rc, out = run(["python3", base + ".py"]) if os.path.exists(base + ".py") else (1, "")
+    label = "targets: each output is a complete program"
+    check_result(label, out.strip() == "16", f"rc={rc} {out}")
+    rc, out = run([SUBC, sb_file, "--targets", "linux,macos"], cwd=ROOT_DIR)
+    outputs += [base + "-linux.c", base + "-macos.c"]
+    label = "targets: targets sharing an extension get the target in their names"
+    check_result(label, rc == 0 and all(os.path.exists(path) for path in outputs[2:]), f"rc={rc} {out}")
+    rc, out = run([SUBC, sb_file, "--targets", "linux,wasm"], cwd=ROOT_DIR)
+    label = "targets: an unknown target is rejected"
+    check_result(label, rc != 0 and "Unknown target 'wasm'" in out, f"rc={rc} {out}")
+    for path in [sb_file] + outputs:
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_emit_manifest():
+    """--emit-manifest writes a JSON sidecar listing the exported functions."""
+    sb_file = write_snippet("manifest", "function add(a: int, b: int): int {\n    return a + b\n}\n"
+                            "function greet(name: string) {\n    print(name)\n}\nconst LIMIT = 3\nprint(add(1, 2))\n")
+    py_file = os.path.join(ROOT_DIR, "_regtest_manifest.py")
+    manifest_file = os.path.join(ROOT_DIR, "_regtest_manifest.manifest.json")
+    rc, out = run([SUB, sb_file, "python", py_file, "--emit-manifest"])
+    label = "manifest: lists the exports and the target"
+    try:
+        with open(manifest_file) as f:
+            manifest = json.load(f)
+        names = [e["name"] for e in manifest["exports"]]
+        ok = (rc == 0 and manifest["target"] == "python" and names == ["add", "greet", "LIMIT"]
+              and manifest["exports"][0]["params"] == [{"name": "a", "type": "int"}, {"name": "b", "type": "int"}]
+              and manifest["exports"][0]["returns"] == "int"
+              and manifest["source_hash"].startswith("fnv1a64:"))
+    except (OSError, ValueError, KeyError, IndexError) as e:
+        ok, out = False, f"{out}\n{e}"
+    check_result(label, ok, out)
+    for path in (sb_file, py_file, manifest_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+    sb_file = write_snippet("manifest_c", "function _helper(): int {\n    return 1\n}\n"
+                            "function api(): int {\n    return _helper()\n}\nprint(api())\n")
+    c_file = os.path.join(ROOT_DIR, "_regtest_manifest_c.c")
+    manifest_file = os.path.join(ROOT_DIR, "_regtest_manifest_c.manifest.json")
+    for level in ("0", "3"):
+        rc, out = run([SUB, sb_file, "c", c_file, f"-O{level}", "--emit-manifest", "-q"])
+        label = f"manifest: -O{level} is the recorded level and _ names are not exports"
+        try:
+            with open(manifest_file) as f:
+                manifest = json.load(f)
+            ok = (rc == 0 and manifest["optimization_level"] == int(level)
+                  and [e["name"] for e in manifest["exports"]] == ["api"])
+        except (OSError, ValueError, KeyError) as e:
+            ok, out = False, f"{out}\n{e}"
+        check_result(label, ok, out)
+    for path in (sb_file, c_file, manifest_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_source_map():
+    """--source-map writes NAME.js.map and points the JavaScript at it."""
+    sb_file = write_snippet("srcmap", "var a = 1\nprint(a)\n")
+    js_file = os.path.join(ROOT_DIR, "_regtest_srcmap.js")
+    map_file = js_file + ".map"
+    rc, out = run([SUB, sb_file, "js", js_file, "--source-map", "-q"])
+    label = "source map: written next to the JavaScript"
+    try:
+        with open(map_file) as f:
+            source_map = json.load(f)
+        with open(js_file) as f:
+            code = f.read()
+        ok = (rc == 0 and source_map["version"] == 3 and source_map["mappings"].endswith(";AAAA;AACA")
+              and code.rstrip().endswith("//# sourceMappingURL=_regtest_srcmap.js.map"))
+    except (OSError, ValueError, KeyError) as e:
+        ok, out = False, f"{out}\n{e}"
+    check_result(label, ok, out)
+    for path in (js_file, map_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+    rc, out = run([SUB, sb_file, "python", "--source-map", "-q"])
+    label = "source map: other targets reject the flag"
+    check_result(label, rc != 0 and "only supported for JavaScript and TypeScript" in out, out)
+    os.remove(sb_file)
+
+
+def test_default_output_names():
+    """Without an output path, the file is named after the input and target."""
+    sb_file = write_snippet("outname", "print(1)\n")
+    for target, ext, hint in [("web", ".html", "Open _regtest_outname.html in a web browser"),
+                              ("js", ".js", "node _regtest_outname.js"),
+                              ("rust", ".rs", "rustc _regtest_outname.rs && ./_regtest_outname")]:
+        label = f"output name: {target} defaults to {ext}"
+        out_file = os.path.join(ROOT_DIR, "_regtest_outname" + ext)
+        rc, out = run([SUB, sb_file, target], cwd=ROOT_DIR)
+        written = rc == 0 and os.path.exists(out_file) and f"Output written to: _regtest_outname{ext}" in out
+        check_result(label, written and hint in out,
+                     f"(next steps should name the file: {hint!r}) {out}" if written else out)
+        if os.path.exists(out_file):
+            os.remove(out_file)
+    os.remove(sb_file)
+
+
+def test_unsupported_constructs():
+    """A construct the target cannot lower is an error naming it, not broken output."""
+    loop = "var i = 0\nwhile i < 9 {\n    i = i + 1\n    if i > 3 {\n        break\n    }\n}\nprint(i)\n"
+    pair = "var p = (1, 2)\nvar (a, b) = p\nprint(a + b)\n"
+    for name, source, target, message in [
+            ("break", loop, "python", "[line 5, col 9] Codegen error: The Python backend does not support 'break'"),
+            ("tuple", pair, "java", "[line 1, col 9] Codegen error: The Java backend does not support tuples")]:
+        sb_file = write_snippet(f"gap_{name}", source)
+        out_file = os.path.join(ROOT_DIR, f"_regtest_gap_{name}.out")
+        rc, out = run([SUB, sb_file, target, out_file])
+        label = f"unsupported: {target} reports {name} with its position"
+        check_result(label, rc != 0 and message in out and not os.path.exists(out_file), f"(exit {rc}) {out}")
+        for path in (sb_file, out_file):
+            if os.path.exists(path):
+                os.remove(path)
+    sb_file = write_snippet("gap_go", loop)
+    out_file = os.path.join(ROOT_DIR, "_regtest_gap_go.go")
+    rc, out = run([SUB, sb_file, "go", out_file])
+    label = "unsupported: a backend with the construct still generates it"
+    check_result(label, rc == 0 and os.path.exists(out_file), f"(exit {rc}) {out}")
+    for path in (sb_file, out_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_golden_programs():
+    """Every tests/programs/<name>.sb must print exactly <name>.expected."""
+    for fname in sorted(os.listdir(PROGRAMS_DIR)):
+        if not fname.endswith(".sb"):
+            continue
+        label = f"golden: {fname}"
+        sb_file = os.path.join(PROGRAMS_DIR, fname)
+        expected_file = os.path.splitext(sb_file)[0] + ".expected"
+        if not os.path.exists(expected_file):
+            print(f"  FAIL {label} (missing {os.path.basename(expected_file)})")
+            failures.append(label)
+            continue
+        with open(expected_file, encoding="utf-8") as f:
+            expected = f.read()
+        rc, out = interpreter_stdout(sb_file)
+        check_result(label, rc == 0 and out == expected,
+                     f"(exit {rc})\n       expected: {expected!r}\n       actual:   {out!r}")
+
+
+def write_snippet(name, source):
+    """Write an inline .sb program next to the repo root for a single check."""
+    path = os.path.join(ROOT_DIR, f"_regtest_{name}.sb")
+    with open(path, "w", encoding="utf-8", newline="\n") as f:
+        f.write(source)
+    return path
+
+
+def check_parse(label, source, expect_error):
+    """Run a snippet through subi and check whether the parser rejected it."""
+    sb_file = write_snippet("parse", source)
+    _, out = run([SUBI, sb_file])
+    os.remove(sb_file)
+    want = "a parse error" if expect_error else "no parse errors"
+    check_result(label, ("Parse error" in out) == expect_error, f"(expected {want}) {out}")
+
+
+def check_lex_error(label, source, message):
+    """Run a snippet through subi and check the lexer reported `message`."""
+    sb_file = write_snippet("lex", source)
+    _, out = run([SUBI, sb_file])
+    os.remove(sb_file)
+    check_result(label, "Lexer error" in out and message in out, f"(expected lexer error: {message!r}) {out}")
+
+
+def check_output(label, source, expected, extra_args=()):
+    """Run a snippet through subi and check its program output."""
+    sb_file = write_snippet("output", source)
+    rc, out = run([SUBI, sb_file, *extra_args])
+    os.remove(sb_file)
+    if rc != 0:
+        print(f"  FAIL {label} (exit {rc})\n       {out}")
+        failures.append(label)
+        return
+    check_suffix(out, expected, label)
+
+
+def check_semantic_error(label, source, message, extra_args=()):
+    """Run a snippet through subi and check it is rejected with `message`."""
+    sb_file = write_snippet("semantic", source)
+    rc, out = run([SUBI, sb_file, *extra_args])
+    os.remove(sb_file)
+    check_result(label, rc != 0 and "Semantic error" in out and message in out,
+                 f"(expected semantic error: {message!r}) {out}")
+
+
+def check_runtime_error(label, source, message, extra_args=()):
+    """Run a snippet through subi and check it stops with a runtime error."""
+    sb_file = write_snippet("runtime", source)
+    rc, out = run([SUBI, sb_file, *extra_args])
+    os.remove(sb_file)
+    check_result(label, rc != 0 and "Runtime error" in out and message in out,
+                 f"(expected runtime error: {message!r}) {out}")
+
+
+def check_exit_status(label, source, status):
+    """Run a snippet through subi and check the process exit status."""
+    sb_file = write_snippet("exit", source)
+    rc, out = run([SUBI, sb_file])
+    os.remove(sb_file)
+    check_result(label, rc == status, f"(expected exit {status}, got {rc}) {out}")
+
+
+def test_exit_codes():
+    check_exit_status("exit: #exit(3) sets the status", 'print("hi")\n#exit(3)\nprint("no")\n', 3)
+    check_exit_status("exit: top-level return sets the status", "return 4\n", 4)
+    check_output("exit: statements before #exit still run", 'print("hi")\n#exit(0)\n', "hi")
+    check_semantic_error("exit: #exit(\"x\") is a type error", '#exit("x")\n',
+                         "Exit code must be int, got string")
+    check_semantic_error("exit: code above 255 is rejected", "#exit(256)\n",
+                         "Exit code 256 is out of range 0-255")
+    check_runtime_error("exit: a computed top-level return above 255 stops", "var n = 300\nreturn n\n",
+                        "[line 2] Runtime error: Exit code 300 is out of range 0-255")
+    check_runtime_error("exit: a computed #exit code below 0 stops", "var n = 0 - 1\n#exit(n)\n",
+                        "[line 2] Runtime error: Exit code -1 is out of range 0-255")
+    check_exit_status("exit: a computed return inside a function is not an exit code",
+                      "function f(): int {\n    return 300\n}\nvar x = f()\nreturn x - 290\n", 10)
+
+
+def test_optionals():
+    check_semantic_error("optional: use without a null check is rejected",
+                         "var x: int? = null\nprint(x + 1)\n",
+                         "Optional value 'x' used as an operand of '+' without a null check")
+    check_semantic_error("optional: null into a non-optional is rejected",
+                         "var x: int = null\n", "Cannot assign null to non-optional int 'x'")
+    check_semantic_error("optional: returned optional into a non-optional is rejected",
+                         "function f(): int? {\n    return null\n}\nvar y: int = f()\n",
+                         "Optional value used as the initializer of non-optional 'y'")
+    check_runtime_error("optional: x? on null fails at runtime",
+                        "var x: int? = none\nprint(x?)\n", "Unwrapped a null optional value")
+    source = ("function half(n: int): int? {\n    if n % 2 == 0 {\n        return n / 2\n    }\n"
+              "    return null\n}\n"
+              "var a = half(10)\nif a != null {\n    print(a + 1)\n}\n"
+              "var b: int? = half(3)\nif b == null {\n    print(\"none\")\n} else {\n    print(b)\n}\n"
+              "print(a? * 2)\n")
+    sb_file = write_snippet("optionals", source)
+    test_interpreter(sb_file, "6\nnone\n10")
+    test_native_compile(sb_file, "6\nnone\n10")
+    test_transpile_and_run(sb_file, "6\nnone\n10", "js", ".js", ["node"])
+    os.remove(sb_file)
+    printed = ("function find(n: int): int? {\n    if n > 0 {\n        return n\n    }\n    return null\n}\n"
+               "var x: int? = null\nprint(x)\nprint(null)\nprint(find(0))\nprint(find(2))\n")
+    sb_file = write_snippet("optionals_print", printed)
+    test_interpreter(sb_file, "null\nnull\nnull\n2")
+    test_native_compile(sb_file, "null\nnull\nnull\n2")
+    test_transpile_and_run(sb_file, "null\nnull\nnull\n2", "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, "null\nnull\nnull\n2", "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_match():
+    check_output("match: '_' makes an int match exhaustive",
+                 'var n = 7\nprint(match n { 1 => "one", _ => "other" })\n', "other")
+    check_semantic_error("match: int match without '_' is rejected",
+                         'var n = 1\nprint(match n { 1 => "one", 2 => "two" })\n',
+                         "Non-exhaustive match on int: add a '_' arm")
+    check_semantic_error("match: bool match missing a case is rejected",
+                         'var b = true\nprint(match b { true => 1 })\n',
+                         "Non-exhaustive match: missing case false")
+    check_semantic_error("match: pattern of the wrong type is rejected",
+                         'var n = 1\nprint(match n { "one" => 1, _ => 0 })\n',
+                         'Match pattern "one" is string, but the matched value is int')
+    source = ("function name(n: int): string {\n    return match n {\n"
+              "        1 => \"one\"\n        -1 => \"minus one\"\n        _ => \"many\"\n    }\n}\n"
+              "print(name(1))\nprint(name(-1))\nprint(name(5))\n"
+              "var s = \"b\"\nprint(match s { \"a\" => 1, \"b\" => 2, _ => 0 } + 10)\n"
+              "var flag = 3 > 2\nprint(match flag { true => \"yes\", false => \"no\" })\n")
+    expected = "one\nminus one\nmany\n12\nyes"
+    sb_file = write_snippet("match", source)
+    test_interpreter(sb_file, expected)
+    test_native_compile(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_tuples():
+    check_output("tuples: a tuple prints its elements",
+                 'var t = (1, "two", true)\nprint(t)\nprint(typeof(t))\n',
+                 "(1, two, true)\n(int, string, bool)")
+    check_semantic_error("tuples: destructuring checks the arity",
+                         "var (a, b, c) = (1, 2)\n",
+                         "Cannot destructure a 2-element tuple (int, int) into 3 variables")
+    check_semantic_error("tuples: only tuples can be destructured",
+                         "var (a, b) = 5\n", "Cannot destructure int")
+    source = ("function sum_product(a, b) {\n    return (a + b, a * b)\n}\n"
+              "var (s, p) = sum_product(3, 4)\nprint(s)\nprint(p)\n"
+              "const (name, n) = (\"x\", 2)\nprint(name)\nprint(n + 1)\n")
+    expected = "7\n12\nx\n3"
+    sb_file = write_snippet("tuples", source)
+    test_interpreter(sb_file, expected)
+    test_native_compile(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+    # A tuple return annotation, and printing tuples in the compiled targets
+    source = ("function divmod(a, b): (int, int) {\n    return (a / b, a % b)\n}\n"
+              "var t = divmod(17, 5)\nprint(t)\nvar (q, r) = t\nprint(q + r)\n"
+              "var nested = (\"x\", (2.5, true))\nprint(nested)\n")
+    expected = "(3, 2)\n5\n(x, (2.5, true))"
+    sb_file = write_snippet("tuples_print", source)
+    test_interpreter(sb_file, expected)
+    test_native_compile(sb_file, expected)
+    test_transpile_and_build(sb_file, expected, "cpp", ".cpp", ["g++", "-std=c++17"])
+    test_transpile_and_build(sb_file, expected, "rust", ".rs", ["rustc", "-A", "warnings"])
+    os.remove(sb_file)
+    check_semantic_error("tuples: a return annotation is checked",
+                         "function f(): (int, string) {\n    return (1, 2)\n}\n",
+                         "Return type (int, int) does not match function return type (int, string)")
+    check_semantic_error("tuples: a return annotation rejects a non-tuple",
+                         "function f(): (int, int) {\n    return 1\n}\n",
+                         "Return type int does not match function return type (int, int)")
+
+
+def test_for_each():
+    source = ('var xs = [1, 2, 3]\nfor x in xs {\n    print(x * 10)\n}\n'
+              'for w in ["a", "b"] {\n    print(w)\n}\n'
+              'for c in "hi" {\n    print(c)\n}\nprint(len(xs))\n')
+    expected = "10\n20\n30\na\nb\nh\ni\n3"
+    sb_file = write_snippet("for_each", source)
+    test_interpreter(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+    # Characters, not bytes: é and → are one step each
+    source = 'var n = 0\nfor c in "h\u00e9\u2192" {\n    print(c)\n    n = n + 1\n}\nprint(n)\n'
+    expected = "h\n\u00e9\n\u2192\n3"
+    sb_file = write_snippet("for_each_utf8", source)
+    test_interpreter(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+    # len(), s[i], substr and index_of count the same characters
+    source = ('var s = "h\u00e9\u2192llo"\nprint(len(s))\nprint(s[1])\nprint(s[2])\n'
+              'print(substr(s, 1, 3))\nprint(index_of(s, "l"))\nvar i = 0\n'
+              'for c in s {\n    if c != s[i] {\n        print("mismatch")\n    }\n    i = i + 1\n}\nprint(i)\n')
+    expected = "6\n\u00e9\n\u2192\n\u00e9\u2192l\n3\n6"
+    sb_file = write_snippet("string_chars_utf8", source)
+    test_interpreter(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+    check_semantic_error("for each: the loop variable takes the element type",
+                         'for s in ["a", "b"] {\n    print(s - 1)\n}\n',
+                         "Cannot apply operator '-' to string and int")
+    check_semantic_error("for each: iterating an int is rejected",
+                         "var n = 5\nfor x in n {\n    print(x)\n}\n",
+                         "Cannot iterate over int")
+
+
+def test_ranges():
+    source = ('for i in 0..3 {\n    print(i)\n}\n'
+              'for i in 1..=2 {\n    print(i * 10)\n}\n'
+              'var xs = 2..=4\nprint(len(xs))\nprint(xs[2])\n')
+    expected = "0\n1\n2\n10\n20\n3\n4"
+    sb_file = write_snippet("ranges", source)
+    test_interpreter(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+    check_semantic_error("ranges: a float bound is rejected",
+                         "for i in 0..2.5 {\n    print(i)\n}\n",
+                         "Range bounds must be int, got float")
+    check_semantic_error("ranges: a constant range has a known length",
+                         "var xs: [int; 2] = 0..=2\n",
+                         "Cannot initialize 'xs' of length 2 with 3 elements")
+
+
+def test_not_callable():
+    check_semantic_error("calls: calling an int variable is rejected with its span",
+                         "var x = 5\nprint(x(1))\n",
+                         "[line 2, col 7-10] Semantic error: Type error: "
+                         "'x' is a variable of type int, not a function")
+    check_semantic_error("calls: an unknown name is an undefined function",
+                         "var x = 5\nprint(y(1))\n", "Undefined function 'y'")
+
+
+def test_name_suggestions():
+    check_semantic_error("suggestions: a typo'd variable suggests the close name",
+                         "var xs = [1, 2]\nprint(x)\n",
+                         "[line 2, col 7] Semantic error: Undefined variable 'x'; did you mean 'xs'?")
+    check_semantic_error("suggestions: a typo'd function suggests the close name",
+                         "function compute(n: int): int {\n    return n\n}\nprint(comput(1))\n",
+                         "Undefined function 'comput'; did you mean 'compute'?")
+    sb_file = write_snippet("suggest", "var counter = 0\nprint(zebra)\nprint(counter)\n")
+    rc, out = run([SUB, sb_file, "python", os.path.join(ROOT_DIR, "_regtest_suggest.py")])
+    os.remove(sb_file)
+    label = "suggestions: a wildly different name gets none"
+    check_result(label, rc != 0 and "Undefined variable 'zebra'" in out and "did you mean" not in out,
+                 f"rc={rc} {out}")
+
+
+def test_overflow_modes():
+    source = "var x = 9223372036854775807\nprint(x + 1)\n"
+    check_output("overflow: wraps by default", source, "-9223372036854775808")
+    check_output("overflow: --overflow wrap", source, "-9223372036854775808", ["--overflow", "wrap"])
+    check_runtime_error("overflow: --overflow check traps", source,
+                        "Integer overflow in 9223372036854775807 + 1", ["--overflow", "check"])
+    check_output("overflow: --overflow saturate clamps", source, "9223372036854775807",
+                 ["--overflow=saturate"])
+    check_output("overflow: in-range arithmetic is unaffected by check",
+                 "var x = 40\nprint(x + 2)\n", "42", ["--overflow", "check"])
+    smallest = "var a = -2147483647 - 1\nvar b = -1\nprint(a % b)\nprint(a / b)\nprint(-a)\n"
+    width = ["--int-width", "32"]
+    check_runtime_error("overflow: --overflow check traps on the smallest int / -1", smallest,
+                        "Integer overflow in -2147483648 / -1", [*width, "--overflow", "check"])
+    check_output("overflow: --overflow saturate clamps the smallest int / -1 and its negation", smallest,
+                 "0\n2147483647\n2147483647", [*width, "--overflow", "saturate"])
+    sb_file = write_snippet("overflow_div", smallest)
+    test_native_compile(sb_file, "0\n-2147483648\n-2147483648", width)
+    test_native_compile(sb_file, "0\n2147483647\n2147483647", [*width, "--overflow", "saturate"])
+    if tool_available("gcc"):
+        out_name = os.path.join(ROOT_DIR, "_regtest_overflow_div")
+        rc, out = run([SUBC, sb_file, "-o", out_name, *width, "--overflow", "check"])
+        if rc == 0:
+            rc, out = run([out_name + EXE])
+        check_result("overflow: subc --overflow check traps on the smallest int / -1",
+                     rc != 0 and "[line 4] Runtime error: Integer overflow in -2147483648 / -1" in out, out)
+        for path in (out_name + EXE, out_name + ".c"):
+            if os.path.exists(path):
+                os.remove(path)
+    os.remove(sb_file)
+
+
+def test_select():
+    check_output("select: picks a when true and b when false",
+                 "print(select(2 > 1, 10, 20))\nprint(select(false, \"a\", \"b\"))\n", "10\nb")
+    check_output("select: evaluates both arguments",
+                 "function noisy(x: int): int {\n    print(x)\n    return x\n}\n"
+                 "print(select(true, noisy(1), noisy(2)))\n", "1\n2\n1")
+    check_semantic_error("select: the condition must be boolean", "print(select(1, 2, 3))\n",
+                         "select() condition must be boolean, got int")
+    check_semantic_error("select: the arguments must have compatible types",
+                         "print(select(true, 1, \"a\"))\n",
+                         "select() arguments have incompatible types int and string")
+    check_semantic_error("select: takes three arguments", "print(select(true, 1))\n",
+                         "Function 'select' expects 3 arguments, got 2")
+    sb_file = write_snippet("select", "var x = 5\nprint(select(x > 3, x * 2, x))\n"
+                                      "print(select(x < 3, 1.5, 2.5))\nprint(select(x == 5, \"five\", \"other\"))\n")
+    test_native_compile(sb_file, "10\n2.5\nfive")
+    os.remove(sb_file)
+    widened = "print(select(false, 1, 2.5))\nprint(select(true, 1, 2.5) / 2)\n"
+    check_output("select: an int and a float arm widen to float", widened, "2.5\n0.5")
+    sb_file = write_snippet("select_widen", widened)
+    test_native_compile(sb_file, "2.5\n0.5")
+    go_file = os.path.join(ROOT_DIR, "_regtest_select_widen.go")
+    write_snippet("select_widen", "var x = 5\nprint(select(true, x, 2) + 1)\n")
+    run([SUB, sb_file, "go", go_file])
+    check_generated("select: Go gets a concrete result type", go_file,
+                    ["func(c bool, a, b int) int { if c { return a }; return b }((true), int((x)), int((2)))"],
+                    ["interface{}"])
+    for path in (sb_file, go_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_entry_function():
+    source = ("function helper() {\n    print(\"helper\")\n}\n"
+              "function start() {\n    print(\"start\")\n    helper()\n}\nprint(\"top\")\n")
+    check_output("entry: without --entry only the top-level statements run", source, "top")
+    check_output("entry: --entry start runs start after them", source, "top\nstart\nhelper",
+                 ["--entry", "start"])
+    check_semantic_error("entry: a missing entry lists the functions", source,
+                         "Entry function 'main' is not defined; the program defines: helper, start",
+                         ["--entry=main"])
+    check_semantic_error("entry: the entry takes no parameters", "function f(a: int) {\n    print(a)\n}\n",
+                         "Entry function 'f' must take no parameters, but takes 1", ["--entry", "f"])
+    sb_file = write_snippet("entry", source)
+    test_native_compile(sb_file, "top\nstart\nhelper", ["--entry", "start"])
+    os.remove(sb_file)
+
+
+def test_int_width():
+    big = "var x = 2147483648\nprint(x)\n"
+    check_output("int-width: 64 bits by default accepts 2147483648", big, "2147483648")
+    check_semantic_error("int-width: --int-width 32 rejects 2147483648", big,
+                         "Integer literal 2147483648 does not fit in a 32-bit int", ["--int-width", "32"])
+    check_output("int-width: the 32-bit minimum can be written", "print(-2147483648)\n",
+                 "-2147483648", ["--int-width=32"])
+    source = "var x = 2147483647\nprint(x + 1)\n"
+    check_output("int-width: 32-bit arithmetic wraps at 32 bits", source, "-2147483648",
+                 ["--int-width", "32"])
+    check_runtime_error("int-width: --overflow check traps at 32 bits", source,
+                        "Integer overflow in 2147483647 + 1", ["--int-width", "32", "--overflow", "check"])
+    sb_file = write_snippet("int_width", "print(2147483647 + 1)\nprint(2147483647 * 1)\n")
+    test_native_compile(sb_file, "-2147483648\n2147483647", ["--int-width", "32"])
+    os.remove(sb_file)
+    cast = 'var s = "5000000000"\nprint(int(s))\nvar f = 3000000000.5\nprint(int(f))\n'
+    check_output("int-width: int() narrows to 32 bits", cast, "705032704\n-1294967296",
+                 ["--int-width", "32"])
+    check_output("int-width: int() saturates at 32 bits", cast, "2147483647\n2147483647",
+                 ["--int-width", "32", "--overflow", "saturate"])
+    check_runtime_error("int-width: int() traps at 32 bits under --overflow check", cast,
+                        "Integer overflow converting 5000000000 to a 32-bit int",
+                        ["--int-width", "32", "--overflow", "check"])
+    sb_file = write_snippet("int_width_cast", cast)
+    test_native_compile(sb_file, "705032704\n-1294967296", ["--int-width", "32"])
+    os.remove(sb_file)
+    label = "int-width: only 32 and 64 are accepted"
+    sb_file = write_snippet("int_width_bad", "print(1)\n")
+    rc, out = run([SUBI, sb_file, "--int-width", "16"])
+    os.remove(sb_file)
+    check_result(label, rc != 0 and "invalid --int-width '16'" in out, out)
+
+
+def test_array_lengths():
+    check_output("array length: [T; N] takes a literal of N elements",
+                 "var a: [int; 3] = [1, 2, 3]\na = [0; 3]\nprint(a[2])\n", "0")
+    check_semantic_error("array length: initializer length must match",
+                         "var a: [int; 4] = [1, 2, 3]\n",
+                         "Cannot initialize 'a' of length 4 with 3 elements")
+    check_semantic_error("array length: assignment length must match",
+                         "var a: [int; 2] = [1, 2]\na = [1, 2, 3]\n",
+                         "Cannot assign 3 elements to 'a' of length 2")
+    check_semantic_error("array length: parameter length must match",
+                         "function f(xs: [int; 2]) {\n    print(xs)\n}\nf([1])\n",
+                         "Argument 1 to 'f' expects an array of length 2, got 1 element")
+    check_semantic_error("array length: constant index past a known length",
+                         "const XS = [1, 2, 3]\nprint(XS[3])\n",
+                         "Array index 3 is out of bounds for an array of length 3")
+    check_output("array length: [T] stays dynamic",
+                 "var a: [int] = [1]\na = [1, 2, 3]\nprint(a[2])\n", "3")
+
+
+def test_negative_indices():
+    check_semantic_error("indices: constant xs[-1] is rejected",
+                         "var xs = [1, 2, 3]\nprint(xs[-1])\n", "Array index -1 is negative")
+    check_runtime_error("indices: computed negative index fails at runtime",
+                        "var xs = [1, 2, 3]\nvar i = 1 - 2\nprint(xs[i])\n",
+                        "Negative array index -1")
+
+
+def test_array_repeat():
+    check_output("arrays: [0; 5] is five zeros", "print([0; 5])\n", "[0, 0, 0, 0, 0]")
+    check_output("arrays: a zero count makes an empty array",
+                 "var xs = [1; 0]\nprint(len(xs))\nprint(xs)\n", "0\n[]")
+    check_output("arrays: [v; n] takes a constant-folded size",
+                 "var xs = [7; 2 + 3]\nprint(len(xs))\nprint(xs[4])\n", "5\n7")
+    check_output("arrays: repeat literals nest", "print([[0; 2]; 2])\n", "[[0, 0], [0, 0]]")
+    check_output("arrays: a repeat size may use consts",
+                 "const N = 3\nvar xs = [1; N * 2]\nprint(len(xs))\n", "6")
+    check_semantic_error("arrays: a repeat size from a variable is rejected",
+                         "var n = 3\nvar xs = [0; n]\n",
+                         "[line 2] Semantic error: Array size must be a constant integer expression")
+    check_semantic_error("arrays: a negative repeat size is rejected",
+                         "var xs = [0; 1 - 2]\n", "Array size -1 is out of range")
+    check_semantic_error("arrays: a float repeat size is rejected",
+                         "var xs = [0; 2.5]\n", "Array size must be an integer, got float")
+    check_output("arrays: a repeat evaluates its value once",
+                 'function f(): int {\n    print("f")\n    return 7\n}\nvar xs = [f(); 3]\nprint(xs)\n',
+                 "f\n[7, 7, 7]")
+    big = "var grid = [[0; 65536]; 64]\nprint(len(grid))\nprint(len(grid[63]))\n"
+    check_output("arrays: a large nested repeat is filled, not expanded", big, "64\n65536")
+    sb_file = write_snippet("repeat_big", big)
+    test_transpile_and_run(sb_file, "64\n65536", "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, "64\n65536", "js", ".js", ["node"])
+    os.remove(sb_file)
+    sb_file = write_snippet("repeat_rows", "var grid = [[0; 2]; 2]\ngrid[0][1] = 5\nprint(grid[0][1])\nprint(grid[1][1])\n")
+    test_transpile_and_run(sb_file, "5\n0", "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, "5\n0", "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_default_params():
+    greet = 'function greet(name, greeting = "Hello") {\n    return greeting + ", " + name\n}\n'
+    check_output("defaults: omitted argument uses the default", greet + 'print(greet("Ann"))\n',
+                 "Hello, Ann")
+    check_output("defaults: explicit argument overrides it", greet + 'print(greet("Bob", "Hi"))\n',
+                 "Hi, Bob")
+    check_semantic_error("defaults: required parameter still required", greet + "print(greet())\n",
+                         "Function 'greet' expects 1 to 2 arguments, got 0")
+    check_semantic_error("defaults: must be constant",
+                         'var g = "x"\nfunction f(a = g) {\n    return a\n}\n',
+                         "Default value for parameter 'a' must be a constant expression")
+    check_semantic_error("defaults: only on trailing parameters",
+                         "function f(a = 1, b) {\n    return a\n}\n",
+                         "Parameter 'b' without a default follows a parameter with one")
+
+
+def test_len_builtin():
+    check_output("len(): string length", 'print(len("abc"))\n', "3")
+    check_output("len(): array length", "print(len([1, 2, 3]))\n", "3")
+    check_output("len(): array variable", "var xs = [4, 5]\nprint(len(xs))\n", "2")
+    check_semantic_error("len(): rejects int", "print(len(5))\n",
+                         "len() expects a string or array, got int")
+    check_semantic_error("len(): rejects bool", "print(len(true))\n",
+                         "len() expects a string or array, got bool")
+
+
+def test_string_builtins():
+    check_output("strings: substr", 'print(substr("Hello, World", 7, 5))\n', "World")
+    check_output("strings: index_of", 'print(index_of("Hello, World", "World"))\n', "7")
+    check_output("strings: index_of missing", 'print(index_of("Hello", "xyz"))\n', "-1")
+    check_output("strings: to_upper", 'print(to_upper("Hello"))\n', "HELLO")
+    check_output("strings: to_lower", 'print(to_lower("Hello"))\n', "hello")
+    check_semantic_error("strings: substr arity", 'print(substr("abc", 1))\n',
+                         "Function 'substr' expects 3 arguments, got 2")
+    check_semantic_error("strings: to_upper rejects int", "print(to_upper(5))\n",
+                         "Argument 1 to 'to_upper' expects string, got int")
+    check_runtime_error("strings: substr out of range", 'var s = "abc"\nprint(substr(s, 2, 5))\n',
+                        "out of range")
+    test_substr_out_of_range_targets()
+
+
+def test_substr_out_of_range_targets():
+    """The generated code stops on an out-of-range substr() with subi's message."""
+    message = "substr(): start 2, length 5 is out of range for a string of length 3"
+    sb_file = write_snippet("substr_range", 'var s = "abc"\nprint(substr(s, 1, 2))\nprint(substr(s, 2, 5))\n')
+    stem = os.path.join(ROOT_DIR, "_regtest_substr_range")
+    java_dir = os.path.join(ROOT_DIR, "_regtest_substr_java")
+    os.makedirs(java_dir, exist_ok=True)
+    for lang, out_file, build, runner in [
+            ("python", stem + ".py", None, ["python3", stem + ".py"]),
+            ("js", stem + ".js", None, ["node", stem + ".js"]),
+            ("cpp", stem + ".cpp", ["g++", stem + ".cpp", "-o", stem + "_cpp"], [stem + "_cpp"]),
+            ("rust", stem + ".rs", ["rustc", "-A", "warnings", stem + ".rs", "-o", stem + "_rs"], [stem + "_rs"]),
+            ("java", os.path.join(java_dir, "SubProgram.java"),
+             ["javac", os.path.join(java_dir, "SubProgram.java")], ["java", "-cp", java_dir, "SubProgram"])]:
+        label = f"substr out of range: {lang} stops with the same message"
+        tool = (build or runner)[0]
+        if not tool_available(tool):
+            print(f"  SKIP {label} ('{tool}' not available)")
+            continue
+        rc, out = run([SUB, sb_file, lang, out_file, "-q"])
+        if rc == 0 and build:
+            rc, out = run(build)
+        if rc != 0:
+            print(f"  FAIL {label} (build failed, exit {rc})\n       {out}")
+            failures.append(label)
+            continue
+        rc, out = run(runner)
+        check_result(label, rc != 0 and out.startswith("bc") and message in out, f"(exit {rc}) {out}")
+    for path in (sb_file, stem + ".py", stem + ".js", stem + ".cpp", stem + "_cpp", stem + ".rs", stem + "_rs"):
+        if os.path.exists(path):
+            os.remove(path)
+    shutil.rmtree(java_dir, ignore_errors=True)
+
+
+def check_warning(label, source, message, extra_args=(), expect=True):
+    """Run a snippet through subi and check whether `message` is warned about."""
+    sb_file = write_snippet("warning", source)
+    rc, out = run([SUBI, sb_file, *extra_args])
+    os.remove(sb_file)
+    warned = "Semantic warning" in out and message in out
+    want = "a warning" if expect else "no warning"
+    check_result(label, rc == 0 and warned == expect, f"(expected {want}: {message!r}, exit {rc}) {out}")
+
+
+def test_pointless_statements():
+    check_warning("pointless-statement: x = x warns", "var x = 1\nx = x\nprint(x)\n",
+                  "[line 2] Semantic warning: Assignment to 'x' has no effect")
+    check_warning("pointless-statement: adding 0 in place warns", "var x = 1\nx = x + 0\nprint(x)\n",
+                  "Assignment to 'x' has no effect")
+    check_warning("pointless-statement: appending 0 to a string is not a no-op",
+                  'var s = "a"\ns = s + 0\nprint(s)\n', "has no effect", expect=False)
+    check_warning("pointless-statement: a pure bare expression warns", "var x = 1\nx + 2\nprint(x)\n",
+                  "[line 2] Semantic warning: Statement has no effect; its value is discarded")
+    check_warning("pointless-statement: indexing can trap and does not warn",
+                  "var a = [1, 2]\na[1]\nprint(a)\n", "has no effect", expect=False)
+    check_warning("pointless-statement: a call statement does not warn",
+                  "function f() {\n    print(1)\n}\nf()\n", "has no effect", expect=False)
+    check_warning("pointless-statement: -Wno-pointless-statement silences it",
+                  "var x = 1\nx = x\nprint(x)\n", "has no effect", ["-Wno-pointless-statement"], expect=False)
+
+
+def test_float_literals():
+    check_output("float literals: scientific notation",
+                 "print(1.5e10)\nprint(2E-3)\nprint(typeof(1e3))\n", "1.5e+10\n0.002\nfloat")
+    check_output("float literals: a hex float is scaled by a power of 2",
+                 "print(0x1.8p3)\nprint(0x1p-2)\n", "12\n0.25")
+    check_output("float literals: hex integers keep their value", "print(0x10 + 1)\n", "17")
+    check_lex_error("float literals: an exponent needs digits", "print(1e)\n",
+                    "Expected digit after exponent")
+    check_lex_error("float literals: a signed exponent needs digits", "print(1e+)\n",
+                    "Expected digit after exponent")
+    check_lex_error("float literals: a hex fraction needs a p exponent", "print(0x1.8)\n",
+                    "Hex float literal needs a 'p' exponent")
+    check_semantic_error("float literals: a decimal literal beyond a double is rejected", "print(1e400)\n",
+                         "Float literal 1e400 is too large for a 64-bit float")
+    check_semantic_error("float literals: a hex literal beyond a double is rejected", "print(0x1p2000)\n",
+                         "Float literal 0x1p2000 is too large for a 64-bit float")
+    lint = ["-Wfloat-precision"]
+    check_warning("float literals: digits a double cannot hold warn", "print(3.14159265358979323846)\n",
+                  "Float literal 3.14159265358979323846 loses precision", lint)
+    check_warning("float literals: a literal that reads back the same does not warn",
+                  "print(0.1 + 0.5 + 1.7976931348623157e308)\n", "loses precision", lint, expect=False)
+    check_warning("float literals: the precision lint is off by default", "print(3.14159265358979323846)\n",
+                  "loses precision", expect=False)
+
+
+def test_short_circuit():
+    """The right operand of && and || is skipped once the left one decides."""
+    source = ("function t(tag: int): bool {\n    print(tag)\n    return true\n}\n"
+              "function f(tag: int): bool {\n    print(tag)\n    return false\n}\n"
+              "if f(1) && t(2) {\n    print(\"and\")\n}\n"
+              "if t(3) || f(4) {\n    print(\"or\")\n}\n"
+              "var x = f(5) && t(6)\nvar y = !x || t(7)\nif y && t(8) {\n    print(\"both\")\n}\n")
+    expected = "1\n3\nor\n5\n8\nboth"
+    sb_file = write_snippet("short_circuit", source)
+    test_interpreter(sb_file, expected)
+    test_native_compile(sb_file, expected)
+    test_transpile_and_run(sb_file, expected, "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, expected, "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_interpreter_limits():
+    check_runtime_error("limits: an infinite loop halts at --max-steps", "while true {\n}\n",
+                        "Resource limit exceeded: more than 100000 steps", ["--max-steps", "100000"])
+    check_runtime_error("limits: an oversized array is refused by --max-memory",
+                        "var xs = [0; 1000]\nprint(len(xs))\n",
+                        "Resource limit exceeded: allocating 16000 bytes goes over --max-memory 1000",
+                        ["--max-memory", "1000"])
+    check_output("limits: an array within --max-memory is allowed",
+                 "var xs = [0; 1000]\nprint(len(xs))\n", "1000", ["--max-memory", "100000"])
+    check_runtime_error("limits: runaway recursion halts at --max-depth",
+                        "function r(n: int): int {\n    return r(n + 1)\n}\nprint(r(0))\n",
+                        "calls nested more than 500 deep", ["--max-depth", "500"])
+    runaway = "function r(n: int): int {\n    return r(n + 1)\n}\nprint(r(0))\n"
+    check_runtime_error("limits: recursion halts at 1000 calls by default", runaway,
+                        "calls nested more than 1000 deep")
+    check_runtime_error("limits: a huge --max-depth stops before the stack overflows", runaway,
+                        "nests too deeply for the interpreter's stack", ["--max-depth", "1000000"])
+    check_runtime_error("limits: strings from the string builtins count against --max-memory",
+                        "var s = \"abcdefghij\"\nvar i = 0\nwhile i < 1000 {\n"
+                        "    var t = to_upper(s)\n    var u = substr(t, 1, 5)\n    i = i + 1\n}\n",
+                        "goes over --max-memory 4000", ["--max-memory", "4000"])
+
+
+def test_eval():
+    """subi --eval runs a program from the command line and prints its last value."""
+    for label, source, expected in [("eval: print(2 + 3) prints 5", "print(2 + 3)", "5"),
+                                    ("eval: a final expression is printed", "var x = 4\nx * 2", "8"),
+                                    ("eval: a final declaration prints nothing", "var x = 4", "")]:
+        rc, out = run([SUBI, "--quiet", "--eval", source])
+        check_result(label, rc == 0 and out.strip() == expected, f"(exit {rc}) {out}")
+    for label, args, ok_rc, message in [
+            ("eval: a semantic error fails", ["--eval", "print(missing)"], False, "Undefined variable 'missing'"),
+            ("eval: only the echoed expression is exempt from -Wpointless-statement",
+             ["--eval", "1 + 1\n2 + 3"], True, "[line 1] Semantic warning: Statement has no effect"),
+            ("eval: the echoed expression passes --strict", ["--strict", "--eval", "2 + 3"], True, "5"),
+            ("eval: a second --eval is rejected", ["--eval", "1", "--eval", "2"], False,
+             "--eval given more than once")]:
+        rc, out = run([SUBI, "--quiet", *args])
+        check_result(label, (rc == 0) == ok_rc and message in out, f"(exit {rc}) {out}")
+
+
+def test_infinite_loop():
+    # The loops sit in functions nothing calls, so subi finishes
+    message = "Loop never terminates"
+    spin = "function spin() {\n    while true {\n    }\n}\n"
+    check_warning("infinite-loop: while true {} warns", spin, message)
+    check_warning("infinite-loop: a const true condition warns",
+                  "const GO = true\nfunction spin() {\n    while GO {\n        print(1)\n    }\n}\n", message)
+    check_warning("infinite-loop: while true with a break does not warn",
+                  "function once() {\n    while true {\n        break\n    }\n}\n", message, expect=False)
+    check_warning("infinite-loop: a break of a nested loop does not count",
+                  "function spin() {\n    while true {\n        while true {\n            break\n"
+                  "        }\n    }\n}\n", message)
+    check_warning("infinite-loop: a return ends the loop",
+                  "function f() {\n    while true {\n        return\n    }\n}\nf()\n", message, expect=False)
+    check_warning("infinite-loop: a runtime condition does not warn",
+                  "var n = 3\nwhile n > 0 {\n    n = n - 1\n}\n", message, expect=False)
+    check_warning("infinite-loop: -Wno-infinite-loop silences it", spin, message,
+                  ["-Wno-infinite-loop"], expect=False)
+
+
+def test_float_equality():
+    source = "var a = 0.1 + 0.2\nvar b = 0.3\nprint(a == b)\n"
+    check_warning("float-equality: == on two floats warns", source,
+                  "Comparing floats with '==' is exact")
+    check_warning("float-equality: != warns too", "var a = 1.5\nprint(a != 2.5)\n",
+                  "Comparing floats with '!='")
+    check_warning("float-equality: == on two ints does not warn", "var a = 1\nprint(a == 2)\n",
+                  "Comparing floats", expect=False)
+    check_warning("float-equality: an ordering comparison does not warn", "var a = 1.5\nprint(a < 2.5)\n",
+                  "Comparing floats", expect=False)
+    check_warning("float-equality: -Wno-float-equality silences it", source,
+                  "Comparing floats", ["-Wno-float-equality"], expect=False)
+
+
+def test_numeric_conversions():
+    check_semantic_error("conversions: float -> int narrowing is an error",
+                         "var x: int = 3.7\n", "Implicit narrowing conversion from float to int")
+    check_semantic_error("conversions: narrowing on reassignment",
+                         "var x = 1\nx = 2.5\n", "Implicit narrowing conversion from float to int")
+    check_output("conversions: explicit int() cast is accepted",
+                 "var x: int = int(3.7)\nprint(x)\n", "3")
+    widening = "var i = 7\nvar f: float = i\nprint(f)\n"
+    check_warning("conversions: int -> float widening warns", widening,
+                  "Implicit conversion from int to float")
+    check_warning("conversions: -Wno-implicit-widening silences it", widening,
+                  "Implicit conversion from int to float",
+                  ["-Wno-implicit-widening"], expect=False)
+    lint = ["-Winteger-division"]
+    check_warning("conversions: 5 / 2 into a float warns with -Winteger-division",
+                  "var f: float = 5 / 2\nprint(f)\n", "Integer division 5 / 2 discards a remainder of 1", lint)
+    check_warning("conversions: 4 / 2 divides evenly",
+                  "var f: float = 4 / 2\nprint(f)\n", "Integer division", lint, expect=False)
+    check_warning("conversions: float(5) / 2 is float division",
+                  "var f: float = float(5) / 2\nprint(f)\n", "Integer division", lint, expect=False)
+    check_warning("conversions: integer-division is off by default",
+                  "var f: float = 5 / 2\nprint(f)\n", "Integer division", expect=False)
+
+
+def test_dump_symbols():
+    label = "--dump-symbols: nested block is its own scope"
+    sb_file = write_snippet("symbols", "var outer = 1\nif outer > 0 {\n    var inner = 2\n    print(inner)\n}\n")
+    rc, out = run([SUBI, sb_file, "--dump-symbols"])
+    os.remove(sb_file)
+    expected = ("Symbols:\n"
+                "scope 0: global\n"
+                "  var outer: int  (line 1, col 1)\n"
+                "  scope 1: block (line 2)\n"
+                "    var inner: int  (line 3, col 5)\n")
+    check_result(label, rc == 0 and expected in out, out)
+
+
+def test_stats():
+    label = "--stats: node, depth and function counts"
+    sb_file = write_snippet("stats", "function add(a: int, b: int): int {\n    return a + b\n}\n"
+                                     "var x = add(1, 2 * 3)\nprint(x)\n")
+    rc, out = run([SUBI, sb_file, "--stats"])
+    os.remove(sb_file)
+    expected = ("  AST nodes: 17\n"
+                "  max expression depth: 3\n"
+                "  functions: 1\n"
+                "  variables: 1\n"
+                "  constants: 0\n")
+    check_result(label, rc == 0 and "Stats:\n  tokens: 40\n    IDENTIFIER     9\n" in out and expected in out,
+                 out)
+
+
+def test_unicode_escapes():
+    check_output("strings: \\u{1F600} decodes to UTF-8", 'print("a\\u{1F600}\\u{e9}")\n', "a\U0001F600\u00e9")
+    check_lex_error("strings: \\u{110000} is out of range", 'print("\\u{110000}")\n',
+                    "Unicode escape \\u{110000} is above U+10FFFF")
+    check_lex_error("strings: \\u{D800} is a surrogate", 'print("\\u{D800}")\n',
+                    "Unicode escape \\u{D800} is a surrogate code point")
+    check_lex_error("strings: \\u needs braces", 'print("\\u41")\n', "Invalid Unicode escape")
+
+
+def test_byte_strings():
+    check_output("byte strings: b\"\\x00\\xFF\" is a two-byte int array",
+                 'var data = b"\\x00\\xFF"\nprint(len(data))\nprint(data[0])\nprint(data[1] + 1)\n',
+                 "2\n0\n256")
+    check_output("byte strings: ASCII text and escapes give their byte values", 'print(b"Hi\\n")\n', "[72, 105, 10]")
+    check_lex_error("byte strings: \\u escapes are rejected", 'var a = b"caf\\u{e9}"\n',
+                    "Escape '\\u' is not allowed in a byte string")
+    check_lex_error("byte strings: non-ASCII text is rejected", 'var a = b"caf\u00e9"\n',
+                    "Byte strings may only contain ASCII")
+    sb_file = write_snippet("bytes", 'var data = b"\\x00\\xFF"\nprint(len(data))\nprint(data[1])\n')
+    test_transpile_and_run(sb_file, "2\n255", "python", ".py", ["python3"])
+    test_transpile_and_run(sb_file, "2\n255", "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_casts():
+    check_output("casts: int() truncates toward zero", "print(int(3.9))\n", "3")
+    check_output("casts: string() formats an int", "print(string(42))\n", "42")
+    check_semantic_error("casts: string -> bool is rejected",
+                         'var b = bool("x")\n', "Cannot convert string to bool")
+
+
+def test_typeof():
+    check_output("typeof: int literal", "print(typeof(3))\n", "int")
+    check_output("typeof: float literal", "print(typeof(3.0))\n", "float")
+    check_output("typeof: string literal", 'print(typeof("x"))\n', "string")
+    check_output("typeof: optional variable", "var o: int? = null\nprint(typeof(o))\n", "int?")
+    sb_file = write_snippet("typeof", 'var n = 1 < 2\nprint(typeof(n) + "!")\n')
+    test_native_compile(sb_file, "bool!")
+    test_transpile_and_run(sb_file, "bool!", "python", ".py", ["python3"])
+    os.remove(sb_file)
+
+
+def test_strict_mode():
+    unused = "var spare = 1\nprint(\"ok\")\n"
+    check_output("--strict: unused variable is fine by default", unused, "ok")
+    check_semantic_error("--strict: unused variable becomes an error", unused,
+                         "Variable 'spare' is never used", ["--strict"])
+
+    shadow = "var n = 1\nfunction f(n: int): int {\n    return n\n}\nprint(f(n))\n"
+    check_warning("shadowing: off by default", shadow, "shadows", expect=False)
+    check_warning("shadowing: a parameter hiding a variable warns", shadow,
+                  "'n' shadows the variable declared on line 1", ["-Wshadowing"])
+    check_warning("shadowing: names starting with _ are exempt",
+                  "var _n = 1\nfor _n in range(2) {\n    print(_n)\n}\n", "shadows", ["-Wshadowing"], expect=False)
+    dead = "function f(): int {\n    return 1\n    print(\"a\")\n    print(\"b\")\n}\nprint(f())\n"
+    check_warning("unreachable-code: off by default", dead, "Unreachable code", expect=False)
+    check_warning("unreachable-code: a statement after return warns once", dead,
+                  "[line 3] Semantic warning: Unreachable code", ["-Wunreachable-code"])
+    check_warning("unreachable-code: only the first of the stretch is reported", dead,
+                  "[line 4] Semantic warning: Unreachable code", ["-Wunreachable-code"], expect=False)
+    check_warning("constant-comparison: literals on both sides",
+                  "if 1 < 2 {\n    print(1)\n}\n", "Comparison with '<' is always true",
+                  ["-Wconstant-comparison"])
+    check_warning("constant-comparison: an int compared with itself",
+                  "var x = 3\nprint(x != x)\n", "Comparison with '!=' is always false",
+                  ["-Wconstant-comparison"])
+    check_warning("constant-comparison: a float compared with itself can be NaN",
+                  "var f = 0.5\nprint(f == f)\n", "Comparison", ["-Wconstant-comparison"], expect=False)
+    check_warning("constant-comparison: #static_assert is meant to be constant",
+                  "#static_assert(1 + 1 == 2, \"math\")\nprint(1)\n", "Comparison",
+                  ["-Wconstant-comparison"], expect=False)
+    check_semantic_error("--strict: turns the new lints on too", dead, "Unreachable code", ["--strict"])
+
+
+def test_prelude():
+    """--prelude adds the functions a program uses; its own definitions shadow them."""
+    prelude = write_snippet("prelude", "const TAU = 6.28\nfunction square(x: int): int {\n    return x * x\n}\n"
+                            "function cube(x: int): int {\n    return square(x) * x\n}\n"
+                            "function greet(): string {\n    return \"prelude\"\n}\n")
+    check_output("prelude: a prelude function is callable without defining it",
+                 "print(cube(3))\nprint(TAU)\n", "27\n6.28", ["--prelude", prelude])
+    check_output("prelude: a user function shadows the prelude's",
+                 "function greet(): string {\n    return \"mine\"\n}\nprint(greet())\n", "mine",
+                 ["--prelude", prelude])
+    check_semantic_error("prelude: without --prelude the name is undefined", "print(cube(3))\n",
+                         "Undefined function 'cube'")
+    sb_file = write_snippet("prelude_use", "print(square(4))\n")
+    test_native_compile(sb_file, "16", ["--prelude", prelude])
+    py_file = os.path.join(ROOT_DIR, "_regtest_prelude_use.py")
+    run([SUB, sb_file, "python", py_file, "--prelude", prelude])
+    code = (read_bytes(py_file) or b"").decode("utf-8", "replace")
+    label = "prelude: only the functions the program reaches are emitted"
+    check_result(label, "def square(" in code and "cube" not in code and "greet" not in code, code)
+    # cube() still squares with the prelude's square(), which the program's hides
+    shadow = "function square(x: int): int {\n    return 0\n}\nprint(cube(2))\nprint(square(3))\n"
+    check_output("prelude: a prelude function keeps the prelude's copy of a shadowed helper",
+                 shadow, "8\n0", ["--prelude", prelude])
+    shadow_file = write_snippet("prelude_shadow", shadow)
+    test_native_compile(shadow_file, "8\n0", ["--prelude", prelude])
+    bad = write_snippet("prelude_bad", "print(1)\n")
+    check_semantic_error("prelude: a prelude with statements is rejected", "print(2)\n",
+                         "A prelude may only declare functions and constants", ["--prelude", bad])
+    broken = write_snippet("prelude_broken", "function f(): int {\n    return missing\n}\n")
+    check_semantic_error("prelude: an error in the prelude names the prelude's file", "print(1)\n",
+                         f"[{broken}, line 2, col 12-18] Semantic error: Undefined variable 'missing'",
+                         ["--prelude", broken])
+    noisy = write_snippet("prelude_noisy", "function helper(x: int): int {\n    var unused = 1\n"
+                                           "    return x\n}\n")
+    args = ["--prelude", noisy, "-Wunused-variable"]
+    check_warning("prelude: a warning in the prelude names the prelude's file", "\n\nprint(helper(3))\n",
+                  f"[{noisy}, line 2] Semantic warning: Variable 'unused'", args)
+    check_warning("prelude: a prelude's warning is not reported against the program",
+                  "\n\nprint(helper(3))\n", "[line 2] Semantic warning", args, expect=False)
+    for path in (prelude, sb_file, py_file, shadow_file, bad, broken, noisy):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_unused_parameters():
+    source = ("function scale(value: int, factor: int, _reserved: int): int {\n"
+              "    return value * 2\n}\nprint(scale(1, 2, 3))\n")
+    lint = ["-Wunused-parameter"]
+    check_warning("unused-parameter: a parameter the body never reads warns", source,
+                  "[line 1] Semantic warning: Parameter 'factor' is never used", lint)
+    check_warning("unused-parameter: a used parameter does not warn", source, "'value'", lint, expect=False)
+    check_warning("unused-parameter: a leading underscore opts out", source, "'_reserved'", lint, expect=False)
+    check_warning("unused-parameter: off by default", source, "'factor'", expect=False)
+    check_warning("unused-parameter: -Wunused-variable leaves parameters alone", source, "'factor'",
+                  ["-Wunused-variable"], expect=False)
+
+
+def test_unused_functions():
+    source = ("function helper(): int {\n    return 1\n}\n"
+              "function used(): int {\n    return helper()\n}\n"
+              "function spare() {\n    print(1)\n}\n"
+              "function _quiet() {\n}\n"
+              "print(used())\n")
+    lint = ["-Wunused-function"]
+    check_warning("unused-function: an uncalled function warns", source,
+                  "[line 7] Semantic warning: Function 'spare' is never called", lint)
+    check_warning("unused-function: a called function does not warn", source, "'used'", lint, expect=False)
+    check_warning("unused-function: reached through another function", source, "'helper'", lint, expect=False)
+    check_warning("unused-function: a leading underscore opts out", source, "'_quiet'", lint, expect=False)
+    check_warning("unused-function: off by default", source, "'spare'", expect=False)
+    check_warning("unused-function: only called from dead code",
+                  "function inner() {\n}\nfunction outer() {\n    inner()\n}\nprint(1)\n",
+                  "Function 'inner' is only called from functions that are never called", lint)
+
+    label = "unused-function: library functions are public and exempt"
+    sb_file = write_snippet("unused_lib", "function exported(): int {\n    return 1\n}\n")
+    js_file = os.path.join(ROOT_DIR, "_regtest_unused_lib.js")
+    rc, out = run([SUB, sb_file, "js", js_file, "--crate-type", "lib", *lint])
+    check_result(label, rc == 0 and "Semantic warning" not in out, out)
+    for path in (sb_file, js_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_static_assert():
+    check_output("#static_assert: true condition passes",
+                 '#static_assert(1 + 1 == 2, "math")\nprint("ok")\n', "ok")
+    check_semantic_error("#static_assert: false condition fails with message",
+                         '#static_assert(false, "boom")\n', "boom")
+    check_semantic_error("#static_assert: non-constant condition",
+                         'var x = 1\n#static_assert(x == 1, "runtime")\n',
+                         "not a compile-time constant")
+    check_output("#static_assert: a condition may use consts",
+                 'const N = 4\nconst M = N * 2\n#static_assert(M == 8, "eight")\nprint("ok")\n', "ok")
+    check_semantic_error("#static_assert: a false condition on consts fails",
+                         'const N = 4\n#static_assert(N > 5, "too small")\n', "too small")
+
+
+TRAILING_COMMA_CASES = [
+    ("call arguments", "function add(a, b) {\n    return a + b\n}\nprint(add(1, 2,))\n",
+     "function add(a, b) {\n    return a + b\n}\nprint(add(1,, 2))\n"),
+    ("array literal", "var xs = [1, 2, 3,]\n", "var xs = [1,, 3]\n"),
+    ("object fields", "var pt = {x: 1, y: 2,}\n", "var pt = {x: 1,, y: 2}\n"),
+]
+
+
+def test_trailing_commas():
+    for kind, accepted, rejected in TRAILING_COMMA_CASES:
+        check_parse(f"parser: trailing comma in {kind}", accepted, False)
+        check_parse(f"parser: doubled comma in {kind}", rejected, True)
+
+
+LINE_CONTINUATION_CASES = [
+    ("inside parentheses", "var x = (1 +\n    2)\nprint(x)\n", "3"),
+    ("before a closing paren", "var x = (10\n    - 4)\nprint(x)\n", "6"),
+    ("after a binary operator", "var x = 3 *\n    4\nprint(x)\n", "12"),
+    ("inside call arguments", "print(len(\n    \"abc\"\n))\n", "3"),
+    ("inside an index", "var xs = [5, 6]\nprint(xs[\n    1])\n", "6"),
+    ("stops at a complete statement", "var x = 1\n-2\nprint(x)\n", "1"),
+]
+
+
+def test_line_continuation():
+    for kind, source, expected in LINE_CONTINUATION_CASES:
+        check_output(f"parser: line continuation {kind}", source, expected)
+    check_parse("parser: newline before a binary operator ends the statement",
+                "var x = 1\n* 2\n", True)
+
+
+def test_error_limit():
+    label = "subi --error-limit: parsing stops at the limit"
+    sb_file = write_snippet("garbage", ")\n" * 50)
+    rc, out = run([SUBI, sb_file, "--error-limit", "3"])
+    os.remove(sb_file)
+    reported = out.count("Parse error")
+    check_result(label, reported == 3 and "too many parse errors, aborting" in out,
+                 f"({reported} parse errors reported) {out}")
+
+
+def test_caret_tabs():
+    """The caret under a parse error lines up with a tab-indented line."""
+    sb_file = write_snippet("tabs", "var a = 1\n\tvar b = * 2\n")
+    for extra, indent in [((), 4), (("--tab-width", "8"), 8)]:
+        label = f"diagnostics: caret under a tab-indented line (tab width {indent})"
+        rc, out = run([SUBI, sb_file, *extra])
+        line = " " * indent + "var b = * 2"
+        caret = " " * (indent + len("var b = ")) + "^"
+        check_result(label, f"    2 | {line}\n      | {caret}\n" in out, out)
+    os.remove(sb_file)
+
+
+def test_unclosed_diagnostics():
+    """A construct left open at EOF points at its opener and at the end."""
+    cases = [
+        ("block comment", "var a = 1\n/* start\nvar b = 2\n",
+         "Unterminated block comment starting at line 2",
+         "    2 | /* start\n      | ^~ opened here\n"
+         "    3 | var b = 2\n      |          ^ expected closing here\n"),
+        ("brace", "function f() {\n    print(1)\n",
+         "Expected '}' to close block",
+         "    1 | function f() {\n      |              ^ opened here\n"
+         "    2 |     print(1)\n      |             ^ expected closing here\n"),
+    ]
+    for name, source, message, excerpt in cases:
+        label = f"diagnostics: unclosed {name} shows both locations"
+        sb_file = write_snippet("unclosed", source)
+        _, out = run([SUBI, sb_file])
+        os.remove(sb_file)
+        check_result(label, message in out and excerpt in out, out)
+
+
+def test_duplicate_definitions():
+    """A name declared twice in one scope is an error showing both places."""
+    check_semantic_error("names: two functions with one name are rejected",
+                         "function f() {\n    return 1\n}\nfunction f() {\n    return 2\n}\nprint(f())\n",
+                         "[line 4, col 1] Semantic error: 'f' is already declared in this scope, "
+                         "as a function on line 1\n"
+                         "    4 | function f() {\n      | ^ declared again here\n"
+                         "    1 | function f() {\n      | ^ first declared here\n")
+    check_semantic_error("names: a function and a variable cannot share a name",
+                         "var f = 1\nfunction f() {\n    return 2\n}\n",
+                         "'f' is already declared in this scope, as a variable on line 1")
+    check_semantic_error("names: a variable cannot reuse a function's name",
+                         "function f() {\n    return 2\n}\nvar f = 1\n",
+                         "'f' is already declared in this scope, as a function on line 1")
+    check_semantic_error("names: a variable cannot reuse a constant's name",
+                         "const f = 1\nvar f = 2\n",
+                         "as a constant on line 1")
+    check_output("names: an inner scope may shadow a function",
+                 "function f() {\n    return 1\n}\nfunction g() {\n    var f = 5\n    return f\n}\nprint(g())\n",
+                 "5")
+
+
+def test_semicolons():
+    label = "semicolons: two statements on one line parse like two lines"
+    trees = []
+    for name, source in [("lines", "var x = 1\nvar y = 2\nprint(x + y)\n"),
+                         ("semis", "var x = 1; var y = 2; print(x + y)\n")]:
+        sb_file = write_snippet(name, source)
+        rc, out = run([SUBC, sb_file, "--emit", "ast"])
+        os.remove(sb_file)
+        trees.append(re.sub(r" \[[0-9:-]+\]", "", out[out.find("PROGRAM"):]) if rc == 0 else None)
+    check_result(label, trees[0] is not None and trees[0] == trees[1], trees)
+    check_output("semicolons: a trailing ';' is accepted", "var x = 1;\nprint(x);\n", "1")
+    check_output("semicolons: ';;' is an empty statement",
+                 "var x = 2;;\nif x > 1 { print(x); }\n", "2")
+
+
+def test_line_endings():
+    label = "line endings: \\r\\n and lone \\r give the same spans as \\n"
+    source = "var x = 1 + 2\nif x > 0 {\n    print(x)\n}\n/* a\nb */ print(\"done\")\n"
+    trees = []
+    for name, ending in [("lf", "\n"), ("crlf", "\r\n"), ("cr", "\r")]:
+        sb_file = write_snippet(name, source.replace("\n", ending))
+        rc, out = run([SUBC, sb_file, "--emit", "ast"])
+        os.remove(sb_file)
+        trees.append(out[out.find("PROGRAM"):] if rc == 0 else None)
+    check_result(label, trees[0] is not None and trees[0] == trees[1] and trees[0] == trees[2], trees)
+    check_semantic_error("line endings: excerpts from a \\r\\n file have no stray \\r",
+                         "var x = 1\r\n\r\nvar y = 2147483648\r\n",
+                         "[line 3, col 9-18] Semantic error: Integer literal 2147483648 does not fit in a 32-bit int "
+                         "(the largest is 2147483647)\n    3 | var y = 2147483648\n", ["--int-width", "32"])
+    check_output("line endings: a lone \\r ends a statement", "var x = 1\rprint(x + 1)\r", "2")
+
+
+def test_raw_identifiers():
+    check_output("raw identifiers: r#true names a variable called true",
+                 "var r#true = 1\nprint(r#true)\nprint(true)\n", "1\ntrue")
+    check_output("raw identifiers: the prefix is not part of the name",
+                 "var r#count = 3\nprint(count)\nvar r = 2 # comment\nprint(r)\n", "3\n2")
+    sb_file = write_snippet("raw_ident", "var r#true = 1\nfunction r#if(r#while: int): int {\n"
+                                         "    return r#while + 1\n}\nprint(r#true + r#if(4))\n")
+    test_native_compile(sb_file, "6")
+    os.remove(sb_file)
+
+
+def test_doc_comments():
+    label = "docs: --emit docs lists public functions with their doc comments"
+    sb_file = write_snippet("docs", "/// Adds two numbers.\n/// Both are ints.\nfunction add(a: int, b: int): int {\n"
+                                    "    return a + b\n}\n//// not a doc comment\nfunction plain() {\n}\n"
+                                    "/// private\nfunction _helper() {\n}\nprint(add(1, 2))\n")
+    rc, out = run([SUBC, sb_file, "--emit", "docs"])
+    test_interpreter(sb_file, "3")
+    os.remove(sb_file)
+    expected = f"# {sb_file}\n\n## add(a, b)\n\nAdds two numbers.\nBoth are ints.\n\n## plain()\n"
+    check_result(label, rc == 0 and expected in out, f"(exit {rc}) {out}")
+    sb_file = write_snippet("docs", "/// Adds two numbers.\nfunction add(a: int, b: int): int {\n    return a + b\n}\n")
+    md_file = os.path.join(ROOT_DIR, "_regtest_api.md")
+    rc, out = run([SUBC, sb_file, "--emit", "docs", "-o", md_file])
+    written = open(md_file, encoding="utf-8").read() if os.path.exists(md_file) else ""
+    check_result("docs: --emit docs -o writes the listing to the file", rc == 0 and "## add(a, b)" not in out
+                 and f"# {sb_file}\n\n## add(a, b)\n\nAdds two numbers.\n" in written, out + written)
+    rc, out = run([SUBC, sb_file, "--emit", "ast", "-o", md_file])
+    check_result("docs: the other --emit kinds reject -o", rc != 0 and
+                 "-o cannot be used with --emit ast, which prints to stdout" in out, out)
+    for path in (sb_file, md_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_parser_recovery():
+    label = "parser: recovers and reports independent syntax errors"
+    sb_file = write_snippet("recovery", 'var a = 1 )\nprint("ok")\nvar b = * 2\nprint(b)\n')
+    _, out = run([SUBI, sb_file])
+    os.remove(sb_file)
+    errors = [line for line in out.splitlines() if "Parse error" in line]
+    check_result(label, len(errors) == 2 and "[line 1," in errors[0] and "[line 3," in errors[1],
+                 f"({len(errors)} parse errors reported) {out}")
+    cascade = 'var x = (1 +\nprint(2)\nfunction f(): int {\n    return "a"\n}\n'
+    partial = "print(1)\nvar = 3\nprint(2)\n"
+    for name, source in [("a dangling expression", cascade), ("a nameless var", partial)]:
+        sb_file = write_snippet("recovery", source)
+        for tool, cmd in [("subi", [SUBI, sb_file, "--quiet"]), ("subc check", [SUBC, "check", sb_file]),
+                          ("subc", [SUBC, sb_file, "-q", "-o", os.path.join(ROOT_DIR, "_regtest_recovery")])]:
+            rc, out = run(cmd)
+            ran = any(line in ("1", "2") for line in out.splitlines())
+            check_result(f"parser: {tool} stops after {name} without checking the partial tree",
+                         rc != 0 and "Parse error" in out and "Semantic" not in out and not ran, out)
+        os.remove(sb_file)
+
+
+def test_max_nesting():
+    label = "parser: 10000 nested parentheses fail cleanly instead of overflowing"
+    sb_file = write_snippet("deep", "print(" + "(" * 10000 + "1" + ")" * 10000 + ")\n")
+    rc, out = run([SUBC, sb_file, "--emit", "ast"])
+    os.remove(sb_file)
+    errors = [line for line in out.splitlines() if "Parse error" in line]
+    check_result(label, rc >= 0 and len(errors) == 1 and
+                 "Expression nesting too deep (more than 256 levels" in errors[0],
+                 f"(exit {rc}, {len(errors)} parse errors) {out[-500:]}")
+    for name, source in [("blocks", "if true {\n" * 10000 + "}\n" * 10000), ("unary", "print(" + "- " * 10000 + "1)\n")]:
+        label = f"parser: deeply nested {name} fail cleanly"
+        sb_file = write_snippet("deep", source)
+        rc, out = run([SUBI, sb_file])
+        os.remove(sb_file)
+        check_result(label, rc >= 0 and out.count("Expression nesting too deep") == 1,
+                     f"(exit {rc}) {out[-500:]}")
+    check_output("parser: --max-nesting raises or lowers the limit", "print(((1)))\n", "1", ["--max-nesting", "5"])
+    label = "parser: --max-nesting 3 rejects print(((1)))"
+    sb_file = write_snippet("deep", "print(((1)))\n")
+    _, out = run([SUBI, sb_file, "--max-nesting=3"])
+    os.remove(sb_file)
+    check_result(label, "Expression nesting too deep (more than 3 levels" in out, out)
+    label = "parser: --max-nesting beyond what the stack allows is rejected"
+    rc, out = run([SUBI, "--max-nesting", "100000", "--eval", "print(1)"])
+    check_result(label, rc != 0 and "invalid --max-nesting value '100000'" in out, out)
+    rc, out = run([SUBI, "--max-nesting", "0", "--eval", "print(1)"])
+    limit = re.search(r"expected 1-(\d+)", out)
+    if limit:
+        most = int(limit.group(1))
+        depth = most - 2   # print( and its argument are levels too
+        for name, source in [("blocks", "if true {\n" * depth + "print(1)\n" + "}\n" * depth),
+                             ("parentheses", "print(" + "(" * depth + "1" + ")" * depth + ")\n")]:
+            label = f"parser: {name} nested to the largest --max-nesting still run"
+            sb_file = write_snippet("deep", source)
+            rc, out = run([SUBI, "--quiet", sb_file, "--max-nesting", str(most)])
+            os.remove(sb_file)
+            check_result(label, rc == 0 and out.strip() == "1", f"(exit {rc}) {out[-500:]}")
+
+
+def check_emit_ast(label, source, needle, extra_args=()):
+    """Check that `subc --emit ast` prints `needle` for the snippet."""
+    sb_file = write_snippet("emit_ast", source)
+    rc, out = run([SUBC, sb_file, "--emit", "ast", *extra_args])
+    os.remove(sb_file)
+    check_result(label, rc == 0 and needle in out, f"(expected {needle!r}, exit {rc}) {out}")
+
+
+def test_empty_input():
+    """Empty and comment-only files are valid programs that print nothing."""
+    for name, source in [("empty", ""), ("comments_only", "# nothing here\n\n   \n# still nothing\n")]:
+        sb_file = write_snippet(name, source)
+        test_interpreter(sb_file, "")
+        test_native_compile(sb_file, "")
+        test_transpile_and_run(sb_file, "", "python", ".py", ["python3"])
+        test_transpile_and_run(sb_file, "", "js", ".js", ["node"])
+        os.remove(sb_file)
+
+
+def test_name_mangling():
+    """Target keywords and non-ASCII names still produce runnable output."""
+    source = ("function delete(x) {\n    return x + 1\n}\n"
+              "function caf\u00e9(n) {\n    return n * 2\n}\n"
+              "function double(v) {\n    return v + 100\n}\n"
+              "print(delete(1))\nprint(caf\u00e9(21))\nprint(double(5))\n")
+    sb_file = write_snippet("mangling", source)
+    test_interpreter(sb_file, "2\n42\n105")
+    test_native_compile(sb_file, "2\n42\n105")
+    test_transpile_and_run(sb_file, "2\n42\n105", "js", ".js", ["node"])
+    os.remove(sb_file)
+
+
+def test_optimizer_spans():
+    check_emit_ast("optimizer: folded constant keeps the span of '2 + 3'",
+                   "var x = 2 + 3\nprint(x)\n", "LITERAL: 5 [1:9-1:14]")
+
+
+def test_emit_tokens_json():
+    """--emit tokens-json gives each token's kind, text and byte offsets."""
+    sb_file = write_snippet("tokens", "var x = 10 // ten\r\nprint(x)\n")
+    label = "tokens-json: var x = 10 has its kinds, texts and offsets"
+    proc = subprocess.run([SUBC, sb_file, "--emit", "tokens-json"], capture_output=True, text=True)
+    try:
+        tokens = json.loads(proc.stdout)
+    except ValueError:
+        tokens = []
+    os.remove(sb_file)
+    got = [(t["kind"], t["text"], t["start_byte"], t["end_byte"], t["line"], t["column"]) for t in tokens[:5]]
+    want = [("VAR", "var", 0, 3, 1, 1), ("IDENTIFIER", "x", 4, 5, 1, 5), ("OPERATOR", "=", 6, 7, 1, 7),
+            ("NUMBER", "10", 8, 10, 1, 9), ("NEWLINE", "\r\n", 17, 19, 1, 18)]
+    check_result(label, proc.returncode == 0 and got == want and tokens[-1]["kind"] == "EOF",
+                 f"(exit {proc.returncode}) {got}\n       {proc.stderr}")
+
+
+def test_emit_reachability():
+    """--emit reachability marks the statements after a return unreachable."""
+    sb_file = write_snippet("reach", "function f(): int {\n    print(1)\n    return 2\n    print(3)\n}\n"
+                            "print(f())\nif false {\n    print(4)\n}\n"
+                            "const GO = true\nfunction g(GO: bool) {\n    while GO {\n        print(5)\n    }\n"
+                            "    print(6)\n}\ng(false)\nwhile GO {\n    print(7)\n}\nprint(8)\n")
+    rc, out = run([SUBC, sb_file, "--emit", "reachability"])
+    os.remove(sb_file)
+    marks = {}
+    for line in out.splitlines():
+        m = re.match(r"(\d+):\d+-\d+:\d+ (reachable|unreachable)$", line)
+        if m:
+            marks[int(m.group(1))] = m.group(2)
+    for label, line, want in [("reachability: a statement before the return is reachable", 2, "reachable"),
+                              ("reachability: the return itself is reachable", 3, "reachable"),
+                              ("reachability: a statement after the return is unreachable", 4, "unreachable"),
+                              ("reachability: code after the function is reachable", 6, "reachable"),
+                              ("reachability: an if false branch is unreachable", 8, "unreachable"),
+                              ("reachability: a parameter hides a const of its name", 15, "reachable"),
+                              ("reachability: nothing runs after while on a true const", 21, "unreachable")]:
+        check_result(label, rc == 0 and marks.get(line) == want,
+                     f"(exit {rc}, line {line}: {marks.get(line)}) {out}")
+
+
+def test_emit_no_banner():
+    """What --emit prints is all of stdout, without -q too."""
+    sb_file = write_snippet("emit_plain", "/// Doubles.\nfunction twice(x: int): int {\n    return x * 2\n}\nprint(twice(2))\n")
+    for kind, start in [("ast", "PROGRAM"), ("docs", "# "), ("tokens", "1:1"), ("tokens-json", "["),
+                        ("reachability", "2:")]:
+        proc = subprocess.run([SUBC, sb_file, "--emit", kind], capture_output=True, text=True)
+        check_result(f"emit: --emit {kind} prints no banner", proc.returncode == 0 and proc.stdout.startswith(start),
+                     proc.stdout[:300])
+    os.remove(sb_file)
+
+
+def test_emit_ast_after_errors():
+    """--emit ast still prints the tree, unoptimized, when semantic analysis fails."""
+    sb_file = write_snippet("emit_ast", "var x = 2 + 3\nprint(y)\n")
+    rc, out = run([SUBC, sb_file, "--emit", "ast"])
+    os.remove(sb_file)
+    label = "emit ast: an undefined variable is reported and the tree still printed"
+    check_result(label, rc != 0 and "Undefined variable 'y'" in out and "BINARY_EXPR: + [1:9-1:14]" in out
+                 and "IDENTIFIER: y [2:7-2:8]" in out, f"(exit {rc}) {out}")
+
+
+def test_string_folding():
+    check_emit_ast("string folding: len of a literal folds to its length",
+                   "var n = len(\"abc\")\nprint(n)\n", "VAR_DECL: n [1:1-1:19]\n    LITERAL: 3 [1:9-1:19]")
+    check_emit_ast("string folding: literals concatenate",
+                   "var s = \"ab\" + \"cd\"\nprint(s)\n", "LITERAL: abcd [1:9-1:20]")
+    check_emit_ast("string folding: an index into a literal folds to the character",
+                   "var c = \"xyz\"[1 + 1]\nprint(c)\n", "LITERAL: z [1:9-1:20]")
+    check_emit_ast("string folding: numeric-looking strings concatenate, not add",
+                   "var k = \"1\" + \"2\"\nprint(k)\n", "LITERAL: 12 ")
+    check_emit_ast("string folding: a non-ASCII literal keeps its len()",
+                   "var u = len(\"h\u00e9\")\nprint(u)\n", "CALL_EXPR: len")
+    check_emit_ast("string folding: a literal with an escape keeps its len()",
+                   "var e = len(\"a\\0b\")\nprint(e)\n", "CALL_EXPR: len")
+    sb_file = write_snippet("string_index_fold", "print(\"xyz\"[2])\nprint(len(\"abc\"))\n")
+    test_native_compile(sb_file, "z\n3")
+    os.remove(sb_file)
+    check_output("string folding: #static_assert can use len() of a literal",
+                 "#static_assert(len(\"abcd\") == 4, \"len\")\nprint(\"ok\")\n", "ok")
+    check_semantic_error("string folding: a constant index past the end is an error",
+                         "print(\"abc\"[3])\n", "String index 3 is out of bounds for a string of length 3")
+
+
+def test_fast_math():
+    source = "var a = 1.5\nvar b = a + 0.0\nprint(b)\n"
+    check_emit_ast("fast-math: a + 0.0 is kept by default", source,
+                   "VAR_DECL: b [2:1-2:16]\n    BINARY_EXPR: +")
+    check_emit_ast("fast-math: a + 0.0 is kept with --no-fast-math", source,
+                   "VAR_DECL: b [2:1-2:16]\n    BINARY_EXPR: +", ["--no-fast-math"])
+    check_emit_ast("fast-math: a + 0.0 folds to a with --fast-math", source,
+                   "VAR_DECL: b [2:1-2:16]\n    IDENTIFIER: a [2:9-2:16]", ["--fast-math"])
+    check_emit_ast("fast-math: a * 1.0 is exact and always simplified",
+                   "var a = 1.5\nvar c = a * 1.0\nprint(c)\n",
+                   "VAR_DECL: c [2:1-2:16]\n    IDENTIFIER: a [2:9-2:16]")
+
+
+def test_loop_invariant_motion():
+    """-O3 computes an invariant a * b once, before the loop."""
+    source = ("var a = 3\nvar b = 4\nvar i = 0\nvar total = 0\n"
+              "while i < 5 {\n    total = total + a * b + i * 2\n    i = i + 1\n}\nprint(total)\n")
+    check_emit_ast("licm: invariant a * b is hoisted above the while loop", source,
+                   "  VAR_DECL: sub_licm$0 [6:21-6:31]\n    BINARY_EXPR: * [6:21-6:26]\n"
+                   "      IDENTIFIER: a [6:21-6:22]\n      IDENTIFIER: b [6:25-6:26]\n  WHILE_STMT", ["-O3"])
+    check_emit_ast("licm: the loop reads the hoisted value", source,
+                   "IDENTIFIER: sub_licm$0 [6:21-6:26]", ["-O3"])
+    check_emit_ast("licm: loop-dependent i * 2 stays in the loop", source,
+                   "          BINARY_EXPR: * [6:29-6:34]\n            IDENTIFIER: i", ["-O3"])
+    check_emit_ast("licm: -O2 leaves the loop alone", source,
+                   "        BINARY_EXPR: + [6:13-6:26]\n            IDENTIFIER: total [6:13-6:18]\n"
+                   "            BINARY_EXPR: * [6:21-6:26]", ["-O2"])
+    check_emit_ast("licm: a loop calling a user function is left alone",
+                   "#[no_inline]\nfunction g(): int {\n    return 1\n}\nvar x = 2\nvar t = 0\n"
+                   "while t < 3 {\n    t = t + x * 2 + g()\n}\nprint(t)\n",
+                   "              IDENTIFIER: x [8:13-8:14]", ["-O3"])
+    sb_file = write_snippet("licm", source)
+    test_native_compile(sb_file, "80", ["-O3"])
+    os.remove(sb_file)
+
+
+def test_loop_unrolling():
+    """-O3 unrolls a loop with literal bounds and a small trip count."""
+    source = "for i in 0..3 {\n    print(i * 10)\n}\n"
+    check_emit_ast("unroll: for i in 0..3 becomes three copies of its body", source,
+                   "PROGRAM: program\n  CALL_EXPR: print [2:5-2:18]\n    LITERAL: 0 [2:11-2:17]\n"
+                   "  CALL_EXPR: print [2:5-2:18]\n    LITERAL: 10 [2:11-2:17]\n"
+                   "  CALL_EXPR: print [2:5-2:18]\n    LITERAL: 20 [2:11-2:17]\n", ["-O3"])
+    check_emit_ast("unroll: -O2 keeps the loop", source, "  FOR_STMT: i [1:1-1:14]", ["-O2"])
+    check_emit_ast("unroll: a runtime-bounded loop is untouched",
+                   "var n = len([1, 2, 3])\nfor i in 0..n {\n    print(i)\n}\n",
+                   "  FOR_STMT: i [2:1-2:14]\n    RANGE_EXPR: .. [2:10-2:14]", ["-O3"])
+    check_emit_ast("unroll: more trips than the limit are not unrolled",
+                   "for i in 0..9 {\n    print(i)\n}\n", "  FOR_STMT: i", ["-O3"])
+    check_emit_ast("unroll: a loop assigning its counter is not unrolled",
+                   "for i in 0..2 {\n    i = i + 1\n    print(i)\n}\n", "  FOR_STMT: i", ["-O3"])
+    sb_file = write_snippet("unroll", "var total = 0\nfor i in 1..=3 {\n    for j in range(2) {\n"
+                            "        total = total + i * j\n    }\n    if i > 1 {\n        var t = i * 2\n"
+                            "        print(t)\n    }\n}\nprint(total)\n")
+    test_native_compile(sb_file, "4\n6\n6", ["-O3"])
+    os.remove(sb_file)
+
+
+def test_function_attributes():
+    """#[no_inline] and #[inline] override the inliner's size heuristic."""
+    small = "function scale(x: int): int {\n    return x * 3\n}\nvar n = 5\nprint(scale(n))\n"
+    check_emit_ast("attributes: a small function is inlined at -O2", small,
+                   "  CALL_EXPR: print [5:1-5:16]\n    BINARY_EXPR: * [5:7-5:15]\n"
+                   "      IDENTIFIER: n [5:13-5:14]")
+    check_emit_ast("attributes: #[no_inline] keeps the call", "#[no_inline]\n" + small,
+                   "    CALL_EXPR: scale [6:7-6:15]")
+    check_emit_ast("attributes: -O1 does not inline", small, "    CALL_EXPR: scale", ["-O1"])
+    large = ("function mix(a: int, b: int): int {\n    return a * b + a * b + a * b + a - b\n}\n"
+             "var n = 5\nprint(mix(n, 2))\n")
+    check_emit_ast("attributes: a large function is not inlined", large, "    CALL_EXPR: mix [5:7-5:16]")
+    check_emit_ast("attributes: #[inline] inlines it anyway", "#[inline]\n" + large,
+                   "  CALL_EXPR: print [6:1-6:17]\n    BINARY_EXPR: - [6:7-6:16]")
+    sb_file = write_snippet("attributes", "#[inline]\n" + large + "print(mix(1, 1))\n")
+    test_native_compile(sb_file, "33\n3")
+    os.remove(sb_file)
+    shadowed = ("function f(x: int): int {\n    return x + 1\n}\nfunction g(): int {\n"
+                "    function f(x: int): int {\n        return x * 100\n    }\n    return f(2)\n}\n"
+                "print(g())\n")
+    check_emit_ast("attributes: a call to a shadowed name is not inlined", shadowed,
+                   "      CALL_EXPR: f [8:12-8:16]")
+    sb_file = write_snippet("attributes_shadow", shadowed)
+    test_native_compile(sb_file, "200")
+    os.remove(sb_file)
+    check_output("attributes: a #[...] line with no function after it is a comment",
+                 "#[note] remember this\n#[1] first\nprint(7)\n", "7")
+    check_warning("attributes: an unknown attribute warns", "#[fast]\n" + small,
+                  "Unknown attribute #[fast] on 'scale' is ignored")
+    two_statements = ("#[inline]\nfunction twice(x: int): int {\n    var y = x * 2\n    return y\n}\n"
+                      "print(twice(4))\n")
+    check_warning("attributes: #[inline] on a body the inliner cannot take warns", two_statements,
+                  "#[inline] on 'twice' is ignored")
+    check_warning("attributes: -Wno-ignored-attribute silences it", two_statements,
+                  "#[inline] on 'twice' is ignored", ["-Wno-ignored-attribute"], expect=False)
+    check_warning("attributes: #[inline] on a single return does not warn", "#[inline]\n" + small,
+                  "is ignored", expect=False)
+    check_semantic_error("attributes: #[hot] and #[cold] conflict", "#[hot]\n#[cold]\n" + small,
+                         "Function 'scale' cannot be both #[hot] and #[cold]")
+
+
+def test_target_attribute():
+    """#[target(...)] compiles a function only for the targets it lists."""
+    python_only = '#[target(web, python)]\nfunction greet() {\n    print("hi from python")\n}\n'
+    sb_file = write_snippet("target_attr", python_only + "greet()\n")
+    test_transpile_and_run(sb_file, "hi from python", "python", ".py", ["python3"])
+    os.remove(sb_file)
+
+    sb_file = write_snippet("target_attr", python_only + 'print("native")\n')
+    out_file = os.path.join(ROOT_DIR, "_regtest_target_attr.c")
+    exe_file = os.path.join(ROOT_DIR, "_regtest_target_attr" + EXE)
+    rc, out = run([SUB, sb_file, "linux", out_file])
+    code = open(out_file).read() if rc == 0 and os.path.exists(out_file) else ""
+    label = "target attribute: a python-only function is omitted for linux"
+    check_result(label, code and "greet" not in code and "native" in code, out)
+    for path in [out_file, exe_file]:
+        if os.path.exists(path):
+            os.remove(path)
+    os.remove(sb_file)
+
+    sb_file = write_snippet("target_attr", python_only + "greet()\n")
+    rc, out = run([SUB, sb_file, "linux", out_file])
+    label = "target attribute: calling it for linux is an error"
+    check_result(label, rc != 0 and
+                 "Function 'greet' is only compiled for #[target(web, python)], not for linux" in out,
+                 out)
+    os.remove(sb_file)
+
+    both = ('#[target(linux)]\nfunction where(): string {\n    return "native"\n}\n'
+            '#[target(interpret, py)]\nfunction where(): string {\n    return "script"\n}\nprint(where())\n')
+    check_output("target attribute: each target keeps its own function", both, "script")
+    sb_file = write_snippet("target_attr", both)
+    test_native_compile(sb_file, "native")
+    os.remove(sb_file)
+    check_semantic_error("target attribute: an unknown target is an error", "#[target(lnx)]\nfunction f() {\n}\n",
+                         "Unknown target 'lnx' in #[target(...)] on 'f'")
+    check_semantic_error("target attribute: wasm has no backend to name", "#[target(wasm)]\nfunction f() {\n}\n",
+                         "Unknown target 'wasm' in #[target(...)] on 'f'")
+    check_semantic_error("target attribute: the list is required", "#[target]\nfunction f() {\n}\n",
+                         "#[target] on 'f' needs the targets it is compiled for")
+
+
+def test_string_pool():
+    """A string literal used twice is defined once in the C output."""
+    sb_file = write_snippet("string_pool", 'print("once")\nprint("hello")\nprint("hello")\n')
+    out_file = os.path.join(ROOT_DIR, "_regtest_string_pool.c")
+    rc, out = run([SUB, sb_file, "c", out_file])
+    code = open(out_file).read() if rc == 0 and os.path.exists(out_file) else ""
+    label = "codegen: repeated string literal is pooled"
+    check_result(label, code.count('static char sub_str_0[] = "hello";') == 1 and code.count('"hello"') == 1
+                 and code.count("sub_str_0);") == 2 and "sub_str_1" not in code, code)
+    test_native_compile(sb_file, "once\nhello\nhello")
+    for path in (sb_file, out_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+    # Pooled and inline literals are escaped the same way
+    sb_file = write_snippet("string_pool_escape", 'print("say \\"hi\\"\\n")\nprint("say \\"hi\\"\\n")\nprint("a\\\\b\\tc")\n')
+    rc, out = run([SUB, sb_file, "c", out_file])
+    code = open(out_file).read() if rc == 0 and os.path.exists(out_file) else ""
+    check_result("codegen: pooled string literal is escaped",
+                 'static char sub_str_0[] = "say \\"hi\\"\\n";' in code and '"a\\\\b\\tc"' in code, out)
+    test_native_compile(sb_file, 'say "hi"\n\nsay "hi"\n\na\\b\tc')
+    for path in (sb_file, out_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def read_bytes(path):
+    if not os.path.exists(path):
+        return None
+    with open(path, "rb") as f:
+        return f.read()
+
+
+def check_same(label, first, second):
+    check_result(label, first is not None and first == second, "")
+
+
+def test_reproducible_builds():
+    """Two builds of the same input are byte-identical, and --remap-path
+    rewrites the source path embedded in the C output."""
+    sb_file = write_snippet("repro", 'print("same")\n')
+    c_file = os.path.join(ROOT_DIR, "_regtest_repro.c")
+    bins = [os.path.join(ROOT_DIR, f"_regtest_repro{i}") for i in (1, 2)]
+    saved = os.environ.get("SOURCE_DATE_EPOCH")
+    outputs = []
+    for epoch in ("0", "1700000000"):
+        os.environ["SOURCE_DATE_EPOCH"] = epoch
+        run([SUB, sb_file, "c", c_file])
+        outputs.append(read_bytes(c_file))
+    check_same("reproducible: C output is identical across runs", *outputs)
+    if tool_available("gcc"):
+        for path in bins:
+            run([SUBC, sb_file, "-o", path])
+        check_same("reproducible: subc binaries are identical across runs",
+                   read_bytes(bins[0] + EXE), read_bytes(bins[1] + EXE))
+    if saved is None:
+        os.environ.pop("SOURCE_DATE_EPOCH", None)
+    else:
+        os.environ["SOURCE_DATE_EPOCH"] = saved
+
+    run([SUB, sb_file, "c", c_file, "--remap-path", ROOT_DIR + os.sep + "=src/"])
+    code = (read_bytes(c_file) or b"").decode("utf-8", "replace")
+    label = "reproducible: --remap-path rewrites the embedded source path"
+    check_result(label, " * Source: src/_regtest_repro.sb\n" in code and ROOT_DIR not in code, "")
+    for path in [sb_file, c_file] + [b + EXE for b in bins]:
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_function_order():
+    """Every backend emits functions in the order the source declares them."""
+    sb_file = write_snippet("order", "function zeta(): int {\n    return 1\n}\n"
+                            "function alpha(): int {\n    return zeta() + 1\n}\n"
+                            "function mid(): int {\n    return alpha() + 1\n}\nprint(mid())\n")
+    out_file = os.path.join(ROOT_DIR, "_regtest_order.out")
+    for lang in ("c", "cpp", "python", "js", "rust", "go", "java", "kotlin"):
+        run([SUB, sb_file, lang, out_file])
+        code = (read_bytes(out_file) or b"").decode("utf-8", "replace")
+        positions = [code.find(name + "(") for name in ("zeta", "alpha", "mid")]
+        label = f"function order: {lang} keeps zeta, alpha, mid in source order"
+        check_result(label, -1 not in positions and positions == sorted(positions),
+                     f"(positions {positions})")
+    for path in (sb_file, out_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
+def test_python_layout():
+    """The Python backend indents each nested block by four spaces."""
+    sb_file = write_snippet("pylayout", "function classify(n: int): string {\n    if n % 2 == 0 {\n"
+                            "        return \"even\"\n    }\n    return \"odd\"\n}\n"
+                            "for i in range(1, 4) {\n    print(classify(i))\n}\n")
+    test_transpile_and_run(sb_file, "odd\neven\nodd", "python", ".py", ["python3"])
+    py_file = os.path.join(ROOT_DIR, "_regtest_pylayout.py")
+    run([SUB, sb_file, "python", py_file])
+    code = (read_bytes(py_file) or b"").decode("utf-8", "replace")
+    for label, needle in [("python layout: a def for the function", "def classify(n):\n    if "),
+                          ("python layout: a nested block is indented twice", "\n        return \"even\"\n"),
+                          ("python layout: the loop body is indented once",
+                           "for i in range(1, 4):\n    print(classify(i))\n")]:
+        check_result(label, needle in code, code)
+    for path in (sb_file, py_file):
+        if os.path.exists(path):
+            os.remove(path)
+
+
 def main():
     if not (os.path.exists(SUB) and os.path.exists(SUBC) and os.path.exists(SUBI)):
         print("Error: sub/subc/subi not found. Run 'make all' first.")
@@ -184,6 +2258,96 @@ def main():
         else:
             print(f"  SKIP sub cpp(compiled): {fname} (g++ not found)")
 
+    print("\n=== golden programs ===")
+    test_golden_programs()
+
+    print("\n=== snippets ===")
+    test_empty_input()
+    test_name_mangling()
+    test_error_limit()
+    test_caret_tabs()
+    test_unclosed_diagnostics()
+    test_duplicate_definitions()
+    test_semicolons()
+    test_line_endings()
+    test_raw_identifiers()
+    test_doc_comments()
+    test_parser_recovery()
+    test_max_nesting()
+    test_optimizer_spans()
+    test_emit_ast_after_errors()
+    test_emit_tokens_json()
+    test_emit_reachability()
+    test_emit_no_banner()
+    test_string_folding()
+    test_fast_math()
+    test_loop_invariant_motion()
+    test_loop_unrolling()
+    test_function_attributes()
+    test_target_attribute()
+    test_string_pool()
+    test_reproducible_builds()
+    test_function_order()
+    test_link()
+    test_crate_type()
+    test_print_size()
+    test_emit_manifest()
+    test_source_map()
+    test_quiet()
+    test_banner()
+    test_output_newlines()
+    test_source_encoding()
+    test_typescript()
+    test_subcommands()
+    test_fmt()
+    test_repl()
+    test_check_keep_going()
+    test_multiple_targets()
+    test_target_from_environment()
+    test_default_output_names()
+    test_unsupported_constructs()
+    test_trailing_commas()
+    test_line_continuation()
+    test_default_params()
+    test_len_builtin()
+    test_string_builtins()
+    test_negative_indices()
+    test_array_lengths()
+    test_array_repeat()
+    test_overflow_modes()
+    test_int_width()
+    test_select()
+    test_entry_function()
+    test_optionals()
+    test_match()
+    test_tuples()
+    test_for_each()
+    test_ranges()
+    test_not_callable()
+    test_name_suggestions()
+    test_exit_codes()
+    test_static_assert()
+    test_float_literals()
+    test_short_circuit()
+    test_interpreter_limits()
+    test_eval()
+    test_python_layout()
+    test_float_equality()
+    test_infinite_loop()
+    test_numeric_conversions()
+    test_pointless_statements()
+    test_casts()
+    test_unicode_escapes()
+    test_byte_strings()
+    test_dump_symbols()
+    test_stats()
+    test_typeof()
+    test_strict_mode()
+    test_unused_functions()
+    test_unused_parameters()
+    test_prelude()
+    test_library_api()
+
     print(f"\n{'='*40}")
     if failures:
         print(f"{len(failures)} check(s) failed:")